@@ -0,0 +1,236 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Block generation controlled by the dev RPCs.
+//!
+//! A snapshot records the pivot chain tip. Reverting to it generates a fork
+//! from the tip until the fork becomes the pivot chain, and the blocks
+//! generated after the snapshot are never referenced again, so the executed
+//! state of the new pivot chain is built on the state of the snapshot.
+
+use crate::BlockGenerator;
+use cfx_types::H256;
+use cfxcore::{
+    consensus::consensus_inner::consensus_executor::DevStateOverride,
+    ConsensusGraphTrait,
+};
+use primitives::SignedTransaction;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximal number of transactions packed from the pool in a dev block.
+const DEV_BLOCK_MAX_TXS: usize = 3000;
+
+struct DevSnapshot {
+    best_block_hash: H256,
+    terminal_block_hashes: Vec<H256>,
+    block_count: u64,
+    time_offset: u64,
+}
+
+#[derive(Default)]
+pub struct DevState {
+    /// The seconds added to the system time as the timestamp of new blocks.
+    pub time_offset: u64,
+    /// The timestamp of the next generated block.
+    next_timestamp: Option<u64>,
+    /// The blocks left out of the pivot chain by reverting to a snapshot.
+    pub reverted_blocks: HashSet<H256>,
+    snapshots: BTreeMap<u64, DevSnapshot>,
+    last_snapshot_id: u64,
+}
+
+impl DevState {
+    /// Take the timestamp of the next block. The timestamps of the later
+    /// blocks continue from it.
+    pub fn take_next_timestamp(&mut self) -> Option<u64> {
+        let timestamp = self.next_timestamp.take()?;
+        self.time_offset = timestamp.saturating_sub(unix_now());
+        Some(timestamp)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+impl BlockGenerator {
+    /// Generate `count` blocks on the pivot chain and return the hash of the
+    /// last one. The first block packs `transactions` with the transactions
+    /// in the pool, and `state_overrides` are applied before executing its
+    /// epoch.
+    pub fn generate_dev_blocks(
+        &self, count: usize, mut transactions: Vec<Arc<SignedTransaction>>,
+        mut state_overrides: Vec<DevStateOverride>,
+    ) -> H256
+    {
+        let _generation = self.dev_generation_lock.lock();
+        let consensus_graph = self.consensus_graph();
+        let mut hash = consensus_graph.best_block_hash();
+        for _ in 0..count {
+            let mut block = self.assemble_new_block(
+                DEV_BLOCK_MAX_TXS,
+                self.graph.verification_config.max_block_size_in_bytes,
+                std::mem::take(&mut transactions),
+            );
+            self.solve_block_pow(&mut block);
+            hash = block.block_header.compute_hash();
+            if !state_overrides.is_empty() {
+                consensus_graph.set_dev_state_overrides(
+                    hash,
+                    std::mem::take(&mut state_overrides),
+                );
+            }
+            self.on_mined_block(block);
+            consensus_graph.wait_for_generation(&hash);
+        }
+        hash
+    }
+
+    /// Record the pivot chain tip and the block time, and return the id of
+    /// the snapshot.
+    pub fn dev_snapshot(&self) -> u64 {
+        let _generation = self.dev_generation_lock.lock();
+        let consensus_graph = self.consensus_graph();
+        let best_info = consensus_graph.best_info();
+        let block_count = consensus_graph.block_count();
+        let mut dev_state = self.dev_state.lock();
+        dev_state.last_snapshot_id += 1;
+        let id = dev_state.last_snapshot_id;
+        let snapshot = DevSnapshot {
+            best_block_hash: best_info.best_block_hash,
+            terminal_block_hashes: best_info
+                .bounded_terminal_block_hashes
+                .clone(),
+            block_count,
+            time_offset: dev_state.time_offset,
+        };
+        dev_state.snapshots.insert(id, snapshot);
+        id
+    }
+
+    /// Revert the pivot chain to the tip recorded by snapshot `id`. The
+    /// snapshot and the ones taken after it are removed. Return `false` if
+    /// the snapshot does not exist.
+    pub fn dev_revert(&self, id: u64) -> Result<bool, String> {
+        let _generation = self.dev_generation_lock.lock();
+        let snapshot = {
+            let mut dev_state = self.dev_state.lock();
+            if !dev_state.snapshots.contains_key(&id) {
+                return Ok(false);
+            }
+            dev_state
+                .snapshots
+                .split_off(&id)
+                .remove(&id)
+                .expect("checked above")
+        };
+
+        let consensus_graph = self.consensus_graph();
+        let data_man = &self.graph.data_man;
+        let height = data_man
+            .block_height_by_hash(&snapshot.best_block_hash)
+            .ok_or("The block of the snapshot has been removed")?;
+        if data_man.state_availability_boundary.read().lower_bound > height {
+            return Err("The state of the snapshot has been removed".into());
+        }
+        let best_info = consensus_graph.best_info();
+        {
+            let mut dev_state = self.dev_state.lock();
+            dev_state.reverted_blocks.extend(
+                best_info
+                    .bounded_terminal_block_hashes
+                    .iter()
+                    .filter(|h| !snapshot.terminal_block_hashes.contains(h)),
+            );
+            dev_state.time_offset = snapshot.time_offset;
+            dev_state.next_timestamp = None;
+        }
+
+        // The fork needs more blocks than the ones generated after the
+        // snapshot to become the pivot chain.
+        let max_fork_len =
+            consensus_graph.block_count() - snapshot.block_count + 1;
+        let mut parent = snapshot.best_block_hash;
+        let mut referees = snapshot.terminal_block_hashes;
+        referees.retain(|h| *h != parent);
+        for _ in 0..max_fork_len {
+            if consensus_graph.best_block_hash() == parent {
+                break;
+            }
+            let pos_reference = self.get_pos_reference(&parent);
+            let adaptive = {
+                let mut inner = consensus_graph.inner.write();
+                referees
+                    .retain(|h| inner.hash_to_arena_indices.contains_key(h));
+                let difficulty = inner.expected_difficulty(&parent);
+                consensus_graph.check_mining_adaptive_block(
+                    &mut *inner,
+                    &parent,
+                    &referees,
+                    &difficulty,
+                    pos_reference,
+                )
+            };
+            parent = self.generate_custom_block_with_parent(
+                parent,
+                std::mem::take(&mut referees),
+                Vec::new(),
+                adaptive,
+            )?;
+        }
+        if consensus_graph.best_block_hash() != parent {
+            return Err("The blocks generated after the snapshot cannot be \
+                        removed from the pivot chain"
+                .into());
+        }
+        // The transactions sent after the snapshot are dropped with the
+        // reverted blocks.
+        self.txpool.clear_tx_pool();
+        Ok(true)
+    }
+
+    /// Move the timestamps of the later blocks forward by `seconds`. Return
+    /// the total seconds added to the system time.
+    pub fn dev_increase_time(&self, seconds: u64) -> Result<u64, String> {
+        let mut dev_state = self.dev_state.lock();
+        let time_offset = dev_state
+            .time_offset
+            .checked_add(seconds)
+            .filter(|offset| unix_now().checked_add(*offset).is_some())
+            .ok_or_else(|| {
+                format!("Increasing the time by {} seconds overflows", seconds)
+            })?;
+        dev_state.time_offset = time_offset;
+        Ok(time_offset)
+    }
+
+    /// Set the timestamp of the next generated block, which cannot be earlier
+    /// than the pivot chain tip.
+    pub fn dev_set_next_block_timestamp(
+        &self, timestamp: u64,
+    ) -> Result<(), String> {
+        let best_block_hash = self.consensus_graph().best_block_hash();
+        let best_timestamp = self
+            .graph
+            .data_man
+            .block_header_by_hash(&best_block_hash)
+            .ok_or("The best block header is missing")?
+            .timestamp();
+        if timestamp < best_timestamp {
+            return Err(format!(
+                "Timestamp {} is earlier than the latest block timestamp {}",
+                timestamp, best_timestamp
+            ));
+        }
+        self.dev_state.lock().next_timestamp = Some(timestamp);
+        Ok(())
+    }
+}
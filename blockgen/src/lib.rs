@@ -1,11 +1,15 @@
 // Copyright 2019 Conflux Foundation. All rights reserved.
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
+mod dev;
 mod miner;
 
-use crate::miner::{
-    stratum::{Options as StratumOption, Stratum},
-    work_notify::NotifyWork,
+use crate::{
+    dev::DevState,
+    miner::{
+        stratum::{Options as StratumOption, Stratum},
+        work_notify::NotifyWork,
+    },
 };
 use cfx_parameters::consensus::GENESIS_GAS_LIMIT;
 use cfx_types::{Address, H256, U256};
@@ -63,6 +67,11 @@ pub struct BlockGenerator {
     workers: Mutex<Vec<(Worker, mpsc::Sender<ProofOfWorkProblem>)>>,
     pub stratum: RwLock<Option<Stratum>>,
     pos_verifier: Arc<PosVerifier>,
+    /// The block timestamps and snapshots controlled by the dev RPCs.
+    dev_state: Mutex<DevState>,
+    /// Held while generating blocks in dev mode, so the blocks generated
+    /// for a dev RPC are not interleaved with the automatic ones.
+    dev_generation_lock: Mutex<()>,
 }
 
 pub struct Worker {
@@ -162,6 +171,8 @@ impl BlockGenerator {
             workers: Mutex::new(Vec::new()),
             stratum: RwLock::new(None),
             pos_verifier,
+            dev_state: Default::default(),
+            dev_generation_lock: Mutex::new(()),
         }
     }
 
@@ -223,6 +234,13 @@ impl BlockGenerator {
         // checkpoint making that happens before we acquire the inner lock
         referees
             .retain(|h| consensus_inner.hash_to_arena_indices.contains_key(h));
+        let (time_offset, next_timestamp) = {
+            let mut dev_state = self.dev_state.lock();
+            // The blocks reverted by `evm_revert` should not be executed
+            // again.
+            referees.retain(|h| !dev_state.reverted_blocks.contains(h));
+            (dev_state.time_offset, dev_state.take_next_timestamp())
+        };
         let mut expected_difficulty =
             consensus_inner.expected_difficulty(&parent_hash);
         let adaptive = if let Some(x) = adaptive_opt {
@@ -249,10 +267,13 @@ impl BlockGenerator {
             expected_difficulty = U256::from(difficulty);
         }
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = next_timestamp.unwrap_or(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .saturating_add(time_offset),
+        );
 
         // Adjust the timestamp of the currently mined block to be later
         // than or equal to its parent's.
@@ -624,8 +645,8 @@ impl BlockGenerator {
         Ok(hash)
     }
 
-    fn generate_block_impl(&self, block_init: Block) -> H256 {
-        let mut block = block_init;
+    /// Find a nonce for `block` that satisfies its difficulty.
+    fn solve_block_pow(&self, block: &mut Block) {
         let difficulty = block.block_header.difficulty();
         let problem = ProofOfWorkProblem::new(
             block.block_header.height(),
@@ -646,6 +667,11 @@ impl BlockGenerator {
             }
             nonce += 1;
         }
+    }
+
+    fn generate_block_impl(&self, block_init: Block) -> H256 {
+        let mut block = block_init;
+        self.solve_block_pow(&mut block);
         let hash = block.block_header.compute_hash();
         debug!(
             "generate_block with block header:{:?} tx_number:{}, block_size:{}",
@@ -860,6 +886,7 @@ impl BlockGenerator {
                 _ => {}
             }
             if !self.sync.catch_up_mode() {
+                let _generation = self.dev_generation_lock.lock();
                 self.generate_block(
                     3000,
                     self.graph.verification_config.max_block_size_in_bytes,
@@ -26,6 +26,7 @@ pub use cfxcore::pos::pos::PosDropHandle;
 use cfxcore::{
     block_data_manager::BlockDataManager,
     consensus::pos_handler::{PosConfiguration, PosVerifier},
+    forked_state::ForkedState,
     machine::{new_machine_with_builtin, Machine},
     pow::PowComputer,
     spec::genesis::{self, genesis_block, DEV_GENESIS_KEY_PAIR_2},
@@ -299,10 +300,14 @@ pub fn initialize_common_modules(
         .map_err(|e| format!("Failed to open database {:?}", e))?;
 
     let secret_store = Arc::new(SecretStore::new());
-    let storage_manager = Arc::new(
-        StorageManager::new(conf.storage_config())
-            .expect("Failed to initialize storage."),
-    );
+    let mut storage_manager = StorageManager::new(conf.storage_config())
+        .expect("Failed to initialize storage.");
+    if let Some(fork_config) = conf.fork_config() {
+        storage_manager = storage_manager.with_forked_state(Arc::new(
+            ForkedState::open(fork_config, ledger_db.clone())?,
+        ));
+    }
+    let storage_manager = Arc::new(storage_manager);
     {
         let storage_manager_log_weak_ptr = Arc::downgrade(&storage_manager);
         let exit_clone = exit.clone();
@@ -392,6 +397,7 @@ pub fn initialize_common_modules(
         conf.data_mananger_config(),
         pow.clone(),
     ));
+    data_man.start_freezer_thread();
 
     let network = {
         let mut rng = StdRng::from_rng(OsRng).unwrap();
@@ -584,6 +590,42 @@ pub fn initialize_not_light_node_modules(
     ));
     sync.register().unwrap();
 
+    {
+        // Index the confirmed epochs missing in the log bloom index in
+        // batches, and wait for new epochs after catching up.
+        const LOG_BLOOM_INDEX_BATCH_SIZE: u64 = 4096;
+        let consensus_weak_ptr = Arc::downgrade(&consensus);
+        let exit_clone = exit.clone();
+        thread::Builder::new()
+            .name("Log Bloom Index".into())
+            .spawn(move || {
+                let mut wait_time = Duration::from_millis(5000);
+                loop {
+                    {
+                        let mut exit_lock = exit_clone.0.lock();
+                        if !exit_clone
+                            .1
+                            .wait_for(&mut exit_lock, wait_time)
+                            .timed_out()
+                        {
+                            return;
+                        }
+                    }
+                    let indexed_count = match consensus_weak_ptr.upgrade() {
+                        None => return,
+                        Some(consensus) => consensus
+                            .update_log_bloom_index(LOG_BLOOM_INDEX_BATCH_SIZE),
+                    };
+                    wait_time = if indexed_count < LOG_BLOOM_INDEX_BATCH_SIZE {
+                        Duration::from_millis(5000)
+                    } else {
+                        Duration::from_millis(10)
+                    };
+                }
+            })
+            .expect("Log bloom index thread spawn error");
+    }
+
     if let Some(print_memory_usage_period_s) =
         conf.raw_conf.print_memory_usage_period_s
     {
@@ -19,7 +19,10 @@ use cfx_storage::{
 };
 use cfx_types::{Address, AllChainID, H256, U256};
 use cfxcore::{
-    block_data_manager::{DataManagerConfiguration, DbType},
+    block_data_manager::{
+        db_gc_manager::{PruningPolicy, RetentionPolicy},
+        DataManagerConfiguration, DbType,
+    },
     block_parameters::*,
     cache_config::{
         DEFAULT_INVALID_BLOCK_HASH_CACHE_SIZE_IN_COUNT,
@@ -28,10 +31,13 @@ use cfxcore::{
     },
     consensus::{
         consensus_inner::consensus_executor::ConsensusExecutionConfiguration,
-        pos_handler::PosVerifier, ConsensusConfig, ConsensusInnerConfig,
+        gas_price_oracle::{GasPriceOracleConfig, GasPriceTarget},
+        pos_handler::PosVerifier,
+        ConsensusConfig, ConsensusInnerConfig,
     },
     consensus_internal_parameters::*,
     consensus_parameters::*,
+    forked_state::ForkConfiguration,
     light_protocol::LightNodeConfiguration,
     machine::Machine,
     spec::CommonParams,
@@ -45,7 +51,12 @@ use diem_types::term_state::{
     OUT_QUEUE_LOCKED_VIEWS, ROUND_PER_TERM, TERM_ELECTED_SIZE, TERM_MAX_SIZE,
 };
 use metrics::MetricsConfiguration;
-use network::DiscoveryConfiguration;
+use network::{
+    encryption::SessionEncryptionConfig, in_memory::InMemoryNetwork,
+    node_record::NodeCapabilities, reputation::ReputationConfig,
+    transport::QuicConfiguration, Compression, DiscoveryConfiguration,
+    NODE_TAG_ARCHIVE, NODE_TAG_FULL, NODE_TAG_LIGHT,
+};
 use txgen::TransactionGeneratorConfig;
 
 use crate::rpc::{
@@ -57,6 +68,7 @@ lazy_static! {
     pub static ref CHAIN_ID: RwLock<Option<ChainIdParams>> = Default::default();
 }
 const BLOCK_DB_DIR_NAME: &str = "blockchain_db";
+const FREEZER_DIR_NAME: &str = "freezer";
 const NET_CONFIG_DB_DIR_NAME: &str = "net_config";
 
 // usage:
@@ -110,6 +122,9 @@ build_config! {
         // Controls block generation speed.
         // Only effective in `dev` mode
         (dev_block_interval_ms, (Option<u64>), None)
+        // Fork the state of a remote node. Only effective in dev mode.
+        (fork_url, (Option<String>), None)
+        (fork_epoch, (Option<u64>), None)
         (enable_state_expose, (bool), false)
         (generate_tx, (bool), false)
         (generate_tx_period_us, (Option<u64>), Some(100_000))
@@ -151,6 +166,9 @@ build_config! {
         (cip78_patch_transition_number,(Option<u64>),None)
         (cip90_transition_height,(Option<u64>),None)
         (cip90_transition_number,(Option<u64>),None)
+        (cip_create2_evm_transition_number,(Option<u64>),None)
+        (cip_core_space_reader_transition_number,(Option<u64>),None)
+        (cip_epoch_history_transition_number,(Option<u64>),None)
         (referee_bound, (usize), REFEREE_DEFAULT_BOUND)
         (timer_chain_beta, (u64), TIMER_CHAIN_DEFAULT_BETA)
         (timer_chain_block_difficulty_ratio, (u64), TIMER_CHAIN_BLOCK_DEFAULT_DIFFICULTY_RATIO)
@@ -198,6 +216,7 @@ build_config! {
         (egress_queue_capacity, (usize), 256)
         (egress_min_throttle, (usize), 10)
         (egress_max_throttle, (usize), 64)
+        (enable_tx_announcement, (bool), true)
         (expire_block_gc_period_s, (u64), 900)
         (headers_request_timeout_ms, (u64), 10_000)
         (heartbeat_period_interval_ms, (u64), 30_000)
@@ -225,6 +244,7 @@ build_config! {
         (throttling_conf, (Option<String>), None)
         (timeout_observing_period_s, (u64), 600)
         (transaction_request_timeout_ms, (u64), 30_000)
+        (tx_announcement_size_threshold, (usize), 4096)
         (tx_maintained_for_peer_timeout_ms, (u64), 600_000)
 
         // Peer management section.
@@ -236,6 +256,7 @@ build_config! {
         (discovery_housekeeping_timeout_ms, (u64), 1_000)
         (discovery_max_nodes_ping, (usize), 32)
         (discovery_ping_timeout_ms, (u64), 2_000)
+        (discovery_record_ttl_s, (u64), 3600)
         (discovery_round_timeout_ms, (u64), 500)
         (discovery_throttling_interval_ms, (u64), 1_000)
         (discovery_throttling_limit_ping, (usize), 20)
@@ -246,6 +267,19 @@ build_config! {
         (node_table_timeout_s, (u64), 300)
         (node_table_promotion_timeout_s, (u64), 3 * 24 * 3600)
         (session_ip_limits, (String), "1,8,4,2".into())
+        (session_encryption, (String), "disabled".into())
+        (session_encryption_peers, (Option<String>), None)
+        (session_compression, (Option<String>), None)
+        (session_compression_threshold, (usize), 1024)
+        (peer_score_half_life_s, (u64), 1800)
+        (peer_ban_score, (f64), -100.0)
+        (peer_ban_duration_s, (u64), 3600)
+        (quic_cert_path, (Option<String>), None)
+        (quic_idle_timeout_s, (u64), 120)
+        (quic_key_path, (Option<String>), None)
+        (quic_port, (Option<u16>), None)
+        (reserved_peers, (Option<String>), None)
+        (reserved_only, (bool), false)
         (subnet_quota, (usize), 128)
 
         // Transaction cache/transaction pool section.
@@ -266,6 +300,8 @@ build_config! {
         (block_cache_gc_period_ms, (u64), 5_000)
         (block_db_dir, (Option<String>), None)
         (block_db_type, (String), "rocksdb".to_string())
+        (enable_block_data_freezer, (bool), false)
+        (block_data_freezer_dir, (Option<String>), None)
         (checkpoint_gc_time_in_era_count, (f64), 0.5)
         // The conflux data dir, if unspecified, is the workdir where conflux is started.
         (conflux_data_dir, (String), "./blockchain_data".to_string())
@@ -294,12 +330,21 @@ build_config! {
         (get_logs_filter_max_epoch_range, (Option<u64>), None)
         (get_logs_filter_max_block_number_range, (Option<u64>), None)
         (get_logs_epoch_batch_size, (usize), 32)
+        (gas_price_slow_percentile, (u8), 10)
+        (gas_price_slow_target_blocks, (u64), 40)
+        (gas_price_normal_percentile, (u8), 40)
+        (gas_price_normal_target_blocks, (u64), 10)
+        (gas_price_fast_percentile, (u8), 75)
+        (gas_price_fast_target_blocks, (u64), 2)
+        (gas_price_low_utilization_percent, (u64), 20)
         (max_trans_count_received_in_catch_up, (u64), 60_000)
         (persist_tx_index, (bool), false)
         (persist_block_number_index, (bool), true)
+        (persist_address_tx_index, (bool), false)
         (print_memory_usage_period_s, (Option<u64>), None)
         (target_block_gas_limit, (u64), DEFAULT_TARGET_BLOCK_GAS_LIMIT)
         (executive_trace, (bool), false)
+        (parallel_execution, (bool), false)
         (check_status_genesis, (bool), true)
         (packing_gas_limit_block_count, (u64), 10)
 
@@ -359,6 +404,18 @@ build_config! {
             vec![ProvideExtraSnapshotSyncConfig::StableCheckpoint],
             ProvideExtraSnapshotSyncConfig::parse_config_list)
         (node_type, (Option<NodeType>), None, NodeType::from_str)
+        // The retention policies of block data, which override the
+        // corresponding `additional_maintained*` parameters.
+        (transaction_index_retention, (Option<RetentionPolicy>), None,
+            RetentionPolicy::from_str)
+        (block_body_retention, (Option<RetentionPolicy>), None,
+            RetentionPolicy::from_str)
+        (execution_result_retention, (Option<RetentionPolicy>), None,
+            RetentionPolicy::from_str)
+        (reward_retention, (Option<RetentionPolicy>), None,
+            RetentionPolicy::from_str)
+        (trace_retention, (Option<RetentionPolicy>), None,
+            RetentionPolicy::from_str)
         (public_rpc_apis, (ApiSet), ApiSet::Safe, ApiSet::from_str)
         (public_evm_rpc_apis, (ApiSet), ApiSet::Evm, ApiSet::from_str)
     }
@@ -366,12 +423,16 @@ build_config! {
 
 pub struct Configuration {
     pub raw_conf: RawConfiguration,
+    /// In-memory network to connect the nodes in the same process, which is
+    /// only set by the tests.
+    pub in_memory_network: Option<InMemoryNetwork>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
             raw_conf: Default::default(),
+            in_memory_network: None,
         }
     }
 }
@@ -388,10 +449,30 @@ impl Configuration {
         } else if matches.is_present("light") {
             config.raw_conf.node_type = Some(NodeType::Light);
         }
+        config.validate_block_data_freezer()?;
 
         Ok(config)
     }
 
+    /// The freezer is append-only, so the frozen data cannot be garbage
+    /// collected.
+    fn validate_block_data_freezer(&self) -> Result<(), String> {
+        if !self.raw_conf.enable_block_data_freezer {
+            return Ok(());
+        }
+        let pruning_policy = self.pruning_policy();
+        if pruning_policy.block_body != RetentionPolicy::Archive
+            || pruning_policy.execution_result != RetentionPolicy::Archive
+            || pruning_policy.trace != RetentionPolicy::Archive
+        {
+            return Err("enable_block_data_freezer requires the archive \
+                        retention policy for block bodies, execution \
+                        results, and traces"
+                .into());
+        }
+        Ok(())
+    }
+
     fn network_id(&self) -> u64 {
         match self.raw_conf.network_id {
             Some(x) => x,
@@ -417,6 +498,11 @@ impl Configuration {
         network_config.discovery_enabled = self.raw_conf.enable_discovery;
         network_config.boot_nodes = to_bootnodes(&self.raw_conf.bootnodes)
             .map_err(|e| format!("failed to parse bootnodes: {}", e))?;
+        network_config.reserved_nodes =
+            to_bootnodes(&self.raw_conf.reserved_peers).map_err(|e| {
+                format!("failed to parse reserved_peers: {}", e)
+            })?;
+        network_config.reserved_only = self.raw_conf.reserved_only;
         network_config.config_path = Some(match &self.raw_conf.netconf_dir {
             Some(dir) => dir.clone(),
             None => Path::new(&self.raw_conf.conflux_data_dir)
@@ -460,6 +546,28 @@ impl Configuration {
             self.raw_conf.session_ip_limits.clone().try_into().map_err(
                 |e| format!("failed to parse session ip limit config: {}", e),
             )?;
+        network_config.session_encryption = SessionEncryptionConfig::new(
+            &self.raw_conf.session_encryption,
+            self.raw_conf.session_encryption_peers.as_deref(),
+        )
+        .map_err(|e| format!("failed to parse session encryption: {}", e))?;
+        if let Some(compression) = &self.raw_conf.session_compression {
+            network_config.session_compression =
+                Compression::parse_list(compression).map_err(|e| {
+                    format!("failed to parse session compression: {}", e)
+                })?;
+        }
+        network_config.session_compression_threshold =
+            self.raw_conf.session_compression_threshold;
+        network_config.reputation = ReputationConfig {
+            score_half_life: Duration::from_secs(
+                self.raw_conf.peer_score_half_life_s,
+            ),
+            ban_score: self.raw_conf.peer_ban_score,
+            ban_duration: Duration::from_secs(
+                self.raw_conf.peer_ban_duration_s,
+            ),
+        };
         network_config.fast_discovery_refresh_timeout = Duration::from_millis(
             self.raw_conf.discovery_fast_refresh_timeout_ms,
         );
@@ -473,6 +581,48 @@ impl Configuration {
         network_config.max_outgoing_peers = self.raw_conf.max_outgoing_peers;
         network_config.max_outgoing_peers_archive =
             self.raw_conf.max_outgoing_peers_archive.unwrap_or(0);
+        network_config.in_memory = self.in_memory_network.clone();
+        if let Some(port) = self.raw_conf.quic_port {
+            let required = |path: &Option<String>, name: &str| {
+                path.clone().ok_or_else(|| {
+                    format!("{} is required if quic_port is set", name)
+                })
+            };
+            network_config.quic = Some(QuicConfiguration {
+                port,
+                cert_path: required(
+                    &self.raw_conf.quic_cert_path,
+                    "quic_cert_path",
+                )?,
+                key_path: required(
+                    &self.raw_conf.quic_key_path,
+                    "quic_key_path",
+                )?,
+                idle_timeout: Duration::from_secs(
+                    self.raw_conf.quic_idle_timeout_s,
+                ),
+            });
+        }
+        let node_type = match self.node_type() {
+            NodeType::Archive => NODE_TAG_ARCHIVE,
+            NodeType::Full => NODE_TAG_FULL,
+            NodeType::Light => NODE_TAG_LIGHT,
+            NodeType::Unknown => "",
+        };
+        network_config.node_capabilities = Some(NodeCapabilities {
+            node_type: node_type.into(),
+            // The protocols are added when registered to the network.
+            protocols: Vec::new(),
+            // Set by the sync protocol once the snapshot is available.
+            snapshot: false,
+            chain_id: self
+                .chain_id_params()
+                .read()
+                .get_chain_id(/* epoch_number = */ 0)
+                .in_native_space() as u64,
+            // The transports are added when bound by the network.
+            transports: Vec::new(),
+        });
         Ok(network_config)
     }
 
@@ -486,12 +636,16 @@ impl Configuration {
         cache_config
     }
 
-    pub fn db_config(&self) -> (PathBuf, DatabaseConfig) {
-        let db_dir: PathBuf = match &self.raw_conf.block_db_dir {
+    fn block_db_dir(&self) -> PathBuf {
+        match &self.raw_conf.block_db_dir {
             Some(dir) => dir.into(),
             None => Path::new(&self.raw_conf.conflux_data_dir)
                 .join(BLOCK_DB_DIR_NAME),
-        };
+        }
+    }
+
+    pub fn db_config(&self) -> (PathBuf, DatabaseConfig) {
+        let db_dir = self.block_db_dir();
         if let Err(e) = fs::create_dir_all(&db_dir) {
             panic!("Error creating database directory: {:?}", e);
         }
@@ -586,6 +740,24 @@ impl Configuration {
             get_logs_filter_max_block_number_range: self.raw_conf.get_logs_filter_max_block_number_range,
             sync_state_starting_epoch: self.raw_conf.sync_state_starting_epoch,
             sync_state_epoch_gap: self.raw_conf.sync_state_epoch_gap,
+            gas_price_oracle: GasPriceOracleConfig {
+                slow: GasPriceTarget {
+                    percentile: self.raw_conf.gas_price_slow_percentile,
+                    blocks: self.raw_conf.gas_price_slow_target_blocks,
+                },
+                normal: GasPriceTarget {
+                    percentile: self.raw_conf.gas_price_normal_percentile,
+                    blocks: self.raw_conf.gas_price_normal_target_blocks,
+                },
+                fast: GasPriceTarget {
+                    percentile: self.raw_conf.gas_price_fast_percentile,
+                    blocks: self.raw_conf.gas_price_fast_target_blocks,
+                },
+                low_utilization_percent: self
+                    .raw_conf
+                    .gas_price_low_utilization_percent,
+                min_gas_price: self.raw_conf.tx_pool_min_tx_gas_price.into(),
+            },
         };
         match self.raw_conf.node_type {
             Some(NodeType::Archive) => {
@@ -754,6 +926,10 @@ impl Configuration {
                 .max_trans_count_received_in_catch_up,
             min_peers_tx_propagation: self.raw_conf.min_peers_tx_propagation,
             max_peers_tx_propagation: self.raw_conf.max_peers_tx_propagation,
+            enable_tx_announcement: self.raw_conf.enable_tx_announcement,
+            tx_announcement_size_threshold: self
+                .raw_conf
+                .tx_announcement_size_threshold,
             max_downloading_chunks: self.raw_conf.max_downloading_chunks,
             test_mode: self.is_test_mode(),
             dev_mode: self.is_dev_mode(),
@@ -826,6 +1002,7 @@ impl Configuration {
             persist_block_number_index: self
                 .raw_conf
                 .persist_block_number_index,
+            persist_address_tx_index: self.raw_conf.persist_address_tx_index,
             tx_cache_index_maintain_timeout: Duration::from_millis(
                 self.raw_conf.tx_cache_index_maintain_timeout_ms,
             ),
@@ -834,65 +1011,81 @@ impl Configuration {
                 "sqlite" => DbType::Sqlite,
                 _ => panic!("Invalid block_db_type parameter!"),
             },
-            additional_maintained_block_body_epoch_count: self
-                .raw_conf
-                .additional_maintained_block_body_epoch_count,
-            additional_maintained_execution_result_epoch_count: self
-                .raw_conf
-                .additional_maintained_execution_result_epoch_count,
-            additional_maintained_reward_epoch_count: self
-                .raw_conf
-                .additional_maintained_reward_epoch_count,
-            additional_maintained_trace_epoch_count: self
-                .raw_conf
-                .additional_maintained_trace_epoch_count,
-            additional_maintained_transaction_index_epoch_count: self
-                .raw_conf
-                .additional_maintained_transaction_index_epoch_count,
+            pruning_policy: self.pruning_policy(),
             checkpoint_gc_time_in_epoch_count: (self
                 .raw_conf
                 .checkpoint_gc_time_in_era_count
                 * self.raw_conf.era_epoch_count as f64)
                 as usize,
             strict_tx_index_gc: self.raw_conf.strict_tx_index_gc,
+            freezer_dir: None,
         };
 
-        // By default, we do not keep the block data for additional period,
-        // but `node_type = "archive"` is a shortcut for keeping all them.
-        if !matches!(self.raw_conf.node_type, Some(NodeType::Archive)) {
-            if conf.additional_maintained_block_body_epoch_count.is_none() {
-                conf.additional_maintained_block_body_epoch_count = Some(0);
-            }
-            if conf
-                .additional_maintained_execution_result_epoch_count
-                .is_none()
-            {
-                conf.additional_maintained_execution_result_epoch_count =
-                    Some(0);
-            }
-            if conf
-                .additional_maintained_transaction_index_epoch_count
-                .is_none()
-            {
-                conf.additional_maintained_transaction_index_epoch_count =
-                    Some(0);
-            }
-            if conf.additional_maintained_reward_epoch_count.is_none() {
-                conf.additional_maintained_reward_epoch_count = Some(0);
-            }
-            if conf.additional_maintained_trace_epoch_count.is_none() {
-                conf.additional_maintained_trace_epoch_count = Some(0);
-            }
-        }
-        if conf
-            .additional_maintained_transaction_index_epoch_count
-            .is_some()
-        {
+        if conf.pruning_policy.transaction_index != RetentionPolicy::Archive {
             conf.persist_tx_index = true;
         }
+        // The retention policies required by the freezer are checked in
+        // `validate_block_data_freezer`.
+        if self.raw_conf.enable_block_data_freezer {
+            conf.freezer_dir =
+                Some(match &self.raw_conf.block_data_freezer_dir {
+                    Some(dir) => dir.into(),
+                    None => self.block_db_dir().join(FREEZER_DIR_NAME),
+                });
+        }
         conf
     }
 
+    fn pruning_policy(&self) -> PruningPolicy {
+        PruningPolicy {
+            transaction_index: self.retention_policy(
+                self.raw_conf.transaction_index_retention,
+                self.raw_conf
+                    .additional_maintained_transaction_index_epoch_count,
+            ),
+            block_body: self.retention_policy(
+                self.raw_conf.block_body_retention,
+                self.raw_conf.additional_maintained_block_body_epoch_count,
+            ),
+            execution_result: self.retention_policy(
+                self.raw_conf.execution_result_retention,
+                self.raw_conf
+                    .additional_maintained_execution_result_epoch_count,
+            ),
+            reward: self.retention_policy(
+                self.raw_conf.reward_retention,
+                self.raw_conf.additional_maintained_reward_epoch_count,
+            ),
+            trace: self.retention_policy(
+                self.raw_conf.trace_retention,
+                self.raw_conf.additional_maintained_trace_epoch_count,
+            ),
+        }
+    }
+
+    /// `retention` overrides the legacy `additional_maintained*` parameter of
+    /// the same kind of data.
+    fn retention_policy(
+        &self, retention: Option<RetentionPolicy>,
+        additional_maintained_epoch_count: Option<usize>,
+    ) -> RetentionPolicy
+    {
+        match (retention, additional_maintained_epoch_count) {
+            (Some(retention), _) => retention,
+            (None, Some(epochs)) => RetentionPolicy::KeepEpochs(epochs as u64),
+            // By default, we do not keep the block data for additional
+            // period, but `node_type = "archive"` is a shortcut for keeping
+            // all them.
+            (None, None) => {
+                if matches!(self.raw_conf.node_type, Some(NodeType::Archive)) {
+                    RetentionPolicy::Archive
+                } else {
+                    RetentionPolicy::KeepEpochs(0)
+                }
+            }
+        }
+    }
+
     pub fn sync_graph_config(&self) -> SyncGraphConfig {
         SyncGraphConfig {
             future_block_buffer_capacity: self
@@ -1009,6 +1202,7 @@ impl Configuration {
     pub fn execution_config(&self) -> ConsensusExecutionConfiguration {
         ConsensusExecutionConfiguration {
             executive_trace: self.raw_conf.executive_trace,
+            parallel_execution: self.raw_conf.parallel_execution,
         }
     }
 
@@ -1034,6 +1228,9 @@ impl Configuration {
             throttling_limit_find_nodes: self
                 .raw_conf
                 .discovery_throttling_limit_find_nodes,
+            record_ttl: Duration::from_secs(
+                self.raw_conf.discovery_record_ttl_s,
+            ),
         }
     }
 
@@ -1051,6 +1248,17 @@ impl Configuration {
         }
     }
 
+    pub fn fork_config(&self) -> Option<ForkConfiguration> {
+        let url = self.raw_conf.fork_url.clone()?;
+        if !self.is_dev_mode() {
+            panic!("fork_url is only supported in dev mode");
+        }
+        Some(ForkConfiguration {
+            url,
+            epoch: self.raw_conf.fork_epoch,
+        })
+    }
+
     pub fn is_test_or_dev_mode(&self) -> bool {
         match self.raw_conf.mode.as_ref().map(|s| s.as_str()) {
             Some("dev") | Some("test") => true,
@@ -1153,6 +1361,18 @@ impl Configuration {
             .raw_conf
             .hydra_transition_number
             .unwrap_or(default_transition_time);
+        params.transition_numbers.cip_create2_evm = self
+            .raw_conf
+            .cip_create2_evm_transition_number
+            .unwrap_or(default_transition_time);
+        params.transition_numbers.cip_core_space_reader = self
+            .raw_conf
+            .cip_core_space_reader_transition_number
+            .unwrap_or(default_transition_time);
+        params.transition_numbers.cip_epoch_history = self
+            .raw_conf
+            .cip_epoch_history_transition_number
+            .unwrap_or(default_transition_time);
 
         params.transition_heights.cip76 = self
             .raw_conf
@@ -1257,7 +1477,9 @@ pub fn parse_config_address_string(
 mod tests {
     use cfx_addr::Network;
 
-    use crate::configuration::parse_config_address_string;
+    use crate::configuration::{
+        parse_config_address_string, Configuration, NodeType,
+    };
 
     #[test]
     fn test_config_address_string() {
@@ -1294,4 +1516,16 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn test_validate_block_data_freezer() {
+        let mut config = Configuration::default();
+        config.raw_conf.enable_block_data_freezer = true;
+        config.raw_conf.node_type = Some(NodeType::Full);
+        assert!(config.validate_block_data_freezer().is_err());
+        config.raw_conf.node_type = Some(NodeType::Archive);
+        assert!(config.validate_block_data_freezer().is_ok());
+        config.raw_conf.trace_retention = Some("epochs:10000".parse().unwrap());
+        assert!(config.validate_block_data_freezer().is_err());
+    }
 }
@@ -40,7 +40,10 @@ pub use cfxcore::rpc_errors::{
 
 use self::{
     impls::{
-        cfx::{CfxHandler, LocalRpcImpl, RpcImpl, TestRpcImpl},
+        cfx::{
+            CfxHandler, DevRpcImpl, EthDevRpcImpl, LocalRpcImpl, RpcImpl,
+            TestRpcImpl,
+        },
         common::RpcImpl as CommonImpl,
         light::{
             CfxHandler as LightCfxHandler, DebugRpcImpl as LightDebugRpcImpl,
@@ -54,7 +57,8 @@ use self::{
     traits::{
         cfx::Cfx,
         debug::LocalRpc,
-        eth_space::{eth::Eth, trace::Trace as EthTrace},
+        dev::DevRpc,
+        eth_space::{dev::Dev as EthDev, eth::Eth, trace::Trace as EthTrace},
         pool::TransactionPool,
         pos::Pos,
         pubsub::PubSub,
@@ -174,7 +178,7 @@ pub fn setup_public_rpc_apis(
         pubsub,
         &conf.raw_conf.throttling_conf,
         "rpc",
-        conf.raw_conf.public_rpc_apis.list_apis(),
+        public_apis(&conf.raw_conf.public_rpc_apis, conf),
     )
 }
 
@@ -189,22 +193,35 @@ pub fn setup_public_eth_rpc_apis(
         pubsub,
         &conf.raw_conf.throttling_conf,
         "rpc",
-        conf.raw_conf.public_evm_rpc_apis.list_apis(),
+        public_apis(&conf.raw_conf.public_evm_rpc_apis, conf),
     )
 }
 
+/// The dev RPCs are only served in dev mode.
+fn public_apis(apis: &ApiSet, conf: &Configuration) -> HashSet<Api> {
+    let mut apis = apis.list_apis();
+    if !conf.is_dev_mode() && apis.remove(&Api::Dev) {
+        warn!("The dev RPC is only available in dev mode");
+    }
+    apis
+}
+
 pub fn setup_debug_rpc_apis(
     common: Arc<CommonImpl>, rpc: Arc<RpcImpl>, pubsub: PubSubClient,
     conf: &Configuration,
 ) -> MetaIoHandler<Metadata>
 {
+    let mut apis = ApiSet::All.list_apis();
+    if conf.is_dev_mode() {
+        apis.insert(Api::Dev);
+    }
     setup_rpc_apis(
         common,
         rpc,
         pubsub,
         &conf.raw_conf.throttling_conf,
         "rpc_local",
-        ApiSet::All.list_apis(),
+        apis,
     )
 }
 
@@ -292,6 +309,15 @@ fn setup_rpc_apis(
                     PoSInterceptor::new(common.pos_handler.clone());
                 handler.extend_with(RpcProxy::new(pos, pos_interceptor));
             }
+            Api::Dev => {
+                handler.extend_with(DevRpcImpl::new(rpc.clone()).to_delegate());
+                // The eSpace dev RPCs are served with the eSpace RPCs.
+                if apis.contains(&Api::Eth) {
+                    handler.extend_with(
+                        EthDevRpcImpl::new(rpc.clone()).to_delegate(),
+                    );
+                }
+            }
         }
     }
 
@@ -403,6 +429,9 @@ fn setup_rpc_apis_light(
             Api::Pos => {
                 warn!("Light nodes do not support PoS RPC");
             }
+            Api::Dev => {
+                warn!("Light nodes do not support dev RPC");
+            }
         }
     }
     handler
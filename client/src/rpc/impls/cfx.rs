@@ -4,17 +4,20 @@
 
 use crate::rpc::types::{
     call_request::rpc_call_request_network, errors::check_rpc_address_network,
-    pos::PoSEpochReward, PoSEconomics, RpcAddress, SponsorInfo,
-    TokenSupplyInfo, MAX_GAS_CALL_REQUEST,
+    pos::PoSEpochReward, AddressTransactions, DataPruningStatus, PoSEconomics,
+    RpcAddress, SponsorInfo, StorageRange, TokenSupplyInfo,
+    MAX_ADDRESS_TRANSACTIONS_RESULT, MAX_GAS_CALL_REQUEST,
+    MAX_STORAGE_RANGE_RESULT,
 };
 use blockgen::BlockGenerator;
 use cfx_state::state_trait::StateOpsTrait;
 use cfx_statedb::{StateDbExt, StateDbGetOriginalMethods};
 use cfx_types::{
-    Address, AddressSpaceUtil, BigEndianHash, Space, H256, H520, U128, U256,
-    U64,
+    Address, AddressSpaceUtil, AddressWithSpace, BigEndianHash, Space, H160,
+    H256, H520, U128, U256, U64,
 };
 use cfxcore::{
+    consensus::consensus_inner::consensus_executor::DevStateOverride,
     executive::{ExecutionError, ExecutionOutcome, TxDropError},
     rpc_errors::{account_result_to_rpc_result, invalid_params_check},
     state_exposer::STATE_EXPOSER,
@@ -27,19 +30,26 @@ use diem_types::transaction::TransactionPayload;
 use jsonrpc_core::{BoxFuture, Error as JsonRpcError, Result as JsonRpcResult};
 use network::{
     node_table::{Node, NodeId},
+    reputation::PeerScore,
     throttling, SessionDetails, UpdateNodeOperation,
 };
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use primitives::{
-    filter::LogFilter, Account, Block, BlockReceipts, DepositInfo,
-    SignedTransaction, StorageKey, StorageRoot, StorageValue, Transaction,
-    TransactionIndex, TransactionWithSignature, VoteStakeInfo,
+    filter::LogFilter, Account, Action, Block, BlockReceipts, DepositInfo,
+    Eip155Transaction, SignedTransaction, StorageKey, StorageRoot,
+    StorageValue, Transaction, TransactionIndex, TransactionWithSignature,
+    VoteStakeInfo,
 };
 use random_crash::*;
 use rlp::Rlp;
 use rustc_hex::ToHex;
 use std::{
-    collections::BTreeMap, net::SocketAddr, sync::Arc, thread, time::Duration,
+    cmp::min,
+    collections::{BTreeMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 use txgen::{DirectTransactionGenerator, TransactionGenerator};
 // To convert from RpcResult to BoxFuture by delegate! macro automatically.
@@ -54,14 +64,18 @@ use crate::{
             common::{self, RpcImpl as CommonImpl},
             RpcImplConfiguration,
         },
-        traits::{cfx::Cfx, debug::LocalRpc, test::TestRpc},
+        traits::{
+            cfx::Cfx, debug::LocalRpc, dev::DevRpc,
+            eth_space::dev::Dev as EthDev, test::TestRpc,
+        },
         types::{
-            pos::Block as PosBlock, sign_call, Account as RpcAccount,
-            AccountPendingInfo, AccountPendingTransactions, BlameInfo,
-            Block as RpcBlock, BlockHashOrEpochNumber, Bytes, CallRequest,
-            CfxRpcLogFilter, CheckBalanceAgainstTransactionResponse,
-            ConsensusGraphStates, EpochNumber,
-            EstimateGasAndCollateralResponse, Log as RpcLog, PackedOrExecuted,
+            eth::CallRequest as EthCallRequest, pos::Block as PosBlock,
+            sign_call, Account as RpcAccount, AccountPendingInfo,
+            AccountPendingTransactions, BlameInfo, Block as RpcBlock,
+            BlockHashOrEpochNumber, Bytes, CallRequest, CfxRpcLogFilter,
+            CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
+            EpochNumber, EstimateGasAndCollateralResponse, FinalityStatus,
+            GasPriceEstimates, Log as RpcLog, PackedOrExecuted,
             Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, SendTxRequest,
             Status as RpcStatus, SyncGraphStates,
             Transaction as RpcTransaction,
@@ -110,6 +124,9 @@ pub struct RpcImpl {
     maybe_txgen: Option<Arc<TransactionGenerator>>,
     maybe_direct_txgen: Option<Arc<Mutex<DirectTransactionGenerator>>>,
     accounts: Arc<AccountProvider>,
+    /// The senders whose transactions are sent without signatures in dev
+    /// mode.
+    dev_impersonated_accounts: RwLock<HashSet<AddressWithSpace>>,
 }
 
 impl RpcImpl {
@@ -130,6 +147,7 @@ impl RpcImpl {
             maybe_direct_txgen,
             config,
             accounts,
+            dev_impersonated_accounts: Default::default(),
         }
     }
 
@@ -517,9 +535,11 @@ impl RpcImpl {
         }
     }
 
-    fn prepare_transaction(
-        &self, mut tx: SendTxRequest, password: Option<String>,
-    ) -> RpcResult<TransactionWithSignature> {
+    /// Check the network of the addresses and fill the nonce if it is not
+    /// given.
+    fn complete_send_tx_request(
+        &self, tx: &mut SendTxRequest,
+    ) -> RpcResult<()> {
         let consensus_graph = self.consensus_graph();
         tx.check_rpc_address_network(
             "tx",
@@ -542,7 +562,14 @@ impl RpcImpl {
             tx.nonce.replace(nonce.into());
             debug!("after loading nonce in latest state, tx = {:?}", tx);
         }
+        Ok(())
+    }
 
+    fn prepare_transaction(
+        &self, mut tx: SendTxRequest, password: Option<String>,
+    ) -> RpcResult<TransactionWithSignature> {
+        self.complete_send_tx_request(&mut tx)?;
+        let consensus_graph = self.consensus_graph();
         let epoch_height = consensus_graph.best_epoch_number();
         let chain_id = consensus_graph.best_chain_id();
         tx.sign_with(
@@ -558,6 +585,9 @@ impl RpcImpl {
     ) -> RpcResult<H256> {
         info!("RPC Request: cfx_sendTransaction, tx = {:?}", tx);
 
+        if self.is_impersonated(&tx.from.hex_address.with_native_space()) {
+            return self.send_impersonated_transaction(tx);
+        }
         self.prepare_transaction(tx, password)
             .and_then(|tx| self.send_transaction_with_signature(tx))
     }
@@ -1088,6 +1118,43 @@ impl RpcImpl {
         Ok(logs)
     }
 
+    fn transactions_by_address(
+        &self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>,
+    ) -> RpcResult<AddressTransactions> {
+        self.check_address_network(address.network)?;
+
+        info!(
+            "RPC Request: cfx_getTransactionsByAddress address={:?}, cursor={:?}, limit={:?}",
+            address, cursor, limit
+        );
+
+        let limit = match limit {
+            Some(limit) => {
+                min(limit.as_usize(), MAX_ADDRESS_TRANSACTIONS_RESULT)
+            }
+            None => MAX_ADDRESS_TRANSACTIONS_RESULT,
+        };
+        let (entries, next_cursor) =
+            self.consensus_graph().get_transactions_by_address(
+                &address.hex_address.with_native_space(),
+                cursor.map(|cursor| cursor.as_u64()),
+                limit,
+            )?;
+
+        Ok(AddressTransactions {
+            transactions: entries.into_iter().map(Into::into).collect(),
+            next_cursor: next_cursor.map(Into::into),
+        })
+    }
+
+    fn gas_price_estimates(&self) -> RpcResult<GasPriceEstimates> {
+        info!("RPC Request: cfx_gasPriceEstimates");
+        Ok(self
+            .consensus_graph()
+            .gas_price_estimates(Space::Native)
+            .into())
+    }
+
     fn get_block_reward_info(
         &self, epoch: EpochNumber,
     ) -> RpcResult<Vec<RpcRewardInfo>> {
@@ -1579,6 +1646,289 @@ impl RpcImpl {
 
         Ok(Some(epoch_receipts))
     }
+
+    fn storage_range_at(
+        &self, address: RpcAddress, start_key: Option<Bytes>,
+        max_result: usize, epoch_num: Option<EpochNumber>,
+    ) -> RpcResult<StorageRange>
+    {
+        self.check_address_network(address.network)?;
+        let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: debug_storageRangeAt address={:?}, start_key={:?}, max_result={:?}, epoch_num={:?}",
+            address, start_key, max_result, epoch_num
+        );
+        if max_result == 0 {
+            bail!(invalid_params("max_result", "should be positive"));
+        }
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "epoch_num")?;
+
+        let address = &address.hex_address;
+        let start_key = start_key.map(|key| {
+            StorageKey::new_storage_key(address, &key.0)
+                .with_native_space()
+                .to_key_bytes()
+        });
+        let (kvs, next_key) = state_db.get_original_range(
+            StorageKey::new_storage_root_key(address).with_native_space(),
+            start_key.as_ref().map(|key| &key[..]),
+            min(max_result, MAX_STORAGE_RANGE_RESULT),
+        )?;
+
+        Ok(StorageRange::try_from(kvs, next_key)?)
+    }
+
+    fn pruning_status(&self) -> RpcResult<Vec<DataPruningStatus>> {
+        info!("RPC Request: debug_pruningStatus");
+        Ok(self
+            .consensus
+            .get_data_manager()
+            .pruning_status()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn dev_snapshot(&self) -> RpcResult<U64> {
+        info!("RPC Request: evm_snapshot");
+        Ok(self.block_gen.dev_snapshot().into())
+    }
+
+    fn dev_revert(&self, snapshot_id: U64) -> RpcResult<bool> {
+        info!("RPC Request: evm_revert snapshot_id={:?}", snapshot_id);
+        Ok(self.block_gen.dev_revert(snapshot_id.as_u64())?)
+    }
+
+    fn dev_mine(&self, timestamp: Option<u64>) -> RpcResult<H256> {
+        info!("RPC Request: evm_mine timestamp={:?}", timestamp);
+        if let Some(timestamp) = timestamp {
+            self.block_gen.dev_set_next_block_timestamp(timestamp)?;
+        }
+        Ok(self
+            .block_gen
+            .generate_dev_blocks(1, Vec::new(), Vec::new()))
+    }
+
+    fn dev_increase_time(&self, seconds: u64) -> RpcResult<u64> {
+        info!("RPC Request: evm_increaseTime seconds={}", seconds);
+        Ok(self.block_gen.dev_increase_time(seconds)?)
+    }
+
+    fn dev_set_next_block_timestamp(&self, timestamp: u64) -> RpcResult<()> {
+        info!(
+            "RPC Request: evm_setNextBlockTimestamp timestamp={}",
+            timestamp
+        );
+        Ok(self.block_gen.dev_set_next_block_timestamp(timestamp)?)
+    }
+
+    /// Generate blocks to apply `state_override`, so it is visible in the
+    /// latest state.
+    fn dev_override_state(
+        &self, state_override: DevStateOverride,
+    ) -> RpcResult<()> {
+        info!("RPC Request: dev state override {:?}", state_override);
+        self.block_gen.generate_dev_blocks(
+            DEFERRED_STATE_EPOCH_COUNT as usize,
+            Vec::new(),
+            vec![state_override],
+        );
+        Ok(())
+    }
+
+    fn dev_native_address(
+        &self, address: RpcAddress,
+    ) -> RpcResult<AddressWithSpace> {
+        self.check_address_network(address.network)?;
+        Ok(address.hex_address.with_native_space())
+    }
+
+    fn dev_set_balance(
+        &self, address: RpcAddress, balance: U256,
+    ) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Balance(
+            self.dev_native_address(address)?,
+            balance,
+        ))
+    }
+
+    fn dev_set_code(&self, address: RpcAddress, code: Bytes) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Code(
+            self.dev_native_address(address)?,
+            code.into_vec(),
+        ))
+    }
+
+    fn dev_set_storage_at(
+        &self, address: RpcAddress, position: U256, value: H256,
+    ) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Storage(
+            self.dev_native_address(address)?,
+            H256::from_uint(&position),
+            value.into_uint(),
+        ))
+    }
+
+    fn dev_set_nonce(&self, address: RpcAddress, nonce: U256) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Nonce(
+            self.dev_native_address(address)?,
+            nonce,
+        ))
+    }
+
+    fn dev_impersonate_account(&self, address: RpcAddress) -> RpcResult<()> {
+        info!("RPC Request: dev_impersonateAccount address={:?}", address);
+        let address = self.dev_native_address(address)?;
+        self.dev_impersonated_accounts.write().insert(address);
+        Ok(())
+    }
+
+    fn dev_stop_impersonating_account(
+        &self, address: RpcAddress,
+    ) -> RpcResult<()> {
+        info!(
+            "RPC Request: dev_stopImpersonatingAccount address={:?}",
+            address
+        );
+        let address = self.dev_native_address(address)?;
+        self.dev_impersonated_accounts.write().remove(&address);
+        Ok(())
+    }
+
+    fn dev_send_transaction(&self, tx: SendTxRequest) -> RpcResult<H256> {
+        info!("RPC Request: dev_sendTransaction, tx = {:?}", tx);
+        if !self.is_impersonated(&tx.from.hex_address.with_native_space()) {
+            bail!(invalid_params("tx", "The sender is not impersonated"));
+        }
+        self.send_impersonated_transaction(tx)
+    }
+
+    fn dev_eth_set_balance(
+        &self, address: H160, balance: U256,
+    ) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Balance(
+            address.with_evm_space(),
+            balance,
+        ))
+    }
+
+    fn dev_eth_set_code(&self, address: H160, code: Bytes) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Code(
+            address.with_evm_space(),
+            code.into_vec(),
+        ))
+    }
+
+    fn dev_eth_set_storage_at(
+        &self, address: H160, position: U256, value: H256,
+    ) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Storage(
+            address.with_evm_space(),
+            H256::from_uint(&position),
+            value.into_uint(),
+        ))
+    }
+
+    fn dev_eth_set_nonce(&self, address: H160, nonce: U256) -> RpcResult<()> {
+        self.dev_override_state(DevStateOverride::Nonce(
+            address.with_evm_space(),
+            nonce,
+        ))
+    }
+
+    fn dev_eth_impersonate_account(&self, address: H160) -> RpcResult<()> {
+        info!(
+            "RPC Request: hardhat_impersonateAccount address={:?}",
+            address
+        );
+        self.dev_impersonated_accounts
+            .write()
+            .insert(address.with_evm_space());
+        Ok(())
+    }
+
+    fn dev_eth_stop_impersonating_account(
+        &self, address: H160,
+    ) -> RpcResult<()> {
+        info!(
+            "RPC Request: hardhat_stopImpersonatingAccount address={:?}",
+            address
+        );
+        self.dev_impersonated_accounts
+            .write()
+            .remove(&address.with_evm_space());
+        Ok(())
+    }
+
+    fn dev_eth_send_transaction(
+        &self, request: EthCallRequest,
+    ) -> RpcResult<H256> {
+        info!("RPC Request: eth_sendTransaction request={:?}", request);
+        let from = match request.from {
+            Some(from) => from.with_evm_space(),
+            None => bail!(invalid_params("request", "The sender is missing")),
+        };
+        if !self.is_impersonated(&from) {
+            bail!(invalid_params("request", "The sender is not impersonated"));
+        }
+        let nonce = match request.nonce {
+            Some(nonce) => nonce,
+            None => self.consensus_graph().next_nonce(
+                from,
+                BlockHashOrEpochNumber::EpochNumber(EpochNumber::LatestState)
+                    .into_primitive(),
+                "internal EpochNumber::LatestState",
+            )?,
+        };
+        let tx = Eip155Transaction {
+            nonce,
+            gas_price: request.gas_price.unwrap_or(1.into()),
+            gas: request
+                .gas
+                .unwrap_or_else(|| self.tx_pool.calc_max_tx_gas()),
+            action: request.to.map_or(Action::Create, Action::Call),
+            value: request.value.unwrap_or_default(),
+            chain_id: Some(self.consensus.best_chain_id().in_evm_space()),
+            data: request.data.unwrap_or_default().into_vec(),
+        }
+        .fake_sign_impersonated(from);
+        self.pack_impersonated_transaction(tx)
+    }
+
+    fn is_impersonated(&self, address: &AddressWithSpace) -> bool {
+        self.dev_impersonated_accounts.read().contains(address)
+    }
+
+    fn send_impersonated_transaction(
+        &self, mut tx: SendTxRequest,
+    ) -> RpcResult<H256> {
+        self.complete_send_tx_request(&mut tx)?;
+        let consensus_graph = self.consensus_graph();
+        let tx = tx.fake_sign_impersonated(
+            consensus_graph.best_epoch_number(),
+            consensus_graph.best_chain_id().in_native_space(),
+        );
+        self.pack_impersonated_transaction(tx)
+    }
+
+    /// The transaction pool rejects the transactions without valid
+    /// signatures, so the transactions of impersonated senders are packed
+    /// directly into the generated blocks.
+    fn pack_impersonated_transaction(
+        &self, tx: SignedTransaction,
+    ) -> RpcResult<H256> {
+        let hash = tx.hash();
+        self.block_gen.generate_dev_blocks(
+            DEFERRED_STATE_EPOCH_COUNT as usize,
+            vec![Arc::new(tx)],
+            Vec::new(),
+        );
+        Ok(hash)
+    }
 }
 
 #[allow(dead_code)]
@@ -1606,6 +1956,7 @@ impl Cfx for CfxHandler {
                 -> BoxFuture<Option<RpcBlock>>;
             fn block_by_block_number(&self, block_number: U64, include_txs: bool) -> BoxFuture<Option<RpcBlock>>;
             fn confirmation_risk_by_hash(&self, block_hash: H256) -> JsonRpcResult<Option<U256>>;
+            fn finality_status(&self, block_hash: H256) -> JsonRpcResult<Option<FinalityStatus>>;
             fn blocks_by_epoch(&self, num: EpochNumber) -> JsonRpcResult<Vec<H256>>;
             fn skipped_blocks_by_epoch(&self, num: EpochNumber) -> JsonRpcResult<Vec<H256>>;
             fn epoch_number(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<U256>;
@@ -1645,7 +1996,9 @@ impl Cfx for CfxHandler {
                 &self, account_addr: RpcAddress, contract_addr: RpcAddress, gas_limit: U256, gas_price: U256, storage_limit: U256, epoch: Option<EpochNumber>,
             ) -> BoxFuture<CheckBalanceAgainstTransactionResponse>;
             fn get_logs(&self, filter: CfxRpcLogFilter) -> BoxFuture<Vec<RpcLog>>;
+            fn gas_price_estimates(&self) -> JsonRpcResult<GasPriceEstimates>;
             fn get_block_reward_info(&self, num: EpochNumber) -> JsonRpcResult<Vec<RpcRewardInfo>>;
+            fn transactions_by_address(&self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>) -> JsonRpcResult<AddressTransactions>;
             fn send_raw_transaction(&self, raw: Bytes) -> JsonRpcResult<H256>;
             fn storage_at(&self, addr: RpcAddress, pos: U256, epoch_number: Option<EpochNumber>)
                 -> BoxFuture<Option<H256>>;
@@ -1729,6 +2082,74 @@ impl TestRpc for TestRpcImpl {
     }
 }
 
+pub struct DevRpcImpl {
+    rpc_impl: Arc<RpcImpl>,
+}
+
+impl DevRpcImpl {
+    pub fn new(rpc_impl: Arc<RpcImpl>) -> Self { DevRpcImpl { rpc_impl } }
+}
+
+impl DevRpc for DevRpcImpl {
+    delegate! {
+        to self.rpc_impl {
+            #[call(dev_snapshot)]
+            fn snapshot(&self) -> JsonRpcResult<U64>;
+            #[call(dev_revert)]
+            fn revert(&self, snapshot_id: U64) -> JsonRpcResult<bool>;
+            #[call(dev_mine)]
+            fn mine(&self, timestamp: Option<u64>) -> JsonRpcResult<H256>;
+            #[call(dev_increase_time)]
+            fn increase_time(&self, seconds: u64) -> JsonRpcResult<u64>;
+            #[call(dev_set_next_block_timestamp)]
+            fn set_next_block_timestamp(&self, timestamp: u64) -> JsonRpcResult<()>;
+            #[call(dev_set_balance)]
+            fn set_balance(&self, address: RpcAddress, balance: U256) -> JsonRpcResult<()>;
+            #[call(dev_set_code)]
+            fn set_code(&self, address: RpcAddress, code: Bytes) -> JsonRpcResult<()>;
+            #[call(dev_set_storage_at)]
+            fn set_storage_at(&self, address: RpcAddress, position: U256, value: H256) -> JsonRpcResult<()>;
+            #[call(dev_set_nonce)]
+            fn set_nonce(&self, address: RpcAddress, nonce: U256) -> JsonRpcResult<()>;
+            #[call(dev_impersonate_account)]
+            fn impersonate_account(&self, address: RpcAddress) -> JsonRpcResult<()>;
+            #[call(dev_stop_impersonating_account)]
+            fn stop_impersonating_account(&self, address: RpcAddress) -> JsonRpcResult<()>;
+            #[call(dev_send_transaction)]
+            fn send_transaction(&self, tx: SendTxRequest) -> JsonRpcResult<H256>;
+        }
+    }
+}
+
+pub struct EthDevRpcImpl {
+    rpc_impl: Arc<RpcImpl>,
+}
+
+impl EthDevRpcImpl {
+    pub fn new(rpc_impl: Arc<RpcImpl>) -> Self { EthDevRpcImpl { rpc_impl } }
+}
+
+impl EthDev for EthDevRpcImpl {
+    delegate! {
+        to self.rpc_impl {
+            #[call(dev_eth_set_balance)]
+            fn set_balance(&self, address: H160, balance: U256) -> JsonRpcResult<()>;
+            #[call(dev_eth_set_code)]
+            fn set_code(&self, address: H160, code: Bytes) -> JsonRpcResult<()>;
+            #[call(dev_eth_set_storage_at)]
+            fn set_storage_at(&self, address: H160, position: U256, value: H256) -> JsonRpcResult<()>;
+            #[call(dev_eth_set_nonce)]
+            fn set_nonce(&self, address: H160, nonce: U256) -> JsonRpcResult<()>;
+            #[call(dev_eth_impersonate_account)]
+            fn impersonate_account(&self, address: H160) -> JsonRpcResult<()>;
+            #[call(dev_eth_stop_impersonating_account)]
+            fn stop_impersonating_account(&self, address: H160) -> JsonRpcResult<()>;
+            #[call(dev_eth_send_transaction)]
+            fn send_transaction(&self, request: EthCallRequest) -> JsonRpcResult<H256>;
+        }
+    }
+}
+
 pub struct LocalRpcImpl {
     common: Arc<CommonImpl>,
     rpc_impl: Arc<RpcImpl>,
@@ -1754,6 +2175,12 @@ impl LocalRpc for LocalRpcImpl {
                 -> JsonRpcResult<bool>;
             fn net_sessions(&self, node_id: Option<NodeId>) -> JsonRpcResult<Vec<SessionDetails>>;
             fn net_throttling(&self) -> JsonRpcResult<throttling::Service>;
+            fn net_peer_scores(&self) -> JsonRpcResult<Vec<PeerScore>>;
+            fn net_ban(&self, id: NodeId, duration: Option<u64>) -> JsonRpcResult<bool>;
+            fn net_unban(&self, id: NodeId) -> JsonRpcResult<bool>;
+            fn net_add_reserved_peer(&self, url: String) -> JsonRpcResult<()>;
+            fn net_remove_reserved_peer(&self, id: NodeId) -> JsonRpcResult<bool>;
+            fn net_set_reserved_only(&self, reserved_only: bool) -> JsonRpcResult<bool>;
             fn accounts(&self) -> JsonRpcResult<Vec<RpcAddress>>;
             fn new_account(&self, password: String) -> JsonRpcResult<RpcAddress>;
             fn unlock_account(
@@ -1773,6 +2200,8 @@ impl LocalRpc for LocalRpcImpl {
             fn send_transaction(
                 &self, tx: SendTxRequest, password: Option<String>) -> BoxFuture<H256>;
             fn sign_transaction(&self, tx: SendTxRequest, password: Option<String>) -> JsonRpcResult<String>;
+            fn storage_range_at(&self, address: RpcAddress, start_key: Option<Bytes>, max_result: usize, epoch_num: Option<EpochNumber>) -> JsonRpcResult<StorageRange>;
+            fn pruning_status(&self) -> JsonRpcResult<Vec<DataPruningStatus>>;
         }
     }
 }
@@ -15,7 +15,7 @@ use crate::rpc::{
         errors::check_rpc_address_network, pos::PoSEpochReward,
         AccountPendingInfo, AccountPendingTransactions, Block as RpcBlock,
         BlockHashOrEpochNumber, Bytes, CheckBalanceAgainstTransactionResponse,
-        EpochNumber, RpcAddress, Status as RpcStatus,
+        EpochNumber, FinalityStatus, RpcAddress, Status as RpcStatus,
         Transaction as RpcTransaction, TxPoolPendingNonceRange, TxPoolStatus,
         TxWithPoolInfo,
     },
@@ -48,15 +48,17 @@ use diem_crypto::hash::HashValue;
 use diem_types::{
     account_address::{from_consensus_public_key, AccountAddress},
     block_info::PivotBlockDecision,
+    committed_block::CommittedBlock,
     transaction::TransactionPayload,
 };
 use network::{
     node_table::{Node, NodeEndpoint, NodeEntry, NodeId},
+    reputation::PeerScore,
     throttling::{self, THROTTLING_SERVICE},
     NetworkService, SessionDetails, UpdateNodeOperation,
 };
 use primitives::{Account, Action, Block, SignedTransaction, Transaction};
-use storage_interface::DBReaderForPoW;
+use storage_interface::{DBReaderForPoW, DbReader};
 
 fn grouped_txs<T, F>(
     txs: Vec<Arc<SignedTransaction>>, converter: F,
@@ -204,10 +206,7 @@ impl RpcImpl {
     pub fn gas_price(&self) -> RpcResult<U256> {
         let consensus_graph = self.consensus_graph();
         info!("RPC Request: cfx_gasPrice()");
-        Ok(consensus_graph
-            .gas_price(Space::Native)
-            .unwrap_or(cfxcore::consensus_parameters::ONE_GDRIP_IN_DRIP.into())
-            .into())
+        Ok(consensus_graph.gas_price(Space::Native).into())
     }
 
     pub fn epoch_number(
@@ -352,6 +351,89 @@ impl RpcImpl {
         }
     }
 
+    pub fn finality_status(
+        &self, block_hash: H256,
+    ) -> RpcResult<Option<FinalityStatus>> {
+        info!(
+            "RPC Request: cfx_getFinalityStatus block_hash={:?}",
+            block_hash
+        );
+        let confirmation_risk = self.confirmation_risk_by_hash(block_hash)?;
+
+        let consensus_graph = self.consensus_graph();
+        let epoch_number =
+            match consensus_graph.get_block_epoch_number(&block_hash) {
+                Some(epoch_number) => epoch_number,
+                None => return Ok(None),
+            };
+        let pivot_hash = *consensus_graph
+            .get_block_hashes_by_epoch(
+                EpochNumber::Num(epoch_number.into()).into(),
+            )?
+            .last()
+            .ok_or("Inconsistent state: empty epoch")?;
+        let latest_confirmed = consensus_graph.get_height_from_epoch_number(
+            EpochNumber::LatestConfirmed.into(),
+        )?;
+        let latest_finalized = consensus_graph.get_height_from_epoch_number(
+            EpochNumber::LatestFinalized.into(),
+        )?;
+
+        let finalized = epoch_number <= latest_finalized;
+        let finalized_by =
+            if finalized && self.pos_handler.pos_option().is_some() {
+                self.first_pos_block_finalizing(epoch_number)
+            } else {
+                None
+            };
+
+        Ok(Some(FinalityStatus {
+            epoch_number: epoch_number.into(),
+            pivot_hash,
+            confirmation_risk,
+            confirmed: epoch_number <= latest_confirmed,
+            finalized,
+            finalized_by: finalized_by
+                .as_ref()
+                .map(|block| hash_value_to_h256(block.hash)),
+            pivot_decision: finalized_by
+                .as_ref()
+                .map(|block| Decision::from(&block.pivot_decision)),
+        }))
+    }
+
+    /// Return the first committed PoS block whose pivot decision is not lower
+    /// than `epoch_number`. The heights of the pivot decisions are
+    /// non-decreasing in view, so it's found by binary search.
+    fn first_pos_block_finalizing(
+        &self, epoch_number: u64,
+    ) -> Option<CommittedBlock> {
+        let pos_ledger_db = self.pos_handler.pos_ledger_db();
+        let committed_block = |view| {
+            let hash =
+                pos_ledger_db.get_committed_block_hash_by_view(view).ok()?;
+            pos_ledger_db.get_committed_block_by_hash(&hash).ok()
+        };
+
+        let (mut low, mut high) =
+            (1, pos_ledger_db.get_latest_pos_state().current_view() + 1);
+        let mut found = None;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            // Skipped views have no committed blocks, so take the first
+            // committed block from `mid`.
+            match (mid..high).find_map(&committed_block) {
+                Some(block) if block.pivot_decision.height >= epoch_number => {
+                    high = mid;
+                    found = Some(block);
+                }
+                Some(block) => low = block.view + 1,
+                None => high = mid,
+            }
+        }
+        found
+    }
+
     pub fn block_by_hash(
         &self, hash: H256, include_txs: bool,
     ) -> RpcResult<Option<RpcBlock>> {
@@ -964,6 +1046,41 @@ impl RpcImpl {
         Ok(THROTTLING_SERVICE.read().clone())
     }
 
+    pub fn net_peer_scores(&self) -> JsonRpcResult<Vec<PeerScore>> {
+        Ok(self.network.get_peer_scores())
+    }
+
+    pub fn net_ban(
+        &self, id: NodeId, duration: Option<u64>,
+    ) -> JsonRpcResult<bool> {
+        Ok(self
+            .network
+            .ban_node(&id, duration.map(Duration::from_secs)))
+    }
+
+    pub fn net_unban(&self, id: NodeId) -> JsonRpcResult<bool> {
+        Ok(self.network.unban_node(&id))
+    }
+
+    pub fn net_add_reserved_peer(&self, url: String) -> JsonRpcResult<()> {
+        info!("RPC Request: net_addReservedPeer({})", url);
+        self.network
+            .add_reserved_peer(&url)
+            .map_err(|e| RpcError::invalid_params(format!("{}", e)))
+    }
+
+    pub fn net_remove_reserved_peer(&self, id: NodeId) -> JsonRpcResult<bool> {
+        info!("RPC Request: net_removeReservedPeer({:?})", id);
+        Ok(self.network.remove_reserved_peer(&id))
+    }
+
+    pub fn net_set_reserved_only(
+        &self, reserved_only: bool,
+    ) -> JsonRpcResult<bool> {
+        info!("RPC Request: net_setReservedOnly({})", reserved_only);
+        Ok(self.network.set_reserved_only(reserved_only))
+    }
+
     // MARK: Conflux space rpc supports EVM space transaction
     pub fn txpool_tx_with_pool_info(
         &self, hash: H256,
@@ -14,10 +14,12 @@ use crate::rpc::{
             Block as RpcBlock, BlockNumber, CallRequest, EthRpcLogFilter,
             FilterChanges, Log, Receipt, SyncInfo, SyncStatus, Transaction,
         },
-        Bytes, Index, MAX_GAS_CALL_REQUEST,
+        AddressTransactions, Bytes, Index, StorageRange,
+        MAX_ADDRESS_TRANSACTIONS_RESULT, MAX_GAS_CALL_REQUEST,
+        MAX_STORAGE_RANGE_RESULT,
     },
 };
-use cfx_statedb::StateDbExt;
+use cfx_statedb::{StateDbExt, StateDbGetOriginalMethods};
 use cfx_types::{
     Address, AddressSpaceUtil, BigEndianHash, Space, H160, H256, U256, U64,
 };
@@ -103,7 +105,7 @@ fn block_tx_by_index(
                 let receipt = pb.receipts.get(idx).unwrap();
                 let status = receipt.outcome_status.in_space(Space::Ethereum);
                 let contract_address = match status == EVM_SPACE_SUCCESS {
-                    true => Transaction::deployed_contract_address(&tx),
+                    true => pb.contract_addresses[idx],
                     false => None,
                 };
                 Some(Transaction::from_signed(
@@ -199,6 +201,12 @@ impl EthHandler {
             ));
         }
 
+        if b.transactions.len() != b.contract_addresses.len() {
+            return Err(internal_error(
+                "Inconsistent state: transactions and contract addresses mismatch",
+            ));
+        }
+
         if idx >= b.transactions.len() {
             return Err(internal_error(
                 "Inconsistent state: tx index out of bound",
@@ -215,9 +223,7 @@ impl EthHandler {
         }
 
         let contract_address = match receipt.outcome_status {
-            TransactionOutcome::Success => {
-                Transaction::deployed_contract_address(tx)
-            }
+            TransactionOutcome::Success => b.contract_addresses[idx],
             _ => None,
         };
 
@@ -363,10 +369,7 @@ impl Eth for EthHandler {
 
     fn gas_price(&self) -> jsonrpc_core::Result<U256> {
         info!("RPC Request: eth_gasPrice");
-        Ok(self
-            .consensus_graph()
-            .gas_price(Space::Ethereum)
-            .unwrap_or(5000000000u64.into()))
+        Ok(self.consensus_graph().gas_price(Space::Ethereum))
     }
 
     fn max_priority_fee_per_gas(&self) -> jsonrpc_core::Result<U256> {
@@ -1065,6 +1068,72 @@ impl Eth for EthHandler {
 
         Ok(block_receipts)
     }
+
+    fn storage_range_at(
+        &self, address: H160, start_key: Option<Bytes>, max_result: usize,
+        block_num: Option<BlockNumber>,
+    ) -> jsonrpc_core::Result<StorageRange>
+    {
+        let epoch_num = block_num.unwrap_or_default().try_into()?;
+
+        info!(
+            "RPC Request: eth_getStorageRange address={:?}, start_key={:?}, max_result={:?}, block_num={:?}",
+            address, start_key, max_result, epoch_num
+        );
+        if max_result == 0 {
+            return Err(RpcError::invalid_params(
+                "max_result should be positive",
+            ));
+        }
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "epoch_number")?;
+
+        let start_key = start_key.map(|key| {
+            StorageKey::new_storage_key(&address, &key.0)
+                .with_evm_space()
+                .to_key_bytes()
+        });
+        let (kvs, next_key) = state_db
+            .get_original_range(
+                StorageKey::new_storage_root_key(&address).with_evm_space(),
+                start_key.as_ref().map(|key| &key[..]),
+                min(max_result, MAX_STORAGE_RANGE_RESULT),
+            )
+            .map_err(|err| CfxRpcError::from(err))?;
+
+        StorageRange::try_from(kvs, next_key).map_err(RpcError::invalid_params)
+    }
+
+    fn transactions_by_address(
+        &self, address: H160, cursor: Option<U64>, limit: Option<U64>,
+    ) -> jsonrpc_core::Result<AddressTransactions> {
+        info!(
+            "RPC Request: eth_getTransactionsByAddress address={:?}, cursor={:?}, limit={:?}",
+            address, cursor, limit
+        );
+
+        let limit = match limit {
+            Some(limit) => {
+                min(limit.as_usize(), MAX_ADDRESS_TRANSACTIONS_RESULT)
+            }
+            None => MAX_ADDRESS_TRANSACTIONS_RESULT,
+        };
+        let (entries, next_cursor) = self
+            .consensus_graph()
+            .get_transactions_by_address(
+                &address.with_evm_space(),
+                cursor.map(|cursor| cursor.as_u64()),
+                limit,
+            )
+            .map_err(CfxRpcError::from)?;
+
+        Ok(AddressTransactions {
+            transactions: entries.into_iter().map(Into::into).collect(),
+            next_cursor: next_cursor.map(Into::into),
+        })
+    }
 }
 
 impl EthFilter for EthHandler {
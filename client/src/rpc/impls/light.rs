@@ -22,6 +22,7 @@ use futures::future::{self, FutureExt, TryFutureExt};
 use jsonrpc_core::{BoxFuture, Error as RpcError, Result as JsonRpcResult};
 use network::{
     node_table::{Node, NodeId},
+    reputation::PeerScore,
     throttling, SessionDetails, UpdateNodeOperation,
 };
 use primitives::{
@@ -42,12 +43,14 @@ use crate::{
         types::{
             pos::{Block as PosBlock, PoSEpochReward},
             Account as RpcAccount, AccountPendingInfo,
-            AccountPendingTransactions, BlameInfo, Block as RpcBlock,
-            BlockHashOrEpochNumber, Bytes, CallRequest, CfxRpcLogFilter,
-            CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
-            EpochNumber, EstimateGasAndCollateralResponse, Log as RpcLog,
-            PoSEconomics, Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
-            RpcAddress, SendTxRequest, SponsorInfo, Status as RpcStatus,
+            AccountPendingTransactions, AddressTransactions, BlameInfo,
+            Block as RpcBlock, BlockHashOrEpochNumber, Bytes, CallRequest,
+            CfxRpcLogFilter, CheckBalanceAgainstTransactionResponse,
+            ConsensusGraphStates, DataPruningStatus, EpochNumber,
+            EstimateGasAndCollateralResponse, FinalityStatus,
+            GasPriceEstimates, Log as RpcLog, PoSEconomics,
+            Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, RpcAddress,
+            SendTxRequest, SponsorInfo, Status as RpcStatus, StorageRange,
             SyncGraphStates, TokenSupplyInfo, Transaction as RpcTransaction,
         },
         RpcBoxFuture, RpcResult,
@@ -1072,6 +1075,7 @@ impl Cfx for CfxHandler {
         to self.common {
             fn best_block_hash(&self) -> JsonRpcResult<H256>;
             fn confirmation_risk_by_hash(&self, block_hash: H256) -> JsonRpcResult<Option<U256>>;
+            fn finality_status(&self, block_hash: H256) -> JsonRpcResult<Option<FinalityStatus>>;
             fn get_client_version(&self) -> JsonRpcResult<String>;
             fn get_status(&self) -> JsonRpcResult<RpcStatus>;
             fn skipped_blocks_by_epoch(&self, num: EpochNumber) -> JsonRpcResult<Vec<H256>>;
@@ -1114,9 +1118,11 @@ impl Cfx for CfxHandler {
         fn block_by_block_number(&self, block_number: U64, include_txs: bool) -> BoxFuture<Option<RpcBlock>>;
         fn call(&self, request: CallRequest, epoch: Option<EpochNumber>) -> JsonRpcResult<Bytes>;
         fn estimate_gas_and_collateral(&self, request: CallRequest, epoch_num: Option<EpochNumber>) -> JsonRpcResult<EstimateGasAndCollateralResponse>;
+        fn gas_price_estimates(&self) -> JsonRpcResult<GasPriceEstimates>;
         fn get_block_reward_info(&self, num: EpochNumber) -> JsonRpcResult<Vec<RpcRewardInfo>>;
         fn get_supply_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<TokenSupplyInfo>;
         fn get_pos_reward_by_epoch(&self, epoch: EpochNumber) -> JsonRpcResult<Option<PoSEpochReward>>;
+        fn transactions_by_address(&self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>) -> JsonRpcResult<AddressTransactions>;
     }
 }
 
@@ -1208,6 +1214,12 @@ impl LocalRpc for DebugRpcImpl {
             fn net_node(&self, id: NodeId) -> JsonRpcResult<Option<(String, Node)>>;
             fn net_sessions(&self, node_id: Option<NodeId>) -> JsonRpcResult<Vec<SessionDetails>>;
             fn net_throttling(&self) -> JsonRpcResult<throttling::Service>;
+            fn net_peer_scores(&self) -> JsonRpcResult<Vec<PeerScore>>;
+            fn net_ban(&self, id: NodeId, duration: Option<u64>) -> JsonRpcResult<bool>;
+            fn net_unban(&self, id: NodeId) -> JsonRpcResult<bool>;
+            fn net_add_reserved_peer(&self, url: String) -> JsonRpcResult<()>;
+            fn net_remove_reserved_peer(&self, id: NodeId) -> JsonRpcResult<bool>;
+            fn net_set_reserved_only(&self, reserved_only: bool) -> JsonRpcResult<bool>;
             fn new_account(&self, password: String) -> JsonRpcResult<RpcAddress>;
             fn sign(&self, data: Bytes, address: RpcAddress, password: Option<String>) -> JsonRpcResult<H520>;
             fn unlock_account(&self, address: RpcAddress, password: String, duration: Option<U128>) -> JsonRpcResult<bool>;
@@ -1223,6 +1235,8 @@ impl LocalRpc for DebugRpcImpl {
         fn current_sync_phase(&self) -> JsonRpcResult<String>;
         fn epoch_receipts(&self, epoch: BlockHashOrEpochNumber) -> JsonRpcResult<Option<Vec<Vec<RpcReceipt>>>>;
         fn sign_transaction(&self, tx: SendTxRequest, password: Option<String>) -> JsonRpcResult<String>;
+        fn storage_range_at(&self, address: RpcAddress, start_key: Option<Bytes>, max_result: usize, epoch_number: Option<EpochNumber>) -> JsonRpcResult<StorageRange>;
+        fn pruning_status(&self) -> JsonRpcResult<Vec<DataPruningStatus>>;
         fn sync_graph_state(&self) -> JsonRpcResult<SyncGraphStates>;
     }
 }
@@ -18,6 +18,8 @@ pub enum Api {
     Trace,
     TxPool,
     Pos,
+    /// The chain control for contract tests, only available in dev mode.
+    Dev,
 }
 
 impl FromStr for Api {
@@ -34,6 +36,7 @@ impl FromStr for Api {
             "trace" => Ok(Trace),
             "txpool" => Ok(TxPool),
             "pos" => Ok(Pos),
+            "dev" => Ok(Dev),
             _ => Err("Unknown api type".into()),
         }
     }
@@ -50,6 +53,7 @@ impl Display for Api {
             Api::Trace => write!(f, "trace"),
             Api::TxPool => write!(f, "txpool"),
             Api::Pos => write!(f, "pos"),
+            Api::Dev => write!(f, "dev"),
         }
     }
 }
@@ -4,11 +4,13 @@
 
 use crate::rpc::types::{
     pos::PoSEpochReward, Account as RpcAccount, AccountPendingInfo,
-    AccountPendingTransactions, Block, BlockHashOrEpochNumber, Bytes,
-    CallRequest, CfxRpcLogFilter, CheckBalanceAgainstTransactionResponse,
-    EpochNumber, EstimateGasAndCollateralResponse, Log as RpcLog, PoSEconomics,
-    Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, RpcAddress,
-    SponsorInfo, Status as RpcStatus, TokenSupplyInfo, Transaction,
+    AccountPendingTransactions, AddressTransactions, Block,
+    BlockHashOrEpochNumber, Bytes, CallRequest, CfxRpcLogFilter,
+    CheckBalanceAgainstTransactionResponse, EpochNumber,
+    EstimateGasAndCollateralResponse, FinalityStatus, GasPriceEstimates,
+    Log as RpcLog, PoSEconomics, Receipt as RpcReceipt,
+    RewardInfo as RpcRewardInfo, RpcAddress, SponsorInfo, Status as RpcStatus,
+    TokenSupplyInfo, Transaction,
 };
 use cfx_types::{H256, U256, U64};
 use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
@@ -38,6 +40,11 @@ pub trait Cfx {
     #[rpc(name = "cfx_gasPrice")]
     fn gas_price(&self) -> BoxFuture<U256>;
 
+    /// Returns the gas prices suggested for a transaction to be packed within
+    /// the slow, normal, and fast targets.
+    #[rpc(name = "cfx_gasPriceEstimates")]
+    fn gas_price_estimates(&self) -> JsonRpcResult<GasPriceEstimates>;
+
     /// Returns highest epoch number.
     #[rpc(name = "cfx_epochNumber")]
     fn epoch_number(
@@ -185,6 +192,13 @@ pub trait Cfx {
         maybe_limit: Option<U64>,
     ) -> BoxFuture<AccountPendingTransactions>;
 
+    /// Returns the executed transactions sent or received by an address, from
+    /// the newest to the oldest. Requires `persist_address_tx_index`.
+    #[rpc(name = "cfx_getTransactionsByAddress")]
+    fn transactions_by_address(
+        &self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>,
+    ) -> JsonRpcResult<AddressTransactions>;
+
     /// Return estimated gas and collateral usage.
     #[rpc(name = "cfx_estimateGasAndCollateral")]
     fn estimate_gas_and_collateral(
@@ -243,6 +257,13 @@ pub trait Cfx {
         &self, block_hash: H256,
     ) -> JsonRpcResult<Option<U256>>;
 
+    /// Returns the confirmation risk, the PoS finality and the PoS pivot
+    /// decision that finalized the block.
+    #[rpc(name = "cfx_getFinalityStatus")]
+    fn finality_status(
+        &self, block_hash: H256,
+    ) -> JsonRpcResult<Option<FinalityStatus>>;
+
     #[rpc(name = "cfx_getStatus")]
     fn get_status(&self) -> JsonRpcResult<RpcStatus>;
 
@@ -4,7 +4,8 @@
 
 use crate::rpc::types::{
     BlockHashOrEpochNumber, Bytes as RpcBytes, ConsensusGraphStates,
-    Receipt as RpcReceipt, RpcAddress, SendTxRequest, SyncGraphStates,
+    DataPruningStatus, EpochNumber, Receipt as RpcReceipt, RpcAddress,
+    SendTxRequest, StorageRange, SyncGraphStates,
     Transaction as RpcTransaction,
 };
 use cfx_types::{H256, H520, U128};
@@ -12,6 +13,7 @@ use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
 use jsonrpc_derive::rpc;
 use network::{
     node_table::{Node, NodeId},
+    reputation::PeerScore,
     throttling, SessionDetails, UpdateNodeOperation,
 };
 use std::collections::BTreeMap;
@@ -63,6 +65,35 @@ pub trait LocalRpc {
         &self, node_id: Option<NodeId>,
     ) -> JsonRpcResult<Vec<SessionDetails>>;
 
+    /// Returns the reputation scores of peers, including the banned ones.
+    #[rpc(name = "net_peerScores")]
+    fn net_peer_scores(&self) -> JsonRpcResult<Vec<PeerScore>>;
+
+    /// Bans the node for `duration` seconds, or the configured
+    /// `peer_ban_duration_s` if not specified, and disconnects it.
+    #[rpc(name = "net_ban")]
+    fn net_ban(&self, id: NodeId, duration: Option<u64>)
+        -> JsonRpcResult<bool>;
+
+    /// Lifts the ban of the node. Returns false if not banned.
+    #[rpc(name = "net_unban")]
+    fn net_unban(&self, id: NodeId) -> JsonRpcResult<bool>;
+
+    /// Adds a reserved peer with node URL, e.g. cfxnode://<id>@<ip>:<port>,
+    /// which is always connected.
+    #[rpc(name = "net_addReservedPeer")]
+    fn net_add_reserved_peer(&self, url: String) -> JsonRpcResult<()>;
+
+    /// Removes a reserved peer. Returns false if not reserved.
+    #[rpc(name = "net_removeReservedPeer")]
+    fn net_remove_reserved_peer(&self, id: NodeId) -> JsonRpcResult<bool>;
+
+    /// Enables or disables the reserved-only mode, in which only the reserved
+    /// peers are connected.
+    #[rpc(name = "net_setReservedOnly")]
+    fn net_set_reserved_only(&self, reserved_only: bool)
+        -> JsonRpcResult<bool>;
+
     #[rpc(name = "current_sync_phase")]
     fn current_sync_phase(&self) -> JsonRpcResult<String>;
 
@@ -109,4 +140,18 @@ pub trait LocalRpc {
     fn epoch_receipts(
         &self, epoch: BlockHashOrEpochNumber,
     ) -> JsonRpcResult<Option<Vec<Vec<RpcReceipt>>>>;
+
+    /// Returns at most `max_result` storage entries of the given contract at
+    /// the given epoch, starting from the storage slot `start_key`.
+    /// `max_result` should be positive and is capped at 1024.
+    #[rpc(name = "debug_storageRangeAt")]
+    fn storage_range_at(
+        &self, address: RpcAddress, start_key: Option<RpcBytes>,
+        max_result: usize, epoch_number: Option<EpochNumber>,
+    ) -> JsonRpcResult<StorageRange>;
+
+    /// Returns the retention policy and garbage collection progress of each
+    /// kind of block data.
+    #[rpc(name = "debug_pruningStatus")]
+    fn pruning_status(&self) -> JsonRpcResult<Vec<DataPruningStatus>>;
 }
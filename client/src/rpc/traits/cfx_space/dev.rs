@@ -0,0 +1,63 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{Bytes, RpcAddress, SendTxRequest};
+use cfx_types::{H256, U256, U64};
+use jsonrpc_core::Result as RpcResult;
+use jsonrpc_derive::rpc;
+
+/// Chain control for contract tests in dev mode. The account changes are
+/// applied by generating blocks, so they are visible in the latest state
+/// when the methods return. The eSpace counterparts are in
+/// `eth_space::dev::Dev`.
+#[rpc(server)]
+pub trait DevRpc {
+    /// Records the pivot chain tip and returns the snapshot id.
+    #[rpc(name = "evm_snapshot")]
+    fn snapshot(&self) -> RpcResult<U64>;
+
+    /// Reverts the chain to a snapshot. The snapshot and the ones taken after
+    /// it are removed. Returns `false` if the snapshot does not exist.
+    #[rpc(name = "evm_revert")]
+    fn revert(&self, snapshot_id: U64) -> RpcResult<bool>;
+
+    /// Generates a block, optionally with the given timestamp.
+    #[rpc(name = "evm_mine")]
+    fn mine(&self, timestamp: Option<u64>) -> RpcResult<H256>;
+
+    /// Moves the time of the later blocks forward and returns the total
+    /// seconds added to the system time.
+    #[rpc(name = "evm_increaseTime")]
+    fn increase_time(&self, seconds: u64) -> RpcResult<u64>;
+
+    #[rpc(name = "evm_setNextBlockTimestamp")]
+    fn set_next_block_timestamp(&self, timestamp: u64) -> RpcResult<()>;
+
+    #[rpc(name = "dev_setBalance")]
+    fn set_balance(&self, address: RpcAddress, balance: U256) -> RpcResult<()>;
+
+    #[rpc(name = "dev_setCode")]
+    fn set_code(&self, address: RpcAddress, code: Bytes) -> RpcResult<()>;
+
+    /// Sets a storage entry. The storage collateral is paid by the contract.
+    #[rpc(name = "dev_setStorageAt")]
+    fn set_storage_at(
+        &self, address: RpcAddress, position: U256, value: H256,
+    ) -> RpcResult<()>;
+
+    #[rpc(name = "dev_setNonce")]
+    fn set_nonce(&self, address: RpcAddress, nonce: U256) -> RpcResult<()>;
+
+    /// Allows `cfx_sendTransaction` and `dev_sendTransaction` to send
+    /// transactions from `address` without its key.
+    #[rpc(name = "dev_impersonateAccount")]
+    fn impersonate_account(&self, address: RpcAddress) -> RpcResult<()>;
+
+    #[rpc(name = "dev_stopImpersonatingAccount")]
+    fn stop_impersonating_account(&self, address: RpcAddress) -> RpcResult<()>;
+
+    /// Sends a transaction from an impersonated account.
+    #[rpc(name = "dev_sendTransaction")]
+    fn send_transaction(&self, tx: SendTxRequest) -> RpcResult<H256>;
+}
@@ -1,5 +1,6 @@
 pub mod cfx;
 pub mod debug;
+pub mod dev;
 pub mod pool;
 pub mod pos;
 pub mod pubsub;
@@ -0,0 +1,40 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::{eth::CallRequest, Bytes};
+use cfx_types::{H160, H256, U256};
+use jsonrpc_core::Result as JsonRpcResult;
+use jsonrpc_derive::rpc;
+
+/// Account overrides and impersonation of eSpace for contract tests in dev
+/// mode, with the method names of Hardhat. They are served with the eSpace
+/// RPCs if the dev RPC is enabled.
+#[rpc(server)]
+pub trait Dev {
+    #[rpc(name = "hardhat_setBalance")]
+    fn set_balance(&self, address: H160, balance: U256) -> JsonRpcResult<()>;
+
+    #[rpc(name = "hardhat_setCode")]
+    fn set_code(&self, address: H160, code: Bytes) -> JsonRpcResult<()>;
+
+    #[rpc(name = "hardhat_setStorageAt")]
+    fn set_storage_at(
+        &self, address: H160, position: U256, value: H256,
+    ) -> JsonRpcResult<()>;
+
+    #[rpc(name = "hardhat_setNonce")]
+    fn set_nonce(&self, address: H160, nonce: U256) -> JsonRpcResult<()>;
+
+    /// Allows `eth_sendTransaction` to send transactions from `address`
+    /// without its key.
+    #[rpc(name = "hardhat_impersonateAccount")]
+    fn impersonate_account(&self, address: H160) -> JsonRpcResult<()>;
+
+    #[rpc(name = "hardhat_stopImpersonatingAccount")]
+    fn stop_impersonating_account(&self, address: H160) -> JsonRpcResult<()>;
+
+    /// Sends a transaction from an impersonated account.
+    #[rpc(name = "eth_sendTransaction")]
+    fn send_transaction(&self, request: CallRequest) -> JsonRpcResult<H256>;
+}
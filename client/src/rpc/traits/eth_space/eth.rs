@@ -28,7 +28,7 @@ use crate::rpc::types::{
         Block, BlockNumber, CallRequest, EthRpcLogFilter, FilterChanges, Log,
         Receipt, SyncStatus, Transaction,
     },
-    Bytes, Index,
+    AddressTransactions, Bytes, Index, StorageRange,
 };
 
 /// Eth rpc interface.
@@ -230,6 +230,21 @@ pub trait Eth {
 
     #[rpc(name = "parity_getBlockReceipts")]
     fn block_receipts(&self, _: Option<BlockNumber>) -> Result<Vec<Receipt>>;
+
+    /// Returns at most `max_result` storage entries of the given contract at
+    /// the given block, starting from the storage slot `start_key`.
+    /// `max_result` should be positive and is capped at 1024.
+    #[rpc(name = "eth_getStorageRange")]
+    fn storage_range_at(
+        &self, _: H160, _: Option<Bytes>, _: usize, _: Option<BlockNumber>,
+    ) -> Result<StorageRange>;
+
+    /// Returns the executed transactions sent or received by an address, from
+    /// the newest to the oldest. Requires `persist_address_tx_index`.
+    #[rpc(name = "eth_getTransactionsByAddress")]
+    fn transactions_by_address(
+        &self, _: H160, _: Option<U64>, _: Option<U64>,
+    ) -> Result<AddressTransactions>;
 }
 
 /// Eth filters rpc api (polling).
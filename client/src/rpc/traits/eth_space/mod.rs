@@ -1,2 +1,3 @@
+pub mod dev;
 pub mod eth;
 pub mod trace;
@@ -4,6 +4,7 @@
 
 mod account;
 pub mod address;
+mod address_transactions;
 mod blame_info;
 mod block;
 mod bytes;
@@ -13,16 +14,20 @@ mod epoch_number;
 pub mod errors;
 pub mod eth;
 mod filter;
+mod finality_status;
+mod gas_price_estimates;
 mod index;
 mod log;
 pub mod pos;
 mod pos_economics;
 mod provenance;
+mod pruning_status;
 pub mod pubsub;
 mod receipt;
 mod reward_info;
 mod sponsor_info;
 mod status;
+mod storage_range;
 mod sync_graph_states;
 mod token_supply_info;
 mod trace;
@@ -33,6 +38,9 @@ mod tx_pool;
 pub use self::{
     account::Account,
     address::RpcAddress,
+    address_transactions::{
+        AddressTransactions, MAX_ADDRESS_TRANSACTIONS_RESULT,
+    },
     blame_info::BlameInfo,
     block::{Block, BlockTransactions, Header},
     bytes::Bytes,
@@ -43,14 +51,18 @@ pub use self::{
     consensus_graph_states::ConsensusGraphStates,
     epoch_number::{BlockHashOrEpochNumber, EpochNumber},
     filter::CfxRpcLogFilter,
+    finality_status::FinalityStatus,
+    gas_price_estimates::GasPriceEstimates,
     index::Index,
     log::Log,
     pos_economics::PoSEconomics,
     provenance::Origin,
+    pruning_status::DataPruningStatus,
     receipt::Receipt,
     reward_info::RewardInfo,
     sponsor_info::SponsorInfo,
     status::Status,
+    storage_range::{StorageRange, MAX_STORAGE_RANGE_RESULT},
     sync_graph_states::SyncGraphStates,
     token_supply_info::TokenSupplyInfo,
     trace::{
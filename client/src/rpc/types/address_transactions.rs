@@ -0,0 +1,62 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_types::{H256, U64};
+use cfxcore::block_data_manager::address_tx_index::{
+    AddressTxIndexEntry, AddressTxRelation,
+};
+
+/// The maximum number of transactions returned by one
+/// `cfx_getTransactionsByAddress` request.
+pub const MAX_ADDRESS_TRANSACTIONS_RESULT: usize = 1000;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum AddressTransactionRelation {
+    Sender,
+    Receiver,
+    InternalTransfer,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransaction {
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    pub epoch_number: U64,
+    /// The index of the transaction in the block.
+    pub transaction_index: U64,
+    pub relation: AddressTransactionRelation,
+}
+
+impl From<AddressTxIndexEntry> for AddressTransaction {
+    fn from(entry: AddressTxIndexEntry) -> Self {
+        AddressTransaction {
+            transaction_hash: entry.transaction_hash,
+            block_hash: entry.block_hash,
+            epoch_number: entry.epoch_number.into(),
+            transaction_index: entry.transaction_index.into(),
+            relation: match entry.relation {
+                AddressTxRelation::Sender => AddressTransactionRelation::Sender,
+                AddressTxRelation::Receiver => {
+                    AddressTransactionRelation::Receiver
+                }
+                AddressTxRelation::InternalTransfer => {
+                    AddressTransactionRelation::InternalTransfer
+                }
+            },
+        }
+    }
+}
+
+/// A page of the transaction history of an address, from the newest to the
+/// oldest.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransactions {
+    pub transactions: Vec<AddressTransaction>,
+    /// The cursor to start the next page from, `None` if there are no more
+    /// transactions.
+    pub next_cursor: Option<U64>,
+}
@@ -106,7 +106,40 @@ impl SendTxRequest {
         accounts: Arc<AccountProvider>,
     ) -> RpcResult<TransactionWithSignature>
     {
-        let tx = PrimitiveTransaction {
+        let from = self.from.clone();
+        let tx = self.into_unsigned(best_epoch_height, chain_id);
+
+        if tx.epoch_height == u64::MAX {
+            return Err("Can not sign Ethereum like transaction by RPC.".into());
+        }
+
+        let password = password.map(Password::from);
+        let sig = accounts
+            .sign(
+                from.into(),
+                password,
+                Transaction::from(tx.clone()).signature_hash(),
+            )
+            // TODO: sign error into secret store error codes.
+            .map_err(|e| format!("failed to sign transaction: {:?}", e))?;
+
+        Ok(Transaction::from(tx).with_signature(sig))
+    }
+
+    /// Sign the transaction with a fake signature for an impersonated sender
+    /// in dev mode.
+    pub fn fake_sign_impersonated(
+        self, best_epoch_height: u64, chain_id: u32,
+    ) -> SignedTransaction {
+        let from = self.from.hex_address.with_native_space();
+        self.into_unsigned(best_epoch_height, chain_id)
+            .fake_sign_impersonated(from)
+    }
+
+    fn into_unsigned(
+        self, best_epoch_height: u64, chain_id: u32,
+    ) -> PrimitiveTransaction {
+        PrimitiveTransaction {
             nonce: self.nonce.unwrap_or_default().into(),
             gas_price: self.gas_price.into(),
             gas: self.gas.into(),
@@ -123,23 +156,7 @@ impl SendTxRequest {
                 .as_usize() as u64,
             chain_id: self.chain_id.unwrap_or(chain_id.into()).as_u32(),
             data: self.data.unwrap_or(Bytes::new(vec![])).into(),
-        };
-
-        if tx.epoch_height == u64::MAX {
-            return Err("Can not sign Ethereum like transaction by RPC.".into());
         }
-
-        let password = password.map(Password::from);
-        let sig = accounts
-            .sign(
-                self.from.into(),
-                password,
-                Transaction::from(tx.clone()).signature_hash(),
-            )
-            // TODO: sign error into secret store error codes.
-            .map_err(|e| format!("failed to sign transaction: {:?}", e))?;
-
-        Ok(Transaction::from(tx).with_signature(sig))
     }
 }
 
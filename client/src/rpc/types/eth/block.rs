@@ -155,7 +155,7 @@ impl Block {
                             .outcome_status
                             .in_space(Space::Ethereum);
 
-                        let contract_address = match Transaction::deployed_contract_address(&**t) {
+                        let contract_address = match pb.contract_addresses[idx] {
                             Some(a) if status == EVM_SPACE_SUCCESS => Some(a),
                             _ => None,
                         };
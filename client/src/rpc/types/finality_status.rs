@@ -0,0 +1,30 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::pos::Decision;
+use cfx_types::{H256, U256, U64};
+
+/// How final a block is, by the confirmation meter and by PoS.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalityStatus {
+    /// The epoch the block is executed in, which is also the eSpace block
+    /// number.
+    pub epoch_number: U64,
+    /// The pivot block of the epoch.
+    pub pivot_hash: H256,
+    /// The confirmation risk of the block, the same as
+    /// `cfx_getConfirmationRiskByHash`.
+    pub confirmation_risk: Option<U256>,
+    /// If the epoch is no later than the latest confirmed epoch, i.e., the
+    /// `latest_confirmed` epoch tag and the `safe` block tag.
+    pub confirmed: bool,
+    /// If the epoch is no later than the latest PoS-finalized epoch, i.e., the
+    /// `latest_finalized` epoch tag and the `finalized` block tag.
+    pub finalized: bool,
+    /// The first committed PoS block whose pivot decision covers the epoch.
+    pub finalized_by: Option<H256>,
+    /// The pivot decision of `finalized_by`.
+    pub pivot_decision: Option<Decision>,
+}
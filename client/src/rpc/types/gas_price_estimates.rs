@@ -0,0 +1,26 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_types::U256;
+use cfxcore::consensus::gas_price_oracle::GasPriceEstimates as PrimitiveGasPriceEstimates;
+
+/// The gas prices suggested for a transaction to be packed within the slow,
+/// normal, and fast targets of the gas price oracle.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceEstimates {
+    pub slow: U256,
+    pub normal: U256,
+    pub fast: U256,
+}
+
+impl From<PrimitiveGasPriceEstimates> for GasPriceEstimates {
+    fn from(estimates: PrimitiveGasPriceEstimates) -> Self {
+        GasPriceEstimates {
+            slow: estimates.slow,
+            normal: estimates.normal,
+            fast: estimates.fast,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_types::U64;
+use cfxcore::block_data_manager::db_gc_manager;
+
+/// The retention policy and garbage collection progress of a kind of block
+/// data.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DataPruningStatus {
+    /// The kind of data, e.g., `blockBody`.
+    pub data: String,
+    /// `archive`, `epochs:<count>`, or `since:<unix timestamp>`.
+    pub policy: String,
+    /// The earliest epoch that has not been garbage collected.
+    pub earliest_epoch: U64,
+    /// The epoch before which the data will be garbage collected, `None` if
+    /// it's decided by the pivot block timestamps. If it's larger than
+    /// `earliest_epoch`, the garbage collection is in progress.
+    pub target_epoch: Option<U64>,
+}
+
+impl From<db_gc_manager::DataPruningStatus> for DataPruningStatus {
+    fn from(status: db_gc_manager::DataPruningStatus) -> Self {
+        DataPruningStatus {
+            data: status.data.name().into(),
+            policy: status.policy.to_string(),
+            earliest_epoch: status.earliest_epoch.into(),
+            target_epoch: status.target_epoch.map(Into::into),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::Bytes;
+use cfx_types::{BigEndianHash, H256};
+use keccak_hash::keccak;
+use primitives::{
+    SkipInputCheck, StorageKey, StorageKeyWithSpace, StorageValue,
+};
+
+/// The maximum number of storage entries returned by one
+/// `debug_storageRangeAt` or `eth_getStorageRange` request.
+pub const MAX_STORAGE_RANGE_RESULT: usize = 1024;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRangeEntry {
+    /// The storage slot. Conflux keeps storage keys unhashed in its state, so
+    /// the preimage of `hashed_key` is always known.
+    pub key: Bytes,
+    /// The keccak hash of `key`, as used by the Ethereum storage trie.
+    pub hashed_key: H256,
+    pub value: H256,
+}
+
+/// A page of the storage entries of a contract, sorted by the storage slot.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRange {
+    pub storage: Vec<StorageRangeEntry>,
+    /// The storage slot to start the next page from, `None` if there are no
+    /// more entries.
+    pub next_key: Option<Bytes>,
+}
+
+impl StorageRange {
+    /// Build the page from the key/values read under the storage root key of
+    /// an account. The storage root key itself is skipped.
+    pub fn try_from(
+        kvs: Vec<(Vec<u8>, Box<[u8]>)>, next_key: Option<Vec<u8>>,
+    ) -> Result<Self, String> {
+        let mut storage = Vec::with_capacity(kvs.len());
+        for (key, value) in kvs {
+            let slot = match Self::storage_slot(&key) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let value = rlp::decode::<StorageValue>(&value)
+                .map_err(|e| format!("Invalid storage value: {:?}", e))?;
+            storage.push(StorageRangeEntry {
+                hashed_key: keccak(&slot),
+                key: Bytes::new(slot),
+                value: H256::from_uint(&value.value),
+            });
+        }
+
+        Ok(Self {
+            storage,
+            next_key: next_key
+                .and_then(|key| Self::storage_slot(&key))
+                .map(Bytes::new),
+        })
+    }
+
+    fn storage_slot(key: &[u8]) -> Option<Vec<u8>> {
+        match StorageKeyWithSpace::from_key_bytes::<SkipInputCheck>(key).key {
+            StorageKey::StorageKey { storage_key, .. } => {
+                Some(storage_key.to_vec())
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,508 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! In-process cluster of full and light nodes for the integration tests.
+//!
+//! The nodes are started with `FullClient` and `LightClient` in the same
+//! process, and are connected through an `InMemoryNetwork` instead of TCP, so
+//! that tests could control the links, partitions and latencies between the
+//! nodes directly, and inspect the consensus state of each node.
+//!
+//! Each node runs in test mode with its own temporary data directory, and
+//! without RPC servers or mining threads. PoS is not enabled, since its
+//! configuration is global to the process.
+
+use blockgen::BlockGenerator;
+use cfx_types::H256;
+use cfxcore::{
+    ConsensusGraph, ConsensusGraphTrait, NodeType, SynchronizationService,
+};
+use cfxkey::{Generator, KeyPair, Random, Secret};
+use client::{
+    common::{client_methods, ClientComponents, ClientTrait},
+    configuration::Configuration,
+    full::{FullClient, FullClientExtraComponents},
+    light::{LightClient, LightClientExtraComponents},
+};
+use network::{in_memory::InMemoryNetwork, node_table::NodeId};
+use parking_lot::{Condvar, Mutex};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tempdir::TempDir;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Bound of the waits for the nodes, which only elapses if a test fails. The
+/// link latency does not count against it with a manual clock.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+type ConfigureFn = Box<dyn Fn(usize, &mut Configuration)>;
+
+/// Builds a cluster with `full_nodes` full nodes followed by `light_nodes`
+/// light nodes, which are indexed in this order.
+pub struct ClusterBuilder {
+    full_nodes: usize,
+    light_nodes: usize,
+    connect_all: bool,
+    default_latency: Duration,
+    manual_clock: bool,
+    configure: Option<ConfigureFn>,
+}
+
+impl ClusterBuilder {
+    pub fn new() -> Self {
+        ClusterBuilder {
+            full_nodes: 1,
+            light_nodes: 0,
+            connect_all: true,
+            default_latency: Duration::from_secs(0),
+            manual_clock: false,
+            configure: None,
+        }
+    }
+
+    pub fn full_nodes(mut self, count: usize) -> Self {
+        self.full_nodes = count;
+        self
+    }
+
+    pub fn light_nodes(mut self, count: usize) -> Self {
+        self.light_nodes = count;
+        self
+    }
+
+    /// Whether to connect every node to all the full nodes once started,
+    /// which is the default. Otherwise, nodes are connected by
+    /// `Cluster::connect`.
+    pub fn connect_all(mut self, connect_all: bool) -> Self {
+        self.connect_all = connect_all;
+        self
+    }
+
+    /// Latency of the links without a latency set by `Cluster::set_latency`.
+    pub fn default_latency(mut self, latency: Duration) -> Self {
+        self.default_latency = latency;
+        self
+    }
+
+    /// Whether the latency of the links is measured by a manual clock, which is
+    /// advanced by `Cluster::advance_clock`, instead of the wall-clock time.
+    pub fn manual_clock(mut self, manual_clock: bool) -> Self {
+        self.manual_clock = manual_clock;
+        self
+    }
+
+    /// Customizes the configuration of each node by its index, after the
+    /// cluster defaults are applied.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where F: Fn(usize, &mut Configuration) + 'static {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+
+    pub fn build(self) -> Result<Cluster, String> {
+        let data_dir = TempDir::new("conflux_test_cluster")
+            .map_err(|e| format!("failed to create data dir: {}", e))?;
+        let network = if self.manual_clock {
+            InMemoryNetwork::with_manual_clock()
+        } else {
+            InMemoryNetwork::new()
+        };
+        network.set_default_latency(self.default_latency);
+
+        let mut cluster = Cluster {
+            network,
+            nodes: Vec::new(),
+            data_dir,
+        };
+        for index in 0..self.full_nodes + self.light_nodes {
+            let light = index >= self.full_nodes;
+            let mut conf = cluster.node_config(index, light)?;
+            if let Some(configure) = &self.configure {
+                configure(index, &mut conf);
+            }
+            cluster.start_node(conf, light)?;
+        }
+
+        if self.connect_all {
+            for a in 0..cluster.nodes.len() {
+                for b in 0..a.min(self.full_nodes) {
+                    cluster.connect(a, b);
+                }
+            }
+        }
+        Ok(cluster)
+    }
+}
+
+impl Default for ClusterBuilder {
+    fn default() -> Self { ClusterBuilder::new() }
+}
+
+enum Client {
+    Full(Box<ClientComponents<BlockGenerator, FullClientExtraComponents>>),
+    Light(Box<ClientComponents<BlockGenerator, LightClientExtraComponents>>),
+}
+
+/// A node of the cluster, which is stopped when the cluster is dropped.
+pub struct Node {
+    id: NodeId,
+    client: Client,
+    exit: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Node {
+    pub fn id(&self) -> &NodeId { &self.id }
+
+    pub fn is_light(&self) -> bool {
+        match self.client {
+            Client::Full(_) => false,
+            Client::Light(_) => true,
+        }
+    }
+
+    pub fn consensus(&self) -> &Arc<ConsensusGraph> {
+        match &self.client {
+            Client::Full(c) => &c.other_components.consensus,
+            Client::Light(c) => &c.other_components.consensus,
+        }
+    }
+
+    /// Returns the synchronization service of a full node.
+    pub fn sync(&self) -> Option<&Arc<SynchronizationService>> {
+        match &self.client {
+            Client::Full(c) => Some(&c.other_components.sync),
+            Client::Light(_) => None,
+        }
+    }
+
+    /// Returns the block generator of a full node.
+    pub fn blockgen(&self) -> Option<&Arc<BlockGenerator>> {
+        match &self.client {
+            Client::Full(c) => c.blockgen.as_ref(),
+            Client::Light(_) => None,
+        }
+    }
+
+    pub fn best_block_hash(&self) -> H256 { self.consensus().best_block_hash() }
+
+    pub fn best_epoch_number(&self) -> u64 {
+        self.consensus().best_epoch_number()
+    }
+
+    /// Returns whether the block is processed by the consensus graph.
+    pub fn has_block(&self, hash: &H256) -> bool {
+        self.consensus().get_block_epoch_number(hash).is_some()
+    }
+
+    /// Generates empty blocks on a full node as the `generate_empty_blocks`
+    /// RPC does, and returns their hashes.
+    pub fn generate_empty_blocks(&self, count: usize) -> Vec<H256> {
+        let blockgen = self.blockgen().expect("light node cannot mine blocks");
+        let block_size_limit = self
+            .sync()
+            .expect("full node")
+            .get_synchronization_graph()
+            .verification_config
+            .max_block_size_in_bytes;
+        (0..count)
+            .map(|_| blockgen.generate_block(0, block_size_limit, vec![]))
+            .collect()
+    }
+
+    fn shutdown(self) -> bool {
+        {
+            let (lock, cond) = &*self.exit;
+            *lock.lock() = true;
+            cond.notify_all();
+        }
+        let client: Box<dyn ClientTrait> = match self.client {
+            Client::Full(c) => c,
+            Client::Light(c) => c,
+        };
+        client_methods::shutdown(client)
+    }
+}
+
+/// Nodes started in the same process and connected by an in-memory network.
+pub struct Cluster {
+    network: InMemoryNetwork,
+    nodes: Vec<Node>,
+    // dropped after the nodes are stopped
+    data_dir: TempDir,
+}
+
+impl Cluster {
+    pub fn builder() -> ClusterBuilder { ClusterBuilder::new() }
+
+    fn node_config(
+        &self, index: usize, light: bool,
+    ) -> Result<Configuration, String> {
+        let node_dir = self.data_dir.path().join(format!("node{}", index));
+        let path = |name: &str| {
+            node_dir
+                .join(name)
+                .into_os_string()
+                .into_string()
+                .map_err(|p| format!("invalid data dir {:?}", p))
+        };
+
+        let mut conf = Configuration::default();
+        let raw_conf = &mut conf.raw_conf;
+        raw_conf.mode = Some("test".into());
+        raw_conf.conflux_data_dir = path("blockchain_data")?;
+        raw_conf.pos_private_key_path = path("pos_key")?;
+        raw_conf.net_key = Some(
+            Random
+                .generate()
+                .map_err(|e| format!("failed to generate net key: {}", e))?
+                .secret()
+                .to_hex(),
+        );
+        // Nodes are only reachable through the in-memory network, but still
+        // bind local sockets, so let the OS choose the ports.
+        raw_conf.tcp_port = 0;
+        raw_conf.udp_port = Some(0);
+        raw_conf.enable_discovery = false;
+        raw_conf.session_ip_limits = "0,0,0,0".into();
+        raw_conf.subnet_quota = 0;
+        raw_conf.mining_type = Some("disable".into());
+        raw_conf.execute_genesis = false;
+        raw_conf.check_status_genesis = false;
+        raw_conf.check_phase_change_period_ms = 100;
+        raw_conf.dev_allow_phase_change_without_peer = true;
+        raw_conf.min_phase_change_normal_peer_count = 1;
+        raw_conf.storage_delta_mpts_cache_size = 200_000;
+        raw_conf.storage_delta_mpts_cache_start_size = 200_000;
+        if light {
+            raw_conf.node_type = Some(NodeType::Light);
+        }
+        conf.in_memory_network = Some(self.network.clone());
+        Ok(conf)
+    }
+
+    fn start_node(
+        &mut self, conf: Configuration, light: bool,
+    ) -> Result<(), String> {
+        let key_pair = conf
+            .raw_conf
+            .net_key
+            .as_ref()
+            .ok_or("net_key is required to identify the node")?
+            .trim_start_matches("0x")
+            .parse::<Secret>()
+            .and_then(KeyPair::from_secret)
+            .map_err(|e| format!("invalid net_key: {}", e))?;
+        let id = *key_pair.public();
+
+        let exit = Arc::new((Mutex::new(false), Condvar::new()));
+        let client = if light {
+            Client::Light(LightClient::start(conf, exit.clone())?)
+        } else {
+            Client::Full(FullClient::start(conf, exit.clone())?)
+        };
+        self.nodes.push(Node { id, client, exit });
+        Ok(())
+    }
+
+    pub fn network(&self) -> &InMemoryNetwork { &self.network }
+
+    pub fn nodes(&self) -> &[Node] { &self.nodes }
+
+    pub fn node(&self, index: usize) -> &Node { &self.nodes[index] }
+
+    pub fn len(&self) -> usize { self.nodes.len() }
+
+    pub fn is_empty(&self) -> bool { self.nodes.is_empty() }
+
+    /// Connects node `a` to node `b` as if `a` dialed `b`.
+    pub fn connect(&self, a: usize, b: usize) -> bool {
+        self.network.connect(self.nodes[a].id(), self.nodes[b].id())
+    }
+
+    pub fn disconnect(&self, a: usize, b: usize) -> bool {
+        self.network
+            .disconnect(self.nodes[a].id(), self.nodes[b].id())
+    }
+
+    /// Sets the latency of the packets sent from node `from` to node `to`.
+    pub fn set_latency(&self, from: usize, to: usize, latency: Duration) {
+        self.network.set_latency(
+            self.nodes[from].id(),
+            self.nodes[to].id(),
+            latency,
+        );
+    }
+
+    /// Advances the clock of the link latency, if the cluster is built with
+    /// `ClusterBuilder::manual_clock`.
+    pub fn advance_clock(&self, duration: Duration) {
+        self.network.advance_clock(duration);
+    }
+
+    /// Returns the number of the packets from node `from` to node `to` that
+    /// are delayed by the latency.
+    pub fn in_flight(&self, from: usize, to: usize) -> usize {
+        self.network
+            .in_flight(self.nodes[from].id(), self.nodes[to].id())
+    }
+
+    /// Splits the nodes into partitions by their indices. The links between
+    /// partitions are disconnected until `heal` is called.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        let groups: Vec<Vec<NodeId>> = groups
+            .iter()
+            .map(|group| group.iter().map(|i| *self.nodes[*i].id()).collect())
+            .collect();
+        self.network.partition(&groups);
+    }
+
+    pub fn heal(&self) { self.network.heal(); }
+
+    /// Generates empty blocks on the full node `index`.
+    pub fn generate_empty_blocks(
+        &self, index: usize, count: usize,
+    ) -> Vec<H256> {
+        self.nodes[index].generate_empty_blocks(count)
+    }
+
+    /// Waits until `condition` holds, and returns false on timeout.
+    pub fn wait_for<F>(&self, timeout: Duration, condition: F) -> bool
+    where F: Fn(&Cluster) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if condition(self) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Waits until the full nodes in `nodes` have processed all the blocks.
+    pub fn wait_for_blocks(
+        &self, nodes: &[usize], hashes: &[H256], timeout: Duration,
+    ) -> bool {
+        self.wait_for(timeout, |cluster| {
+            nodes.iter().all(|i| {
+                let node = cluster.node(*i);
+                hashes.iter().all(|hash| node.has_block(hash))
+            })
+        })
+    }
+
+    /// Waits until the nodes in `nodes` have the same best block.
+    pub fn wait_for_best_block_sync(
+        &self, nodes: &[usize], timeout: Duration,
+    ) -> bool {
+        self.wait_for(timeout, |cluster| {
+            let best = cluster.node(nodes[0]).best_block_hash();
+            nodes
+                .iter()
+                .all(|i| cluster.node(*i).best_block_hash() == best)
+        })
+    }
+
+    /// Asserts that the nodes in `nodes` have the same best block within
+    /// `timeout`, and returns it.
+    pub fn assert_best_block_synced(
+        &self, nodes: &[usize], timeout: Duration,
+    ) -> H256 {
+        if !self.wait_for_best_block_sync(nodes, timeout) {
+            let states: Vec<(usize, H256, u64)> = nodes
+                .iter()
+                .map(|i| {
+                    let node = self.node(*i);
+                    (*i, node.best_block_hash(), node.best_epoch_number())
+                })
+                .collect();
+            panic!(
+                "best blocks are not synced in {:?}: (node, best block, best epoch) = {:?}",
+                timeout, states
+            );
+        }
+        self.node(nodes[0]).best_block_hash()
+    }
+
+    /// Stops all the nodes, and returns whether they are stopped cleanly.
+    pub fn shutdown(mut self) -> bool { self.stop_nodes() }
+
+    fn stop_nodes(&mut self) -> bool {
+        let mut graceful = true;
+        for node in self.nodes.drain(..) {
+            self.network.remove_node(node.id());
+            graceful &= node.shutdown();
+        }
+        graceful
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) { self.stop_nodes(); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_partition_and_heal() {
+        let cluster = Cluster::builder()
+            .full_nodes(3)
+            .light_nodes(1)
+            .build()
+            .unwrap();
+        let all = [0, 1, 2, 3];
+
+        let blocks = cluster.generate_empty_blocks(0, 5);
+        assert!(cluster.wait_for_blocks(&[1, 2], &blocks, DEFAULT_TIMEOUT));
+        cluster.assert_best_block_synced(&all, DEFAULT_TIMEOUT);
+
+        // mine on both sides of a partition
+        cluster.partition(&[&[0, 3], &[1, 2]]);
+        let left = cluster.generate_empty_blocks(0, 3);
+        let right = cluster.generate_empty_blocks(1, 5);
+        assert!(cluster.wait_for_blocks(&[2], &right, DEFAULT_TIMEOUT));
+        assert!(!cluster.node(2).has_block(&left[0]));
+        assert!(!cluster.node(0).has_block(&right[0]));
+
+        // the nodes converge to the same pivot chain once healed
+        cluster.heal();
+        let blocks = cluster.generate_empty_blocks(2, 1);
+        assert!(cluster.wait_for_blocks(&[0, 1, 2], &blocks, DEFAULT_TIMEOUT));
+        assert!(cluster.wait_for_blocks(&[0], &right, DEFAULT_TIMEOUT));
+        assert!(cluster.wait_for_blocks(&[1, 2], &left, DEFAULT_TIMEOUT));
+        cluster.assert_best_block_synced(&all, DEFAULT_TIMEOUT);
+        assert!(cluster.shutdown());
+    }
+
+    #[test]
+    fn test_latency() {
+        let cluster = Cluster::builder()
+            .full_nodes(2)
+            .manual_clock(true)
+            .build()
+            .unwrap();
+        let latency = Duration::from_millis(500);
+        cluster.set_latency(0, 1, latency);
+
+        let blocks = cluster.generate_empty_blocks(0, 1);
+        assert!(cluster
+            .wait_for(DEFAULT_TIMEOUT, |cluster| cluster.in_flight(0, 1) > 0));
+        // nothing from node 0 reaches node 1 before the latency elapses
+        cluster.advance_clock(latency - Duration::from_millis(1));
+        assert!(!cluster.node(1).has_block(&blocks[0]));
+
+        // the block is relayed in a few round trips, each of which is delayed
+        assert!(cluster.wait_for(DEFAULT_TIMEOUT, |cluster| {
+            cluster.advance_clock(latency);
+            cluster.node(1).has_block(&blocks[0])
+        }));
+    }
+}
@@ -26,6 +26,7 @@ macro_rules! impl_db_encoding_as_rlp {
 }
 
 impl_db_encoding_as_rlp!(H256);
+impl_db_encoding_as_rlp!(Bloom);
 impl_db_encoding_as_rlp!(u64);
 impl_db_encoding_as_rlp!(TransactionIndex);
 
@@ -44,6 +45,6 @@ impl DatabaseEncodable for BlockHeader {
 }
 
 use cfx_bytes::Bytes;
-use cfx_types::H256;
+use cfx_types::{Bloom, H256};
 use primitives::{BlockHeader, TransactionIndex};
 use rlp::*;
@@ -41,3 +41,10 @@ lazy_static! {
     pub static ref RESERVED11: Address =
         Address::from_str("088800000000000000000000000000000000000b").unwrap();
 }
+
+// The internal contracts in the EVM space. They have no accounts in the state,
+// like the precompiled contracts.
+lazy_static! {
+    pub static ref CORE_SPACE_READER_CONTRACT_ADDRESS: Address =
+        Address::from_str("0888000000000000000000000000000000000100").unwrap();
+}
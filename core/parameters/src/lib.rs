@@ -210,6 +210,9 @@ pub mod block {
     // space in the cross space call. Setting it to N means that only 1/N of gas
     // left can be passed to the cross space call.
     pub const CROSS_SPACE_GAS_RATIO: u64 = 10;
+    // The number of recent epochs whose state root, pivot block hash and PoS
+    // block id can be queried from the context internal contracts.
+    pub const EPOCH_HISTORY_WINDOW: u64 = 8192;
 }
 
 pub mod staking {
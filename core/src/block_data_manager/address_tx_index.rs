@@ -0,0 +1,463 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::observer::{
+    trace::{Action as TraceAction, TransactionExecTraces},
+    AddressPocket,
+};
+use cfx_types::{Address, AddressSpaceUtil, AddressWithSpace, Space, H256};
+use primitives::{Action, Receipt, SignedTransaction, TransactionOutcome};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::{collections::HashSet, sync::Arc};
+
+/// How an address is related to an indexed transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddressTxRelation {
+    /// The address is the sender of the transaction.
+    Sender,
+    /// The address is the receiver of the transaction.
+    Receiver,
+    /// The address sends or receives value in an internal call or internal
+    /// transfer of the transaction. Only indexed with `executive_trace`.
+    InternalTransfer,
+}
+
+impl Encodable for AddressTxRelation {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        let type_int: u8 = match self {
+            AddressTxRelation::Sender => 0,
+            AddressTxRelation::Receiver => 1,
+            AddressTxRelation::InternalTransfer => 2,
+        };
+        type_int.rlp_append(s)
+    }
+}
+
+impl Decodable for AddressTxRelation {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match u8::decode(rlp)? {
+            0u8 => Ok(AddressTxRelation::Sender),
+            1u8 => Ok(AddressTxRelation::Receiver),
+            2u8 => Ok(AddressTxRelation::InternalTransfer),
+            _ => Err(DecoderError::Custom("Unrecognized relation byte.")),
+        }
+    }
+}
+
+/// An entry in the transaction history of an address.
+///
+/// The entries of an address in an epoch are stored together, keyed by the
+/// address and the epoch, and are rewritten when the epoch is executed on the
+/// pivot chain again. The entries of an epoch whose pivot block is no longer
+/// on the pivot chain should be ignored by checking `pivot_hash`.
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct AddressTxIndexEntry {
+    pub epoch_number: u64,
+    pub pivot_hash: H256,
+    pub block_hash: H256,
+    /// The index of the transaction in the block.
+    pub transaction_index: u64,
+    pub transaction_hash: H256,
+    pub relation: AddressTxRelation,
+}
+
+impl_db_encoding_as_rlp!(AddressTxIndexEntry);
+
+/// The epochs with entries in the transaction history of an address are
+/// numbered with consecutive sequence numbers in the order they are first
+/// indexed. The epochs in `[first, next)` have not been garbage collected.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, RlpEncodable, RlpDecodable,
+)]
+pub struct AddressTxHistory {
+    pub first: u64,
+    pub next: u64,
+}
+
+impl_db_encoding_as_rlp!(AddressTxHistory);
+
+/// The sequence number of an epoch in the transaction history of an address.
+/// The slots of all the addresses indexed in an epoch are kept with the epoch,
+/// so the epoch keeps its sequence numbers when it's indexed again, and its
+/// entries can be garbage collected.
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct AddressTxSlot {
+    pub space: Space,
+    pub address: Address,
+    pub seq: u64,
+}
+
+impl AddressTxSlot {
+    pub fn address_with_space(&self) -> AddressWithSpace {
+        AddressWithSpace {
+            address: self.address,
+            space: self.space,
+        }
+    }
+}
+
+impl_db_encoding_as_rlp!(AddressTxSlot);
+
+/// Return the address transaction index entries of the executed transactions
+/// in the block `block_hash`, executed in the epoch `epoch_number` with the
+/// pivot block `pivot_hash`. An address is indexed at most once for each
+/// transaction, with the first relation among sender, receiver, and internal
+/// transfer.
+///
+/// Internal transfers are only collected if `traces` are available.
+pub fn address_tx_index_entries(
+    epoch_number: u64, pivot_hash: &H256, block_hash: &H256,
+    transactions: &[Arc<SignedTransaction>], receipts: &[Receipt],
+    traces: Option<&[TransactionExecTraces]>,
+) -> Vec<(AddressWithSpace, AddressTxIndexEntry)>
+{
+    let mut entries = Vec::new();
+    for (index, tx) in transactions.iter().enumerate() {
+        // Skipped transactions are not executed in this block.
+        match receipts.get(index) {
+            Some(receipt)
+                if receipt.outcome_status != TransactionOutcome::Skipped => {}
+            _ => continue,
+        }
+
+        let mut related = vec![(tx.sender(), AddressTxRelation::Sender)];
+        if let Action::Call(to) = tx.action() {
+            related
+                .push((to.with_space(tx.space()), AddressTxRelation::Receiver));
+        }
+        if let Some(tx_traces) = traces.and_then(|traces| traces.get(index)) {
+            for trace in tx_traces.0.iter().filter(|trace| trace.valid) {
+                match &trace.action {
+                    TraceAction::Call(call) if !call.value.is_zero() => {
+                        related.push((
+                            call.from.with_space(call.space),
+                            AddressTxRelation::InternalTransfer,
+                        ));
+                        related.push((
+                            call.to.with_space(call.space),
+                            AddressTxRelation::InternalTransfer,
+                        ));
+                    }
+                    TraceAction::Create(create) if !create.value.is_zero() => {
+                        related.push((
+                            create.from.with_space(create.space),
+                            AddressTxRelation::InternalTransfer,
+                        ));
+                    }
+                    TraceAction::InternalTransferAction(transfer)
+                        if !transfer.value.is_zero() =>
+                    {
+                        for pocket in &[&transfer.from, &transfer.to] {
+                            if let AddressPocket::Balance(address) = pocket {
+                                related.push((
+                                    *address,
+                                    AddressTxRelation::InternalTransfer,
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut indexed = HashSet::new();
+        for (address, relation) in related {
+            if indexed.insert(address) {
+                entries.push((
+                    address,
+                    AddressTxIndexEntry {
+                        epoch_number,
+                        pivot_hash: *pivot_hash,
+                        block_hash: *block_hash,
+                        transaction_index: index as u64,
+                        transaction_hash: tx.hash(),
+                        relation,
+                    },
+                ));
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_data_manager::db_manager::DBManager,
+        observer::trace::{Call, ExecTrace, InternalTransferAction},
+        pow::PowComputer,
+        vm::CallType,
+    };
+    use cfx_types::{Address, Bloom, Space, U256};
+    use keylib::{Generator, KeyPair, Random};
+    use primitives::{NativeTransaction, Transaction};
+    use tempdir::TempDir;
+
+    fn new_test_tx(sender: &KeyPair, to: Address) -> Arc<SignedTransaction> {
+        Arc::new(
+            Transaction::from(NativeTransaction {
+                nonce: U256::zero(),
+                gas_price: U256::one(),
+                gas: U256::from(50000),
+                action: Action::Call(to),
+                value: U256::one(),
+                storage_limit: 0,
+                epoch_height: 0,
+                chain_id: 1,
+                data: Vec::new(),
+            })
+            .sign(sender.secret()),
+        )
+    }
+
+    fn new_test_receipt(outcome: TransactionOutcome) -> Receipt {
+        Receipt::new(
+            outcome,
+            U256::zero(),
+            U256::zero(),
+            false,
+            Vec::new(),
+            Bloom::zero(),
+            false,
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    fn related_addresses(
+        entries: &[(AddressWithSpace, AddressTxIndexEntry)],
+        transaction_index: u64,
+    ) -> Vec<(AddressWithSpace, AddressTxRelation)>
+    {
+        entries
+            .iter()
+            .filter(|(_, entry)| entry.transaction_index == transaction_index)
+            .map(|(address, entry)| (*address, entry.relation))
+            .collect()
+    }
+
+    #[test]
+    fn test_address_tx_index_entries() {
+        let sender = Random.generate().unwrap();
+        let sender_address = sender.address().with_native_space();
+        let receiver = Address::random().with_native_space();
+        let transactions = vec![
+            new_test_tx(&sender, receiver.address),
+            new_test_tx(&sender, receiver.address),
+            new_test_tx(&sender, sender_address.address),
+        ];
+        let receipts = vec![
+            new_test_receipt(TransactionOutcome::Success),
+            new_test_receipt(TransactionOutcome::Skipped),
+            new_test_receipt(TransactionOutcome::Failure),
+        ];
+
+        let entries = address_tx_index_entries(
+            10,
+            &H256::random(),
+            &H256::random(),
+            &transactions,
+            &receipts,
+            None,
+        );
+        assert_eq!(
+            related_addresses(&entries, 0),
+            vec![
+                (sender_address, AddressTxRelation::Sender),
+                (receiver, AddressTxRelation::Receiver),
+            ]
+        );
+        // Skipped transactions are not indexed.
+        assert_eq!(related_addresses(&entries, 1), vec![]);
+        // A transaction is indexed once for an address.
+        assert_eq!(
+            related_addresses(&entries, 2),
+            vec![(sender_address, AddressTxRelation::Sender)]
+        );
+        assert!(entries.iter().all(|(_, entry)| entry.epoch_number == 10
+            && entry.transaction_hash
+                == transactions[entry.transaction_index as usize].hash()));
+    }
+
+    #[test]
+    fn test_address_tx_index_entries_with_traces() {
+        let sender = Random.generate().unwrap();
+        let sender_address = sender.address().with_native_space();
+        let contract = Address::random().with_native_space();
+        let callee = Address::random().with_native_space();
+        let evm_address = Address::random().with_evm_space();
+        let no_value_callee = Address::random().with_native_space();
+        let failed_callee = Address::random().with_native_space();
+
+        let call =
+            |to: &AddressWithSpace, value: U256, valid: bool| ExecTrace {
+                action: TraceAction::Call(Call {
+                    space: Space::Native,
+                    from: contract.address,
+                    to: to.address,
+                    value,
+                    gas: U256::zero(),
+                    input: Default::default(),
+                    call_type: CallType::Call,
+                }),
+                valid,
+            };
+        let traces = vec![TransactionExecTraces(vec![
+            call(&contract, U256::zero(), true),
+            call(&callee, U256::one(), true),
+            call(&no_value_callee, U256::zero(), true),
+            call(&failed_callee, U256::one(), false),
+            ExecTrace {
+                action: TraceAction::InternalTransferAction(
+                    InternalTransferAction {
+                        from: AddressPocket::Balance(contract),
+                        to: AddressPocket::Balance(evm_address),
+                        value: U256::one(),
+                    },
+                ),
+                valid: true,
+            },
+            ExecTrace {
+                action: TraceAction::InternalTransferAction(
+                    InternalTransferAction {
+                        from: AddressPocket::MintBurn,
+                        to: AddressPocket::Balance(sender_address),
+                        value: U256::one(),
+                    },
+                ),
+                valid: true,
+            },
+        ])];
+
+        let entries = address_tx_index_entries(
+            10,
+            &H256::random(),
+            &H256::random(),
+            &[new_test_tx(&sender, contract.address)],
+            &[new_test_receipt(TransactionOutcome::Success)],
+            Some(&traces[..]),
+        );
+        assert_eq!(
+            related_addresses(&entries, 0),
+            vec![
+                (sender_address, AddressTxRelation::Sender),
+                (contract, AddressTxRelation::Receiver),
+                (callee, AddressTxRelation::InternalTransfer),
+                (evm_address, AddressTxRelation::InternalTransfer),
+            ]
+        );
+    }
+
+    fn new_test_entry(
+        epoch_number: u64, transaction_index: u64,
+    ) -> AddressTxIndexEntry {
+        AddressTxIndexEntry {
+            epoch_number,
+            pivot_hash: H256::from_low_u64_be(epoch_number),
+            block_hash: H256::from_low_u64_be(epoch_number),
+            transaction_index,
+            transaction_hash: H256::random(),
+            relation: AddressTxRelation::Sender,
+        }
+    }
+
+    fn epochs_of(entries: &[AddressTxIndexEntry]) -> Vec<(u64, u64)> {
+        entries
+            .iter()
+            .map(|entry| (entry.epoch_number, entry.transaction_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_epoch_entries_in_db() {
+        let dir = TempDir::new("address_tx_index_test").unwrap();
+        let db_manager = DBManager::new_from_sqlite(
+            dir.path(),
+            Arc::new(PowComputer::new(false)),
+        );
+        let alice = Address::random().with_native_space();
+        let bob = Address::random().with_evm_space();
+
+        for epoch in 1..=3 {
+            db_manager.insert_epoch_address_tx_entries_to_db(
+                epoch,
+                vec![
+                    (alice, new_test_entry(epoch, 0)),
+                    (alice, new_test_entry(epoch, 1)),
+                    (bob, new_test_entry(epoch, 1)),
+                ],
+            );
+        }
+        // Indexing an epoch again replaces its entries.
+        db_manager.insert_epoch_address_tx_entries_to_db(
+            2,
+            vec![(alice, new_test_entry(2, 5))],
+        );
+        let (entries, cursor) =
+            db_manager.address_tx_entries_page_from_db(&alice, None, 10);
+        assert_eq!(
+            epochs_of(&entries),
+            vec![(3, 1), (3, 0), (2, 5), (1, 1), (1, 0)]
+        );
+        assert_eq!(cursor, None);
+        let (entries, _) =
+            db_manager.address_tx_entries_page_from_db(&bob, None, 10);
+        assert_eq!(epochs_of(&entries), vec![(3, 1), (1, 1)]);
+
+        // Pagination continues in the middle of an epoch.
+        let (entries, cursor) =
+            db_manager.address_tx_entries_page_from_db(&alice, None, 2);
+        assert_eq!(epochs_of(&entries), vec![(3, 1), (3, 0)]);
+        let (entries, cursor) =
+            db_manager.address_tx_entries_page_from_db(&alice, cursor, 2);
+        assert_eq!(epochs_of(&entries), vec![(2, 5), (1, 1)]);
+        let (entries, cursor) =
+            db_manager.address_tx_entries_page_from_db(&alice, cursor, 2);
+        assert_eq!(epochs_of(&entries), vec![(1, 0)]);
+        assert_eq!(cursor, None);
+
+        // Bob is indexed in epoch 2 again with its old sequence number.
+        db_manager.insert_epoch_address_tx_entries_to_db(
+            2,
+            vec![(bob, new_test_entry(2, 0))],
+        );
+        assert_eq!(
+            db_manager.address_tx_history_from_db(&bob),
+            AddressTxHistory { first: 0, next: 3 }
+        );
+
+        db_manager.remove_epoch_address_tx_entries_from_db(1);
+        db_manager.remove_epoch_address_tx_entries_from_db(2);
+        let (entries, _) =
+            db_manager.address_tx_entries_page_from_db(&alice, None, 10);
+        assert_eq!(epochs_of(&entries), vec![(3, 1), (3, 0)]);
+        assert_eq!(
+            db_manager.address_tx_history_from_db(&alice),
+            AddressTxHistory { first: 2, next: 3 }
+        );
+        assert_eq!(
+            db_manager.address_tx_history_from_db(&bob),
+            AddressTxHistory { first: 2, next: 3 }
+        );
+    }
+
+    #[test]
+    fn test_entry_encoding() {
+        let entry = AddressTxIndexEntry {
+            epoch_number: 1,
+            pivot_hash: H256::random(),
+            block_hash: H256::random(),
+            transaction_index: 2,
+            transaction_hash: H256::random(),
+            relation: AddressTxRelation::InternalTransfer,
+        };
+        assert_eq!(
+            rlp::decode::<AddressTxIndexEntry>(&rlp::encode(&entry)),
+            Ok(entry)
+        );
+    }
+}
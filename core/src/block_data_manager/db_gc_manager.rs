@@ -1,5 +1,14 @@
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
-use std::cmp::min;
+use rlp::DecoderError;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::{cmp::min, fmt, str::FromStr};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// The maximum number of epochs of each kind of data to garbage collect in one
+/// `database_gc` call. When the retention policy is tightened, the data kept
+/// by the previous policy is removed gradually with this speed.
+pub const MAX_PRUNED_EPOCHS_PER_GC: u64 = 2000;
 
 /// Each time we make a new checkpoint, we will mark more data as garbage
 /// depending on the parameters. To avoid the GC process affecting normal
@@ -9,10 +18,15 @@ use std::cmp::min;
 /// finish GC with half an era).
 #[derive(Default, DeriveMallocSizeOf, Debug)]
 pub struct GCProgress {
-    // The earliest not-garbage-collected epoch.
-    // This is the only field that we persist to disk as the GC progress.
+    // The earliest not-garbage-collected base epoch. The data of each kind is
+    // garbage collected up to an epoch computed from this and its
+    // `RetentionPolicy`.
     pub next_to_process: u64,
 
+    // The earliest epoch that has not been garbage collected for each kind of
+    // data. This is persisted together with `next_to_process`.
+    pub pruned: PrunedEpochs,
+
     // The last epoch that we are allowed to garbage collect.
     pub gc_end: u64,
 
@@ -27,17 +41,18 @@ pub struct GCProgress {
 }
 
 impl GCProgress {
-    pub fn new(next_to_process: u64) -> Self {
+    pub fn new(next_to_process: u64, pruned: PrunedEpochs) -> Self {
         Self {
             next_to_process,
+            pruned,
             ..Default::default()
         }
     }
 
     /// Compute the GC base range to make sure the GC progress is proportional
     /// to the consensus progress.
-    /// The actual GC range for each kind of data is the returned base range
-    /// minus the corresponding `additional_maintained*` offset.
+    /// The actual GC range for each kind of data is decided by the returned
+    /// base range and the corresponding `RetentionPolicy`.
     ///
     /// Return `Some((start_epoch, end_epoch))` and the range `[start_epoch,
     /// end_epoch)` will be GCed. Return `None` if there is no work to be
@@ -56,4 +71,239 @@ impl GCProgress {
                 - self.last_consensus_best_epoch);
         Some((self.next_to_process, self.next_to_process + batch_size))
     }
+
+    /// Return the epoch before which the data should be garbage collected
+    /// according to `policy`, or `None` if it's decided by the timestamps of
+    /// the epochs before `next_to_process`.
+    pub fn pruning_target(&self, policy: RetentionPolicy) -> Option<u64> {
+        match policy {
+            RetentionPolicy::Archive => Some(0),
+            RetentionPolicy::KeepEpochs(epochs) => {
+                Some(self.next_to_process.saturating_sub(epochs))
+            }
+            RetentionPolicy::KeepSince(_) => None,
+        }
+    }
+}
+
+/// How long a kind of block data is kept before the current era genesis
+/// checkpoint. The data after the checkpoint is always kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Never garbage collect the data.
+    Archive,
+    /// Keep the data of this number of epochs before the checkpoint.
+    KeepEpochs(u64),
+    /// Keep the data of the epochs whose pivot block timestamp is not earlier
+    /// than this unix timestamp in seconds.
+    KeepSince(u64),
+}
+
+impl FromStr for RetentionPolicy {
+    type Err = String;
+
+    /// Parse `archive`, `epochs:<count>`, or `since:<unix timestamp>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_u64 = |v: &str| {
+            v.parse::<u64>()
+                .map_err(|e| format!("Invalid retention policy {}: {}", s, e))
+        };
+        if s == "archive" {
+            Ok(RetentionPolicy::Archive)
+        } else if let Some(epochs) = s.strip_prefix("epochs:") {
+            Ok(RetentionPolicy::KeepEpochs(parse_u64(epochs)?))
+        } else if let Some(timestamp) = s.strip_prefix("since:") {
+            Ok(RetentionPolicy::KeepSince(parse_u64(timestamp)?))
+        } else {
+            Err(format!(
+                "Invalid retention policy {}, expect \"archive\", \
+                 \"epochs:<count>\", or \"since:<unix timestamp>\"",
+                s
+            ))
+        }
+    }
+}
+
+impl fmt::Display for RetentionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionPolicy::Archive => write!(f, "archive"),
+            RetentionPolicy::KeepEpochs(epochs) => {
+                write!(f, "epochs:{}", epochs)
+            }
+            RetentionPolicy::KeepSince(timestamp) => {
+                write!(f, "since:{}", timestamp)
+            }
+        }
+    }
+}
+
+/// The kinds of block data that can be garbage collected. They are garbage
+/// collected in this order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub enum PrunableData {
+    // Transaction indices must be garbage collected before block bodies,
+    // otherwise we may be unable to get the transactions in an epoch.
+    TransactionIndex,
+    BlockBody,
+    ExecutionResult,
+    Reward,
+    Trace,
+}
+
+impl PrunableData {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrunableData::TransactionIndex => "transactionIndex",
+            PrunableData::BlockBody => "blockBody",
+            PrunableData::ExecutionResult => "executionResult",
+            PrunableData::Reward => "reward",
+            PrunableData::Trace => "trace",
+        }
+    }
+}
+
+/// The retention policy of each kind of block data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PruningPolicy {
+    pub transaction_index: RetentionPolicy,
+    pub block_body: RetentionPolicy,
+    pub execution_result: RetentionPolicy,
+    pub reward: RetentionPolicy,
+    pub trace: RetentionPolicy,
+}
+
+impl PruningPolicy {
+    pub fn archive() -> Self {
+        Self {
+            transaction_index: RetentionPolicy::Archive,
+            block_body: RetentionPolicy::Archive,
+            execution_result: RetentionPolicy::Archive,
+            reward: RetentionPolicy::Archive,
+            trace: RetentionPolicy::Archive,
+        }
+    }
+
+    pub fn get(&self, data: PrunableData) -> RetentionPolicy {
+        match data {
+            PrunableData::TransactionIndex => self.transaction_index,
+            PrunableData::BlockBody => self.block_body,
+            PrunableData::ExecutionResult => self.execution_result,
+            PrunableData::Reward => self.reward,
+            PrunableData::Trace => self.trace,
+        }
+    }
+}
+
+/// The earliest epoch that has not been garbage collected for each kind of
+/// block data.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    DeriveMallocSizeOf,
+    RlpEncodable,
+    RlpDecodable,
+)]
+pub struct PrunedEpochs {
+    pub transaction_index: u64,
+    pub block_body: u64,
+    pub execution_result: u64,
+    pub reward: u64,
+    pub trace: u64,
+}
+
+impl PrunedEpochs {
+    /// Assume the data before `next_to_process` has been garbage collected
+    /// with `policy`. This is used for the databases created before the
+    /// progress of each kind of data is persisted.
+    pub fn from_legacy_gc_progress(
+        next_to_process: u64, policy: &PruningPolicy,
+    ) -> Self {
+        let gc_progress = GCProgress::new(next_to_process, Default::default());
+        let mut pruned = Self::default();
+        for data in PrunableData::iter() {
+            if let Some(epoch) = gc_progress.pruning_target(policy.get(data)) {
+                pruned.set(data, epoch);
+            }
+        }
+        pruned
+    }
+
+    pub fn get(&self, data: PrunableData) -> u64 {
+        match data {
+            PrunableData::TransactionIndex => self.transaction_index,
+            PrunableData::BlockBody => self.block_body,
+            PrunableData::ExecutionResult => self.execution_result,
+            PrunableData::Reward => self.reward,
+            PrunableData::Trace => self.trace,
+        }
+    }
+
+    pub fn set(&mut self, data: PrunableData, epoch: u64) {
+        match data {
+            PrunableData::TransactionIndex => self.transaction_index = epoch,
+            PrunableData::BlockBody => self.block_body = epoch,
+            PrunableData::ExecutionResult => self.execution_result = epoch,
+            PrunableData::Reward => self.reward = epoch,
+            PrunableData::Trace => self.trace = epoch,
+        }
+    }
+}
+
+impl_db_encoding_as_rlp!(PrunedEpochs);
+
+/// The GC status of a kind of data.
+#[derive(Clone, Copy, Debug)]
+pub struct DataPruningStatus {
+    pub data: PrunableData,
+    pub policy: RetentionPolicy,
+    /// The earliest epoch that has not been garbage collected.
+    pub earliest_epoch: u64,
+    /// The epoch before which the data should be garbage collected, or
+    /// `None` if it's decided by the timestamps of the epochs.
+    pub target_epoch: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_policy() {
+        for policy in &[
+            RetentionPolicy::Archive,
+            RetentionPolicy::KeepEpochs(100),
+            RetentionPolicy::KeepSince(1650000000),
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(*policy));
+        }
+        assert!("epochs:".parse::<RetentionPolicy>().is_err());
+        assert!("since:-1".parse::<RetentionPolicy>().is_err());
+        assert!("full".parse::<RetentionPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_pruned_epochs_from_legacy_gc_progress() {
+        let policy = PruningPolicy {
+            transaction_index: RetentionPolicy::KeepEpochs(100),
+            block_body: RetentionPolicy::KeepEpochs(0),
+            execution_result: RetentionPolicy::KeepEpochs(2000),
+            reward: RetentionPolicy::Archive,
+            trace: RetentionPolicy::KeepSince(1650000000),
+        };
+        assert_eq!(
+            PrunedEpochs::from_legacy_gc_progress(1000, &policy),
+            PrunedEpochs {
+                transaction_index: 900,
+                block_body: 1000,
+                execution_result: 0,
+                reward: 0,
+                trace: 0,
+            }
+        );
+    }
 }
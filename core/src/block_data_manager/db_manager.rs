@@ -1,14 +1,20 @@
 use crate::{
     block_data_manager::{
-        db_decode_list, db_encode_list, BlamedHeaderVerifiedRoots,
-        BlockExecutionResultWithEpoch, BlockRewardResult, BlockTracesWithEpoch,
-        CheckpointHashes, DataVersionTuple, EpochExecutionContext,
-        LocalBlockInfo, PosRewardInfo,
+        address_tx_index::{
+            AddressTxHistory, AddressTxIndexEntry, AddressTxSlot,
+        },
+        db_decode_list, db_encode_list,
+        db_gc_manager::PrunedEpochs,
+        freezer::FrozenBlock,
+        log_bloom_index::LogBloomIndexProgress,
+        BlamedHeaderVerifiedRoots, BlockExecutionResultWithEpoch,
+        BlockRewardResult, BlockTracesWithEpoch, CheckpointHashes,
+        DataVersionTuple, EpochExecutionContext, LocalBlockInfo, PosRewardInfo,
     },
     db::{
-        COL_BLAMED_HEADER_VERIFIED_ROOTS, COL_BLOCKS, COL_BLOCK_TRACES,
-        COL_EPOCH_NUMBER, COL_HASH_BY_BLOCK_NUMBER, COL_MISC,
-        COL_REWARD_BY_POS_EPOCH, COL_TX_INDEX,
+        COL_ADDRESS_TX_INDEX, COL_BLAMED_HEADER_VERIFIED_ROOTS, COL_BLOCKS,
+        COL_BLOCK_TRACES, COL_EPOCH_NUMBER, COL_HASH_BY_BLOCK_NUMBER,
+        COL_LOG_BLOOM_INDEX, COL_MISC, COL_REWARD_BY_POS_EPOCH, COL_TX_INDEX,
     },
     pow::PowComputer,
     verification::VerificationConfig,
@@ -18,14 +24,20 @@ use cfx_internal_common::{
     DatabaseDecodable, DatabaseEncodable, EpochExecutionCommitment,
 };
 use cfx_storage::{
-    storage_db::KeyValueDbTrait, KvdbRocksdb, KvdbSqlite, KvdbSqliteStatements,
+    storage_db::{KeyValueDbTrait, KeyValueDbTraitTransactionalDyn},
+    KvdbRocksdb, KvdbSqlite, KvdbSqliteStatements,
 };
-use cfx_types::H256;
+use cfx_types::{AddressWithSpace, Bloom, Space, H256};
 use db::SystemDB;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use primitives::{Block, BlockHeader, SignedTransaction, TransactionIndex};
 use rlp::Rlp;
-use std::{collections::HashMap, fs, path::Path, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -37,8 +49,17 @@ const EPOCH_CONSENSUS_EXECUTION_INFO_SUFFIX_BYTE: u8 = 5;
 const EPOCH_EXECUTED_BLOCK_SET_SUFFIX_BYTE: u8 = 6;
 const EPOCH_SKIPPED_BLOCK_SET_SUFFIX_BYTE: u8 = 7;
 const BLOCK_REWARD_RESULT_SUFFIX_BYTE: u8 = 8;
+const FROZEN_EPOCH_SUFFIX_BYTE: u8 = 9;
+const ADDRESS_TX_ENTRIES_SUFFIX_BYTE: u8 = 0;
+/// The number of low bits of an address transaction index cursor for the
+/// offset in the entries of an epoch.
+const ADDRESS_TX_CURSOR_OFFSET_BITS: u32 = 24;
+const ADDRESS_TX_CURSOR_OFFSET_MASK: u64 =
+    (1 << ADDRESS_TX_CURSOR_OFFSET_BITS) - 1;
 const BLOCK_TERMINAL_KEY: &[u8] = b"block_terminals";
 const GC_PROGRESS_KEY: &[u8] = b"gc_progress";
+const PRUNED_EPOCHS_KEY: &[u8] = b"pruned_epochs";
+const LOG_BLOOM_INDEX_PROGRESS_KEY: &[u8] = b"log_bloom_index_progress";
 
 #[derive(Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq, EnumIter)]
 enum DBTable {
@@ -50,6 +71,23 @@ enum DBTable {
     BlockTraces,
     HashByBlockNumber,
     RewardByPosEpoch,
+    LogBloomIndex,
+    AddressTxIndex,
+}
+
+/// The db of a table. Multiple keys can be written atomically in a
+/// transaction.
+trait TableDbTrait:
+    KeyValueDbTrait<ValueType = Box<[u8]>>
+    + KeyValueDbTraitTransactionalDyn<ValueType = Box<[u8]>>
+{
+}
+
+impl<
+        T: KeyValueDbTrait<ValueType = Box<[u8]>>
+            + KeyValueDbTraitTransactionalDyn<ValueType = Box<[u8]>>,
+    > TableDbTrait for T
+{
 }
 
 fn rocks_db_col(table: DBTable) -> u32 {
@@ -62,6 +100,8 @@ fn rocks_db_col(table: DBTable) -> u32 {
         DBTable::BlockTraces => COL_BLOCK_TRACES,
         DBTable::HashByBlockNumber => COL_HASH_BY_BLOCK_NUMBER,
         DBTable::RewardByPosEpoch => COL_REWARD_BY_POS_EPOCH,
+        DBTable::LogBloomIndex => COL_LOG_BLOOM_INDEX,
+        DBTable::AddressTxIndex => COL_ADDRESS_TX_INDEX,
     }
 }
 
@@ -75,12 +115,14 @@ fn sqlite_db_table(table: DBTable) -> String {
         DBTable::BlockTraces => "block_traces",
         DBTable::HashByBlockNumber => "hash_by_block_number",
         DBTable::RewardByPosEpoch => "reward_by_pos_epoch",
+        DBTable::LogBloomIndex => "log_bloom_index",
+        DBTable::AddressTxIndex => "address_tx_index",
     }
     .into()
 }
 
 pub struct DBManager {
-    table_db: HashMap<DBTable, Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>>,
+    table_db: HashMap<DBTable, Box<dyn TableDbTrait>>,
     pow: Arc<PowComputer>,
 }
 
@@ -94,8 +136,7 @@ impl DBManager {
                 Box::new(KvdbRocksdb {
                     kvdb: db.key_value().clone(),
                     col: rocks_db_col(table),
-                })
-                    as Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>,
+                }) as Box<dyn TableDbTrait>,
             );
         }
         Self { table_db, pow }
@@ -126,11 +167,8 @@ impl DBManager {
                 false, /* unsafe_mode */
             )
             .expect("Open sqlite failure");
-            table_db.insert(
-                table,
-                Box::new(sqlite_db)
-                    as Box<dyn KeyValueDbTrait<ValueType = Box<[u8]>>>,
-            );
+            table_db
+                .insert(table, Box::new(sqlite_db) as Box<dyn TableDbTrait>);
         }
         Self { table_db, pow }
     }
@@ -348,6 +386,39 @@ impl DBManager {
         self.remove_from_db(DBTable::BlockTraces, hash.as_bytes())
     }
 
+    /// Return the encoded block body, execution result, and traces of the
+    /// block `hash` to be moved to the freezer.
+    pub fn frozen_block_from_db(&self, hash: &H256) -> FrozenBlock {
+        let load = |table, key: &[u8]| {
+            self.load_from_db(table, key)
+                .map(Vec::from)
+                .unwrap_or_default()
+        };
+        FrozenBlock {
+            hash: *hash,
+            body: load(DBTable::Blocks, &block_body_key(hash)),
+            execution_result: load(
+                DBTable::Blocks,
+                &block_execution_result_key(hash),
+            ),
+            traces: load(DBTable::BlockTraces, hash.as_bytes()),
+        }
+    }
+
+    /// Record that the block data of `hash` has been moved to the freezer
+    /// with the epoch `epoch_number`.
+    pub fn insert_frozen_epoch_to_db(&self, hash: &H256, epoch_number: u64) {
+        self.insert_encodable_val(
+            DBTable::Blocks,
+            &frozen_epoch_key(hash),
+            &epoch_number,
+        )
+    }
+
+    pub fn frozen_epoch_from_db(&self, hash: &H256) -> Option<u64> {
+        self.load_decodable_val(DBTable::Blocks, &frozen_epoch_key(hash))
+    }
+
     pub fn remove_transaction_index_from_db(&self, hash: &H256) {
         self.remove_from_db(DBTable::Transactions, hash.as_bytes())
     }
@@ -475,11 +546,18 @@ impl DBManager {
         self.remove_from_db(DBTable::Blocks, &epoch_execution_context_key(hash))
     }
 
-    pub fn insert_gc_progress_to_db(&self, next_to_process: u64) {
-        self.insert_encodable_val(
+    /// Persist the GC base progress and the earliest not-garbage-collected
+    /// epoch of each kind of data. They are written in one transaction, so
+    /// they are consistent after a crash.
+    pub fn insert_gc_progress_to_db(
+        &self, next_to_process: u64, pruned: &PrunedEpochs,
+    ) {
+        self.write_batch_to_db(
             DBTable::Misc,
-            GC_PROGRESS_KEY,
-            &next_to_process,
+            vec![
+                (GC_PROGRESS_KEY.to_vec(), Some(next_to_process.db_encode())),
+                (PRUNED_EPOCHS_KEY.to_vec(), Some(pruned.db_encode())),
+            ],
         );
     }
 
@@ -487,6 +565,249 @@ impl DBManager {
         self.load_decodable_val(DBTable::Misc, GC_PROGRESS_KEY)
     }
 
+    pub fn pruned_epochs_from_db(&self) -> Option<PrunedEpochs> {
+        self.load_decodable_val(DBTable::Misc, PRUNED_EPOCHS_KEY)
+    }
+
+    pub fn insert_log_bloom_index_progress_to_db(
+        &self, progress: &LogBloomIndexProgress,
+    ) {
+        self.insert_encodable_val(
+            DBTable::Misc,
+            LOG_BLOOM_INDEX_PROGRESS_KEY,
+            progress,
+        );
+    }
+
+    pub fn log_bloom_index_progress_from_db(
+        &self,
+    ) -> Option<LogBloomIndexProgress> {
+        self.load_decodable_val(DBTable::Misc, LOG_BLOOM_INDEX_PROGRESS_KEY)
+    }
+
+    pub fn insert_log_bloom_group_to_db(
+        &self, level: usize, group: u64, bloom: &Bloom,
+    ) {
+        self.insert_encodable_val(
+            DBTable::LogBloomIndex,
+            &log_bloom_group_key(level, group),
+            bloom,
+        );
+    }
+
+    pub fn log_bloom_group_from_db(
+        &self, level: usize, group: u64,
+    ) -> Option<Bloom> {
+        self.load_decodable_val(
+            DBTable::LogBloomIndex,
+            &log_bloom_group_key(level, group),
+        )
+    }
+
+    pub fn remove_log_bloom_group_from_db(&self, level: usize, group: u64) {
+        self.remove_from_db(
+            DBTable::LogBloomIndex,
+            &log_bloom_group_key(level, group),
+        )
+    }
+
+    pub fn address_tx_history_from_db(
+        &self, address: &AddressWithSpace,
+    ) -> AddressTxHistory {
+        self.load_decodable_val(
+            DBTable::AddressTxIndex,
+            &address_tx_history_key(address),
+        )
+        .unwrap_or_default()
+    }
+
+    /// Return the epoch with the sequence number `seq` in the transaction
+    /// history of `address`.
+    pub fn address_tx_epoch_from_db(
+        &self, address: &AddressWithSpace, seq: u64,
+    ) -> Option<u64> {
+        self.load_decodable_val(
+            DBTable::AddressTxIndex,
+            &address_tx_epoch_key(address, seq),
+        )
+    }
+
+    pub fn address_tx_entries_from_db(
+        &self, address: &AddressWithSpace, epoch_number: u64,
+    ) -> Option<Vec<AddressTxIndexEntry>> {
+        self.load_decodable_list(
+            DBTable::AddressTxIndex,
+            &address_tx_entries_key(address, epoch_number),
+        )
+    }
+
+    /// Return at most `limit` entries in the transaction history of
+    /// `address`, from the newest to the oldest, starting from the entry
+    /// `cursor` (or the newest entry if it's `None`). Also return the cursor
+    /// of the next page if there are older entries.
+    ///
+    /// The epochs are ordered by when they are first indexed for the address,
+    /// so the epochs executed again after a pivot chain switch may be out of
+    /// order.
+    pub fn address_tx_entries_page_from_db(
+        &self, address: &AddressWithSpace, cursor: Option<u64>, limit: usize,
+    ) -> (Vec<AddressTxIndexEntry>, Option<u64>) {
+        let history = self.address_tx_history_from_db(address);
+        // The cursor is the sequence number of an epoch and the number of the
+        // entries of the address in the epoch that have been returned.
+        let (mut seq, mut skipped) = match cursor {
+            Some(cursor) => (
+                cursor >> ADDRESS_TX_CURSOR_OFFSET_BITS,
+                (cursor & ADDRESS_TX_CURSOR_OFFSET_MASK) as usize,
+            ),
+            None => (history.next, 0),
+        };
+        if seq >= history.next {
+            seq = history.next;
+            skipped = 0;
+        } else {
+            // Include the epoch of the cursor.
+            seq += 1;
+        }
+        let mut entries = Vec::new();
+        while seq > history.first {
+            seq -= 1;
+            let epoch_entries = match self
+                .address_tx_epoch_from_db(address, seq)
+                .and_then(|epoch_number| {
+                    self.address_tx_entries_from_db(address, epoch_number)
+                }) {
+                Some(epoch_entries) => epoch_entries,
+                None => continue,
+            };
+            for (offset, entry) in
+                epoch_entries.into_iter().rev().enumerate().skip(skipped)
+            {
+                if entries.len() == limit {
+                    return (
+                        entries,
+                        Some(
+                            seq << ADDRESS_TX_CURSOR_OFFSET_BITS
+                                | offset as u64,
+                        ),
+                    );
+                }
+                entries.push(entry);
+            }
+            skipped = 0;
+        }
+        (entries, None)
+    }
+
+    /// Replace the address transaction index entries of the epoch
+    /// `epoch_number` with `entries`. An address keeps the sequence number of
+    /// the epoch if it has been indexed in the epoch before, so indexing an
+    /// epoch again does not add duplicate entries. The caller should make
+    /// sure there are no concurrent writes.
+    pub fn insert_epoch_address_tx_entries_to_db(
+        &self, epoch_number: u64,
+        entries: Vec<(AddressWithSpace, AddressTxIndexEntry)>,
+    )
+    {
+        let mut address_entries: HashMap<_, Vec<_>> = HashMap::new();
+        for (address, entry) in entries {
+            address_entries.entry(address).or_default().push(entry);
+        }
+        let mut slots: Vec<AddressTxSlot> = self
+            .load_decodable_list(
+                DBTable::AddressTxIndex,
+                &address_tx_epoch_slots_key(epoch_number),
+            )
+            .unwrap_or_default();
+        if slots.is_empty() && address_entries.is_empty() {
+            return;
+        }
+        let mut batch = Vec::new();
+        let mut indexed = HashSet::new();
+        // The addresses which are not related to the epoch anymore keep their
+        // slots, in case they are related to it again.
+        for slot in &slots {
+            let address = slot.address_with_space();
+            if !address_entries.contains_key(&address) {
+                batch.push((
+                    address_tx_entries_key(&address, epoch_number),
+                    None,
+                ));
+            }
+            indexed.insert(address);
+        }
+        for (address, entries) in address_entries {
+            if !indexed.contains(&address) {
+                let mut history = self.address_tx_history_from_db(&address);
+                slots.push(AddressTxSlot {
+                    space: address.space,
+                    address: address.address,
+                    seq: history.next,
+                });
+                batch.push((
+                    address_tx_epoch_key(&address, history.next).to_vec(),
+                    Some(epoch_number.db_encode()),
+                ));
+                history.next += 1;
+                batch.push((
+                    address_tx_history_key(&address).to_vec(),
+                    Some(history.db_encode()),
+                ));
+            }
+            batch.push((
+                address_tx_entries_key(&address, epoch_number),
+                Some(db_encode_list(&entries)),
+            ));
+        }
+        batch.push((
+            address_tx_epoch_slots_key(epoch_number).to_vec(),
+            Some(db_encode_list(&slots)),
+        ));
+        self.write_batch_to_db(DBTable::AddressTxIndex, batch);
+    }
+
+    /// Remove the address transaction index entries of the epoch
+    /// `epoch_number`. The caller should make sure there are no concurrent
+    /// writes.
+    pub fn remove_epoch_address_tx_entries_from_db(&self, epoch_number: u64) {
+        let slots: Vec<AddressTxSlot> = match self.load_decodable_list(
+            DBTable::AddressTxIndex,
+            &address_tx_epoch_slots_key(epoch_number),
+        ) {
+            Some(slots) => slots,
+            None => return,
+        };
+        let mut batch = Vec::new();
+        for slot in &slots {
+            let address = slot.address_with_space();
+            batch.push((address_tx_entries_key(&address, epoch_number), None));
+            batch.push((
+                address_tx_epoch_key(&address, slot.seq).to_vec(),
+                None,
+            ));
+            // The epochs are mostly garbage collected in the order of their
+            // sequence numbers, so `first` is only advanced over the removed
+            // ones.
+            let mut history = self.address_tx_history_from_db(&address);
+            if slot.seq == history.first {
+                history.first += 1;
+                while history.first < history.next
+                    && self
+                        .address_tx_epoch_from_db(&address, history.first)
+                        .is_none()
+                {
+                    history.first += 1;
+                }
+                batch.push((
+                    address_tx_history_key(&address).to_vec(),
+                    Some(history.db_encode()),
+                ));
+            }
+        }
+        batch.push((address_tx_epoch_slots_key(epoch_number).to_vec(), None));
+        self.write_batch_to_db(DBTable::AddressTxIndex, batch);
+    }
+
     pub fn insert_pos_reward(
         &self, pos_epoch: u64, pos_reward: &PosRewardInfo,
     ) {
@@ -524,6 +845,25 @@ impl DBManager {
             .expect("db removal failure");
     }
 
+    /// Write the values in `batch` to `table` in one transaction. The keys
+    /// with `None` values are removed.
+    fn write_batch_to_db(
+        &self, table: DBTable, batch: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    ) {
+        let db = self.table_db.get(&table).unwrap();
+        let mut transaction = db
+            .start_transaction_dyn(true /* immediate_write */)
+            .expect("db transaction failure");
+        for (db_key, value) in batch {
+            match value {
+                Some(value) => transaction.put(&db_key, &value),
+                None => transaction.delete(&db_key),
+            }
+            .expect("db batch write failure");
+        }
+        transaction.commit(db.as_any()).expect("db commit failure");
+    }
+
     fn load_from_db(&self, table: DBTable, db_key: &[u8]) -> Option<Box<[u8]>> {
         self.table_db
             .get(&table)
@@ -590,6 +930,10 @@ fn block_body_key(block_hash: &H256) -> Vec<u8> {
     append_suffix(block_hash, BLOCK_BODY_SUFFIX_BYTE)
 }
 
+fn frozen_epoch_key(block_hash: &H256) -> Vec<u8> {
+    append_suffix(block_hash, FROZEN_EPOCH_SUFFIX_BYTE)
+}
+
 fn executed_epoch_set_key(epoch_number: u64) -> [u8; 9] {
     let mut epoch_key = [0; 9];
     LittleEndian::write_u64(&mut epoch_key[0..8], epoch_number);
@@ -604,6 +948,47 @@ fn skipped_epoch_set_key(epoch_number: u64) -> [u8; 9] {
     epoch_key
 }
 
+/// The groups of the same level are stored together in the order of epochs.
+fn log_bloom_group_key(level: usize, group: u64) -> [u8; 9] {
+    let mut group_key = [0; 9];
+    group_key[0] = level as u8;
+    group_key[1..9].copy_from_slice(&group.to_be_bytes());
+    group_key
+}
+
+fn address_tx_history_key(address: &AddressWithSpace) -> [u8; 21] {
+    let mut history_key = [0; 21];
+    history_key[0] = match address.space {
+        Space::Native => 0,
+        Space::Ethereum => 1,
+    };
+    history_key[1..21].copy_from_slice(address.address.as_bytes());
+    history_key
+}
+
+/// The epochs in the transaction history of the same address are stored
+/// together in the order of their sequence numbers.
+fn address_tx_epoch_key(address: &AddressWithSpace, seq: u64) -> [u8; 29] {
+    let mut epoch_key = [0; 29];
+    epoch_key[0..21].copy_from_slice(&address_tx_history_key(address));
+    epoch_key[21..29].copy_from_slice(&seq.to_be_bytes());
+    epoch_key
+}
+
+fn address_tx_entries_key(
+    address: &AddressWithSpace, epoch_number: u64,
+) -> Vec<u8> {
+    let mut entries_key = Vec::with_capacity(30);
+    entries_key.extend_from_slice(&address_tx_history_key(address));
+    entries_key.extend_from_slice(&epoch_number.to_be_bytes());
+    entries_key.push(ADDRESS_TX_ENTRIES_SUFFIX_BYTE);
+    entries_key
+}
+
+fn address_tx_epoch_slots_key(epoch_number: u64) -> [u8; 8] {
+    epoch_number.to_be_bytes()
+}
+
 fn block_execution_result_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, BLOCK_EXECUTION_RESULT_SUFFIX_BYTE)
 }
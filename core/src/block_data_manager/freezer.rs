@@ -0,0 +1,336 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The freezer keeps the block data of the epochs that can no longer be
+//! reverted in append-only segment files instead of the key-value database.
+//!
+//! Each segment stores `epochs_per_segment` consecutive epochs in two files:
+//! `<segment>.dat` holds the compressed record of each epoch, and
+//! `<segment>.idx` holds the end offset of each record in `<segment>.dat` as
+//! a big-endian u64. A record is written before its index entry, so a
+//! partially written record is discarded when the freezer is reopened.
+
+use crate::block_data_manager::{
+    BlockExecutionResultWithEpoch, BlockTracesWithEpoch,
+};
+use cfx_internal_common::DatabaseDecodable;
+use cfx_types::H256;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use parking_lot::Mutex;
+use primitives::{Block, SignedTransaction};
+use rlp::Rlp;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::{
+    cmp::max,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The number of epochs stored in one segment.
+pub const EPOCHS_PER_FREEZER_SEGMENT: u64 = 100_000;
+
+const INDEX_ENTRY_SIZE: u64 = 8;
+
+/// The block data of a frozen block. Each field is encoded in the same format
+/// as in the database, and is empty if the data does not exist when the
+/// block is frozen.
+#[derive(Clone, Debug, Default, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct FrozenBlock {
+    pub hash: H256,
+    pub body: Vec<u8>,
+    pub execution_result: Vec<u8>,
+    pub traces: Vec<u8>,
+}
+
+impl FrozenBlock {
+    pub fn block_body(&self) -> Option<Vec<Arc<SignedTransaction>>> {
+        if self.body.is_empty() {
+            return None;
+        }
+        Some(
+            Block::decode_body_with_tx_public(&Rlp::new(&self.body))
+                .expect("Wrong block rlp format!"),
+        )
+    }
+
+    pub fn execution_result(&self) -> Option<BlockExecutionResultWithEpoch> {
+        decode_field(&self.execution_result)
+    }
+
+    pub fn traces(&self) -> Option<BlockTracesWithEpoch> {
+        decode_field(&self.traces)
+    }
+}
+
+fn decode_field<V: DatabaseDecodable>(encoded: &[u8]) -> Option<V> {
+    if encoded.is_empty() {
+        None
+    } else {
+        Some(V::db_decode(encoded).expect("decode succeeds"))
+    }
+}
+
+/// The position to append the next epoch.
+struct FreezerTail {
+    next_epoch: u64,
+    /// The end offset of the last record in the data file of the segment of
+    /// `next_epoch`.
+    next_offset: u64,
+}
+
+pub struct Freezer {
+    dir: PathBuf,
+    epochs_per_segment: u64,
+    /// Also held while appending an epoch.
+    tail: Mutex<FreezerTail>,
+}
+
+impl Freezer {
+    /// Open the freezer in `dir`, or create an empty one starting from
+    /// epoch 0.
+    pub fn open(dir: &Path, epochs_per_segment: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut last_segment = None;
+        for entry in fs::read_dir(dir)? {
+            let file_name = entry?.file_name();
+            if let Some(segment) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".idx"))
+                .and_then(|segment| segment.parse::<u64>().ok())
+            {
+                last_segment = max(last_segment, Some(segment));
+            }
+        }
+        let mut freezer = Self {
+            dir: dir.to_path_buf(),
+            epochs_per_segment,
+            tail: Mutex::new(FreezerTail {
+                next_epoch: 0,
+                next_offset: 0,
+            }),
+        };
+        if let Some(segment) = last_segment {
+            let (epoch_count, next_offset) =
+                freezer.recover_segment(segment)?;
+            *freezer.tail.get_mut() = FreezerTail {
+                next_epoch: segment * epochs_per_segment + epoch_count,
+                next_offset,
+            };
+        }
+        Ok(freezer)
+    }
+
+    /// Remove the partially written records and index entries at the end of
+    /// `segment`. Return the number of epochs in it and the end offset of
+    /// its last record.
+    fn recover_segment(&self, segment: u64) -> io::Result<(u64, u64)> {
+        let mut index_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(self.index_path(segment))?;
+        let data_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(self.data_path(segment))?;
+        let data_len = data_file.metadata()?.len();
+        let mut epoch_count = index_file.metadata()?.len() / INDEX_ENTRY_SIZE;
+        let mut next_offset = 0;
+        while epoch_count > 0 {
+            next_offset = read_index_entry(&mut index_file, epoch_count - 1)?;
+            if next_offset <= data_len {
+                break;
+            }
+            next_offset = 0;
+            epoch_count -= 1;
+        }
+        index_file.set_len(epoch_count * INDEX_ENTRY_SIZE)?;
+        data_file.set_len(next_offset)?;
+        Ok((epoch_count, next_offset))
+    }
+
+    /// The epoch to append next. All the epochs before it are frozen.
+    pub fn next_epoch(&self) -> u64 { self.tail.lock().next_epoch }
+
+    /// Append the blocks of `epoch`, which must be `self.next_epoch()`.
+    pub fn append_epoch(
+        &self, epoch: u64, blocks: &[FrozenBlock],
+    ) -> io::Result<()> {
+        let mut tail = self.tail.lock();
+        if epoch != tail.next_epoch {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Epoch {} is not the next epoch {} to freeze",
+                    epoch, tail.next_epoch
+                ),
+            ));
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&rlp::encode_list(blocks))?;
+        let record = encoder.finish()?;
+
+        let segment = epoch / self.epochs_per_segment;
+        let start = if epoch % self.epochs_per_segment == 0 {
+            0
+        } else {
+            tail.next_offset
+        };
+        let end = start + record.len() as u64;
+        let mut data_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(self.data_path(segment))?;
+        // Overwrite the data left by a failed append.
+        data_file.set_len(start)?;
+        data_file.seek(SeekFrom::Start(start))?;
+        data_file.write_all(&record)?;
+        data_file.sync_data()?;
+        let mut index_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(self.index_path(segment))?;
+        let index_offset = (epoch % self.epochs_per_segment) * INDEX_ENTRY_SIZE;
+        index_file.set_len(index_offset)?;
+        index_file.seek(SeekFrom::Start(index_offset))?;
+        index_file.write_all(&end.to_be_bytes())?;
+        index_file.sync_data()?;
+
+        tail.next_epoch = epoch + 1;
+        tail.next_offset = end;
+        Ok(())
+    }
+
+    /// Return the blocks of `epoch`, or `None` if it is not frozen.
+    pub fn epoch_blocks(
+        &self, epoch: u64,
+    ) -> io::Result<Option<Vec<FrozenBlock>>> {
+        if epoch >= self.next_epoch() {
+            return Ok(None);
+        }
+        let segment = epoch / self.epochs_per_segment;
+        let position = epoch % self.epochs_per_segment;
+        let mut index_file = File::open(self.index_path(segment))?;
+        let start = if position == 0 {
+            0
+        } else {
+            read_index_entry(&mut index_file, position - 1)?
+        };
+        let end = read_index_entry(&mut index_file, position)?;
+
+        let mut data_file = File::open(self.data_path(segment))?;
+        data_file.seek(SeekFrom::Start(start))?;
+        let mut record = vec![0; (end - start) as usize];
+        data_file.read_exact(&mut record)?;
+        let mut encoded = Vec::new();
+        ZlibDecoder::new(&record[..]).read_to_end(&mut encoded)?;
+        let blocks = Rlp::new(&encoded).as_list().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid frozen epoch {}: {:?}", epoch, e),
+            )
+        })?;
+        Ok(Some(blocks))
+    }
+
+    /// Return the frozen block `hash` in `epoch`.
+    pub fn frozen_block(
+        &self, epoch: u64, hash: &H256,
+    ) -> io::Result<Option<FrozenBlock>> {
+        Ok(self.epoch_blocks(epoch)?.and_then(|blocks| {
+            blocks.into_iter().find(|block| block.hash == *hash)
+        }))
+    }
+
+    fn data_path(&self, segment: u64) -> PathBuf {
+        self.dir.join(format!("{:010}.dat", segment))
+    }
+
+    fn index_path(&self, segment: u64) -> PathBuf {
+        self.dir.join(format!("{:010}.idx", segment))
+    }
+}
+
+fn read_index_entry(index_file: &mut File, position: u64) -> io::Result<u64> {
+    let mut entry = [0; INDEX_ENTRY_SIZE as usize];
+    index_file.seek(SeekFrom::Start(position * INDEX_ENTRY_SIZE))?;
+    index_file.read_exact(&mut entry)?;
+    Ok(u64::from_be_bytes(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn new_test_blocks(epoch: u64) -> Vec<FrozenBlock> {
+        (0..epoch % 3)
+            .map(|i| FrozenBlock {
+                hash: H256::from_low_u64_be(epoch * 10 + i),
+                body: vec![epoch as u8; 100],
+                execution_result: vec![i as u8; 10],
+                traces: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let dir = TempDir::new("freezer_test").unwrap();
+        let freezer = Freezer::open(dir.path(), 2).unwrap();
+        for epoch in 0..5 {
+            freezer
+                .append_epoch(epoch, &new_test_blocks(epoch))
+                .unwrap();
+        }
+        assert!(freezer.append_epoch(6, &[]).is_err());
+        assert_eq!(freezer.next_epoch(), 5);
+
+        let freezer = Freezer::open(dir.path(), 2).unwrap();
+        assert_eq!(freezer.next_epoch(), 5);
+        for epoch in 0..5 {
+            assert_eq!(
+                freezer.epoch_blocks(epoch).unwrap(),
+                Some(new_test_blocks(epoch))
+            );
+        }
+        assert_eq!(freezer.epoch_blocks(5).unwrap(), None);
+        assert_eq!(
+            freezer.frozen_block(2, &H256::from_low_u64_be(21)).unwrap(),
+            Some(new_test_blocks(2)[1].clone())
+        );
+        assert_eq!(
+            freezer.frozen_block(2, &H256::from_low_u64_be(11)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recover_partial_append() {
+        let dir = TempDir::new("freezer_test").unwrap();
+        let freezer = Freezer::open(dir.path(), 4).unwrap();
+        for epoch in 0..3 {
+            freezer
+                .append_epoch(epoch, &new_test_blocks(epoch))
+                .unwrap();
+        }
+        // Simulate a crash after writing the record of epoch 2 but before
+        // its index entry is written completely.
+        let index_path = freezer.index_path(0);
+        let index_len = fs::metadata(&index_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&index_path)
+            .unwrap()
+            .set_len(index_len - 3)
+            .unwrap();
+
+        let freezer = Freezer::open(dir.path(), 4).unwrap();
+        assert_eq!(freezer.next_epoch(), 2);
+        freezer.append_epoch(2, &new_test_blocks(5)).unwrap();
+        assert_eq!(freezer.epoch_blocks(2).unwrap(), Some(new_test_blocks(5)));
+        assert_eq!(freezer.epoch_blocks(1).unwrap(), Some(new_test_blocks(1)));
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_types::Bloom;
+use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
+use rlp::DecoderError;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::cmp::{max, min};
+
+/// The number of epochs in a bloom group at each level of the log bloom index,
+/// from the lowest level to the highest level. The group size of a level must
+/// be a multiple of the group size of the level below.
+pub const LOG_BLOOM_INDEX_LEVEL_SIZES: [u64; 3] = [16, 256, 4096];
+
+/// The log bloom index keeps, for each level in `LOG_BLOOM_INDEX_LEVEL_SIZES`,
+/// the union of the blooms of all blocks in a group of consecutive epochs, so
+/// the log filter can skip a whole group if its bloom does not match.
+///
+/// Blooms are only added to a group and never removed from it. After a pivot
+/// chain reorg, a group contains the blooms of both the old and the new epoch
+/// sets, which is still a superset and safe for skipping epochs.
+///
+/// All epochs in `[start, next)` are indexed. Epochs executed out of this
+/// range are also added to their groups, but the groups are only used for
+/// the epochs in this range.
+#[derive(
+    Clone, Copy, Debug, DeriveMallocSizeOf, RlpEncodable, RlpDecodable,
+)]
+pub struct LogBloomIndexProgress {
+    // The earliest indexed epoch. The groups that end before it have been
+    // garbage collected.
+    pub start: u64,
+
+    // The next epoch to index. It is only advanced one epoch at a time, so
+    // there is no gap in the indexed epochs.
+    pub next: u64,
+}
+
+impl_db_encoding_as_rlp!(LogBloomIndexProgress);
+
+impl LogBloomIndexProgress {
+    pub fn new(start: u64) -> Self { Self { start, next: start } }
+
+    fn covers(&self, from_epoch: u64, to_epoch: u64) -> bool {
+        self.start <= from_epoch && to_epoch < self.next
+    }
+
+    /// Return the ranges `[start, end]` in `[from_epoch, to_epoch]` that may
+    /// contain logs matching any of `bloom_possibilities`, in ascending order.
+    /// `load_group(level, group)` loads the bloom of the `group`-th group of
+    /// `level` from the index.
+    pub fn candidate_epoch_ranges<F>(
+        &self, from_epoch: u64, to_epoch: u64, bloom_possibilities: &[Bloom],
+        load_group: F,
+    ) -> Vec<(u64, u64)>
+    where F: Fn(usize, u64) -> Option<Bloom> {
+        let mut ranges = Vec::new();
+        if from_epoch > to_epoch {
+            return ranges;
+        }
+        let top_level = LOG_BLOOM_INDEX_LEVEL_SIZES.len() - 1;
+        let group_size = LOG_BLOOM_INDEX_LEVEL_SIZES[top_level];
+        for group in from_epoch / group_size..=to_epoch / group_size {
+            self.collect_candidate_epoch_ranges(
+                top_level,
+                group,
+                from_epoch,
+                to_epoch,
+                bloom_possibilities,
+                &load_group,
+                &mut ranges,
+            );
+        }
+        ranges
+    }
+
+    fn collect_candidate_epoch_ranges<F>(
+        &self, level: usize, group: u64, from_epoch: u64, to_epoch: u64,
+        bloom_possibilities: &[Bloom], load_group: &F,
+        ranges: &mut Vec<(u64, u64)>,
+    ) where F: Fn(usize, u64) -> Option<Bloom> {
+        let group_size = LOG_BLOOM_INDEX_LEVEL_SIZES[level];
+        let start = max(group * group_size, from_epoch);
+        let end = min((group + 1) * group_size - 1, to_epoch);
+
+        // The group bloom can only be trusted if all the epochs we are
+        // interested in have been added to it.
+        if self.covers(start, end) {
+            if let Some(group_bloom) = load_group(level, group) {
+                if !bloom_possibilities
+                    .iter()
+                    .any(|bloom| group_bloom.contains_bloom(bloom))
+                {
+                    return;
+                }
+            }
+        }
+
+        if level == 0 {
+            match ranges.last_mut() {
+                Some(last) if last.1 + 1 == start => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+            return;
+        }
+
+        let sub_group_size = LOG_BLOOM_INDEX_LEVEL_SIZES[level - 1];
+        for sub_group in start / sub_group_size..=end / sub_group_size {
+            self.collect_candidate_epoch_ranges(
+                level - 1,
+                sub_group,
+                start,
+                end,
+                bloom_possibilities,
+                load_group,
+                ranges,
+            );
+        }
+    }
+}
+
+/// Return the `(level, group)` of all groups that contain `epoch`.
+pub fn log_bloom_groups_of_epoch(
+    epoch: u64,
+) -> impl Iterator<Item = (usize, u64)> {
+    LOG_BLOOM_INDEX_LEVEL_SIZES
+        .iter()
+        .enumerate()
+        .map(move |(level, group_size)| (level, epoch / group_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfx_types::{Address, BloomInput};
+    use std::collections::HashMap;
+
+    fn address_bloom(n: u64) -> Bloom {
+        BloomInput::Raw(Address::from_low_u64_be(n).as_bytes()).into()
+    }
+
+    fn build_index(
+        epoch_blooms: &HashMap<u64, Bloom>, progress: &LogBloomIndexProgress,
+    ) -> HashMap<(usize, u64), Bloom> {
+        let mut groups: HashMap<(usize, u64), Bloom> = HashMap::new();
+        for epoch in progress.start..progress.next {
+            let bloom =
+                epoch_blooms.get(&epoch).cloned().unwrap_or(Bloom::zero());
+            for key in log_bloom_groups_of_epoch(epoch) {
+                groups.entry(key).or_default().accrue_bloom(&bloom);
+            }
+        }
+        groups
+    }
+
+    #[test]
+    fn test_groups_of_epoch() {
+        assert_eq!(
+            log_bloom_groups_of_epoch(0).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (2, 0)]
+        );
+        assert_eq!(
+            log_bloom_groups_of_epoch(4097).collect::<Vec<_>>(),
+            vec![(0, 256), (1, 16), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_candidate_epoch_ranges() {
+        let mut epoch_blooms = HashMap::new();
+        epoch_blooms.insert(5, address_bloom(1));
+        epoch_blooms.insert(300, address_bloom(2));
+        epoch_blooms.insert(5000, address_bloom(1));
+        let progress = LogBloomIndexProgress {
+            start: 0,
+            next: 6000,
+        };
+        let groups = build_index(&epoch_blooms, &progress);
+        let load_group = |level, group| groups.get(&(level, group)).cloned();
+
+        assert_eq!(
+            progress.candidate_epoch_ranges(
+                0,
+                5999,
+                &[address_bloom(1)],
+                load_group
+            ),
+            vec![(0, 15), (4992, 5007)]
+        );
+        assert_eq!(
+            progress.candidate_epoch_ranges(
+                10,
+                5003,
+                &[address_bloom(1), address_bloom(2)],
+                load_group
+            ),
+            vec![(10, 15), (288, 303), (4992, 5003)]
+        );
+        assert_eq!(
+            progress.candidate_epoch_ranges(
+                0,
+                5999,
+                &[address_bloom(3)],
+                load_group
+            ),
+            vec![]
+        );
+        // An empty bloom matches everything.
+        assert_eq!(
+            progress.candidate_epoch_ranges(
+                100,
+                200,
+                &[Bloom::zero()],
+                load_group
+            ),
+            vec![(100, 200)]
+        );
+    }
+
+    #[test]
+    fn test_candidate_epoch_ranges_out_of_index() {
+        let progress = LogBloomIndexProgress {
+            start: 1000,
+            next: 2000,
+        };
+        let groups = build_index(&HashMap::new(), &progress);
+        let load_group = |level, group| groups.get(&(level, group)).cloned();
+
+        // Epochs out of `[start, next)` are always checked one by one, so are
+        // the other epochs in their lowest level groups.
+        assert_eq!(
+            progress.candidate_epoch_ranges(
+                900,
+                2100,
+                &[address_bloom(1)],
+                load_group
+            ),
+            vec![(900, 1007), (2000, 2100)]
+        );
+    }
+}
@@ -13,7 +13,7 @@ use cfx_storage::{
     state_manager::StateIndex, utils::guarded_value::*, StorageManager,
     StorageManagerTrait, StorageStateTrait,
 };
-use cfx_types::{Bloom, Space, H256};
+use cfx_types::{AddressWithSpace, Bloom, Space, H256};
 use malloc_size_of::{new_malloc_size_ops, MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard};
@@ -25,17 +25,25 @@ use primitives::{
 };
 use rlp::DecoderError;
 use std::{
+    cmp::{max, min},
     collections::{HashMap, HashSet},
-    sync::Arc,
+    sync::{mpsc, Arc},
+    thread,
 };
 use threadpool::ThreadPool;
+pub mod address_tx_index;
 pub mod block_data_types;
 pub mod db_gc_manager;
 pub mod db_manager;
+pub mod freezer;
+pub mod log_bloom_index;
 pub mod tx_data_manager;
 use crate::{
     block_data_manager::{
-        db_manager::DBManager, tx_data_manager::TransactionDataManager,
+        address_tx_index::{address_tx_index_entries, AddressTxIndexEntry},
+        db_manager::DBManager,
+        freezer::{Freezer, FrozenBlock, EPOCHS_PER_FREEZER_SEGMENT},
+        tx_data_manager::TransactionDataManager,
     },
     consensus::pos_handler::PosVerifier,
     executive::internal_contract::{
@@ -47,10 +55,22 @@ pub use block_data_types::*;
 use cfx_internal_common::{
     EpochExecutionCommitment, StateAvailabilityBoundary, StateRootWithAuxInfo,
 };
-use db_gc_manager::GCProgress;
+use db_gc_manager::{
+    DataPruningStatus, GCProgress, PrunableData, PrunedEpochs, PruningPolicy,
+    RetentionPolicy, MAX_PRUNED_EPOCHS_PER_GC,
+};
+use log_bloom_index::{
+    log_bloom_groups_of_epoch, LogBloomIndexProgress,
+    LOG_BLOOM_INDEX_LEVEL_SIZES,
+};
 use metrics::{register_meter_with_group, Meter, MeterTimer};
 use primitives::pos::PosBlockId;
-use std::{hash::Hash, path::Path, time::Duration};
+use std::{
+    hash::Hash,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use strum::IntoEnumIterator;
 
 lazy_static! {
     static ref TX_POOL_RECOVER_TIMER: Arc<dyn Meter> =
@@ -59,6 +79,10 @@ lazy_static! {
 
 pub const NULLU64: u64 = !0;
 
+/// The maximum number of epochs to move to the freezer in one
+/// `freeze_ancient_epochs` call.
+const MAX_FROZEN_EPOCHS_PER_BATCH: u64 = 2000;
+
 #[derive(DeriveMallocSizeOf)]
 pub struct InvalidBlockSet {
     capacity: usize,
@@ -143,6 +167,20 @@ pub struct BlockDataManager {
     cache_man: Arc<Mutex<CacheManager<CacheId>>>,
     pub target_difficulty_manager: TargetDifficultyManager,
     gc_progress: Arc<Mutex<GCProgress>>,
+    /// The range of epochs covered by the log bloom index. The lock is also
+    /// held while updating the bloom groups.
+    log_bloom_index_progress: Mutex<LogBloomIndexProgress>,
+    /// Held while writing the address transaction index, so the transaction
+    /// histories of addresses are updated consistently.
+    address_tx_index_lock: Mutex<()>,
+    /// Keeps the block bodies, execution results, and traces of the
+    /// PoS-finalized epochs if `config.freezer_dir` is set.
+    #[ignore_malloc_size_of = "Data is on disk"]
+    freezer: Option<Freezer>,
+    /// Sends the latest PoS-finalized epoch to the freezer thread if it has
+    /// been started.
+    #[ignore_malloc_size_of = "channels are not handled in MallocSizeOf"]
+    freezer_sender: Mutex<Option<mpsc::Sender<u64>>>,
 
     /// This maintains the boundary height of available state and commitments
     /// (executed but not deleted or in `ExecutionTaskQueue`).
@@ -196,6 +234,20 @@ impl BlockDataManager {
         };
         let previous_db_progress =
             db_manager.gc_progress_from_db().unwrap_or(0);
+        let pruned_epochs =
+            db_manager.pruned_epochs_from_db().unwrap_or_else(|| {
+                PrunedEpochs::from_legacy_gc_progress(
+                    previous_db_progress,
+                    &config.pruning_policy,
+                )
+            });
+        let log_bloom_index_progress = db_manager
+            .log_bloom_index_progress_from_db()
+            .unwrap_or(LogBloomIndexProgress::new(previous_db_progress));
+        let freezer = config.freezer_dir.as_ref().map(|dir| {
+            Freezer::open(dir, EPOCHS_PER_FREEZER_SEGMENT)
+                .expect("Failed to open the block data freezer")
+        });
 
         let data_man = Self {
             block_headers: RwLock::new(HashMap::new()),
@@ -231,7 +283,12 @@ impl BlockDataManager {
             ),
             gc_progress: Arc::new(Mutex::new(GCProgress::new(
                 previous_db_progress,
+                pruned_epochs,
             ))),
+            log_bloom_index_progress: Mutex::new(log_bloom_index_progress),
+            address_tx_index_lock: Mutex::new(()),
+            freezer,
+            freezer_sender: Mutex::new(None),
         };
 
         data_man.initialize_instance_id();
@@ -416,7 +473,7 @@ impl BlockDataManager {
         self.get(
             hash,
             &self.blocks,
-            |key| self.db_manager.block_from_db(key).map(Arc::new),
+            |key| self.block_from_db(key).map(Arc::new),
             if update_cache {
                 Some(CacheId::Block(*hash))
             } else {
@@ -427,7 +484,10 @@ impl BlockDataManager {
 
     /// This function returns the block from db without wrapping it in `Arc`.
     pub fn block_from_db(&self, hash: &H256) -> Option<Block> {
-        self.db_manager.block_from_db(hash)
+        Some(Block::new(
+            self.db_manager.block_header_from_db(hash)?,
+            self.load_block_body(hash)?,
+        ))
     }
 
     pub fn blocks_by_hash_list(
@@ -479,19 +539,17 @@ impl BlockDataManager {
             .and_then(|traces_info| traces_info.get_current_data());
         // Make sure the ReadLock of `block_traces` is dropped here.
         let maybe_traces = maybe_traces_in_mem.or_else(|| {
-            self.db_manager.block_traces_from_db(hash).map(
-                |traces_with_epoch| {
-                    self.block_traces
-                        .write()
-                        .entry(*hash)
-                        .or_insert(BlockTracesInfo::default())
-                        .insert_data(
-                            &traces_with_epoch.0,
-                            traces_with_epoch.1.clone(),
-                        );
-                    traces_with_epoch
-                },
-            )
+            self.load_block_traces(hash).map(|traces_with_epoch| {
+                self.block_traces
+                    .write()
+                    .entry(*hash)
+                    .or_insert(BlockTracesInfo::default())
+                    .insert_data(
+                        &traces_with_epoch.0,
+                        traces_with_epoch.1.clone(),
+                    );
+                traces_with_epoch
+            })
         });
         if maybe_traces.is_some() {
             self.cache_man.lock().note_used(CacheId::BlockTraces(*hash));
@@ -524,7 +582,7 @@ impl BlockDataManager {
                 true => Some(CacheId::BlockTraces(*hash)),
                 false => None,
             },
-            |key| self.db_manager.block_traces_from_db(key),
+            |key| self.load_block_traces(key),
             |key, result| {
                 self.db_manager.insert_block_traces_to_db(key, result);
             },
@@ -648,7 +706,7 @@ impl BlockDataManager {
                 true => Some(CacheId::BlockReceipts(*hash)),
                 false => None,
             },
-            |key| self.db_manager.block_execution_result_from_db(key),
+            |key| self.load_block_execution_result(key),
             |key, result| {
                 self.db_manager
                     .insert_block_execution_result_to_db(key, result);
@@ -659,7 +717,7 @@ impl BlockDataManager {
     pub fn block_execution_result_by_hash_from_db(
         &self, hash: &H256,
     ) -> Option<BlockExecutionResultWithEpoch> {
-        self.db_manager.block_execution_result_from_db(hash)
+        self.load_block_execution_result(hash)
     }
 
     pub fn block_epoch_number(&self, hash: &H256) -> Option<u64> {
@@ -1016,6 +1074,44 @@ impl BlockDataManager {
         Some(res)
     }
 
+    /// Load the block body from db, or from the freezer if it's frozen.
+    fn load_block_body(
+        &self, hash: &H256,
+    ) -> Option<Vec<Arc<SignedTransaction>>> {
+        self.db_manager
+            .block_body_from_db(hash)
+            .or_else(|| self.frozen_block(hash)?.block_body())
+    }
+
+    fn load_block_execution_result(
+        &self, hash: &H256,
+    ) -> Option<BlockExecutionResultWithEpoch> {
+        self.db_manager
+            .block_execution_result_from_db(hash)
+            .or_else(|| self.frozen_block(hash)?.execution_result())
+    }
+
+    fn load_block_traces(&self, hash: &H256) -> Option<BlockTracesWithEpoch> {
+        self.db_manager
+            .block_traces_from_db(hash)
+            .or_else(|| self.frozen_block(hash)?.traces())
+    }
+
+    fn frozen_block(&self, hash: &H256) -> Option<FrozenBlock> {
+        let freezer = self.freezer.as_ref()?;
+        let epoch_number = self.db_manager.frozen_epoch_from_db(hash)?;
+        match freezer.frozen_block(epoch_number, hash) {
+            Ok(block) => block,
+            Err(e) => {
+                error!(
+                    "Failed to read frozen block {:?} in epoch {}: {}",
+                    hash, epoch_number, e
+                );
+                None
+            }
+        }
+    }
+
     pub fn insert_terminals_to_db(&self, terminals: Vec<H256>) {
         self.db_manager.insert_terminals_to_db(&terminals)
     }
@@ -1636,27 +1732,226 @@ impl BlockDataManager {
     }
 
     pub fn earliest_epoch_with_block_body(&self) -> u64 {
-        match self.config.additional_maintained_block_body_epoch_count {
-            Some(defer) => self.gc_progress.lock().gc_end - defer as u64,
-            None => 0,
-        }
+        self.earliest_epoch_with(PrunableData::BlockBody)
     }
 
     pub fn earliest_epoch_with_execution_result(&self) -> u64 {
-        match self
-            .config
-            .additional_maintained_execution_result_epoch_count
+        self.earliest_epoch_with(PrunableData::ExecutionResult)
+    }
+
+    pub fn earliest_epoch_with_trace(&self) -> u64 {
+        self.earliest_epoch_with(PrunableData::Trace)
+    }
+
+    /// Return the earliest epoch whose `data` is available. The data that
+    /// will be garbage collected after the latest checkpoint is regarded as
+    /// unavailable.
+    fn earliest_epoch_with(&self, data: PrunableData) -> u64 {
+        let gc_progress = self.gc_progress.lock();
+        let pruned = gc_progress.pruned.get(data);
+        match self.config.pruning_policy.get(data) {
+            RetentionPolicy::KeepEpochs(epochs) => {
+                max(pruned, gc_progress.gc_end.saturating_sub(epochs))
+            }
+            RetentionPolicy::Archive | RetentionPolicy::KeepSince(_) => pruned,
+        }
+    }
+
+    /// Return the retention policy and the GC progress of each kind of data.
+    pub fn pruning_status(&self) -> Vec<DataPruningStatus> {
+        PrunableData::iter()
+            .map(|data| {
+                let policy = self.config.pruning_policy.get(data);
+                let gc_progress = self.gc_progress.lock();
+                DataPruningStatus {
+                    data,
+                    policy,
+                    earliest_epoch: gc_progress.pruned.get(data),
+                    target_epoch: gc_progress.pruning_target(policy),
+                }
+            })
+            .collect()
+    }
+
+    /// Add the bloom of the epoch `epoch_number` to the log bloom index. This
+    /// is called each time an epoch is executed, so the groups include the
+    /// blooms of all epoch sets that have been on the pivot chain.
+    pub fn insert_epoch_bloom_to_log_bloom_index(
+        &self, epoch_number: u64, bloom: &Bloom,
+    ) {
+        let mut progress = self.log_bloom_index_progress.lock();
+        if epoch_number < progress.start {
+            return;
+        }
+        for (level, group) in log_bloom_groups_of_epoch(epoch_number) {
+            let mut group_bloom =
+                match self.db_manager.log_bloom_group_from_db(level, group) {
+                    Some(b) if b.contains_bloom(bloom) => continue,
+                    Some(b) => b,
+                    None => Bloom::zero(),
+                };
+            group_bloom.accrue_bloom(bloom);
+            self.db_manager.insert_log_bloom_group_to_db(
+                level,
+                group,
+                &group_bloom,
+            );
+        }
+        if epoch_number == progress.next {
+            progress.next += 1;
+            self.db_manager
+                .insert_log_bloom_index_progress_to_db(&progress);
+        }
+    }
+
+    /// Add the blooms of at most `max_epoch_count` epochs not later than
+    /// `to_epoch` that are not covered by the log bloom index yet, e.g.,
+    /// epochs executed before the node restarts or before the index exists.
+    /// Return the number of epochs added.
+    pub fn rebuild_log_bloom_index(
+        &self, to_epoch: u64, max_epoch_count: u64,
+    ) -> u64 {
+        let mut indexed_count = 0;
+        while indexed_count < max_epoch_count {
+            let epoch_number = self.log_bloom_index_progress.lock().next;
+            if epoch_number > to_epoch {
+                break;
+            }
+            match self.epoch_bloom_from_execution_results(epoch_number) {
+                Some(bloom) => self.insert_epoch_bloom_to_log_bloom_index(
+                    epoch_number,
+                    &bloom,
+                ),
+                None => {
+                    // If the execution results have been garbage collected,
+                    // we start the index from the earliest epoch that still
+                    // has them. Otherwise the epoch is not executed yet.
+                    let earliest = self.earliest_epoch_with_execution_result();
+                    if epoch_number >= earliest {
+                        break;
+                    }
+                    self.gc_log_bloom_index(earliest);
+                    continue;
+                }
+            }
+            indexed_count += 1;
+        }
+        indexed_count
+    }
+
+    /// Return the epoch ranges in `[from_epoch, to_epoch]` that may contain
+    /// logs matching any of `bloom_possibilities` according to the log bloom
+    /// index, in ascending order.
+    pub fn log_bloom_index_candidate_epoch_ranges(
+        &self, from_epoch: u64, to_epoch: u64, bloom_possibilities: &[Bloom],
+    ) -> Vec<(u64, u64)> {
+        let progress = *self.log_bloom_index_progress.lock();
+        progress.candidate_epoch_ranges(
+            from_epoch,
+            to_epoch,
+            bloom_possibilities,
+            |level, group| {
+                self.db_manager.log_bloom_group_from_db(level, group)
+            },
+        )
+    }
+
+    fn epoch_bloom_from_execution_results(
+        &self, epoch_number: u64,
+    ) -> Option<Bloom> {
+        // The true genesis is not executed and has no logs.
+        if epoch_number == 0 {
+            return Some(Bloom::zero());
+        }
+        let epoch_set = self.executed_epoch_set_hashes_from_db(epoch_number)?;
+        let pivot_hash = epoch_set.last()?;
+        let mut bloom = Bloom::zero();
+        for hash in &epoch_set {
+            let execution_result = self
+                .block_execution_result_by_hash_with_epoch(
+                    hash, pivot_hash, false, /* update_pivot_assumption */
+                    false, /* update_cache */
+                )?;
+            bloom.accrue_bloom(&execution_result.bloom);
+        }
+        Some(bloom)
+    }
+
+    /// Remove the log bloom groups that only contain epochs before
+    /// `new_start`.
+    fn gc_log_bloom_index(&self, new_start: u64) {
+        let mut progress = self.log_bloom_index_progress.lock();
+        if new_start <= progress.start {
+            return;
+        }
+        for (level, group_size) in
+            LOG_BLOOM_INDEX_LEVEL_SIZES.iter().enumerate()
         {
-            Some(defer) => self.gc_progress.lock().gc_end - defer as u64,
-            None => 0,
+            for group in progress.start / group_size..new_start / group_size {
+                self.db_manager.remove_log_bloom_group_from_db(level, group);
+            }
         }
+        progress.start = new_start;
+        progress.next = max(progress.next, new_start);
+        self.db_manager
+            .insert_log_bloom_index_progress_to_db(&progress);
     }
 
-    pub fn earliest_epoch_with_trace(&self) -> u64 {
-        match self.config.additional_maintained_trace_epoch_count {
-            Some(defer) => self.gc_progress.lock().gc_end - defer as u64,
-            None => 0,
+    /// Return the address transaction index entries of the transactions
+    /// executed in `block`, or an empty list if the index is disabled.
+    /// `traces` are the traces of the block if `executive_trace` is enabled,
+    /// and are used to index the addresses of internal transfers.
+    pub fn block_address_tx_index_entries(
+        &self, epoch_number: u64, pivot_hash: &H256, block: &Block,
+        receipts: &[Receipt], traces: Option<&[TransactionExecTraces]>,
+    ) -> Vec<(AddressWithSpace, AddressTxIndexEntry)>
+    {
+        if !self.config.persist_address_tx_index {
+            return Vec::new();
         }
+        address_tx_index_entries(
+            epoch_number,
+            pivot_hash,
+            &block.hash(),
+            &block.transactions,
+            receipts,
+            traces,
+        )
+    }
+
+    /// Replace the address transaction index entries of the epoch
+    /// `epoch_number` with `entries`, which are all the entries of the blocks
+    /// in the epoch executed on the pivot chain.
+    pub fn insert_epoch_address_tx_index(
+        &self, epoch_number: u64,
+        entries: Vec<(AddressWithSpace, AddressTxIndexEntry)>,
+    )
+    {
+        if !self.config.persist_address_tx_index {
+            return;
+        }
+        let _lock = self.address_tx_index_lock.lock();
+        self.db_manager
+            .insert_epoch_address_tx_entries_to_db(epoch_number, entries);
+    }
+
+    /// Return at most `limit` entries in the transaction history of
+    /// `address`, from the newest to the oldest, starting from the entry
+    /// `cursor` (or the newest entry if it's `None`). Also return the cursor
+    /// of the next page if there are older entries.
+    ///
+    /// The epochs are ordered by when they are first indexed for the address,
+    /// so the epochs executed again after a pivot chain switch may be out of
+    /// order.
+    pub fn address_tx_index_entries(
+        &self, address: &AddressWithSpace, cursor: Option<u64>, limit: usize,
+    ) -> (Vec<AddressTxIndexEntry>, Option<u64>) {
+        self.db_manager
+            .address_tx_entries_page_from_db(address, cursor, limit)
+    }
+
+    pub fn persist_address_tx_index(&self) -> bool {
+        self.config.persist_address_tx_index
     }
 
     pub fn new_checkpoint(
@@ -1672,118 +1967,262 @@ impl BlockDataManager {
     pub fn database_gc(&self, best_epoch: u64) {
         let maybe_range = self.gc_progress.lock().get_gc_base_range(best_epoch);
         debug!("Start database GC, range={:?}", maybe_range);
-        if let Some((start, end)) = maybe_range {
-            for base_epoch in start..end {
-                self.gc_base_epoch(base_epoch);
-            }
+        if let Some((_, end)) = maybe_range {
             let mut gc_progress = self.gc_progress.lock();
             gc_progress.last_consensus_best_epoch = best_epoch;
             gc_progress.next_to_process = end;
-            self.db_manager.insert_gc_progress_to_db(end);
+        }
+        let mut pruned = false;
+        for data in PrunableData::iter() {
+            pruned |= self.prune_data(data);
+        }
+        if maybe_range.is_some() || pruned {
+            let gc_progress = self.gc_progress.lock();
+            self.db_manager.insert_gc_progress_to_db(
+                gc_progress.next_to_process,
+                &gc_progress.pruned,
+            );
             debug!("Database GC progress: {:?}", gc_progress);
         }
     }
 
-    /// Garbage collect different types of data in the corresponding epoch based
-    /// on `base_epoch` and the `additional_maintained*` parameters of these
-    /// data types.
-    fn gc_base_epoch(&self, base_epoch: u64) {
-        // We must GC tx index before block body, otherwise we may be unable to
-        // get the transactions in this epoch.
-        if let Some(defer_epochs) = self
-            .config
-            .additional_maintained_transaction_index_epoch_count
+    /// Garbage collect at most `MAX_PRUNED_EPOCHS_PER_GC` epochs of `data`
+    /// that should not be kept according to its retention policy. This also
+    /// removes the data kept by the previous policy if the policy has been
+    /// tightened. Return `true` if any epoch is garbage collected.
+    fn prune_data(&self, data: PrunableData) -> bool {
+        let policy = self.config.pruning_policy.get(data);
+        let (pruned, target) = {
+            let gc_progress = self.gc_progress.lock();
+            (
+                gc_progress.pruned.get(data),
+                gc_progress
+                    .pruning_target(policy)
+                    .unwrap_or(gc_progress.next_to_process),
+            )
+        };
+        // The true genesis is never garbage collected.
+        let start = max(pruned, 1);
+        let end = min(target, start + MAX_PRUNED_EPOCHS_PER_GC);
+        let mut epoch = start;
+        while epoch < end {
+            if let RetentionPolicy::KeepSince(timestamp) = policy {
+                if !self.is_epoch_earlier_than(epoch, timestamp) {
+                    break;
+                }
+            }
+            self.prune_epoch(data, epoch);
+            epoch += 1;
+        }
+        if epoch == start {
+            return false;
+        }
+        self.gc_progress.lock().pruned.set(data, epoch);
+        if data == PrunableData::ExecutionResult {
+            // Logs cannot be filtered without execution results, so the
+            // corresponding bloom groups are useless.
+            self.gc_log_bloom_index(epoch);
+        }
+        true
+    }
+
+    /// Return `true` if the pivot block of the epoch is earlier than
+    /// `timestamp`. An epoch whose pivot block is missing is not considered
+    /// earlier, so its data is kept until the pivot block is available.
+    fn is_epoch_earlier_than(&self, epoch_number: u64, timestamp: u64) -> bool {
+        match self
+            .executed_epoch_set_hashes_from_db(epoch_number)
+            .and_then(|epoch_set| epoch_set.last().cloned())
+            .and_then(|pivot_hash| self.block_header_by_hash(&pivot_hash))
         {
-            if base_epoch > defer_epochs as u64 {
-                let epoch_to_remove = base_epoch - defer_epochs as u64;
-                match self.all_epoch_set_hashes_from_db(epoch_to_remove) {
-                    None => warn!(
+            Some(pivot_header) => pivot_header.timestamp() < timestamp,
+            None => {
+                warn!("GC pivot block is missing! epoch: {}", epoch_number);
+                false
+            }
+        }
+    }
+
+    /// Garbage collect `data` in the epoch `epoch_to_remove`.
+    fn prune_epoch(&self, data: PrunableData, epoch_to_remove: u64) {
+        let epoch_blocks =
+            match self.all_epoch_set_hashes_from_db(epoch_to_remove) {
+                Some(epoch_blocks) => epoch_blocks,
+                None => {
+                    warn!(
                         "GC epoch set is missing! epoch_to_remove: {}",
                         epoch_to_remove
-                    ),
-                    Some(epoch_blocks) => {
-                        // Store all packed transactions in a set first to
-                        // deduplicate transactions for database operations.
-                        let mut transaction_set = HashSet::new();
-                        for b in &epoch_blocks {
-                            if let Some(transactions) =
-                                self.db_manager.block_body_from_db(&b)
-                            {
-                                for tx in transactions {
-                                    transaction_set.insert(tx.hash());
-                                }
-                            }
-                        }
-                        let epoch_block_set: HashSet<H256> =
-                            epoch_blocks.into_iter().collect();
-                        for tx in transaction_set {
-                            if self.config.strict_tx_index_gc {
-                                // Check if this tx is actually executed in the
-                                // processed epoch.
-                                if let Some(tx_index) = self
-                                    .db_manager
-                                    .transaction_index_from_db(&tx)
-                                {
-                                    if epoch_block_set
-                                        .contains(&tx_index.block_hash)
-                                    {
-                                        self.db_manager
-                                            .remove_transaction_index_from_db(
-                                                &tx,
-                                            );
-                                    }
-                                }
-                            } else {
-                                self.db_manager
-                                    .remove_transaction_index_from_db(&tx);
-                            }
-                        }
+                    );
+                    return;
+                }
+            };
+        match data {
+            PrunableData::TransactionIndex => {
+                self.gc_transaction_index(epoch_blocks);
+                // The address transaction index is kept as long as the
+                // transaction index.
+                let _lock = self.address_tx_index_lock.lock();
+                self.db_manager
+                    .remove_epoch_address_tx_entries_from_db(epoch_to_remove);
+            }
+            PrunableData::BlockBody => {
+                for h in &epoch_blocks {
+                    self.remove_block_body(h, true /* remove_db */);
+                }
+            }
+            PrunableData::ExecutionResult => {
+                for h in &epoch_blocks {
+                    self.remove_block_result(h, true /* remove_db */);
+                }
+            }
+            PrunableData::Reward => {
+                for h in &epoch_blocks {
+                    self.db_manager.remove_block_reward_result_from_db(h);
+                }
+            }
+            PrunableData::Trace => {
+                for h in &epoch_blocks {
+                    self.db_manager.remove_block_trace_from_db(h);
+                }
+            }
+        }
+    }
+
+    fn gc_transaction_index(&self, epoch_blocks: Vec<H256>) {
+        // Store all packed transactions in a set first to deduplicate
+        // transactions for database operations.
+        let mut transaction_set = HashSet::new();
+        for b in &epoch_blocks {
+            if let Some(transactions) = self.load_block_body(&b) {
+                for tx in transactions {
+                    transaction_set.insert(tx.hash());
+                }
+            }
+        }
+        let epoch_block_set: HashSet<H256> = epoch_blocks.into_iter().collect();
+        for tx in transaction_set {
+            if self.config.strict_tx_index_gc {
+                // Check if this tx is actually executed in the processed
+                // epoch.
+                if let Some(tx_index) =
+                    self.db_manager.transaction_index_from_db(&tx)
+                {
+                    if epoch_block_set.contains(&tx_index.block_hash) {
+                        self.db_manager.remove_transaction_index_from_db(&tx);
                     }
                 }
+            } else {
+                self.db_manager.remove_transaction_index_from_db(&tx);
             }
-        };
-        self.gc_epoch_with_defer(
-            base_epoch,
-            self.config.additional_maintained_block_body_epoch_count,
-            |h| self.remove_block_body(h, true /* remove_db */),
-        );
-        self.gc_epoch_with_defer(
-            base_epoch,
-            self.config
-                .additional_maintained_execution_result_epoch_count,
-            |h| self.remove_block_result(h, true /* remove_db */),
-        );
-        self.gc_epoch_with_defer(
-            base_epoch,
-            self.config.additional_maintained_reward_epoch_count,
-            |h| self.db_manager.remove_block_reward_result_from_db(h),
-        );
-        self.gc_epoch_with_defer(
-            base_epoch,
-            self.config.additional_maintained_trace_epoch_count,
-            |h| self.db_manager.remove_block_trace_from_db(h),
-        );
+        }
     }
 
-    fn gc_epoch_with_defer<F>(
-        &self, epoch_number: u64, maybe_defer_epochs: Option<usize>, gc_func: F,
-    ) where F: Fn(&H256) -> () {
-        if let Some(defer_epochs) = maybe_defer_epochs {
-            if epoch_number > defer_epochs as u64 {
-                let epoch_to_remove = epoch_number - defer_epochs as u64;
-                match self.all_epoch_set_hashes_from_db(epoch_to_remove) {
-                    None => warn!(
-                        "GC epoch set is missing! epoch_to_remove: {}",
-                        epoch_to_remove
-                    ),
-                    Some(epoch_set) => {
-                        for b in epoch_set {
-                            gc_func(&b);
+    /// Start the thread that moves the PoS-finalized epochs to the freezer,
+    /// so the disk writes of the freezer do not block the callers of
+    /// `notify_finalized_epoch`. It does nothing if the freezer is disabled.
+    pub fn start_freezer_thread(self: &Arc<Self>) {
+        if self.freezer.is_none() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        *self.freezer_sender.lock() = Some(sender);
+        // The thread only keeps a weak reference, so it exits after the data
+        // manager, which owns the sender, is dropped.
+        let data_man = Arc::downgrade(self);
+        thread::Builder::new()
+            .name("Block Data Freezer".into())
+            .spawn(move || {
+                while let Ok(mut finalized_epoch) = receiver.recv() {
+                    // Only the latest finalized epoch matters.
+                    while let Ok(epoch) = receiver.try_recv() {
+                        finalized_epoch = max(finalized_epoch, epoch);
+                    }
+                    loop {
+                        let data_man = match data_man.upgrade() {
+                            Some(data_man) => data_man,
+                            None => return,
+                        };
+                        if !data_man.freeze_ancient_epochs(finalized_epoch) {
+                            break;
                         }
                     }
                 }
+            })
+            .expect("Failed to start the block data freezer thread");
+    }
+
+    /// Notify the freezer thread that the epochs before `finalized_epoch` can
+    /// be frozen. It does nothing if the freezer thread is not started.
+    pub fn notify_finalized_epoch(&self, finalized_epoch: u64) {
+        if let Some(sender) = &*self.freezer_sender.lock() {
+            // The thread only exits after the sender is dropped.
+            sender.send(finalized_epoch).ok();
+        }
+    }
+
+    /// Move the block bodies, execution results, and traces of at most
+    /// `MAX_FROZEN_EPOCHS_PER_BATCH` epochs before `finalized_epoch` from db
+    /// to the freezer. The epochs before the PoS-finalized epoch can never be
+    /// reverted, so the moved data will not be rewritten.
+    ///
+    /// Return `true` if a whole batch is frozen and there may be more epochs
+    /// to freeze.
+    fn freeze_ancient_epochs(&self, finalized_epoch: u64) -> bool {
+        let freezer = match &self.freezer {
+            Some(freezer) => freezer,
+            None => return false,
+        };
+        let start = freezer.next_epoch();
+        let end = min(finalized_epoch, start + MAX_FROZEN_EPOCHS_PER_BATCH);
+        for epoch_number in start..end {
+            let blocks = match self.frozen_blocks_of_epoch(epoch_number) {
+                Some(blocks) => blocks,
+                None => break,
+            };
+            if let Err(e) = freezer.append_epoch(epoch_number, &blocks) {
+                error!("Failed to freeze epoch {}: {}", epoch_number, e);
+                return false;
+            }
+            // The data is removed from db after the freezer has it, so it's
+            // always available in one of them.
+            for block in &blocks {
+                self.db_manager
+                    .insert_frozen_epoch_to_db(&block.hash, epoch_number);
+                self.db_manager.remove_block_body_from_db(&block.hash);
+                self.db_manager
+                    .remove_block_execution_result_from_db(&block.hash);
+                self.db_manager.remove_block_trace_from_db(&block.hash);
             }
         }
+        if freezer.next_epoch() != start {
+            debug!("Froze epochs {}..{}", start, freezer.next_epoch());
+        }
+        freezer.next_epoch() == start + MAX_FROZEN_EPOCHS_PER_BATCH
+    }
+
+    /// Return the block data of the epoch `epoch_number` to freeze, or `None`
+    /// if the epoch has not been executed.
+    fn frozen_blocks_of_epoch(
+        &self, epoch_number: u64,
+    ) -> Option<Vec<FrozenBlock>> {
+        let epoch_blocks = self.all_epoch_set_hashes_from_db(epoch_number)?;
+        let pivot_hash = epoch_blocks.last()?;
+        // The true genesis is not executed. We do not wait for the execution
+        // results that have been garbage collected.
+        if epoch_number != 0
+            && epoch_number >= self.earliest_epoch_with_execution_result()
+        {
+            match self.db_manager.block_execution_result_from_db(pivot_hash) {
+                Some(DataVersionTuple(pivot, _)) if pivot == *pivot_hash => {}
+                _ => return None,
+            }
+        }
+        Some(
+            epoch_blocks
+                .iter()
+                .map(|hash| self.db_manager.frozen_block_from_db(hash))
+                .collect(),
+        )
     }
 }
 
@@ -1796,15 +2235,15 @@ pub enum DbType {
 pub struct DataManagerConfiguration {
     pub persist_tx_index: bool,
     pub persist_block_number_index: bool,
+    pub persist_address_tx_index: bool,
     pub tx_cache_index_maintain_timeout: Duration,
     pub db_type: DbType,
-    pub additional_maintained_block_body_epoch_count: Option<usize>,
-    pub additional_maintained_execution_result_epoch_count: Option<usize>,
-    pub additional_maintained_reward_epoch_count: Option<usize>,
-    pub additional_maintained_trace_epoch_count: Option<usize>,
-    pub additional_maintained_transaction_index_epoch_count: Option<usize>,
+    pub pruning_policy: PruningPolicy,
     pub checkpoint_gc_time_in_epoch_count: usize,
     pub strict_tx_index_gc: bool,
+    /// The directory of the block data freezer, or `None` if the freezer is
+    /// disabled.
+    pub freezer_dir: Option<PathBuf>,
 }
 
 impl MallocSizeOf for DataManagerConfiguration {
@@ -1820,15 +2259,13 @@ impl DataManagerConfiguration {
         Self {
             persist_tx_index,
             persist_block_number_index,
+            persist_address_tx_index: false,
             tx_cache_index_maintain_timeout,
             db_type,
-            additional_maintained_block_body_epoch_count: None,
-            additional_maintained_execution_result_epoch_count: None,
-            additional_maintained_reward_epoch_count: None,
-            additional_maintained_trace_epoch_count: None,
-            additional_maintained_transaction_index_epoch_count: None,
+            pruning_policy: PruningPolicy::archive(),
             checkpoint_gc_time_in_epoch_count: 1,
             strict_tx_index_gc: true,
+            freezer_dir: None,
         }
     }
 }
@@ -16,9 +16,10 @@ use crate::{
     executive::{
         internal_contract::{
             build_bloom_and_recover_phantom, impls::pos::decode_register_info,
+            record_epoch_history,
         },
         revert_reason_decode, ExecutionError, ExecutionOutcome, Executive,
-        TransactOptions,
+        ExecutiveGeneric, TransactOptions,
     },
     machine::Machine,
     observer::trace::{ExecTrace, TransactionExecTraces},
@@ -28,7 +29,7 @@ use crate::{
         prefetcher::{
             prefetch_accounts, ExecutionStatePrefetcher, PrefetchTaskHandle,
         },
-        State,
+        State, StateGeneric, Substate, TransactionRunner,
     },
     verification::{
         compute_receipts_root, VerificationConfig, VerifyTxLocalMode,
@@ -41,15 +42,15 @@ use cfx_internal_common::{
     debug::*, EpochExecutionCommitment, StateRootWithAuxInfo,
 };
 use cfx_parameters::consensus::*;
-use cfx_state::{state_trait::*, CleanupMode};
+use cfx_state::{state_trait::*, CleanupMode, CollateralCheckResult};
 use cfx_statedb::{ErrorKind as DbErrorKind, Result as DbResult, StateDb};
 use cfx_storage::{
     defaults::DEFAULT_EXECUTION_PREFETCH_THREADS, StateIndex,
-    StorageManagerTrait,
+    StorageManagerTrait, StorageStateTrait,
 };
 use cfx_types::{
-    address_util::AddressUtil, AddressSpaceUtil, AllChainID, BigEndianHash,
-    Space, H160, H256, KECCAK_EMPTY_BLOOM, U256, U512,
+    address_util::AddressUtil, AddressSpaceUtil, AddressWithSpace, AllChainID,
+    BigEndianHash, Bloom, Space, H160, H256, KECCAK_EMPTY_BLOOM, U256, U512,
 };
 use core::convert::TryFrom;
 use hash::KECCAK_EMPTY_LIST_RLP;
@@ -114,6 +115,18 @@ impl Debug for RewardExecutionInfo {
     }
 }
 
+/// An account change requested through the dev RPCs. The changes registered
+/// for a block are applied before the transactions of the epoch whose pivot
+/// block is this block, so they are dropped with the epoch if the block
+/// leaves the pivot chain.
+#[derive(Clone, Debug)]
+pub enum DevStateOverride {
+    Balance(AddressWithSpace, U256),
+    Nonce(AddressWithSpace, U256),
+    Code(AddressWithSpace, Vec<u8>),
+    Storage(AddressWithSpace, H256, U256),
+}
+
 #[derive(Debug)]
 enum ExecutionTask {
     ExecuteEpoch(EpochExecutionTask),
@@ -803,6 +816,9 @@ pub struct ConsensusExecutionHandler {
     machine: Arc<Machine>,
     pos_verifier: Arc<PosVerifier>,
     execution_state_prefetcher: Option<Arc<ExecutionStatePrefetcher>>,
+    /// The account changes registered by the dev RPCs, keyed by the block
+    /// that applies them. They are only kept in memory.
+    dev_state_overrides: RwLock<HashMap<H256, Vec<DevStateOverride>>>,
 }
 
 impl ConsensusExecutionHandler {
@@ -835,9 +851,107 @@ impl ConsensusExecutionHandler {
             } else {
                 None
             },
+            dev_state_overrides: Default::default(),
         }
     }
 
+    /// Register the account changes applied at the beginning of the epoch of
+    /// `block_hash`. This must be called before the epoch is executed.
+    pub fn set_dev_state_overrides(
+        &self, block_hash: H256, overrides: Vec<DevStateOverride>,
+    ) {
+        self.dev_state_overrides
+            .write()
+            .insert(block_hash, overrides);
+    }
+
+    /// Apply the account changes registered for `pivot_hash`. A storage
+    /// change in the native space is skipped if the contract cannot pay the
+    /// storage collateral.
+    fn apply_dev_state_overrides(
+        &self, state: &mut State, pivot_hash: &H256, spec: &Spec,
+    ) -> DbResult<()> {
+        let overrides = match self.dev_state_overrides.read().get(pivot_hash) {
+            Some(overrides) => overrides.clone(),
+            None => return Ok(()),
+        };
+        for state_override in overrides {
+            state.checkpoint();
+            match &state_override {
+                DevStateOverride::Balance(address, balance) => {
+                    let current_balance = state.balance(address)?;
+                    if *balance >= current_balance {
+                        let by = *balance - current_balance;
+                        state.add_balance(
+                            address,
+                            &by,
+                            CleanupMode::ForceCreate,
+                            spec.account_start_nonce,
+                        )?;
+                        state.add_total_issued(by);
+                        if address.space == Space::Ethereum {
+                            state.add_total_evm_tokens(by);
+                        }
+                    } else {
+                        let by = current_balance - *balance;
+                        state.sub_balance(
+                            address,
+                            &by,
+                            &mut CleanupMode::NoEmpty,
+                        )?;
+                        state.subtract_total_issued(by);
+                        if address.space == Space::Ethereum {
+                            state.subtract_total_evm_tokens(by);
+                        }
+                    }
+                }
+                DevStateOverride::Nonce(address, nonce) => {
+                    state.set_nonce(address, nonce)?;
+                }
+                DevStateOverride::Code(address, code) => {
+                    state.add_balance(
+                        address,
+                        &U256::zero(),
+                        CleanupMode::ForceCreate,
+                        spec.account_start_nonce,
+                    )?;
+                    state.init_code(address, code.clone(), address.address)?;
+                }
+                DevStateOverride::Storage(address, key, value) => {
+                    state.add_balance(
+                        address,
+                        &U256::zero(),
+                        CleanupMode::ForceCreate,
+                        spec.account_start_nonce,
+                    )?;
+                    state.set_storage(
+                        address,
+                        key.as_bytes().to_vec(),
+                        *value,
+                        address.address,
+                    )?;
+                }
+            }
+            let mut substate = Substate::new();
+            state.collect_ownership_changed(&mut substate)?;
+            match state.settle_collateral_for_all(
+                &substate,
+                &mut (),
+                spec.account_start_nonce,
+            )? {
+                CollateralCheckResult::Valid => state.discard_checkpoint(),
+                res => {
+                    warn!(
+                        "Skip dev state override {:?}: {:?}",
+                        state_override, res
+                    );
+                    state.revert_to_checkpoint();
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Always return `true` for now
     fn handle_execution_work(&self, task: ExecutionTask) -> bool {
         debug!("Receive execution task: {:?}", task);
@@ -1136,6 +1250,18 @@ impl ConsensusExecutionHandler {
             BlockHeaderBuilder::compute_block_logs_bloom_hash(&epoch_receipts),
         );
 
+        let epoch_bloom = epoch_receipts
+            .iter()
+            .flat_map(|block_receipts| block_receipts.receipts.iter())
+            .fold(Bloom::zero(), |mut b, r| {
+                b.accrue_bloom(&r.log_bloom);
+                b
+            });
+        self.data_man.insert_epoch_bloom_to_log_bloom_index(
+            pivot_block.block_header.height(),
+            &epoch_bloom,
+        );
+
         let epoch_execution_commitment = self
             .data_man
             .get_epoch_execution_commitment(&epoch_hash)
@@ -1193,6 +1319,11 @@ impl ConsensusExecutionHandler {
         drop(prefetch_join_handles);
 
         let pivot_block = epoch_blocks.last().expect("Epoch not empty");
+        self.apply_dev_state_overrides(
+            state,
+            &pivot_block.hash(),
+            &self.machine.spec(start_block_number),
+        )?;
 
         let mut epoch_receipts = Vec::with_capacity(epoch_blocks.len());
         let mut epoch_staking_events = Vec::new();
@@ -1203,7 +1334,20 @@ impl ConsensusExecutionHandler {
         let last_block_header =
             &self.data_man.block_header_by_hash(&last_block_hash);
 
+        if self.machine.spec(start_block_number).cip_epoch_history {
+            record_epoch_history(
+                state,
+                pivot_block.block_header.height(),
+                &last_block_hash,
+                &last_block_header
+                    .as_ref()
+                    .and_then(|header| *header.pos_reference()),
+                pivot_block.block_header.deferred_state_root(),
+            )?;
+        }
+
         let mut evm_tx_index = 0;
+        let mut address_tx_entries = Vec::new();
 
         for block in epoch_blocks.iter() {
             let mut cfx_tx_index = 0;
@@ -1258,21 +1402,28 @@ impl ConsensusExecutionHandler {
             last_block_hash = block.hash();
             let mut block_traces: Vec<TransactionExecTraces> =
                 Default::default();
-            for (idx, transaction) in block.transactions.iter().enumerate() {
+            let runner = BlockTransactionRunner {
+                transactions: &block.transactions,
+                env: &env,
+                machine: self.machine.as_ref(),
+                spec: &spec,
+                executive_trace: self.config.executive_trace,
+            };
+            let outcomes = if self.config.parallel_execution {
+                state.execute_in_parallel(block.transactions.len(), &runner)?
+            } else {
+                (0..block.transactions.len())
+                    .map(|idx| runner.run(&mut *state, idx))
+                    .collect::<DbResult<Vec<_>>>()?
+            };
+            for ((idx, transaction), r) in
+                block.transactions.iter().enumerate().zip(outcomes)
+            {
                 let tx_outcome_status;
                 let mut transaction_logs = Vec::new();
                 let mut storage_released = Vec::new();
                 let mut storage_collateralized = Vec::new();
 
-                let options = if self.config.executive_trace {
-                    TransactOptions::with_tracing()
-                } else {
-                    TransactOptions::with_no_tracing()
-                };
-                let r =
-                    Executive::new(state, &env, self.machine.as_ref(), &spec)
-                        .transact(transaction, options)?;
-
                 let gas_fee;
                 let mut gas_sponsor_paid = false;
                 let mut storage_sponsor_paid = false;
@@ -1461,6 +1612,22 @@ impl ConsensusExecutionHandler {
                 }
             }
 
+            if on_local_pivot {
+                address_tx_entries.extend(
+                    self.data_man.block_address_tx_index_entries(
+                        pivot_block.block_header.height(),
+                        &pivot_block.hash(),
+                        block,
+                        &receipts,
+                        if self.config.executive_trace {
+                            Some(&block_traces[..])
+                        } else {
+                            None
+                        },
+                    ),
+                );
+            }
+
             if self.config.executive_trace {
                 self.data_man.insert_block_traces(
                     block.hash(),
@@ -1485,6 +1652,12 @@ impl ConsensusExecutionHandler {
 
             epoch_receipts.push(block_receipts);
         }
+        if on_local_pivot {
+            self.data_man.insert_epoch_address_tx_index(
+                pivot_block.block_header.height(),
+                address_tx_entries,
+            );
+        }
         if self.pos_verifier.pos_option().is_some() {
             debug!(
                 "put_staking_events: {:?} height={} len={}",
@@ -1989,4 +2162,32 @@ impl ConsensusExecutionHandler {
 
 pub struct ConsensusExecutionConfiguration {
     pub executive_trace: bool,
+    /// Execute the transactions of a block speculatively in parallel.
+    pub parallel_execution: bool,
+}
+
+/// Executes the transactions of a block, either one by one or with
+/// `State::execute_in_parallel`.
+struct BlockTransactionRunner<'a> {
+    transactions: &'a [Arc<SignedTransaction>],
+    env: &'a Env,
+    machine: &'a Machine,
+    spec: &'a Spec,
+    executive_trace: bool,
+}
+
+impl<'a> TransactionRunner for BlockTransactionRunner<'a> {
+    type Output = ExecutionOutcome;
+
+    fn run<S: StorageStateTrait>(
+        &self, state: &mut StateGeneric<S>, index: usize,
+    ) -> DbResult<ExecutionOutcome> {
+        let options = if self.executive_trace {
+            TransactOptions::with_tracing()
+        } else {
+            TransactOptions::with_no_tracing()
+        };
+        ExecutiveGeneric::new(state, self.env, self.machine, self.spec)
+            .transact(&self.transactions[index], options)
+    }
 }
@@ -0,0 +1,220 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Gas price estimation from the recently packed transactions and the ready
+//! transactions in the transaction pool.
+
+use crate::transaction_pool::ReadyPriceBucket;
+use cfx_types::{Space, U256};
+
+/// The gas prices suggested for a transaction to be packed within the
+/// configured numbers of blocks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GasPriceEstimates {
+    pub slow: U256,
+    pub normal: U256,
+    pub fast: U256,
+}
+
+/// The data of a space sampled for gas price estimation.
+#[derive(Clone, Debug, Default)]
+pub struct GasPriceSample {
+    /// The gas prices of the transactions in the recent blocks, sorted in
+    /// ascending order.
+    pub packed_prices: Vec<U256>,
+    /// The total gas limit of the sampled transactions.
+    pub packed_gas: U256,
+    /// The total gas limit available to the space in the sampled blocks.
+    pub block_gas_capacity: U256,
+    /// The gas limit available to the space in the next block.
+    pub next_block_gas_capacity: U256,
+    /// The ready transactions in the transaction pool, in the descending
+    /// order of gas price.
+    pub ready_buckets: Vec<ReadyPriceBucket>,
+}
+
+pub trait GasPriceOracle: Send + Sync {
+    fn estimate(
+        &self, space: Space, sample: &GasPriceSample,
+    ) -> GasPriceEstimates;
+}
+
+/// The target of an estimate.
+#[derive(Clone, Copy, Debug)]
+pub struct GasPriceTarget {
+    /// The percentile (0 to 100) of the recently packed gas prices.
+    pub percentile: u8,
+    /// The number of blocks within which the transaction should be packed,
+    /// given the ready transactions with higher gas prices in the pool.
+    pub blocks: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct GasPriceOracleConfig {
+    pub slow: GasPriceTarget,
+    pub normal: GasPriceTarget,
+    pub fast: GasPriceTarget,
+    /// If the sampled transactions use less than this percentage of the
+    /// block gas capacity, the recently packed gas prices are ignored.
+    pub low_utilization_percent: u64,
+    /// The lowest estimate, which is the minimal gas price accepted by the
+    /// transaction pool.
+    pub min_gas_price: U256,
+}
+
+impl Default for GasPriceOracleConfig {
+    fn default() -> Self {
+        GasPriceOracleConfig {
+            slow: GasPriceTarget {
+                percentile: 10,
+                blocks: 40,
+            },
+            normal: GasPriceTarget {
+                percentile: 40,
+                blocks: 10,
+            },
+            fast: GasPriceTarget {
+                percentile: 75,
+                blocks: 2,
+            },
+            low_utilization_percent: 20,
+            min_gas_price: U256::one(),
+        }
+    }
+}
+
+/// Each estimate is the maximum of the percentile of the recently packed gas
+/// prices and the gas price needed to outbid the ready transactions which
+/// fill the target number of blocks.
+pub struct DefaultGasPriceOracle {
+    config: GasPriceOracleConfig,
+}
+
+impl DefaultGasPriceOracle {
+    pub fn new(config: GasPriceOracleConfig) -> Self {
+        DefaultGasPriceOracle { config }
+    }
+
+    fn packed_price(
+        &self, sample: &GasPriceSample, target: &GasPriceTarget,
+    ) -> U256 {
+        if sample.packed_prices.is_empty()
+            || sample.packed_gas * 100
+                < sample.block_gas_capacity
+                    * self.config.low_utilization_percent
+        {
+            return U256::zero();
+        }
+        let index = (sample.packed_prices.len() - 1)
+            * target.percentile.min(100) as usize
+            / 100;
+        sample.packed_prices[index]
+    }
+
+    fn pool_price(
+        &self, sample: &GasPriceSample, target: &GasPriceTarget,
+    ) -> U256 {
+        let capacity = sample.next_block_gas_capacity * target.blocks;
+        let mut total_gas = U256::zero();
+        for bucket in &sample.ready_buckets {
+            total_gas += bucket.total_gas;
+            if total_gas >= capacity {
+                return bucket.max_price;
+            }
+        }
+        U256::zero()
+    }
+}
+
+impl GasPriceOracle for DefaultGasPriceOracle {
+    fn estimate(
+        &self, _space: Space, sample: &GasPriceSample,
+    ) -> GasPriceEstimates {
+        let estimate = |target: &GasPriceTarget| {
+            self.packed_price(sample, target)
+                .max(self.pool_price(sample, target))
+                .max(self.config.min_gas_price)
+        };
+        let slow = estimate(&self.config.slow);
+        // A faster target never suggests a lower gas price.
+        let normal = estimate(&self.config.normal).max(slow);
+        let fast = estimate(&self.config.fast).max(normal);
+        GasPriceEstimates { slow, normal, fast }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(price: u64, total_gas: u64) -> ReadyPriceBucket {
+        ReadyPriceBucket {
+            min_price: price.into(),
+            max_price: price.into(),
+            tx_count: 1,
+            total_gas: total_gas.into(),
+        }
+    }
+
+    fn sample(
+        packed_gas: u64, ready_buckets: Vec<ReadyPriceBucket>,
+    ) -> GasPriceSample {
+        GasPriceSample {
+            packed_prices: (1..=100u64).map(U256::from).collect(),
+            packed_gas: packed_gas.into(),
+            block_gas_capacity: 1000.into(),
+            next_block_gas_capacity: 100.into(),
+            ready_buckets,
+        }
+    }
+
+    fn estimates(slow: u64, normal: u64, fast: u64) -> GasPriceEstimates {
+        GasPriceEstimates {
+            slow: slow.into(),
+            normal: normal.into(),
+            fast: fast.into(),
+        }
+    }
+
+    #[test]
+    fn test_empty_sample() {
+        let oracle = DefaultGasPriceOracle::new(Default::default());
+        assert_eq!(
+            oracle.estimate(Space::Native, &Default::default()),
+            estimates(1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_packed_price_percentiles() {
+        let oracle = DefaultGasPriceOracle::new(Default::default());
+        // Blocks that are less than 20% full are ignored.
+        assert_eq!(
+            oracle.estimate(Space::Native, &sample(199, vec![])),
+            estimates(1, 1, 1)
+        );
+        assert_eq!(
+            oracle.estimate(Space::Native, &sample(200, vec![])),
+            estimates(10, 40, 75)
+        );
+    }
+
+    #[test]
+    fn test_pool_backlog() {
+        let oracle = DefaultGasPriceOracle::new(Default::default());
+        // The transactions above 500 fill 3 blocks and the ones above 300
+        // fill 20 blocks.
+        let ready_buckets =
+            vec![bucket(1000, 150), bucket(500, 150), bucket(300, 1700)];
+        assert_eq!(
+            oracle.estimate(Space::Ethereum, &sample(0, ready_buckets)),
+            estimates(1, 300, 500)
+        );
+        let ready_buckets = vec![bucket(1000, 150), bucket(50, 10000)];
+        assert_eq!(
+            oracle.estimate(Space::Ethereum, &sample(1000, ready_buckets)),
+            estimates(50, 50, 75)
+        );
+    }
+}
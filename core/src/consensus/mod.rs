@@ -6,6 +6,7 @@ mod anticone_cache;
 pub mod consensus_inner;
 pub mod consensus_trait;
 pub mod debug_recompute;
+pub mod gas_price_oracle;
 mod pastset_cache;
 pub mod pos_handler;
 
@@ -20,16 +21,25 @@ pub use crate::consensus::{
 };
 use crate::{
     block_data_manager::{
-        BlockDataManager, BlockExecutionResultWithEpoch, DataVersionTuple,
+        address_tx_index::AddressTxIndexEntry, BlockDataManager,
+        BlockExecutionResultWithEpoch, DataVersionTuple,
     },
     consensus::{
         consensus_inner::{
-            consensus_executor::ConsensusExecutionConfiguration, StateBlameInfo,
+            consensus_executor::{
+                ConsensusExecutionConfiguration, DevStateOverride,
+            },
+            StateBlameInfo,
+        },
+        gas_price_oracle::{
+            DefaultGasPriceOracle, GasPriceEstimates, GasPriceOracle,
+            GasPriceOracleConfig, GasPriceSample,
         },
         pos_handler::PosVerifier,
     },
     executive::{
-        internal_contract::build_bloom_and_recover_phantom, ExecutionOutcome,
+        contract_address, internal_contract::build_bloom_and_recover_phantom,
+        ExecutionOutcome,
     },
     observer::{
         trace::{
@@ -44,6 +54,7 @@ use crate::{
     statistics::SharedStatistics,
     transaction_pool::SharedTransactionPool,
     verification::VerificationConfig,
+    vm::CreateContractAddress,
     NodeType, Notifications,
 };
 use cfx_internal_common::ChainIdParams;
@@ -58,7 +69,9 @@ use cfx_parameters::{
 use cfx_state::state_trait::StateOpsTrait;
 use cfx_statedb::StateDb;
 use cfx_storage::state_manager::StateManagerTrait;
-use cfx_types::{AddressWithSpace, AllChainID, Bloom, Space, H256, U256};
+use cfx_types::{
+    Address, AddressWithSpace, AllChainID, Bloom, Space, H256, U256,
+};
 use either::Either;
 use itertools::Itertools;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
@@ -74,14 +87,15 @@ use primitives::{
     log_entry::LocalizedLogEntry,
     pos::PosBlockId,
     receipt::Receipt,
-    BlockHeader, EpochId, EpochNumber, SignedTransaction, TransactionIndex,
-    TransactionOutcome,
+    Action, BlockHeader, EpochId, EpochNumber, SignedTransaction,
+    TransactionIndex, TransactionOutcome,
 };
 use rayon::prelude::*;
 use std::{
     any::Any,
     cmp::{max, min},
     collections::HashSet,
+    ops::RangeInclusive,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -114,6 +128,9 @@ pub struct PhantomBlock {
     pub transactions: Vec<Arc<SignedTransaction>>,
     pub receipts: Vec<Receipt>,
     pub errors: Vec<String>,
+    /// The address of the contract created by each transaction, regardless of
+    /// its execution outcome.
+    pub contract_addresses: Vec<Option<Address>>,
     pub bloom: Bloom,
     pub traces: Vec<TransactionExecTraces>,
 }
@@ -154,6 +171,8 @@ pub struct ConsensusConfig {
     /// The number of extra epochs that we want to keep
     /// states/receipts/transactions.
     pub sync_state_epoch_gap: Option<u64>,
+    /// The targets of the default gas price oracle.
+    pub gas_price_oracle: GasPriceOracleConfig,
 }
 
 #[derive(Debug)]
@@ -227,6 +246,7 @@ pub struct ConsensusGraph {
     /// This is always `None` for archive nodes.
     pub synced_epoch_id: Mutex<Option<EpochId>>,
     pub config: ConsensusConfig,
+    gas_price_oracle: RwLock<Arc<dyn GasPriceOracle>>,
 }
 
 impl MallocSizeOf for ConsensusGraph {
@@ -274,6 +294,8 @@ impl ConsensusGraph {
             pos_verifier.clone(),
         );
         let confirmation_meter = ConfirmationMeter::new();
+        let gas_price_oracle =
+            Arc::new(DefaultGasPriceOracle::new(conf.gas_price_oracle.clone()));
 
         let graph = ConsensusGraph {
             inner,
@@ -296,6 +318,7 @@ impl ConsensusGraph {
             ready_for_mining: AtomicBool::new(false),
             synced_epoch_id: Default::default(),
             config: conf,
+            gas_price_oracle: RwLock::new(gas_price_oracle),
         };
         graph.update_best_info(false /* ready_for_mining */);
         graph
@@ -363,6 +386,17 @@ impl ConsensusGraph {
         self.update_best_info(true);
     }
 
+    /// Register the account changes applied at the beginning of the epoch of
+    /// `block_hash`. This API is used by the dev RPCs, so the block should
+    /// be registered before it is inserted.
+    pub fn set_dev_state_overrides(
+        &self, block_hash: H256, overrides: Vec<DevStateOverride>,
+    ) {
+        self.executor
+            .handler
+            .set_dev_state_overrides(block_hash, overrides);
+    }
+
     /// Determine whether the next mined block should have adaptive weight or
     /// not
     pub fn check_mining_adaptive_block(
@@ -501,104 +535,121 @@ impl ConsensusGraph {
         })
     }
 
-    /// Get the average gas price of the last GAS_PRICE_TRANSACTION_SAMPLE_SIZE
-    /// blocks
-    pub fn gas_price(&self, space: Space) -> Option<U256> {
-        let inner = self.inner.read();
-        let mut last_epoch_number = inner.best_epoch_number();
-        let (
-            number_of_tx_to_sample,
-            mut number_of_blocks_to_sample,
-            block_gas_ratio,
-        ) = match space {
-            Space::Native => (
-                GAS_PRICE_TRANSACTION_SAMPLE_SIZE,
-                GAS_PRICE_BLOCK_SAMPLE_SIZE,
-                1,
-            ),
-            Space::Ethereum => (
-                EVM_GAS_PRICE_TRANSACTION_SAMPLE_SIZE,
-                EVM_GAS_PRICE_BLOCK_SAMPLE_SIZE,
-                self.txpool.machine().params().evm_transaction_gas_ratio,
-            ),
-        };
-        let mut prices = Vec::new();
-        let mut total_block_gas_limit: u64 = 0;
-        let mut total_tx_gas_limit: u64 = 0;
+    /// Replace the oracle used by `gas_price` and `gas_price_estimates`.
+    pub fn set_gas_price_oracle(&self, oracle: Arc<dyn GasPriceOracle>) {
+        *self.gas_price_oracle.write() = oracle;
+    }
 
-        loop {
-            if number_of_blocks_to_sample == 0 || last_epoch_number == 0 {
-                break;
-            }
-            if prices.len() == number_of_tx_to_sample {
-                break;
+    /// Get the gas price suggested for a transaction to be packed within the
+    /// normal target of the gas price oracle.
+    pub fn gas_price(&self, space: Space) -> U256 {
+        self.gas_price_estimates(space).normal
+    }
+
+    pub fn gas_price_estimates(&self, space: Space) -> GasPriceEstimates {
+        let sample = self.gas_price_sample(space);
+        self.gas_price_oracle.read().estimate(space, &sample)
+    }
+
+    /// Sample the last GAS_PRICE_TRANSACTION_SAMPLE_SIZE transactions in the
+    /// last GAS_PRICE_BLOCK_SAMPLE_SIZE blocks and the ready transactions in
+    /// the transaction pool.
+    fn gas_price_sample(&self, space: Space) -> GasPriceSample {
+        let (number_of_tx_to_sample, mut number_of_blocks_to_sample) =
+            match space {
+                Space::Native => (
+                    GAS_PRICE_TRANSACTION_SAMPLE_SIZE,
+                    GAS_PRICE_BLOCK_SAMPLE_SIZE,
+                ),
+                Space::Ethereum => (
+                    EVM_GAS_PRICE_TRANSACTION_SAMPLE_SIZE,
+                    EVM_GAS_PRICE_BLOCK_SAMPLE_SIZE,
+                ),
+            };
+        let machine = self.txpool.machine();
+        let params = machine.params();
+        // The gas limit of a block counted for the space, which is multiplied
+        // by `evm_transaction_gas_ratio` for the Ethereum space.
+        let space_gas_limit = |header: &BlockHeader| match space {
+            Space::Native => *header.gas_limit(),
+            Space::Ethereum => {
+                if params.can_pack_evm_transaction(header.height()) {
+                    *header.gas_limit() * params.evm_transaction_gas_ratio
+                } else {
+                    U256::zero()
+                }
             }
-            let mut hashes = inner
-                .block_hashes_by_epoch(last_epoch_number.into())
-                .unwrap();
-            hashes.reverse();
-            last_epoch_number -= 1;
+        };
+        let mut sample = GasPriceSample::default();
 
-            for hash in hashes {
-                let block = self
-                    .data_man
-                    .block_by_hash(&hash, false /* update_cache */)
-                    .unwrap();
-                if space == Space::Ethereum
-                    && !self
-                        .txpool
-                        .machine()
-                        .params()
-                        .can_pack_evm_transaction(block.block_header.height())
-                {
-                    // This block cannot pack Ethereum transactions, so we do
-                    // not need to check every transaction.
-                    continue;
+        {
+            let inner = self.inner.read();
+            let mut last_epoch_number = inner.best_epoch_number();
+            if let Some(header) =
+                self.data_man.block_header_by_hash(&inner.best_block_hash())
+            {
+                sample.next_block_gas_capacity = space_gas_limit(&header);
+            }
+            loop {
+                if number_of_blocks_to_sample == 0 || last_epoch_number == 0 {
+                    break;
+                }
+                if sample.packed_prices.len() == number_of_tx_to_sample {
+                    break;
                 }
-                total_block_gas_limit +=
-                    block.block_header.gas_limit().as_u64() * block_gas_ratio;
-                for tx in block.transactions.iter() {
-                    if space == Space::Ethereum && tx.space() != Space::Ethereum
+                let mut hashes = inner
+                    .block_hashes_by_epoch(last_epoch_number.into())
+                    .unwrap();
+                hashes.reverse();
+                last_epoch_number -= 1;
+
+                for hash in hashes {
+                    let block = self
+                        .data_man
+                        .block_by_hash(&hash, false /* update_cache */)
+                        .unwrap();
+                    if space == Space::Ethereum
+                        && !params.can_pack_evm_transaction(
+                            block.block_header.height(),
+                        )
                     {
-                        // For eth_gasPrice, we only count Ethereum
-                        // transactions.
+                        // This block cannot pack Ethereum transactions, so we
+                        // do not need to check every transaction.
                         continue;
                     }
-                    // add the tx.gas() to total_tx_gas_limit even it is packed
-                    // multiple times because these tx all
-                    // will occupy block's gas space
-                    total_tx_gas_limit += tx.transaction.gas().as_u64();
-                    prices.push(tx.gas_price().clone());
-                    if prices.len() == number_of_tx_to_sample {
+                    sample.block_gas_capacity +=
+                        space_gas_limit(&block.block_header);
+                    for tx in block.transactions.iter() {
+                        if space == Space::Ethereum
+                            && tx.space() != Space::Ethereum
+                        {
+                            // For eth_gasPrice, we only count Ethereum
+                            // transactions.
+                            continue;
+                        }
+                        // add the tx.gas() to packed_gas even it is packed
+                        // multiple times because these tx all
+                        // will occupy block's gas space
+                        sample.packed_gas += *tx.transaction.gas();
+                        sample.packed_prices.push(tx.gas_price().clone());
+                        if sample.packed_prices.len() == number_of_tx_to_sample
+                        {
+                            break;
+                        }
+                    }
+                    number_of_blocks_to_sample -= 1;
+                    if number_of_blocks_to_sample == 0
+                        || sample.packed_prices.len() == number_of_tx_to_sample
+                    {
                         break;
                     }
                 }
-                number_of_blocks_to_sample -= 1;
-                if number_of_blocks_to_sample == 0
-                    || prices.len() == number_of_tx_to_sample
-                {
-                    break;
-                }
             }
         }
 
-        prices.sort();
-        if prices.is_empty() || total_tx_gas_limit == 0 {
-            Some(U256::from(1))
-        } else {
-            let average_gas_limit_multiple =
-                total_block_gas_limit / total_tx_gas_limit;
-            if average_gas_limit_multiple > 5 {
-                // used less than 20%
-                Some(U256::from(1))
-            } else if average_gas_limit_multiple >= 2 {
-                // used less than 50%
-                Some(prices[prices.len() / 8])
-            } else {
-                // used more than 50%
-                Some(prices[prices.len() / 2])
-            }
-        }
+        sample.packed_prices.sort();
+        sample.ready_buckets = self.txpool.ready_price_buckets(space);
+        sample
     }
 
     fn validate_stated_epoch(
@@ -732,6 +783,48 @@ impl ConsensusGraph {
         Ok(state.nonce(&address)?)
     }
 
+    /// Add at most `max_epoch_count` confirmed epochs that are not covered by
+    /// the log bloom index to it. Return the number of epochs added.
+    ///
+    /// Only confirmed epochs are added here, and the epochs that might be
+    /// reverted are covered by adding their blooms during execution.
+    pub fn update_log_bloom_index(&self, max_epoch_count: u64) -> u64 {
+        self.data_man.rebuild_log_bloom_index(
+            min(
+                self.latest_confirmed_epoch_number(),
+                self.best_executed_state_epoch_number(),
+            ),
+            max_epoch_count,
+        )
+    }
+
+    /// Return at most `limit` transactions in the history of `address` from
+    /// the newest to the oldest, starting from `cursor`, and the cursor of the
+    /// next page. The entries of epochs that are no longer on the pivot chain
+    /// are filtered out, so a page may have less than `limit` entries even if
+    /// there are older ones.
+    pub fn get_transactions_by_address(
+        &self, address: &AddressWithSpace, cursor: Option<u64>, limit: usize,
+    ) -> Result<(Vec<AddressTxIndexEntry>, Option<u64>), String> {
+        if !self.data_man.persist_address_tx_index() {
+            return Err("The address transaction index is not enabled. \
+                        Please set persist_address_tx_index to true."
+                .into());
+        }
+        let (entries, next_cursor) = self
+            .data_man
+            .address_tx_index_entries(address, cursor, limit);
+        let inner = self.inner.read();
+        let entries = entries
+            .into_iter()
+            .filter(|entry| {
+                inner.get_pivot_hash_from_epoch_number(entry.epoch_number)
+                    == Ok(entry.pivot_hash)
+            })
+            .collect();
+        Ok((entries, next_cursor))
+    }
+
     fn earliest_epoch_for_log_filter(&self) -> u64 {
         max(
             self.data_man.earliest_epoch_with_block_body(),
@@ -997,7 +1090,7 @@ impl ConsensusGraph {
     pub fn get_log_filter_epoch_range(
         &self, from_epoch: EpochNumber, to_epoch: EpochNumber,
         check_range: bool,
-    ) -> Result<impl Iterator<Item = u64>, FilterError>
+    ) -> Result<RangeInclusive<u64>, FilterError>
     {
         // lock so that we have a consistent view
         let _inner = self.inner.read_recursive();
@@ -1033,7 +1126,7 @@ impl ConsensusGraph {
             }
         }
 
-        return Ok((from_epoch..=to_epoch).rev());
+        return Ok(from_epoch..=to_epoch);
     }
 
     pub fn get_trace_filter_epoch_range(
@@ -1077,9 +1170,21 @@ impl ConsensusGraph {
         // that we can check whether it changed between batches
         let mut consistency_check_data: Option<(u64, H256)> = None;
 
+        let epoch_range =
+            self.get_log_filter_epoch_range(from_epoch, to_epoch, check_range)?;
+
         let mut logs = self
+            .data_man
+            // skip the groups of epochs that cannot match the filter
+            .log_bloom_index_candidate_epoch_ranges(
+                *epoch_range.start(),
+                *epoch_range.end(),
+                &bloom_possibilities,
+            )
+            .into_iter()
             // iterate over epochs in reverse order
-            .get_log_filter_epoch_range(from_epoch, to_epoch, check_range)?
+            .rev()
+            .flat_map(|(start, end)| (start..=end).rev())
             // we process epochs in each batch in parallel
             // but batches are processed one-by-one
             .chunks(self.config.get_logs_epoch_batch_size)
@@ -1681,6 +1786,7 @@ impl ConsensusGraph {
                 transactions: vec![],
                 receipts: vec![],
                 errors: vec![],
+                contract_addresses: vec![],
                 bloom: Bloom::zero(),
                 traces: vec![],
             }));
@@ -1709,6 +1815,7 @@ impl ConsensusGraph {
             transactions: vec![],
             receipts: vec![],
             errors: vec![],
+            contract_addresses: vec![],
             bloom: Default::default(),
             traces: vec![],
         };
@@ -1797,6 +1904,22 @@ impl ConsensusGraph {
                         });
 
                         phantom_block.errors.push(errors[id].clone());
+                        phantom_block.contract_addresses.push(
+                            match tx.action() {
+                                Action::Create => Some(
+                                    contract_address(
+                                        CreateContractAddress::FromSenderNonce,
+                                        0.into(),
+                                        &tx.sender(),
+                                        tx.nonce(),
+                                        tx.data(),
+                                    )
+                                    .0
+                                    .address,
+                                ),
+                                Action::Call(_) => None,
+                            },
+                        );
                         phantom_block.bloom.accrue_bloom(&receipt.log_bloom);
 
                         if include_traces {
@@ -1825,6 +1948,9 @@ impl ConsensusGraph {
                         }
 
                         for p in phantom_txs {
+                            phantom_block
+                                .contract_addresses
+                                .push(p.contract_address);
                             phantom_block.transactions.push(Arc::new(
                                 p.clone().into_eip155(evm_chain_id),
                             ));
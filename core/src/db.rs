@@ -43,8 +43,14 @@ pub const COL_BLOCK_TRACES: u32 = 5;
 pub const COL_HASH_BY_BLOCK_NUMBER: u32 = 6;
 /// Column for PoS interest reward info.
 pub const COL_REWARD_BY_POS_EPOCH: u32 = 7;
+/// Column for the log bloom index of epoch groups
+pub const COL_LOG_BLOOM_INDEX: u32 = 8;
+/// Column for the transaction history of addresses
+pub const COL_ADDRESS_TX_INDEX: u32 = 9;
+/// Column for the remote state cached in fork mode
+pub const COL_FORKED_STATE: u32 = 10;
 /// Number of columns in DB
-pub const NUM_COLUMNS: u32 = 8;
+pub const NUM_COLUMNS: u32 = 11;
 
 /// Modes for updating caches.
 #[derive(Clone, Copy)]
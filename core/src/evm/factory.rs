@@ -31,6 +31,7 @@ use std::sync::Arc;
 pub struct Factory {
     evm: VMType,
     evm_cache: Arc<SharedCache>,
+    precharge_blocks: bool,
 }
 
 impl Factory {
@@ -45,6 +46,7 @@ impl Factory {
                     Box::new(super::interpreter::Interpreter::<usize>::new(
                         params,
                         self.evm_cache.clone(),
+                        self.precharge_blocks,
                         spec,
                         depth,
                     ))
@@ -52,6 +54,7 @@ impl Factory {
                     Box::new(super::interpreter::Interpreter::<U256>::new(
                         params,
                         self.evm_cache.clone(),
+                        self.precharge_blocks,
                         spec,
                         depth,
                     ))
@@ -61,14 +64,24 @@ impl Factory {
     }
 
     /// Create new instance of specific `VMType` factory, with a size in bytes
-    /// for caching jump destinations.
+    /// for caching jump destinations and basic blocks.
     pub fn new(evm: VMType, cache_size: usize) -> Self {
         Factory {
             evm,
             evm_cache: Arc::new(SharedCache::new(cache_size)),
+            precharge_blocks: true,
         }
     }
 
+    /// Charge the gas and check the stack of every instruction on its own
+    /// instead of once per basic block, to compare with the results of
+    /// basic blocks.
+    #[cfg(test)]
+    pub fn without_block_precharge(mut self) -> Self {
+        self.precharge_blocks = false;
+        self
+    }
+
     fn can_fit_in_usize(gas: &U256) -> bool {
         gas == &U256::from(gas.low_u64() as usize)
     }
@@ -80,6 +93,7 @@ impl Default for Factory {
         Factory {
             evm: VMType::Interpreter,
             evm_cache: Arc::new(SharedCache::default()),
+            precharge_blocks: true,
         }
     }
 }
@@ -106,6 +120,10 @@ macro_rules! evm_test(
 		#[test]
 		fn $name_int() {
 			$name_test(Factory::new(VMType::Interpreter, 1024 * 32));
+			$name_test(
+				Factory::new(VMType::Interpreter, 1024 * 32)
+					.without_block_precharge(),
+			);
 		}
 	}
 );
@@ -128,7 +128,6 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
         let default_gas = Gas::from(spec.tier_step_gas[tier]);
 
         let cost = match instruction {
-            instructions::JUMPDEST => Request::Gas(Gas::from(1)),
             instructions::SSTORE => {
                 let gas = if context.space() == Space::Native {
                     spec.sstore_reset_gas
@@ -148,14 +147,6 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
 
                 Request::Gas(Gas::from(gas))
             }
-            instructions::SLOAD => Request::Gas(Gas::from(spec.sload_gas)),
-            instructions::BALANCE => Request::Gas(Gas::from(spec.balance_gas)),
-            instructions::EXTCODESIZE => {
-                Request::Gas(Gas::from(spec.extcodesize_gas))
-            }
-            instructions::EXTCODEHASH => {
-                Request::Gas(Gas::from(spec.extcodehash_gas))
-            }
             instructions::SUICIDE => {
                 let mut gas = Gas::from(spec.suicide_gas);
 
@@ -298,10 +289,11 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
                 let gas = Gas::from(spec.exp_gas + spec.exp_byte_gas * bytes);
                 Request::Gas(gas)
             }
-            instructions::BLOCKHASH => {
-                Request::Gas(Gas::from(spec.blockhash_gas))
-            }
-            _ => Request::Gas(default_gas),
+            _ => Request::Gas(Gas::from(
+                static_gas(spec, instruction, info).expect(
+                    "instructions with dynamic gas are matched above; qed",
+                ),
+            )),
         };
 
         Ok(match cost {
@@ -384,6 +376,47 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
     }
 }
 
+/// The gas cost of `instruction` if it only depends on the spec, or `None` if
+/// it depends on the stack, the memory or the state. The instructions with
+/// static gas are charged once per basic block when possible.
+pub fn static_gas(
+    spec: &Spec, instruction: Instruction, info: &InstructionInfo,
+) -> Option<usize> {
+    match instruction {
+        instructions::JUMPDEST => Some(1),
+        instructions::SLOAD => Some(spec.sload_gas),
+        instructions::BALANCE => Some(spec.balance_gas),
+        instructions::EXTCODESIZE => Some(spec.extcodesize_gas),
+        instructions::EXTCODEHASH => Some(spec.extcodehash_gas),
+        instructions::BLOCKHASH => Some(spec.blockhash_gas),
+        instructions::SSTORE
+        | instructions::SUICIDE
+        | instructions::MSTORE
+        | instructions::MLOAD
+        | instructions::MSTORE8
+        | instructions::RETURN
+        | instructions::REVERT
+        | instructions::SHA3
+        | instructions::CALLDATACOPY
+        | instructions::CODECOPY
+        | instructions::RETURNDATACOPY
+        | instructions::EXTCODECOPY
+        | instructions::LOG0
+        | instructions::LOG1
+        | instructions::LOG2
+        | instructions::LOG3
+        | instructions::LOG4
+        | instructions::CALL
+        | instructions::CALLCODE
+        | instructions::DELEGATECALL
+        | instructions::STATICCALL
+        | instructions::CREATE
+        | instructions::CREATE2
+        | instructions::EXP => None,
+        _ => Some(spec.tier_step_gas[info.tier.idx()]),
+    }
+}
+
 #[inline]
 fn mem_needed_const<Gas: evm::CostType>(
     mem: &U256, add: usize,
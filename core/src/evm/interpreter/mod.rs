@@ -35,6 +35,7 @@ pub use self::shared_cache::SharedCache;
 use self::{
     gasometer::Gasometer,
     memory::Memory,
+    shared_cache::{BasicBlock, BasicBlocks},
     stack::{Stack, VecStack},
 };
 use super::{
@@ -200,6 +201,12 @@ pub struct Interpreter<Cost: CostType> {
     done: bool,
     valid_jump_destinations: Option<Arc<BitSet>>,
     valid_subroutine_destinations: Option<Arc<BitSet>>,
+    /// Whether to charge the static gas once per basic block.
+    precharge_blocks: bool,
+    basic_blocks: Option<Arc<BasicBlocks>>,
+    /// The basic block being executed, whose static gas has been charged and
+    /// whose stack bounds have been checked.
+    current_block: Option<BasicBlock>,
     gasometer: Option<Gasometer<Cost>>,
     stack: VecStack<U256>,
     return_stack: Vec<usize>,
@@ -323,8 +330,8 @@ impl<Cost: 'static + CostType> vm::ResumeCreate for Interpreter<Cost> {
 impl<Cost: CostType> Interpreter<Cost> {
     /// Create a new `Interpreter` instance with shared cache.
     pub fn new(
-        mut params: ActionParams, cache: Arc<SharedCache>, spec: &Spec,
-        depth: usize,
+        mut params: ActionParams, cache: Arc<SharedCache>,
+        precharge_blocks: bool, spec: &Spec, depth: usize,
     ) -> Interpreter<Cost>
     {
         let reader = CodeReader::new(
@@ -348,6 +355,9 @@ impl<Cost: CostType> Interpreter<Cost> {
             informant,
             valid_jump_destinations,
             valid_subroutine_destinations,
+            precharge_blocks,
+            basic_blocks: None,
+            current_block: None,
             gasometer,
             stack,
             return_stack,
@@ -400,7 +410,25 @@ impl<Cost: CostType> Interpreter<Cost> {
         let result = match self.resume_result.take() {
             Some(result) => result,
             None => {
-                let opcode = self.reader.code[self.reader.position];
+                let position = self.reader.position;
+                // The static gas can't be charged in advance when the gas
+                // left of every instruction is traced.
+                if self.current_block.is_none()
+                    && self.precharge_blocks
+                    && !self.do_trace
+                {
+                    self.enter_basic_block(context, position);
+                }
+                let (in_block, precharged) = match self.current_block {
+                    Some(block) if block.last == position => {
+                        self.current_block = None;
+                        (true, !block.dynamic_last)
+                    }
+                    Some(_) => (true, true),
+                    None => (false, false),
+                };
+
+                let opcode = self.reader.code[position];
                 let instruction = Instruction::from_u8(opcode);
                 self.reader.position += 1;
 
@@ -430,53 +458,60 @@ impl<Cost: CostType> Interpreter<Cost> {
 
                 let info = instruction.info();
                 self.last_stack_ret_len = info.ret;
-                if let Err(e) =
-                    self.verify_instruction(context, instruction, info)
-                {
-                    return InterpreterResult::Done(Err(e));
+                // The stack bounds of the instructions in a basic block are
+                // checked when entering the block.
+                if !in_block {
+                    if let Err(e) =
+                        self.verify_instruction(context, instruction, info)
+                    {
+                        return InterpreterResult::Done(Err(e));
+                    }
                 }
 
-                // Calculate gas cost
-                let requirements = match self
-                    .gasometer
-                    .as_mut()
-                    .expect(GASOMETER_PROOF)
-                    .requirements(
-                        context,
-                        instruction,
-                        info,
-                        &self.stack,
-                        self.mem.size(),
-                    ) {
-                    Ok(t) => t,
-                    Err(e) => return InterpreterResult::Done(Err(e)),
-                };
-                if self.do_trace {
-                    context.trace_prepare_execute(
-                        self.reader.position - 1,
-                        opcode,
-                        requirements.gas_cost.as_u256(),
-                        Self::mem_written(instruction, &self.stack),
-                        Self::store_written(instruction, &self.stack),
-                    );
-                }
+                let provide_gas = if precharged {
+                    None
+                } else {
+                    // Calculate gas cost
+                    let requirements = match self
+                        .gasometer
+                        .as_mut()
+                        .expect(GASOMETER_PROOF)
+                        .requirements(
+                            context,
+                            instruction,
+                            info,
+                            &self.stack,
+                            self.mem.size(),
+                        ) {
+                        Ok(t) => t,
+                        Err(e) => return InterpreterResult::Done(Err(e)),
+                    };
+                    if self.do_trace {
+                        context.trace_prepare_execute(
+                            self.reader.position - 1,
+                            opcode,
+                            requirements.gas_cost.as_u256(),
+                            Self::mem_written(instruction, &self.stack),
+                            Self::store_written(instruction, &self.stack),
+                        );
+                    }
 
-                if let Err(e) = self
-                    .gasometer
-                    .as_mut()
-                    .expect(GASOMETER_PROOF)
-                    .verify_gas(&requirements.gas_cost)
-                {
-                    return InterpreterResult::Done(Err(e));
-                }
-                self.mem.expand(requirements.memory_required_size);
-                self.gasometer
-                    .as_mut()
-                    .expect(GASOMETER_PROOF)
-                    .current_mem_gas = requirements.memory_total_gas;
-                self.gasometer.as_mut().expect(GASOMETER_PROOF).current_gas =
-                    self.gasometer.as_mut().expect(GASOMETER_PROOF).current_gas
-                        - requirements.gas_cost;
+                    if let Err(e) = self
+                        .gasometer
+                        .as_mut()
+                        .expect(GASOMETER_PROOF)
+                        .verify_gas(&requirements.gas_cost)
+                    {
+                        return InterpreterResult::Done(Err(e));
+                    }
+                    self.mem.expand(requirements.memory_required_size);
+                    let gasometer =
+                        self.gasometer.as_mut().expect(GASOMETER_PROOF);
+                    gasometer.current_mem_gas = requirements.memory_total_gas;
+                    gasometer.current_gas =
+                        gasometer.current_gas - requirements.gas_cost;
+                    requirements.provide_gas
+                };
 
                 evm_debug!({
                     self.informant.before_instruction(
@@ -499,7 +534,7 @@ impl<Cost: CostType> Interpreter<Cost> {
                     current_gas,
                     context,
                     instruction,
-                    requirements.provide_gas,
+                    provide_gas,
                     tracer,
                 ) {
                     Err(x) => {
@@ -621,6 +656,47 @@ impl<Cost: CostType> Interpreter<Cost> {
         InterpreterResult::Continue
     }
 
+    /// Enter the basic block starting at `position`, if any. The static gas
+    /// of the whole block is charged and the stack bounds of all its
+    /// instructions are checked at once, so that they are skipped for every
+    /// instruction. If the gas or the stack is not enough for the block, its
+    /// instructions are checked and charged one by one instead, which
+    /// reports the same error at the same instruction.
+    fn enter_basic_block(
+        &mut self, context: &dyn vm::Context, position: usize,
+    ) {
+        if self.basic_blocks.is_none() {
+            self.basic_blocks = Some(self.cache.basic_blocks(
+                &self.params.code_hash,
+                &self.reader.code,
+                context.spec(),
+            ));
+        }
+        let block = match self
+            .basic_blocks
+            .as_ref()
+            .expect("basic_blocks are initialized above; qed")
+            .get(position)
+        {
+            Some(block) => *block,
+            None => return,
+        };
+
+        if !self.stack.has(block.stack_required)
+            || self.stack.size() + block.stack_max_growth
+                > context.spec().stack_limit
+        {
+            return;
+        }
+        let gasometer = self.gasometer.as_mut().expect(GASOMETER_PROOF);
+        let static_gas = Cost::from(block.static_gas);
+        if gasometer.verify_gas(&static_gas).is_err() {
+            return;
+        }
+        gasometer.current_gas = gasometer.current_gas - static_gas;
+        self.current_block = Some(block);
+    }
+
     fn verify_instruction(
         &self, context: &dyn vm::Context, _instruction: Instruction,
         info: &InstructionInfo,
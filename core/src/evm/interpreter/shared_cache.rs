@@ -14,14 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with Open Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::super::instructions::{self, Instruction};
-use crate::hash::KECCAK_EMPTY;
+use super::{
+    super::instructions::{self, Instruction},
+    gasometer::static_gas,
+};
+use crate::{hash::KECCAK_EMPTY, vm::Spec};
 use bit_set::BitSet;
 use cfx_types::H256;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use memory_cache::MemoryLruCache;
 use parking_lot::Mutex;
-use std::sync::Arc;
+use std::{cmp, collections::HashMap, mem, sync::Arc};
 
 #[cfg(test)]
 use rustc_hex::FromHex;
@@ -52,18 +55,111 @@ impl MallocSizeOf for CacheItem {
     }
 }
 
+/// A sequence of instructions which can only be entered from its first
+/// instruction and only left after its last instruction, unless an error
+/// occurs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BasicBlock {
+    /// The position of the last instruction.
+    pub last: usize,
+    /// Whether the last instruction has dynamic gas, which is not included in
+    /// `static_gas`. Instructions with dynamic gas always end a block.
+    pub dynamic_last: bool,
+    /// The sum of the static gas of the instructions.
+    pub static_gas: usize,
+    /// The number of stack items needed by the instructions.
+    pub stack_required: usize,
+    /// The maximum number of stack items added after any of the
+    /// instructions, relative to the start of the block.
+    pub stack_max_growth: usize,
+}
+
+/// The spec parameters which decide the static gas of instructions.
+#[derive(Clone, Copy, PartialEq)]
+struct StaticGasSchedule {
+    tier_step_gas: [usize; 8],
+    sload_gas: usize,
+    balance_gas: usize,
+    extcodesize_gas: usize,
+    extcodehash_gas: usize,
+    blockhash_gas: usize,
+}
+
+impl StaticGasSchedule {
+    fn new(spec: &Spec) -> Self {
+        StaticGasSchedule {
+            tier_step_gas: spec.tier_step_gas,
+            sload_gas: spec.sload_gas,
+            balance_gas: spec.balance_gas,
+            extcodesize_gas: spec.extcodesize_gas,
+            extcodehash_gas: spec.extcodehash_gas,
+            blockhash_gas: spec.blockhash_gas,
+        }
+    }
+}
+
+/// The basic blocks of a code, indexed by the positions of their first
+/// instructions.
+pub struct BasicBlocks {
+    schedule: StaticGasSchedule,
+    blocks: HashMap<usize, BasicBlock>,
+}
+
+impl BasicBlocks {
+    pub fn get(&self, position: usize) -> Option<&BasicBlock> {
+        self.blocks.get(&position)
+    }
+}
+
+#[derive(Clone)]
+struct BasicBlocksItem(Arc<BasicBlocks>);
+
+impl MallocSizeOf for BasicBlocksItem {
+    fn size_of(&self, _ops: &mut MallocSizeOfOps) -> usize {
+        self.0.blocks.capacity()
+            * (mem::size_of::<usize>() + mem::size_of::<BasicBlock>())
+    }
+}
+
 /// Global cache for EVM interpreter
 pub struct SharedCache {
     jump_destinations: Mutex<MemoryLruCache<H256, CacheItem>>,
+    basic_blocks: Mutex<MemoryLruCache<H256, BasicBlocksItem>>,
 }
 
 impl SharedCache {
-    /// Create a jump destinations cache with a maximum size in bytes
-    /// to cache.
+    /// Create a jump destinations cache and a basic blocks cache, each with
+    /// a maximum size in bytes to cache.
     pub fn new(max_size: usize) -> Self {
         SharedCache {
             jump_destinations: Mutex::new(MemoryLruCache::new(max_size)),
+            basic_blocks: Mutex::new(MemoryLruCache::new(max_size)),
+        }
+    }
+
+    /// Get the basic blocks of a contract, with the static gas of `spec`.
+    pub fn basic_blocks(
+        &self, code_hash: &Option<H256>, code: &[u8], spec: &Spec,
+    ) -> Arc<BasicBlocks> {
+        let schedule = StaticGasSchedule::new(spec);
+        let code_hash = match code_hash {
+            Some(code_hash) if code_hash != &KECCAK_EMPTY => code_hash,
+            _ => return Arc::new(Self::find_basic_blocks(code, spec)),
+        };
+
+        if let Some(item) = self.basic_blocks.lock().get_mut(code_hash) {
+            // The cached blocks are analyzed again if the gas schedule
+            // changes in a hardfork.
+            if item.0.schedule == schedule {
+                return item.0.clone();
+            }
         }
+
+        let blocks = Arc::new(Self::find_basic_blocks(code, spec));
+        self.basic_blocks
+            .lock()
+            .insert(*code_hash, BasicBlocksItem(blocks.clone()));
+        blocks
     }
 
     /// Get jump destinations bitmap for a contract.
@@ -128,6 +224,82 @@ impl SharedCache {
             sub_entrypoint: Bits(Arc::new(sub_entrypoints)),
         }
     }
+
+    fn find_basic_blocks(code: &[u8], spec: &Spec) -> BasicBlocks {
+        let mut blocks = HashMap::new();
+        // The start position, the block and the current stack height
+        // relative to the start of the block.
+        let mut current: Option<(usize, BasicBlock, isize)> = None;
+        let mut position = 0;
+
+        while position < code.len() {
+            let instruction = match Instruction::from_u8(code[position]) {
+                Some(instruction) => instruction,
+                None => {
+                    // An undefined instruction is executed one by one to
+                    // report the error.
+                    if let Some((start, block, _)) = current.take() {
+                        blocks.insert(start, block);
+                    }
+                    position += 1;
+                    continue;
+                }
+            };
+
+            // Jump destinations start new blocks.
+            if instruction == instructions::JUMPDEST {
+                if let Some((start, block, _)) = current.take() {
+                    blocks.insert(start, block);
+                }
+            }
+            let (_, block, height) = current
+                .get_or_insert_with(|| (position, BasicBlock::default(), 0));
+
+            let info = instruction.info();
+            let required = info.args as isize - *height;
+            block.stack_required =
+                cmp::max(block.stack_required, cmp::max(required, 0) as usize);
+            *height += info.ret as isize - info.args as isize;
+            block.stack_max_growth =
+                cmp::max(block.stack_max_growth, cmp::max(*height, 0) as usize);
+
+            let gas = static_gas(spec, instruction, info);
+            block.static_gas += gas.unwrap_or(0);
+            block.dynamic_last = gas.is_none();
+            block.last = position;
+
+            position += 1 + instruction.push_bytes().unwrap_or(0);
+
+            // The instructions which use the gas left or change the control
+            // flow end the blocks, and so do the instructions with dynamic
+            // gas, which are charged with the exact gas left.
+            let ends_block = gas.is_none()
+                || match instruction {
+                    instructions::JUMP
+                    | instructions::JUMPI
+                    | instructions::STOP
+                    | instructions::GAS
+                    | instructions::BEGINSUB
+                    | instructions::JUMPSUB
+                    | instructions::RETURNSUB => true,
+                    _ => false,
+                };
+            if ends_block {
+                if let Some((start, block, _)) = current.take() {
+                    blocks.insert(start, block);
+                }
+            }
+        }
+        if let Some((start, block, _)) = current.take() {
+            blocks.insert(start, block);
+        }
+
+        blocks.shrink_to_fit();
+        BasicBlocks {
+            schedule: StaticGasSchedule::new(spec),
+            blocks,
+        }
+    }
 }
 
 impl Default for SharedCache {
@@ -21,10 +21,11 @@
 use super::{factory::Factory, vmtype::VMType};
 use crate::{
     evm::interpreter::MAX_SUB_STACK_SIZE,
+    hash::keccak,
     vm::{
         self,
         tests::{test_finalize, MockCall, MockCallType, MockContext},
-        ActionParams, ActionValue, Context,
+        ActionParams, ActionValue, Context, GasLeft,
     },
 };
 use cfx_types::{Address, BigEndianHash, H256, U256};
@@ -35,6 +36,7 @@ use std::{
     hash::Hash,
     str::FromStr,
     sync::Arc,
+    time::Instant,
 };
 
 evm_test! {test_add: test_add_int}
@@ -1372,3 +1374,130 @@ fn assert_store(ctx: &MockContext, pos: u64, val: &str) {
         &H256::from_str(val).unwrap().into_uint()
     );
 }
+
+/// Execute `code` with a stack limit of 16 and describe the result and the
+/// changes in the context.
+fn execute_and_describe(factory: &Factory, code: &[u8], gas: u64) -> String {
+    let address = Address::from_low_u64_be(0x155);
+    let mut params = ActionParams::default();
+    params.address = address.clone();
+    params.gas = U256::from(gas);
+    params.code_hash = Some(keccak(code));
+    params.code = Some(Arc::new(code.to_vec()));
+    let mut ctx = MockContext::new_spec();
+    ctx.spec.stack_limit = 16;
+    ctx.balances.insert(address, U256::zero());
+    let mut tracer = ();
+
+    let result = {
+        let vm = factory.create(params, ctx.spec(), ctx.depth());
+        match vm.exec(&mut ctx, &mut tracer).ok().unwrap() {
+            Ok(GasLeft::Known(gas_left)) => format!("gas left {}", gas_left),
+            Ok(GasLeft::NeedsReturn {
+                gas_left,
+                data,
+                apply_state,
+            }) => format!(
+                "gas left {}, return {:?}, apply {}",
+                gas_left, &*data, apply_state
+            ),
+            Err(e) => format!("{:?}", e),
+        }
+    };
+    let mut store: Vec<_> = ctx.store.iter().collect();
+    store.sort();
+    let logs: Vec<_> = ctx
+        .logs
+        .iter()
+        .map(|log| (&log.topics, &log.data))
+        .collect();
+    let mut calls: Vec<_> =
+        ctx.calls.iter().map(|call| format!("{:?}", call)).collect();
+    calls.sort();
+    format!("{} {:?} {:?} {:?}", result, store, logs, calls)
+}
+
+#[test]
+fn test_block_precharge_matches_per_instruction() {
+    let codes = vec![
+        // ADD and SSTORE.
+        "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff01600055",
+        // A loop of 10 iterations, then SSTORE the counter.
+        "60005b60010180600a1160025760005500",
+        // GAS at the end of blocks.
+        "60016002015a6000555a60015500",
+        // Stack underflow in the middle of a block.
+        "600160020101600055",
+        // Stack overflow in the middle of a block.
+        "60016001600160016001600160016001600160016001600160016001600160016001",
+        // Undefined instruction in the middle of a block.
+        "600160020c600055",
+        // Memory expansion, SHA3 and LOG0.
+        "6020600052602060002060005560206000a0",
+        // Invalid jump destination.
+        "6003566000",
+        // CALL with a constant gas and with all the gas left.
+        "60006000600060006000605561fffff16000556000600060006000600060555af1600155",
+        // CREATE.
+        "6000600060006000f0600055",
+        // Subroutines.
+        "6800000000000000000c5e005c60115e5d5c5d",
+        // EXP, SLOAD and BLOCKHASH.
+        "600260030a600054016000400160015500",
+        // RETURN after MSTORE8.
+        "60ff601f5360206000f3",
+    ];
+    let per_instruction =
+        Factory::new(VMType::Interpreter, 1024 * 32).without_block_precharge();
+    let basic_blocks = Factory::new(VMType::Interpreter, 1024 * 32);
+
+    for code in codes {
+        let code = code.from_hex().unwrap();
+        let gas_limits = (0..6_000)
+            .chain((6_000..30_000).step_by(97))
+            .chain(vec![1_000_000]);
+        for gas in gas_limits {
+            assert_eq!(
+                execute_and_describe(&per_instruction, &code, gas),
+                execute_and_describe(&basic_blocks, &code, gas),
+                "code {:?} with gas {}",
+                code,
+                gas
+            );
+        }
+    }
+}
+
+/// Run with `cargo test --release -p cfxcore bench_block_precharge --
+/// --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_block_precharge() {
+    // A loop of 10000 iterations of arithmetic.
+    let code = "6127105b808002800250600190038060035700".from_hex().unwrap();
+    let runs = 200;
+    let factories = vec![
+        (
+            "per instruction",
+            Factory::new(VMType::Interpreter, 1024 * 32)
+                .without_block_precharge(),
+        ),
+        ("basic blocks", Factory::new(VMType::Interpreter, 1024 * 32)),
+    ];
+
+    for (name, factory) in factories {
+        let start = Instant::now();
+        for _ in 0..runs {
+            let mut params = ActionParams::default();
+            params.gas = U256::from(1_000_000);
+            params.code_hash = Some(keccak(&code));
+            params.code = Some(Arc::new(code.clone()));
+            let mut ctx = MockContext::new_spec();
+            let mut tracer = ();
+            let vm = factory.create(params, ctx.spec(), ctx.depth());
+            test_finalize(vm.exec(&mut ctx, &mut tracer).ok().unwrap())
+                .unwrap();
+        }
+        println!("{}: {:?} per execution", name, start.elapsed() / runs);
+    }
+}
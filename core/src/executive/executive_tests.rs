@@ -5,8 +5,14 @@
 use super::{executive::*, Executed, ExecutionError};
 use crate::{
     evm::FinalizationResult,
-    executive::{CollateralCheckResultToVmResult, ExecutionOutcome},
+    executive::{
+        internal_contract::{
+            cross_space, function::InterfaceTrait, record_epoch_history,
+        },
+        CollateralCheckResultToVmResult, ExecutionOutcome,
+    },
     machine::Machine,
+    observer::{trace::Action as TraceAction, ExecutiveTracer},
     state::{State, Substate},
     test_helpers::get_state_for_genesis_write,
     vm::{
@@ -18,6 +24,8 @@ use crate::{
 use cfx_internal_common::debug::ComputeEpochDebugRecord;
 use cfx_parameters::{
     internal_contract_addresses::{
+        CONTEXT_CONTRACT_ADDRESS, CORE_SPACE_READER_CONTRACT_ADDRESS,
+        CROSS_SPACE_CONTRACT_ADDRESS,
         SPONSOR_WHITELIST_CONTROL_CONTRACT_ADDRESS,
         STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS,
     },
@@ -34,15 +42,17 @@ use cfx_storage::{
     StateIndex,
 };
 use cfx_types::{
-    address_util::AddressUtil, Address, AddressSpaceUtil, BigEndianHash, U256,
-    U512,
+    address_util::AddressUtil, Address, AddressSpaceUtil, BigEndianHash, Space,
+    H256, U256, U512,
 };
+use keccak_hash::keccak;
 use keylib::{Generator, Random};
 use primitives::{
     storage::STORAGE_LAYOUT_REGULAR_V0, transaction::Action, EpochId,
     NativeTransaction, Transaction,
 };
 use rustc_hex::FromHex;
+use solidity_abi::ABIEncodable;
 use std::{
     cmp::{self, min},
     str::FromStr,
@@ -986,6 +996,280 @@ fn test_deposit_withdraw_lock() {
     );
 }
 
+#[test]
+fn test_cross_space_create2() {
+    let mut sender = Address::zero();
+    sender.set_user_account_type_bits();
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let env = Env::default();
+    let machine = make_byzantium_machine(1024);
+    let spec = machine.spec(env.number);
+    let mut substate = Substate::new();
+
+    // The init code returns the runtime code `602a60005260206000f3`.
+    let init: Vec<u8> = "600a600c600039600a6000f3602a60005260206000f3"
+        .from_hex()
+        .unwrap();
+    let salt = H256::from_low_u64_be(1);
+    let mapped_sender = Address::from(keccak(&sender)).with_evm_space();
+    let (expected_address, _) = contract_address(
+        CreateContractAddress::FromSenderSaltAndCodeHash(salt),
+        /* block_number = */ 0.into(),
+        &mapped_sender,
+        &U256::zero(),
+        &init,
+    );
+
+    let mut params = ActionParams::default();
+    params.code_address = *CROSS_SPACE_CONTRACT_ADDRESS;
+    params.address = params.code_address;
+    params.sender = sender;
+    params.original_sender = sender;
+    params.storage_owner = params.code_address;
+    params.gas = U256::from(1_000_000);
+    params.call_type = CallType::Call;
+    let mut data = cross_space::Create2ToEVM::FUNC_SIG.to_vec();
+    data.extend_from_slice(&(init.clone(), salt).abi_encode());
+    params.data = Some(data);
+
+    // create2EVM is not available before its CIP is activated.
+    let mut disabled_machine = crate::machine::new_machine_with_builtin(
+        Default::default(),
+        VmFactory::new(1024 * 32),
+    );
+    disabled_machine.set_spec_creation_rules(Box::new(|s, _| {
+        s.max_depth = 1024;
+        s.cip_create2_evm = false;
+    }));
+    let disabled_spec = disabled_machine.spec(env.number);
+    let mut tracer = ();
+    let result =
+        Executive::new(&mut state, &env, &disabled_machine, &disabled_spec)
+            .call(params.clone(), &mut substate, &mut tracer)
+            .expect("no db error");
+    assert_eq!(
+        result.unwrap_err(),
+        vm::Error::InternalContract("unsupported function".into())
+    );
+
+    let mut tracer = ();
+    let result = Executive::new(&mut state, &env, &machine, &spec)
+        .call(params.clone(), &mut substate, &mut tracer)
+        .expect("no db error")
+        .expect("create2EVM should succeed");
+    assert!(result.apply_state);
+    assert_eq!(
+        &result.return_data[12..],
+        expected_address.address.as_bytes()
+    );
+    let runtime: Vec<u8> = "602a60005260206000f3".from_hex().unwrap();
+    assert_eq!(*state.code(&expected_address).unwrap().unwrap(), runtime);
+    assert_eq!(state.nonce(&mapped_sender).unwrap(), U256::one());
+
+    // Deploying to the same address again fails.
+    let mut tracer = ();
+    let result = Executive::new(&mut state, &env, &machine, &spec)
+        .call(params.clone(), &mut substate, &mut tracer)
+        .expect("no db error");
+    assert!(result.is_err());
+    assert_eq!(state.nonce(&mapped_sender).unwrap(), U256::one());
+}
+
+#[test]
+fn test_core_space_reader() {
+    let sender = Address::from_low_u64_be(1);
+    let mut core_contract = Address::from_low_u64_be(2);
+    core_contract.set_contract_type_bits();
+    let core_contract_with_space = core_contract.with_native_space();
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let env = Env::default();
+    let machine = make_byzantium_machine(1024);
+    let spec = machine.spec(env.number);
+    let mut substate = Substate::new();
+
+    // The Conflux space contract returns 42.
+    state
+        .new_contract_with_admin(
+            &core_contract_with_space,
+            &Address::zero(),
+            U256::from(1000),
+            spec.contract_start_nonce,
+            Some(STORAGE_LAYOUT_REGULAR_V0),
+        )
+        .unwrap();
+    state
+        .init_code(
+            &core_contract_with_space,
+            "602a60005260206000f3".from_hex().unwrap(),
+            Address::zero(),
+        )
+        .unwrap();
+
+    let mut params = ActionParams::default();
+    params.space = Space::Ethereum;
+    params.code_address = *CORE_SPACE_READER_CONTRACT_ADDRESS;
+    params.address = params.code_address;
+    params.sender = sender;
+    params.original_sender = sender;
+    params.storage_owner = sender;
+    params.gas = U256::from(1_000_000);
+    params.call_type = CallType::StaticCall;
+
+    // Read the balance.
+    let mut data = "c92b558d".from_hex::<Vec<u8>>().unwrap();
+    data.extend_from_slice(&core_contract.abi_encode());
+    params.data = Some(data);
+    let mut tracer = ();
+    let result = Executive::new(&mut state, &env, &machine, &spec)
+        .call(params.clone(), &mut substate, &mut tracer)
+        .expect("no db error")
+        .expect("coreBalance should succeed");
+    assert_eq!(result.return_data.to_vec(), U256::from(1000).abi_encode());
+
+    // Static call to the Conflux space contract.
+    let mut data = "c3c4b914".from_hex::<Vec<u8>>().unwrap();
+    data.extend_from_slice(&(core_contract, Vec::<u8>::new()).abi_encode());
+    params.data = Some(data);
+    let mut tracer = ExecutiveTracer::default();
+    let result = Executive::new(&mut state, &env, &machine, &spec)
+        .call(params.clone(), &mut substate, &mut tracer)
+        .expect("no db error")
+        .expect("staticCallCore should succeed");
+    let mut output = vec![0u8; 32];
+    output[31] = 42;
+    assert_eq!(result.return_data.to_vec(), output.abi_encode());
+
+    // The read of the Conflux space is traced as a call, and no value is
+    // transferred.
+    let traces = tracer.drain();
+    assert!(!traces.iter().any(|trace| matches!(
+        trace.action,
+        TraceAction::InternalTransferAction(_)
+    )));
+    let calls: Vec<_> = traces
+        .into_iter()
+        .filter_map(|trace| match trace.action {
+            TraceAction::Call(call) => Some(call),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].space, Space::Ethereum);
+    assert_eq!(calls[0].to, *CORE_SPACE_READER_CONTRACT_ADDRESS);
+    assert_eq!(calls[1].space, Space::Native);
+    assert_eq!(calls[1].from, *CORE_SPACE_READER_CONTRACT_ADDRESS);
+    assert_eq!(calls[1].to, core_contract);
+    assert_eq!(calls[1].call_type, CallType::StaticCall);
+
+    // Reading the Conflux space in a cross space call from the Conflux space
+    // is rejected.
+    let mut core_sender = Address::zero();
+    core_sender.set_user_account_type_bits();
+    let mut inner_data = "c3c4b914".from_hex::<Vec<u8>>().unwrap();
+    inner_data
+        .extend_from_slice(&(core_contract, Vec::<u8>::new()).abi_encode());
+    let mut data = "30b4ef7d".from_hex::<Vec<u8>>().unwrap();
+    data.extend_from_slice(
+        &(CORE_SPACE_READER_CONTRACT_ADDRESS.0, inner_data).abi_encode(),
+    );
+    let mut params = ActionParams::default();
+    params.code_address = *CROSS_SPACE_CONTRACT_ADDRESS;
+    params.address = params.code_address;
+    params.sender = core_sender;
+    params.original_sender = core_sender;
+    params.storage_owner = params.code_address;
+    params.gas = U256::from(1_000_000);
+    params.call_type = CallType::Call;
+    params.data = Some(data);
+    let mut tracer = ();
+    let result = Executive::new(&mut state, &env, &machine, &spec)
+        .call(params.clone(), &mut substate, &mut tracer)
+        .expect("no db error");
+    assert_eq!(
+        result.unwrap_err(),
+        vm::Error::InternalContract("Reentrancy into Conflux space".into())
+    );
+}
+
+#[test]
+fn test_epoch_history() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut env = Env::default();
+    let machine = make_byzantium_machine(1024);
+    let spec = machine.spec(env.number);
+    let mut substate = Substate::new();
+
+    let pivot_hash = |epoch: u64| H256::from_low_u64_be(1000 + epoch);
+    let pos_id = |epoch: u64| H256::from_low_u64_be(2000 + epoch);
+    let state_root = |epoch: u64| H256::from_low_u64_be(3000 + epoch);
+    for epoch in 1..=10 {
+        let deferred_state_root = if epoch >= 5 {
+            state_root(epoch - 5)
+        } else {
+            H256::zero()
+        };
+        record_epoch_history(
+            &mut state,
+            epoch,
+            &pivot_hash(epoch - 1),
+            &Some(pos_id(epoch - 1)),
+            &deferred_state_root,
+        )
+        .unwrap();
+    }
+    env.epoch_height = 10;
+
+    // The history is kept in the system storage of the Conflux space context
+    // contract, without collateral or any eSpace account.
+    assert!(!state
+        .exists(&CONTEXT_CONTRACT_ADDRESS.with_evm_space())
+        .unwrap());
+    assert_eq!(
+        state
+            .collateral_for_storage(&CONTEXT_CONTRACT_ADDRESS)
+            .unwrap(),
+        U256::zero()
+    );
+
+    let mut query = |space: Space, selector: &str, epoch: u64| {
+        let mut params = ActionParams::default();
+        params.space = space;
+        params.code_address = match space {
+            Space::Native => *CONTEXT_CONTRACT_ADDRESS,
+            Space::Ethereum => *CORE_SPACE_READER_CONTRACT_ADDRESS,
+        };
+        params.address = params.code_address;
+        params.gas = U256::from(1_000_000);
+        params.call_type = CallType::StaticCall;
+        let mut data = selector.from_hex::<Vec<u8>>().unwrap();
+        data.extend_from_slice(&U256::from(epoch).abi_encode());
+        params.data = Some(data);
+        let result = Executive::new(&mut state, &env, &machine, &spec)
+            .call(params, &mut substate, &mut ())
+            .expect("no db error")
+            .expect("query should succeed");
+        H256::from_slice(&result.return_data.to_vec())
+    };
+
+    for space in vec![Space::Native, Space::Ethereum] {
+        // pivotBlockHash
+        assert_eq!(query(space, "d765b89b", 9), pivot_hash(9));
+        assert_eq!(query(space, "d765b89b", 0), pivot_hash(0));
+        assert_eq!(query(space, "d765b89b", 10), H256::zero());
+        assert_eq!(query(space, "d765b89b", 11), H256::zero());
+        // posBlockId
+        assert_eq!(query(space, "01bfffca", 3), pos_id(3));
+        assert_eq!(query(space, "01bfffca", 10), H256::zero());
+        // epochHash
+        assert_eq!(query(space, "107ae66b", 5), state_root(5));
+        assert_eq!(query(space, "107ae66b", 0), state_root(0));
+        assert_eq!(query(space, "107ae66b", 6), H256::zero());
+    }
+}
+
 #[test]
 fn test_commission_privilege_all_whitelisted_across_epochs() {
     let code: Vec<u8> = "7c601080600c6000396000f3006000355415600957005b60203560003555600052601d60036017f0600055".from_hex().unwrap();
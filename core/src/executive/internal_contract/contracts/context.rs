@@ -2,7 +2,11 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use super::{macros::*, SimpleExecutionTrait, SolFnTable};
+use super::{
+    super::impls::context::{epoch_hash, pivot_block_hash, pos_block_id},
+    macros::*,
+    SimpleExecutionTrait, SolFnTable,
+};
 #[cfg(test)]
 use crate::check_func_signature;
 use crate::{
@@ -14,7 +18,7 @@ use crate::{
     vm,
 };
 use cfx_parameters::internal_contract_addresses::CONTEXT_CONTRACT_ADDRESS;
-use cfx_types::{Address, U256};
+use cfx_types::{Address, H256, U256};
 #[cfg(test)]
 use rustc_hex::FromHex;
 
@@ -23,9 +27,18 @@ make_solidity_contract! {
 }
 
 fn generate_fn_table() -> SolFnTable {
-    make_function_table!(EpochNumber, PoSHeight, FinalizedEpoch)
+    make_function_table!(
+        EpochNumber,
+        PoSHeight,
+        FinalizedEpoch,
+        EpochHash,
+        PivotBlockHash,
+        PoSBlockId
+    )
 }
 
+// The functions are shared with the `CoreSpaceReader` internal contract in
+// eSpace, where they are also gated by the activation of the contract.
 group_impl_is_active!(
     |spec: &Spec| spec.cip64,
     EpochNumber,
@@ -33,8 +46,15 @@ group_impl_is_active!(
     FinalizedEpoch
 );
 
+group_impl_is_active!(
+    |spec: &Spec| spec.cip_epoch_history,
+    EpochHash,
+    PivotBlockHash,
+    PoSBlockId
+);
+
 make_solidity_function! {
-    struct EpochNumber((), "epochNumber()", U256);
+    pub(super) struct EpochNumber((), "epochNumber()", U256);
 }
 
 // same gas cost as the `NUMBER` opcode
@@ -51,7 +71,7 @@ impl SimpleExecutionTrait for EpochNumber {
 }
 
 make_solidity_function! {
-    struct PoSHeight((), "posHeight()", U256);
+    pub(super) struct PoSHeight((), "posHeight()", U256);
 }
 
 // same gas cost as the `NUMBER` opcode
@@ -68,7 +88,7 @@ impl SimpleExecutionTrait for PoSHeight {
 }
 
 make_solidity_function! {
-    struct FinalizedEpoch((), "finalizedEpochNumber()", U256);
+    pub(super) struct FinalizedEpoch((), "finalizedEpochNumber()", U256);
 }
 
 // same gas cost as the `NUMBER` opcode
@@ -84,7 +104,60 @@ impl SimpleExecutionTrait for FinalizedEpoch {
     }
 }
 
+make_solidity_function! {
+    pub(super) struct EpochHash(U256, "epochHash(uint256)", H256);
+}
+
+impl_function_type!(EpochHash, "query", gas: |spec: &Spec| spec.sload_gas);
+
+impl SimpleExecutionTrait for EpochHash {
+    fn execute_inner(
+        &self, epoch: U256, _params: &ActionParams,
+        context: &mut InternalRefContext, _tracer: &mut dyn VmObserve,
+    ) -> vm::Result<H256>
+    {
+        epoch_hash(epoch, context)
+    }
+}
+
+make_solidity_function! {
+    pub(super) struct PivotBlockHash(U256, "pivotBlockHash(uint256)", H256);
+}
+
+impl_function_type!(PivotBlockHash, "query", gas: |spec: &Spec| spec.sload_gas);
+
+impl SimpleExecutionTrait for PivotBlockHash {
+    fn execute_inner(
+        &self, epoch: U256, _params: &ActionParams,
+        context: &mut InternalRefContext, _tracer: &mut dyn VmObserve,
+    ) -> vm::Result<H256>
+    {
+        pivot_block_hash(epoch, context)
+    }
+}
+
+make_solidity_function! {
+    pub(super) struct PoSBlockId(U256, "posBlockId(uint256)", H256);
+}
+
+impl_function_type!(PoSBlockId, "query", gas: |spec: &Spec| spec.sload_gas);
+
+impl SimpleExecutionTrait for PoSBlockId {
+    fn execute_inner(
+        &self, epoch: U256, _params: &ActionParams,
+        context: &mut InternalRefContext, _tracer: &mut dyn VmObserve,
+    ) -> vm::Result<H256>
+    {
+        pos_block_id(epoch, context)
+    }
+}
+
 #[test]
 fn test_context_contract_sig() {
     check_func_signature!(EpochNumber, "f4145a83");
+    check_func_signature!(PoSHeight, "227907b4");
+    check_func_signature!(FinalizedEpoch, "23adad10");
+    check_func_signature!(EpochHash, "107ae66b");
+    check_func_signature!(PivotBlockHash, "d765b89b");
+    check_func_signature!(PoSBlockId, "01bfffca");
 }
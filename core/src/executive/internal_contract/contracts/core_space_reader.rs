@@ -0,0 +1,96 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{
+    super::impls::{
+        core_space_reader::{
+            core_balance, static_call_core_gas, static_call_to_core,
+        },
+        cross_space::process_trap,
+    },
+    context::{
+        EpochHash, EpochNumber, FinalizedEpoch, PivotBlockHash, PoSBlockId,
+        PoSHeight,
+    },
+    macros::*,
+    SolFnTable,
+};
+use crate::{
+    evm::{ActionParams, Spec},
+    executive::InternalRefContext,
+    impl_function_type, make_function_table, make_solidity_contract,
+    make_solidity_function,
+    observer::VmObserve,
+    vm::{self, ExecTrapResult},
+};
+use cfx_parameters::internal_contract_addresses::CORE_SPACE_READER_CONTRACT_ADDRESS;
+use cfx_types::{Address, U256};
+use std::marker::PhantomData;
+
+type Bytes = Vec<u8>;
+
+make_solidity_contract! {
+    pub struct CoreSpaceReader(CORE_SPACE_READER_CONTRACT_ADDRESS, generate_fn_table, is_active: |spec: &Spec| spec.cip_core_space_reader);
+}
+
+fn generate_fn_table() -> SolFnTable {
+    make_function_table!(
+        StaticCallCore,
+        CoreBalance,
+        EpochNumber,
+        PoSHeight,
+        FinalizedEpoch,
+        EpochHash,
+        PivotBlockHash,
+        PoSBlockId
+    )
+}
+
+// The epoch information functions are shared with the `Context` internal
+// contract.
+group_impl_is_active!(
+    |spec: &Spec| spec.cip_core_space_reader,
+    StaticCallCore,
+    CoreBalance,
+);
+
+make_solidity_function! {
+    struct StaticCallCore((Address,Bytes), "staticCallCore(address,bytes)", Bytes);
+}
+
+impl_function_type!(StaticCallCore, "query", gas: |spec: &Spec| static_call_core_gas(spec));
+
+impl ExecutionTrait for StaticCallCore {
+    fn execute_inner(
+        &self, (to, data): (Address, Bytes), params: &ActionParams,
+        gas_left: U256, context: &mut InternalRefContext,
+        _tracer: &mut dyn VmObserve,
+    ) -> ExecTrapResult<Bytes>
+    {
+        let trap = static_call_to_core(to, data, params, gas_left, context);
+        process_trap(trap, PhantomData)
+    }
+}
+
+make_solidity_function! {
+    struct CoreBalance(Address, "coreBalance(address)", U256);
+}
+
+impl_function_type!(CoreBalance, "query", gas: |spec: &Spec| spec.balance_gas);
+
+impl SimpleExecutionTrait for CoreBalance {
+    fn execute_inner(
+        &self, addr: Address, _params: &ActionParams,
+        context: &mut InternalRefContext, _tracer: &mut dyn VmObserve,
+    ) -> vm::Result<U256>
+    {
+        core_balance(addr, context)
+    }
+}
+
+#[test]
+fn test_core_space_reader_contract_sig() {
+    check_func_signature!(StaticCallCore, "c3c4b914");
+    check_func_signature!(CoreBalance, "c92b558d");
+}
@@ -4,8 +4,8 @@
 
 use super::{
     super::impls::cross_space::{
-        call_gas, call_to_evmcore, create_gas, create_to_evmcore, process_trap,
-        withdraw_from_evmcore,
+        call_gas, call_to_evmcore, create2_gas, create_gas, create_to_evmcore,
+        process_trap, withdraw_from_evmcore,
     },
     macros::*,
     SolFnTable,
@@ -24,7 +24,9 @@ use crate::{
     vm::{self, ExecTrapResult},
 };
 use cfx_parameters::internal_contract_addresses::CROSS_SPACE_CONTRACT_ADDRESS;
-use cfx_types::{Address, AddressSpaceUtil, AddressWithSpace, H160, U256};
+use cfx_types::{
+    Address, AddressSpaceUtil, AddressWithSpace, H160, H256, U256,
+};
 use primitives::storage::STORAGE_LAYOUT_REGULAR_V0;
 use std::marker::PhantomData;
 
@@ -38,6 +40,7 @@ make_solidity_contract! {
 fn generate_fn_table() -> SolFnTable {
     make_function_table!(
         CreateToEVM,
+        Create2ToEVM,
         TransferToEVM,
         CallToEVM,
         StaticCallToEVM,
@@ -60,6 +63,11 @@ group_impl_is_active!(
     DeployEip1820,
 );
 
+group_impl_is_active!(
+    |spec: &Spec| spec.cip90 && spec.cip_create2_evm,
+    Create2ToEVM,
+);
+
 make_solidity_event! {
     pub struct CallEvent("Call(bytes20,bytes20,uint256,uint256,bytes)", indexed: (Bytes20, Bytes20), non_indexed: (U256, U256, Bytes));
 }
@@ -104,33 +112,40 @@ impl ExecutionTrait for CreateToEVM {
     }
 }
 
-// make_solidity_function! {
-//     struct Create2ToEVM((Bytes,H256), "create2EVM(bytes,bytes32)", Bytes20);
-// }
-//
-// impl_function_type!(Create2ToEVM, "payable_write");
-//
-// impl UpfrontPaymentTrait for Create2ToEVM {
-//     fn upfront_gas_payment(
-//         &self, (ref init, _): &(Bytes, H256), _params: &ActionParams,
-//         context: &InternalRefContext,
-//     ) -> DbResult<U256>
-//     {
-//         create_gas(context, init.len())
-//     }
-// }
-// impl ExecutionTrait for Create2ToEVM {
-//     fn execute_inner(
-//         &self, (init, salt): (Bytes, H256), params: &ActionParams,
-//         gas_left: U256, context: &mut InternalRefContext,
-//         _tracer: &mut dyn Tracer,
-//     ) -> ExecTrapResult<Bytes20>
-//     {
-//         let trap =
-//             create_to_evmcore(init, Some(salt), params, gas_left, context);
-//         process_trap(trap, PhantomData)
-//     }
-// }
+make_solidity_function! {
+    pub struct Create2ToEVM((Bytes,H256), "create2EVM(bytes,bytes32)", Bytes20);
+}
+
+impl_function_type!(Create2ToEVM, "payable_write");
+
+impl UpfrontPaymentTrait for Create2ToEVM {
+    fn upfront_gas_payment(
+        &self, (ref init, _): &(Bytes, H256), _params: &ActionParams,
+        context: &InternalRefContext,
+    ) -> DbResult<U256>
+    {
+        create2_gas(context, init.as_ref())
+    }
+}
+
+impl ExecutionTrait for Create2ToEVM {
+    fn execute_inner(
+        &self, (init, salt): (Bytes, H256), params: &ActionParams,
+        gas_left: U256, context: &mut InternalRefContext,
+        tracer: &mut dyn VmObserve,
+    ) -> ExecTrapResult<Bytes20>
+    {
+        let trap = create_to_evmcore(
+            init,
+            Some(salt),
+            params,
+            gas_left,
+            context,
+            tracer,
+        );
+        process_trap(trap, PhantomData)
+    }
+}
 
 make_solidity_function! {
     pub struct TransferToEVM(Bytes20, "transferEVM(bytes20)", Bytes);
@@ -333,6 +348,7 @@ impl SimpleExecutionTrait for DeployEip1820 {
 #[test]
 fn test_cross_space_contract_sig() {
     check_func_signature!(CreateToEVM, "ff311601");
+    check_func_signature!(Create2ToEVM, "50f0d1bd");
     check_func_signature!(TransferToEVM, "da8d5daf");
     check_func_signature!(CallToEVM, "bea05ee3");
     check_func_signature!(StaticCallToEVM, "30b4ef7d");
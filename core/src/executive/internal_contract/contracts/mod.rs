@@ -4,6 +4,7 @@
 
 mod admin;
 mod context;
+mod core_space_reader;
 pub mod cross_space;
 mod future;
 #[allow(unused)]
@@ -41,8 +42,9 @@ mod macros {
 }
 
 pub use self::{
-    admin::AdminControl, context::Context, cross_space::CrossSpaceCall,
-    pos::PoSRegister, sponsor::SponsorWhitelistControl, staking::Staking,
+    admin::AdminControl, context::Context, core_space_reader::CoreSpaceReader,
+    cross_space::CrossSpaceCall, pos::PoSRegister,
+    sponsor::SponsorWhitelistControl, staking::Staking,
 };
 pub(super) use self::{
     cross_space::{CallEvent, CreateEvent, ReturnEvent, WithdrawEvent},
@@ -73,6 +75,12 @@ macro_rules! make_solidity_contract {
             $(#[$attr])* $visibility struct $name ($addr, $gen_table, initialize: |_: &CommonParams| 0u64, is_active: |_: &Spec| true);
         }
     };
+    // The internal contracts in the EVM space have no accounts to initialize.
+    ( $(#[$attr:meta])* $visibility:vis struct $name:ident ($addr:expr, $gen_table:expr, is_active: $is_active:expr); ) => {
+        $crate::make_solidity_contract! {
+            $(#[$attr])* $visibility struct $name ($addr, $gen_table, initialize: |_: &CommonParams| u64::MAX, is_active: $is_active);
+        }
+    };
     ( $(#[$attr:meta])* $visibility:vis struct $name:ident ($addr:expr, $gen_table:expr, initialize: $init:expr, is_active: $is_active:expr); ) => {
         $(#[$attr])*
         $visibility struct $name {
@@ -136,6 +144,7 @@ macro_rules! check_event_signature {
 #[derive(Default)]
 pub struct InternalContractMap {
     builtin: BTreeMap<Address, Box<dyn InternalContractTrait>>,
+    evm_builtin: BTreeMap<Address, Box<dyn InternalContractTrait>>,
     activation_info: BTreeMap<BlockNumber, Vec<Address>>,
 }
 
@@ -171,8 +180,16 @@ impl InternalContractMap {
                 .push(address);
         }
 
+        // The internal contracts in the EVM space have no accounts to
+        // initialize.
+        let evm_builtin = all_evm_internal_contracts()
+            .into_iter()
+            .map(|contract| (*contract.address(), contract))
+            .collect();
+
         Self {
             builtin,
+            evm_builtin,
             activation_info,
         }
     }
@@ -198,10 +215,11 @@ impl InternalContractMap {
     pub fn contract(
         &self, address: &AddressWithSpace, spec: &Spec,
     ) -> Option<&Box<dyn InternalContractTrait>> {
-        if address.space != Space::Native {
-            return None;
-        }
-        self.builtin
+        let builtin = match address.space {
+            Space::Native => &self.builtin,
+            Space::Ethereum => &self.evm_builtin,
+        };
+        builtin
             .get(&address.address)
             .filter(|&func| func.is_active(spec))
     }
@@ -225,3 +243,8 @@ pub fn all_internal_contracts() -> Vec<Box<dyn InternalContractTrait>> {
         Box::new(future::Reserved11::instance()),
     ]
 }
+
+/// All Built-in contracts in the EVM space.
+pub fn all_evm_internal_contracts() -> Vec<Box<dyn InternalContractTrait>> {
+    vec![Box::new(CoreSpaceReader::instance())]
+}
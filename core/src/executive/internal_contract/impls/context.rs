@@ -0,0 +1,103 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{executive::InternalRefContext, vm};
+use cfx_parameters::{
+    block::EPOCH_HISTORY_WINDOW, consensus::DEFERRED_STATE_EPOCH_COUNT,
+    internal_contract_addresses::CONTEXT_CONTRACT_ADDRESS,
+};
+use cfx_state::state_trait::StateOpsTrait;
+use cfx_statedb::Result as DbResult;
+use cfx_types::{AddressSpaceUtil, BigEndianHash, H256, U256};
+
+const EPOCH_HASH: u64 = 0;
+const PIVOT_BLOCK_HASH: u64 = 1;
+const POS_BLOCK_ID: u64 = 2;
+
+fn history_key(kind: u64, epoch: u64) -> Vec<u8> {
+    let slot = U256([epoch % EPOCH_HISTORY_WINDOW, kind, 0, 0]);
+    H256::from_uint(&slot).as_bytes().to_vec()
+}
+
+/// Records the history entries that become available at the beginning of
+/// `epoch`: the pivot block hash and the PoS block id of the parent epoch,
+/// and the state root of the epoch `DEFERRED_STATE_EPOCH_COUNT` epochs ago,
+/// which is committed as the deferred state root of the current pivot block.
+///
+/// The history is kept in the system storage of the context contract, which
+/// is owned by the contract and occupies no storage collateral. Nothing is
+/// recorded before the context contract is initialized.
+pub fn record_epoch_history(
+    state: &mut dyn StateOpsTrait, epoch: u64, parent_hash: &H256,
+    parent_pos_reference: &Option<H256>, deferred_state_root: &H256,
+) -> DbResult<()>
+{
+    let address = &*CONTEXT_CONTRACT_ADDRESS;
+    if !state.exists(&address.with_native_space())? {
+        return Ok(());
+    }
+
+    if epoch >= 1 {
+        let parent_epoch = epoch - 1;
+        state.set_system_storage(
+            address,
+            history_key(PIVOT_BLOCK_HASH, parent_epoch),
+            parent_hash.into_uint(),
+        )?;
+        state.set_system_storage(
+            address,
+            history_key(POS_BLOCK_ID, parent_epoch),
+            parent_pos_reference.unwrap_or_default().into_uint(),
+        )?;
+    }
+    if epoch >= DEFERRED_STATE_EPOCH_COUNT {
+        state.set_system_storage(
+            address,
+            history_key(EPOCH_HASH, epoch - DEFERRED_STATE_EPOCH_COUNT),
+            deferred_state_root.into_uint(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads a history entry of `epoch`, which is available when it is at least
+/// `min_age` and at most `EPOCH_HISTORY_WINDOW` epochs older than the
+/// current epoch. Like `BLOCKHASH`, zero is returned out of that range.
+fn history_at(
+    kind: u64, epoch: U256, min_age: u64, context: &mut InternalRefContext,
+) -> vm::Result<H256> {
+    let current = context.env.epoch_height;
+    if epoch > U256::from(current) {
+        return Ok(H256::zero());
+    }
+    let epoch = epoch.as_u64();
+    let age = current - epoch;
+    if age < min_age || age > EPOCH_HISTORY_WINDOW {
+        return Ok(H256::zero());
+    }
+    let value = context.state.storage_at(
+        &CONTEXT_CONTRACT_ADDRESS.with_native_space(),
+        &history_key(kind, epoch),
+    )?;
+    Ok(H256::from_uint(&value))
+}
+
+/// The epoch hash is the state root after executing the epoch.
+pub fn epoch_hash(
+    epoch: U256, context: &mut InternalRefContext,
+) -> vm::Result<H256> {
+    history_at(EPOCH_HASH, epoch, DEFERRED_STATE_EPOCH_COUNT, context)
+}
+
+pub fn pivot_block_hash(
+    epoch: U256, context: &mut InternalRefContext,
+) -> vm::Result<H256> {
+    history_at(PIVOT_BLOCK_HASH, epoch, 1, context)
+}
+
+pub fn pos_block_id(
+    epoch: U256, context: &mut InternalRefContext,
+) -> vm::Result<H256> {
+    history_at(POS_BLOCK_ID, epoch, 1, context)
+}
@@ -0,0 +1,155 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{
+    evm::{
+        ActionParams, CallType, Context, GasLeft, MessageCallResult, ReturnData,
+    },
+    executive::InternalRefContext,
+    observer::VmObserve,
+    vm::{
+        self, ActionValue, CreateType, Exec, ExecTrapError as ExecTrap,
+        ExecTrapResult, ResumeCall, Spec, TrapResult,
+    },
+};
+use cfx_parameters::{
+    block::CROSS_SPACE_GAS_RATIO,
+    internal_contract_addresses::CROSS_SPACE_CONTRACT_ADDRESS,
+};
+use cfx_types::{Address, AddressSpaceUtil, Space, U256};
+use solidity_abi::ABIEncodable;
+
+pub fn static_call_core_gas(spec: &Spec) -> U256 { U256::from(spec.call_gas) }
+
+pub fn core_balance(
+    address: Address, context: &mut InternalRefContext,
+) -> vm::Result<U256> {
+    Ok(context.state.balance(&address.with_native_space())?)
+}
+
+/// Makes a static call from the EVM space to a contract in the Conflux space.
+/// The Conflux space contract sees the internal contract as both the sender
+/// and the origin.
+pub fn static_call_to_core(
+    receiver: Address, data: Vec<u8>, params: &ActionParams, gas_left: U256,
+    context: &mut InternalRefContext,
+) -> Result<ExecTrap, vm::Error> {
+    if context.depth >= context.spec.max_depth {
+        return Err(vm::Error::InternalContract("Exceed Depth".into()));
+    }
+
+    // If the EVM space is entered from the Conflux space through a cross space
+    // call, the Conflux space contracts in the call stack are in the middle of
+    // execution. Reading their state is not allowed.
+    if context
+        .callstack
+        .contains_key(&CROSS_SPACE_CONTRACT_ADDRESS.with_native_space())
+    {
+        return Err(vm::Error::InternalContract(
+            "Reentrancy into Conflux space".into(),
+        ));
+    }
+
+    let call_gas = gas_left / CROSS_SPACE_GAS_RATIO;
+    let reserved_gas = gas_left - call_gas;
+
+    let address = receiver.with_native_space();
+    let code = context.state.code(&address)?;
+    let code_hash = context.state.code_hash(&address)?;
+
+    let next_params = ActionParams {
+        space: Space::Native,
+        sender: params.address,
+        address: address.address,
+        value: ActionValue::Apparent(U256::zero()),
+        code_address: address.address,
+        original_sender: params.address,
+        storage_owner: params.address,
+        gas: call_gas,
+        gas_price: params.gas_price,
+        code,
+        code_hash,
+        data: Some(data),
+        call_type: CallType::StaticCall,
+        create_type: CreateType::None,
+        params_type: vm::ParamsType::Separate,
+    };
+
+    Ok(ExecTrap::Call(
+        next_params,
+        Box::new(Resume {
+            gas_retained: reserved_gas,
+        }),
+    ))
+}
+
+#[derive(Clone)]
+pub struct Resume {
+    pub gas_retained: U256,
+}
+
+impl ResumeCall for Resume {
+    fn resume_call(
+        self: Box<Self>, result: MessageCallResult,
+    ) -> Box<dyn Exec> {
+        let pass_result = match result {
+            MessageCallResult::Success(gas_left, data) => {
+                let encoded_output = data.to_vec().abi_encode();
+                let length = encoded_output.len();
+                PassResult {
+                    gas_left: gas_left + self.gas_retained,
+                    return_data: Ok(ReturnData::new(encoded_output, 0, length)),
+                    apply_state: true,
+                }
+            }
+            MessageCallResult::Failed(err) => PassResult {
+                gas_left: U256::zero(),
+                return_data: Err(err),
+                apply_state: false,
+            },
+            MessageCallResult::Reverted(gas_left, data) => PassResult {
+                gas_left: gas_left + self.gas_retained,
+                return_data: Ok(data),
+                apply_state: false,
+            },
+        };
+        Box::new(pass_result)
+    }
+}
+
+pub struct PassResult {
+    gas_left: U256,
+    return_data: Result<ReturnData, vm::Error>,
+    apply_state: bool,
+}
+
+impl Exec for PassResult {
+    fn exec(
+        self: Box<Self>, context: &mut dyn Context,
+        _tracer: &mut dyn VmObserve,
+    ) -> ExecTrapResult<GasLeft>
+    {
+        let context = context.internal_ref();
+        let PassResult {
+            gas_left,
+            return_data,
+            apply_state,
+        } = *self;
+
+        let result = return_data.and_then(|data| {
+            let return_cost =
+                U256::from((data.len() + 31) / 32 * context.spec.memory_gas);
+            if gas_left < return_cost {
+                Err(vm::Error::OutOfGas)
+            } else {
+                Ok(GasLeft::NeedsReturn {
+                    gas_left: gas_left - return_cost,
+                    data,
+                    apply_state,
+                })
+            }
+        });
+        TrapResult::Return(result)
+    }
+}
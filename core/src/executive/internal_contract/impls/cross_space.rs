@@ -67,6 +67,15 @@ pub fn create_gas(context: &InternalRefContext, code: &[u8]) -> DbResult<U256> {
         + return_log_gas)
 }
 
+pub fn create2_gas(
+    context: &InternalRefContext, code: &[u8],
+) -> DbResult<U256> {
+    // The init code is hashed for the address derivation, as `CREATE2` does.
+    let code_hash_gas = context.spec.sha3_word_gas * ((code.len() + 31) / 32);
+
+    Ok(create_gas(context, code)? + code_hash_gas)
+}
+
 pub fn call_gas(
     receiver: Address, params: &ActionParams, context: &InternalRefContext,
     data: &[u8],
@@ -506,6 +515,8 @@ pub struct PhantomTransaction {
     pub action: Action,
     pub value: U256,
     pub data: Vec<u8>,
+    /// The address of the contract created by `createEVM` or `create2EVM`.
+    pub contract_address: Option<Address>,
 
     pub log_bloom: Bloom,
     pub logs: Vec<LogEntry>,
@@ -587,10 +598,10 @@ pub fn build_bloom_and_recover_phantom(
                     ABIDecodable::abi_decode(&log.data).unwrap();
 
                 let is_create = event_sig == &CreateEvent::EVENT_SIG;
-                let action = if is_create {
-                    Action::Create
+                let (action, contract_address) = if is_create {
+                    (Action::Create, Some(to))
                 } else {
-                    Action::Call(to)
+                    (Action::Call(to), None)
                 };
                 // The first phantom transaction for cross-space call, transfer
                 // balance and gas fee from the zero address to the mapped
@@ -613,6 +624,7 @@ pub fn build_bloom_and_recover_phantom(
                     action,
                     value,
                     data,
+                    contract_address,
                     ..Default::default()
                 });
             } else if event_sig == &WithdrawEvent::EVENT_SIG {
@@ -3,9 +3,11 @@
 // See http://www.gnu.org/licenses/
 
 pub(super) mod admin;
+pub(super) mod context;
+pub(super) mod core_space_reader;
 pub(super) mod cross_space;
 pub(crate) mod pos;
 pub(super) mod sponsor;
 pub(super) mod staking;
 
-pub use self::admin::suicide;
+pub use self::{admin::suicide, context::record_epoch_history};
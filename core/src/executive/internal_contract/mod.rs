@@ -13,7 +13,7 @@ pub use self::{
     impls::{
         cross_space::{build_bloom_and_recover_phantom, PhantomTransaction},
         pos::{entries as pos_internal_entries, IndexStatus},
-        suicide,
+        record_epoch_history, suicide,
     },
     internal_context::InternalRefContext,
 };
@@ -0,0 +1,356 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Fork mode runs a local dev chain on top of the state of a remote chain at
+//! a fixed epoch. The accounts, code and storage entries which are not in the
+//! local state are read through the JSON-RPC interface of a remote node when
+//! they are first accessed, and the values are cached in the local database,
+//! so a key is fetched at most once.
+//!
+//! A key deleted from the local state must not be read from the remote state
+//! again, so the deletions are recorded in the cache as well. They are not
+//! bound to an epoch, so a key deleted in the blocks which later leave the
+//! pivot chain stays deleted.
+
+mod remote_state;
+
+pub use self::remote_state::{RemoteState, RemoteStatus};
+
+use crate::db::COL_FORKED_STATE;
+use cfx_storage::{
+    storage_db::{KeyValueDbTrait, KeyValueDbTraitRead},
+    ForkedStateTrait, KvdbRocksdb, Result as StorageResult,
+};
+use ext_db::SystemDB;
+use parking_lot::RwLock;
+use primitives::StorageKeyWithSpace;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::sync::Arc;
+
+const FORK_INFO_KEY: &[u8] = b"fork_info";
+const DELETED_PREFIXES_KEY: &[u8] = b"deleted_prefixes";
+/// The cached values are prefixed to be separated from the keys above.
+const CACHED_VALUE_PREFIX: u8 = b'v';
+
+const CACHED_ABSENT: u8 = 0;
+const CACHED_PRESENT: u8 = 1;
+
+#[derive(Clone, Debug)]
+pub struct ForkConfiguration {
+    /// The JSON-RPC url of the remote node.
+    pub url: String,
+    /// The epoch of the remote state. The latest executed epoch of the remote
+    /// node is used if it is not set.
+    pub epoch: Option<u64>,
+}
+
+/// The remote chain a local database is forked from. It's kept so that the
+/// cached values stay consistent after restarts.
+#[derive(RlpEncodable, RlpDecodable)]
+struct ForkInfo {
+    epoch: u64,
+    network_id: u64,
+}
+
+pub struct ForkedState {
+    remote: RemoteState,
+    db: KvdbRocksdb,
+    /// The prefixes of the keys deleted by `delete_all` in the local state.
+    deleted_prefixes: RwLock<Vec<Vec<u8>>>,
+}
+
+impl ForkedState {
+    pub fn open(
+        conf: ForkConfiguration, db: Arc<SystemDB>,
+    ) -> Result<Self, String> {
+        let db = KvdbRocksdb {
+            kvdb: db.key_value().clone(),
+            col: COL_FORKED_STATE,
+        };
+        let fork_info = match db
+            .get(FORK_INFO_KEY)
+            .map_err(|e| format!("Failed to read fork info: {}", e))?
+        {
+            Some(raw) => {
+                let fork_info: ForkInfo = rlp::decode(&raw)
+                    .map_err(|e| format!("Invalid fork info: {}", e))?;
+                if let Some(epoch) = conf.epoch {
+                    if epoch != fork_info.epoch {
+                        return Err(format!(
+                            "The database is forked at epoch {}, which \
+                             mismatches fork_epoch {}",
+                            fork_info.epoch, epoch
+                        ));
+                    }
+                }
+                fork_info
+            }
+            None => {
+                let status = RemoteState::status(&conf.url)?;
+                let fork_info = ForkInfo {
+                    epoch: conf.epoch.unwrap_or(status.latest_state.as_u64()),
+                    network_id: status.network_id.as_u64(),
+                };
+                db.put(FORK_INFO_KEY, &rlp::encode(&fork_info))
+                    .map_err(|e| format!("Failed to write fork info: {}", e))?;
+                fork_info
+            }
+        };
+        let deleted_prefixes = match db
+            .get(DELETED_PREFIXES_KEY)
+            .map_err(|e| format!("Failed to read deleted prefixes: {}", e))?
+        {
+            Some(raw) => rlp::decode_list(&raw),
+            None => Vec::new(),
+        };
+        info!(
+            "Fork from {} at epoch {}, network id {}",
+            conf.url, fork_info.epoch, fork_info.network_id
+        );
+
+        Ok(Self {
+            remote: RemoteState::new(
+                conf.url,
+                fork_info.epoch,
+                fork_info.network_id,
+            ),
+            db,
+            deleted_prefixes: RwLock::new(deleted_prefixes),
+        })
+    }
+
+    pub fn epoch(&self) -> u64 { self.remote.epoch() }
+}
+
+impl ForkedStateTrait for ForkedState {
+    fn get(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        let key = access_key.to_key_bytes();
+        if self
+            .deleted_prefixes
+            .read()
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        {
+            return Ok(None);
+        }
+        let cache_key = cached_value_key(&key);
+        if let Some(cached) = self.db.get(&cache_key)? {
+            return Ok(match cached[0] {
+                CACHED_PRESENT => Some(cached[1..].into()),
+                _ => None,
+            });
+        }
+
+        let value = self.remote.get(access_key)?;
+        debug!("Fetch forked state key={:?} value={:?}", access_key, value);
+        let cached = match &value {
+            Some(value) => [&[CACHED_PRESENT], value.as_slice()].concat(),
+            None => vec![CACHED_ABSENT],
+        };
+        self.db.put(&cache_key, &cached)?;
+        Ok(value.map(Into::into))
+    }
+
+    fn on_deleted(
+        &self, access_key: StorageKeyWithSpace, is_prefix: bool,
+    ) -> StorageResult<()> {
+        let key = access_key.to_key_bytes();
+        if is_prefix {
+            let mut deleted_prefixes = self.deleted_prefixes.write();
+            if !deleted_prefixes.contains(&key) {
+                deleted_prefixes.push(key);
+                self.db.put(
+                    DELETED_PREFIXES_KEY,
+                    &rlp::encode_list::<Vec<u8>, _>(&deleted_prefixes),
+                )?;
+            }
+        } else {
+            self.db.put(&cached_value_key(&key), &[CACHED_ABSENT])?;
+        }
+        Ok(())
+    }
+}
+
+fn cached_value_key(key: &[u8]) -> Vec<u8> {
+    [&[CACHED_VALUE_PREFIX], key].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use cfx_types::{Address, AddressSpaceUtil, U256};
+    use primitives::{Account, StorageKey, StorageValue};
+    use serde_json::{json, Value};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+    use tempdir::TempDir;
+
+    /// Serve the JSON-RPC requests with `respond` in the place of a remote
+    /// node. Return the url and the counter of requests.
+    fn start_stand_in_server(
+        respond: fn(&str, &Value) -> Value,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    let line = line.trim().to_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(len) = line.strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": respond(
+                        request["method"].as_str().unwrap(),
+                        &request["params"],
+                    ),
+                })
+                .to_string();
+                write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn respond(method: &str, params: &Value) -> Value {
+        if method == "cfx_getStatus" {
+            return json!({ "networkId": "0x2a", "latestState": "0x64" });
+        }
+        assert_eq!(params.as_array().unwrap().last().unwrap(), "0x64");
+        match method {
+            "eth_getBalance" => json!("0x10"),
+            "eth_getTransactionCount" => json!("0x1"),
+            "eth_getCode" => json!("0x"),
+            "eth_getStorageAt" => json!(format!("0x{:064x}", 7)),
+            _ => panic!("Unexpected method {}", method),
+        }
+    }
+
+    fn open_db(dir: &TempDir) -> Arc<SystemDB> {
+        let config = ext_db::db_config(
+            dir.path(),
+            None,
+            Default::default(),
+            NUM_COLUMNS,
+            false,
+        );
+        ext_db::open_database(dir.path().to_str().unwrap(), &config).unwrap()
+    }
+
+    #[test]
+    fn test_fetch_and_cache() {
+        let (url, requests) = start_stand_in_server(respond);
+        let dir = TempDir::new("forked_state").unwrap();
+        let db = open_db(&dir);
+        let conf = ForkConfiguration { url, epoch: None };
+        let address = Address::from_low_u64_be(1).with_evm_space();
+        let account_key =
+            StorageKey::new_account_key(&address.address).with_evm_space();
+
+        let forked_state = ForkedState::open(conf.clone(), db.clone()).unwrap();
+        assert_eq!(forked_state.epoch(), 100);
+        let raw = forked_state.get(account_key).unwrap().unwrap();
+        let account =
+            Account::new_from_rlp(address.address, &rlp::Rlp::new(&raw))
+                .unwrap();
+        assert_eq!(account.balance, U256::from(16));
+        assert_eq!(account.nonce, U256::from(1));
+        let fetched = requests.load(Ordering::SeqCst);
+        assert_eq!(forked_state.get(account_key).unwrap(), Some(raw.clone()));
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+
+        // The fork epoch and the cached values are kept after reopening.
+        drop(forked_state);
+        let forked_state = ForkedState::open(conf, db).unwrap();
+        assert_eq!(forked_state.epoch(), 100);
+        assert_eq!(forked_state.get(account_key).unwrap(), Some(raw));
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+    }
+
+    #[test]
+    fn test_deleted_keys() {
+        let (url, requests) = start_stand_in_server(respond);
+        let dir = TempDir::new("forked_state").unwrap();
+        let db = open_db(&dir);
+        let conf = ForkConfiguration {
+            url,
+            epoch: Some(100),
+        };
+        let address = Address::from_low_u64_be(1);
+        let (slot_1, slot_2) = ([1; 32], [2; 32]);
+        let key_1 =
+            StorageKey::new_storage_key(&address, &slot_1).with_evm_space();
+        let key_2 =
+            StorageKey::new_storage_key(&address, &slot_2).with_evm_space();
+
+        let forked_state = ForkedState::open(conf.clone(), db.clone()).unwrap();
+        let raw = forked_state.get(key_1).unwrap().unwrap();
+        let value: StorageValue = rlp::decode(&raw).unwrap();
+        assert_eq!(value.value, U256::from(7));
+
+        forked_state
+            .on_deleted(key_1, /* is_prefix = */ false)
+            .unwrap();
+        assert_eq!(forked_state.get(key_1).unwrap(), None);
+
+        forked_state
+            .on_deleted(
+                StorageKey::new_storage_root_key(&address).with_evm_space(),
+                /* is_prefix = */ true,
+            )
+            .unwrap();
+        let fetched = requests.load(Ordering::SeqCst);
+        drop(forked_state);
+        let forked_state = ForkedState::open(conf, db).unwrap();
+        assert_eq!(forked_state.get(key_2).unwrap(), None);
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+    }
+
+    #[test]
+    fn test_fork_epoch_mismatch() {
+        let (url, _) = start_stand_in_server(respond);
+        let dir = TempDir::new("forked_state").unwrap();
+        let db = open_db(&dir);
+        let conf = ForkConfiguration {
+            url: url.clone(),
+            epoch: Some(100),
+        };
+        drop(ForkedState::open(conf, db.clone()).unwrap());
+        let conf = ForkConfiguration {
+            url,
+            epoch: Some(101),
+        };
+        assert!(ForkedState::open(conf, db).is_err());
+    }
+}
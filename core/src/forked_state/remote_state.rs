@@ -0,0 +1,386 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_addr::{cfx_addr_decode, cfx_addr_encode, EncodingOptions, Network};
+use cfx_parameters::internal_contract_addresses::STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS;
+use cfx_statedb::{
+    ACCUMULATE_INTEREST_RATE_KEY, INTEREST_RATE_KEY, TOTAL_BANK_TOKENS_KEY,
+    TOTAL_EVM_TOKENS_KEY, TOTAL_STORAGE_TOKENS_KEY, TOTAL_TOKENS_KEY,
+};
+use cfx_types::{
+    address_util::AddressUtil, Address, AddressSpaceUtil, AddressWithSpace,
+    Space, H256, U256, U64,
+};
+use hash::{keccak, KECCAK_EMPTY};
+use network::NetworkConfiguration;
+use primitives::{
+    Account, CodeInfo, DepositInfo, DepositList, SponsorInfo, StorageKey,
+    StorageKeyWithSpace, StorageValue, VoteStakeInfo, VoteStakeList,
+};
+use rustc_hex::FromHex;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStatus {
+    pub network_id: U64,
+    pub latest_state: U64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteAccount {
+    balance: U256,
+    nonce: U256,
+    code_hash: H256,
+    staking_balance: U256,
+    collateral_for_storage: U256,
+    accumulated_interest_return: U256,
+    admin: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteSponsorInfo {
+    sponsor_for_gas: String,
+    sponsor_for_collateral: String,
+    sponsor_gas_bound: U256,
+    sponsor_balance_for_gas: U256,
+    sponsor_balance_for_collateral: U256,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteSupplyInfo {
+    total_issued: U256,
+    total_staking: U256,
+    total_collateral: U256,
+    total_espace_tokens: U256,
+}
+
+/// Read the state of a remote chain at a fixed epoch through its JSON-RPC
+/// interface, and encode the values as they are stored in the local state.
+pub struct RemoteState {
+    url: String,
+    epoch: u64,
+    network: Network,
+    next_request_id: AtomicU64,
+}
+
+impl RemoteState {
+    pub fn new(url: String, epoch: u64, network_id: u64) -> Self {
+        Self {
+            url,
+            epoch,
+            network: NetworkConfiguration::network_id_to_known_cfx_network(
+                network_id,
+            ),
+            next_request_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn status(url: &str) -> Result<RemoteStatus, String> {
+        call(url, 0, "cfx_getStatus", json!([]))
+    }
+
+    pub fn epoch(&self) -> u64 { self.epoch }
+
+    /// Return the encoded value of `access_key`, or `None` if the key does
+    /// not exist or cannot be read through the RPCs.
+    pub fn get(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let space = access_key.space;
+        match access_key.key {
+            StorageKey::AccountKey(address) => {
+                self.account(&to_address(address)?.with_space(space))
+            }
+            StorageKey::CodeKey {
+                address_bytes,
+                code_hash_bytes,
+            } => self.code(
+                &to_address(address_bytes)?.with_space(space),
+                &H256::from_slice(code_hash_bytes),
+            ),
+            StorageKey::StorageKey {
+                address_bytes,
+                storage_key,
+            } => self.storage_at(
+                &to_address(address_bytes)?.with_space(space),
+                storage_key,
+            ),
+            StorageKey::DepositListKey(address) if space == Space::Native => {
+                let deposits: Vec<DepositInfo> = self.request(
+                    "cfx_getDepositList",
+                    json!([
+                        self.base32(&to_address(address)?)?,
+                        self.epoch_param()
+                    ]),
+                )?;
+                Ok(if deposits.is_empty() {
+                    None
+                } else {
+                    Some(rlp::encode(&DepositList(deposits)))
+                })
+            }
+            StorageKey::VoteListKey(address) if space == Space::Native => {
+                let votes: Vec<VoteStakeInfo> = self.request(
+                    "cfx_getVoteList",
+                    json!([
+                        self.base32(&to_address(address)?)?,
+                        self.epoch_param()
+                    ]),
+                )?;
+                Ok(if votes.is_empty() {
+                    None
+                } else {
+                    Some(rlp::encode(&VoteStakeList(votes)))
+                })
+            }
+            // The storage roots and code roots are only used for the proofs
+            // of the local state.
+            _ => Ok(None),
+        }
+    }
+
+    fn account(
+        &self, address: &AddressWithSpace,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let mut account = Account::new_empty(address);
+        match address.space {
+            Space::Native => {
+                let remote: RemoteAccount = self.request(
+                    "cfx_getAccount",
+                    json!([self.base32(&address.address)?, self.epoch_param()]),
+                )?;
+                account.balance = remote.balance;
+                account.nonce = remote.nonce;
+                account.code_hash = remote.code_hash;
+                account.staking_balance = remote.staking_balance;
+                account.collateral_for_storage = remote.collateral_for_storage;
+                account.accumulated_interest_return =
+                    remote.accumulated_interest_return;
+                account.admin = parse_base32(&remote.admin)?;
+                if account.code_hash != KECCAK_EMPTY
+                    || address.address.is_contract_address()
+                {
+                    account.sponsor_info =
+                        self.sponsor_info(&address.address)?;
+                }
+            }
+            Space::Ethereum => {
+                let hex_address = format!("{:?}", address.address);
+                account.balance = self.request(
+                    "eth_getBalance",
+                    json!([hex_address, self.epoch_param()]),
+                )?;
+                account.nonce = self.request(
+                    "eth_getTransactionCount",
+                    json!([hex_address, self.epoch_param()]),
+                )?;
+                let code = self.code_bytes(address)?;
+                if !code.is_empty() {
+                    account.code_hash = keccak(&code);
+                }
+            }
+        }
+        Ok(if account == Account::new_empty(address) {
+            None
+        } else {
+            Some(rlp::encode(&account))
+        })
+    }
+
+    fn sponsor_info(&self, address: &Address) -> Result<SponsorInfo, String> {
+        let remote: RemoteSponsorInfo = self.request(
+            "cfx_getSponsorInfo",
+            json!([self.base32(address)?, self.epoch_param()]),
+        )?;
+        Ok(SponsorInfo {
+            sponsor_for_gas: parse_base32(&remote.sponsor_for_gas)?,
+            sponsor_for_collateral: parse_base32(
+                &remote.sponsor_for_collateral,
+            )?,
+            sponsor_gas_bound: remote.sponsor_gas_bound,
+            sponsor_balance_for_gas: remote.sponsor_balance_for_gas,
+            sponsor_balance_for_collateral: remote
+                .sponsor_balance_for_collateral,
+        })
+    }
+
+    fn code(
+        &self, address: &AddressWithSpace, code_hash: &H256,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let code = self.code_bytes(address)?;
+        if code.is_empty() || keccak(&code) != *code_hash {
+            return Ok(None);
+        }
+        // The remote RPCs do not tell who paid the collateral for code, so it
+        // is charged to the contract itself in Core space.
+        let owner = match address.space {
+            Space::Native => address.address,
+            Space::Ethereum => Address::zero(),
+        };
+        Ok(Some(rlp::encode(&CodeInfo {
+            code: Arc::new(code),
+            owner,
+        })))
+    }
+
+    fn code_bytes(
+        &self, address: &AddressWithSpace,
+    ) -> Result<Vec<u8>, String> {
+        let code: String = match address.space {
+            Space::Native => self.request(
+                "cfx_getCode",
+                json!([self.base32(&address.address)?, self.epoch_param()]),
+            )?,
+            Space::Ethereum => self.request(
+                "eth_getCode",
+                json!([format!("{:?}", address.address), self.epoch_param()]),
+            )?,
+        };
+        parse_hex(&code)
+    }
+
+    fn storage_at(
+        &self, address: &AddressWithSpace, key: &[u8],
+    ) -> Result<Option<Vec<u8>>, String> {
+        if address.space == Space::Native
+            && address.address == STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS
+        {
+            return self.global_variable(key);
+        }
+        // The keys of the internal contracts which are not 32 bytes cannot be
+        // read through the RPCs.
+        if key.len() != 32 {
+            return Ok(None);
+        }
+        let position = format!("{:?}", H256::from_slice(key));
+        let value: Option<H256> = match address.space {
+            Space::Native => self.request(
+                "cfx_getStorageAt",
+                json!([
+                    self.base32(&address.address)?,
+                    position,
+                    self.epoch_param()
+                ]),
+            )?,
+            Space::Ethereum => self.request(
+                "eth_getStorageAt",
+                json!([
+                    format!("{:?}", address.address),
+                    position,
+                    self.epoch_param()
+                ]),
+            )?,
+        };
+        let value = U256::from_big_endian(&value.unwrap_or_default().0);
+        // The remote RPCs do not tell who paid the collateral for the entry,
+        // so it is charged to the contract itself.
+        Ok(if value.is_zero() {
+            None
+        } else {
+            Some(rlp::encode(&StorageValue { value, owner: None }))
+        })
+    }
+
+    fn global_variable(&self, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let value: U256 = match key {
+            TOTAL_TOKENS_KEY => self.supply_info()?.total_issued,
+            TOTAL_BANK_TOKENS_KEY => self.supply_info()?.total_staking,
+            TOTAL_STORAGE_TOKENS_KEY => self.supply_info()?.total_collateral,
+            TOTAL_EVM_TOKENS_KEY => self.supply_info()?.total_espace_tokens,
+            INTEREST_RATE_KEY => self
+                .request("cfx_getInterestRate", json!([self.epoch_param()]))?,
+            ACCUMULATE_INTEREST_RATE_KEY => self.request(
+                "cfx_getAccumulateInterestRate",
+                json!([self.epoch_param()]),
+            )?,
+            _ => return Ok(None),
+        };
+        Ok(if value.is_zero() {
+            None
+        } else {
+            Some(rlp::encode(&value))
+        })
+    }
+
+    fn supply_info(&self) -> Result<RemoteSupplyInfo, String> {
+        self.request("cfx_getSupplyInfo", json!([self.epoch_param()]))
+    }
+
+    fn epoch_param(&self) -> String { format!("{:#x}", self.epoch) }
+
+    fn base32(&self, address: &Address) -> Result<String, String> {
+        cfx_addr_encode(&address.0, self.network, EncodingOptions::Simple)
+            .map_err(|e| format!("Failed to encode {:?}: {}", address, e))
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self, method: &str, params: Value,
+    ) -> Result<T, String> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        call(&self.url, id, method, params)
+    }
+}
+
+fn call<T: DeserializeOwned>(
+    url: &str, id: u64, method: &str, params: Value,
+) -> Result<T, String> {
+    let response = ureq::post(url).timeout(REQUEST_TIMEOUT).send_json(json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+    }));
+    if !response.ok() {
+        return Err(format!(
+            "{} to {} failed: {} {}",
+            method,
+            url,
+            response.status(),
+            response.into_string().unwrap_or_default()
+        ));
+    }
+    let mut body: Value = response
+        .into_json()
+        .map_err(|e| format!("{} returned invalid JSON: {}", method, e))?;
+    if let Some(error) = body.get("error") {
+        return Err(format!("{} failed: {}", method, error));
+    }
+    serde_json::from_value(body["result"].take())
+        .map_err(|e| format!("{} returned invalid result: {}", method, e))
+}
+
+fn to_address(bytes: &[u8]) -> Result<Address, String> {
+    if bytes.len() != Address::len_bytes() {
+        return Err(format!("Invalid address {:?}", bytes));
+    }
+    Ok(Address::from_slice(bytes))
+}
+
+fn parse_base32(address: &str) -> Result<Address, String> {
+    cfx_addr_decode(address)
+        .map_err(|e| format!("Invalid address {}: {}", address, e))?
+        .hex_address
+        .ok_or_else(|| format!("Invalid address {}", address))
+}
+
+fn parse_hex(bytes: &str) -> Result<Vec<u8>, String> {
+    bytes
+        .trim_start_matches("0x")
+        .from_hex()
+        .map_err(|e| format!("Invalid bytes {}: {}", bytes, e))
+}
@@ -36,6 +36,7 @@ pub mod db;
 pub mod error;
 mod evm;
 pub mod executive;
+pub mod forked_state;
 pub mod light_protocol;
 pub mod machine;
 pub mod node_type;
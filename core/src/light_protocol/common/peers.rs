@@ -10,10 +10,12 @@ use std::{
     sync::Arc,
 };
 
-use crate::message::MsgId;
+use crate::{light_protocol::LIGHT_PROTOCOL_ID, message::MsgId};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
-use network::{node_table::NodeId, service::ProtocolVersion};
+use network::{
+    node_record::NodeCapabilities, node_table::NodeId, service::ProtocolVersion,
+};
 use rand::prelude::SliceRandom;
 use throttling::token_bucket::{ThrottledManager, TokenBucketManager};
 
@@ -25,6 +27,8 @@ pub struct FullPeerState {
     pub terminals: HashSet<H256>,
     pub throttled_msgs: ThrottledManager<MsgId>,
     pub unexpected_msgs: TokenBucketManager,
+    /// Capabilities advertised in the discovery record of the peer, if any.
+    pub advertised: Option<NodeCapabilities>,
 }
 
 #[derive(Default, DeriveMallocSizeOf)]
@@ -118,6 +122,14 @@ impl FullPeerFilter {
                 return false;
             }
 
+            // peers that advertise they cannot serve light nodes, e.g. other
+            // light nodes, are not selected
+            if let Some(advertised) = &peer.advertised {
+                if !advertised.serves_light_nodes(&LIGHT_PROTOCOL_ID) {
+                    return false;
+                }
+            }
+
             let min_best_epoch = self.min_best_epoch.unwrap_or_default();
             peer.best_epoch >= min_best_epoch
         })
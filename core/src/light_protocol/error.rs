@@ -281,7 +281,9 @@ pub fn handle(
             | network::ErrorKind::OversizedPacket
             | network::ErrorKind::Throttling(_) => disconnect = false,
 
-            network::ErrorKind::BadProtocol | network::ErrorKind::Decoder(_) => {
+            network::ErrorKind::BadProtocol
+            | network::ErrorKind::Compression(_)
+            | network::ErrorKind::Decoder(_) => {
                 op = Some(UpdateNodeOperation::Remove)
             }
 
@@ -1007,8 +1007,12 @@ impl NetworkProtocolHandler for Handler {
             Ok(_) => {
                 // insert handshaking peer
                 self.peers.insert(*node_id);
-                self.peers.get(node_id).unwrap().write().protocol_version =
-                    peer_protocol_version;
+                {
+                    let peer = self.peers.get(node_id).unwrap();
+                    let mut peer = peer.write();
+                    peer.protocol_version = peer_protocol_version;
+                    peer.advertised = io.get_peer_capabilities(node_id);
+                }
 
                 if let Some(ref file) = self.throttling_config_file {
                     let peer = self.peers.get(node_id).expect("peer not found");
@@ -891,6 +891,7 @@ pub fn recover_phantom_traces(
                 && trace.valid
                 && (input[0..4] == cross_space::TransferToEVM::FUNC_SIG
                     || input[0..4] == cross_space::CreateToEVM::FUNC_SIG
+                    || input[0..4] == cross_space::Create2ToEVM::FUNC_SIG
                     || input[0..4] == cross_space::CallToEVM::FUNC_SIG) =>
             {
                 let phantom_traces = recover_phantom_trace_for_call(
@@ -336,6 +336,9 @@ impl HotStuffSynchronizationProtocol {
                     op = Some(UpdateNodeOperation::Remove)
                 }
                 network::ErrorKind::BadAddr => disconnect = false,
+                network::ErrorKind::Compression(_) => {
+                    op = Some(UpdateNodeOperation::Remove)
+                }
                 network::ErrorKind::Decoder(_) => {
                     op = Some(UpdateNodeOperation::Remove)
                 }
@@ -73,6 +73,12 @@ pub struct TransitionsBlockNumber {
     pub cip90b: BlockNumber,
     /// CIP92: Enable Blake2F builtin function
     pub cip92: BlockNumber,
+    /// Enable `create2EVM` in the cross-space internal contract
+    pub cip_create2_evm: BlockNumber,
+    /// Enable read-only access to the Conflux space from the EVM space
+    pub cip_core_space_reader: BlockNumber,
+    /// Record the epoch history queried by the context internal contracts
+    pub cip_epoch_history: BlockNumber,
 }
 
 #[derive(Default, Debug, Clone)]
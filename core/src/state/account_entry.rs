@@ -642,6 +642,21 @@ impl OverlayAccount {
         }
     }
 
+    /// Sets a storage entry owned by the account itself outside transactions.
+    /// The ownership is committed directly, so no storage collateral is
+    /// settled for it.
+    pub fn set_system_storage(&mut self, key: Vec<u8>, value: U256) {
+        self.address.assert_native();
+        let owner = if value.is_zero() {
+            None
+        } else {
+            Some(self.address.address)
+        };
+        Arc::make_mut(self.storage_owner_lv2_write_cache.get_mut())
+            .insert(key.clone(), owner);
+        Arc::make_mut(&mut self.storage_value_write_cache).insert(key, value);
+    }
+
     #[cfg(test)]
     pub fn storage_layout_change(&self) -> Option<&StorageLayout> {
         self.storage_layout_change.as_ref()
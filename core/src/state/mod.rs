@@ -55,9 +55,13 @@ use crate::{
     transaction_pool::SharedTransactionPool,
 };
 
-use self::account_entry::{AccountEntry, AccountState};
+use self::{
+    account_entry::{AccountEntry, AccountState},
+    speculative::Speculation,
+};
 pub use self::{
     account_entry::{OverlayAccount, COMMISSION_PRIVILEGE_SPECIAL_KEY},
+    speculative::{SpeculativeState, SpeculativeStorage, TransactionRunner},
     substate::{cleanup_mode, CallStackInfo, Substate},
 };
 
@@ -65,6 +69,9 @@ mod account_entry;
 #[cfg(test)]
 mod account_entry_tests;
 pub mod prefetcher;
+mod speculative;
+#[cfg(test)]
+mod speculative_tests;
 #[cfg(test)]
 mod state_tests;
 mod substate;
@@ -118,6 +125,9 @@ pub struct StateGeneric<StateDbStorage: StorageStateTrait> {
     // Checkpoint to the changes.
     world_statistics_checkpoints: RwLock<Vec<WorldStatistics>>,
     checkpoints: RwLock<Vec<HashMap<AddressWithSpace, Option<AccountEntry>>>>,
+
+    // Only set for a state in speculative execution.
+    speculation: Option<Speculation>,
 }
 
 impl<StateDbStorage: StorageStateTrait> StateTrait
@@ -1090,6 +1100,14 @@ impl<StateDbStorage: StorageStateTrait> StateOpsTrait
         Ok(())
     }
 
+    fn set_system_storage(
+        &mut self, address: &Address, key: Vec<u8>, value: U256,
+    ) -> DbResult<()> {
+        self.require_exists(&address.with_native_space(), false)?
+            .set_system_storage(key, value);
+        Ok(())
+    }
+
     fn pos_locked_staking(&self, address: &Address) -> DbResult<U256> {
         let identifier = BigEndianHash::from_uint(&self.storage_at(
             &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
@@ -1282,6 +1300,7 @@ impl<StateDbStorage: StorageStateTrait> StateGeneric<StateDbStorage> {
             checkpoints: Default::default(),
             world_statistics: world_stat,
             accounts_to_notify: Default::default(),
+            speculation: None,
         })
     }
 
@@ -1801,17 +1820,35 @@ impl<StateDbStorage: StorageStateTrait> StateGeneric<StateDbStorage> {
 
     fn insert_cache_if_fresh_account(
         cache: &mut HashMap<AddressWithSpace, AccountEntry>,
-        address: &AddressWithSpace, maybe_account: Option<OverlayAccount>,
+        address: &AddressWithSpace, entry: AccountEntry,
     ) -> bool
     {
         if !cache.contains_key(address) {
-            cache.insert(*address, AccountEntry::new_clean(maybe_account));
+            cache.insert(*address, entry);
             true
         } else {
             false
         }
     }
 
+    /// Load the entry of an account which is not in the cache.
+    fn load_account_entry(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<AccountEntry> {
+        if let Some(entry) = self
+            .speculation
+            .as_ref()
+            .and_then(|speculation| speculation.load(address))
+        {
+            return Ok(entry);
+        }
+        Ok(AccountEntry::new_clean(
+            self.db
+                .get_account(address)?
+                .map(|acc| OverlayAccount::from_loaded(address, acc)),
+        ))
+    }
+
     pub fn ensure_account_loaded<F, U>(
         &self, address: &AddressWithSpace, require: RequireCache, f: F,
     ) -> DbResult<U>
@@ -1836,11 +1873,8 @@ impl<StateDbStorage: StorageStateTrait> StateGeneric<StateDbStorage> {
                 RwLockUpgradableReadGuard::upgrade(upgradable_lock)
             } else {
                 // Load the account from db.
-                let mut maybe_loaded_acc = self
-                    .db
-                    .get_account(address)?
-                    .map(|acc| OverlayAccount::from_loaded(address, acc));
-                if let Some(account) = &mut maybe_loaded_acc {
+                let mut loaded_entry = self.load_account_entry(address)?;
+                if let Some(account) = &mut loaded_entry.account {
                     Self::update_account_cache(require, account, &self.db)?;
                 }
                 let mut cache_write_lock =
@@ -1848,7 +1882,7 @@ impl<StateDbStorage: StorageStateTrait> StateGeneric<StateDbStorage> {
                 Self::insert_cache_if_fresh_account(
                     &mut *cache_write_lock,
                     address,
-                    maybe_loaded_acc,
+                    loaded_entry,
                 );
 
                 cache_write_lock
@@ -1908,12 +1942,9 @@ impl<StateDbStorage: StorageStateTrait> StateGeneric<StateDbStorage> {
     where F: FnOnce(&AddressWithSpace) -> DbResult<OverlayAccount> {
         let mut cache;
         if !self.cache.read().contains_key(address) {
-            let account = self
-                .db
-                .get_account(address)?
-                .map(|acc| OverlayAccount::from_loaded(address, acc));
+            let entry = self.load_account_entry(address)?;
             cache = self.cache.write();
-            Self::insert_cache_if_fresh_account(&mut *cache, address, account);
+            Self::insert_cache_if_fresh_account(&mut *cache, address, entry);
         } else {
             cache = self.cache.write();
         };
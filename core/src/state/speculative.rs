@@ -0,0 +1,290 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Optimistic parallel execution of the transactions in a block.
+//!
+//! Every transaction is first executed speculatively on its own
+//! `SpeculativeState`, which reads the accounts from the state before the
+//! block and keeps all the changes in its cache. Then the results are
+//! committed in the order of the transactions. A result is only committed if
+//! none of the accounts accessed by the transaction has been written by a
+//! transaction before it in the block, otherwise the transaction is executed
+//! again on the state. So the resulting state is exactly the same as
+//! executing the transactions one by one.
+
+use super::{account_entry::AccountEntry, State, StateGeneric};
+use cfx_internal_common::StateRootWithAuxInfo;
+use cfx_state::state_trait::CheckpointTrait;
+use cfx_statedb::{Result as DbResult, StateDbGeneric as StateDb};
+use cfx_storage::{
+    utils::access_mode, MptKeyValue, Result as StorageResult, StorageState,
+    StorageStateTrait,
+};
+use cfx_types::{AddressWithSpace, U256};
+use parking_lot::Mutex;
+use primitives::{EpochId, StorageKeyWithSpace};
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
+
+pub type SpeculativeState<'a> = StateGeneric<SpeculativeStorage<'a>>;
+
+/// Executes a transaction of a block on a state. It is implemented for both
+/// `State` and `SpeculativeState`, so the same transaction can be speculated
+/// and re-executed.
+pub trait TransactionRunner: Sync {
+    type Output: Send;
+
+    fn run<S: StorageStateTrait>(
+        &self, state: &mut StateGeneric<S>, index: usize,
+    ) -> DbResult<Self::Output>;
+}
+
+/// The storage of a `SpeculativeState`. It reads the state db of the state
+/// before the block, which is not modified while the transactions are
+/// speculated. The transactions which write the storage directly, e.g. by
+/// killing a contract, are not supported and are executed again on the
+/// state.
+pub struct SpeculativeStorage<'a> {
+    db: &'a StateDb<StorageState>,
+}
+
+impl<'a> SpeculativeStorage<'a> {
+    fn unsupported<T>() -> StorageResult<T> {
+        Err("Unsupported in speculative execution".into())
+    }
+}
+
+impl<'a> StorageStateTrait for SpeculativeStorage<'a> {
+    fn get(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        match self.db.get_raw(access_key) {
+            Ok(value) => Ok(value.map(|v| v.to_vec().into_boxed_slice())),
+            Err(e) => Err(format!("{}", e).into()),
+        }
+    }
+
+    fn set(
+        &mut self, _access_key: StorageKeyWithSpace, _value: Box<[u8]>,
+    ) -> StorageResult<()> {
+        Self::unsupported()
+    }
+
+    fn delete(
+        &mut self, _access_key: StorageKeyWithSpace,
+    ) -> StorageResult<()> {
+        Self::unsupported()
+    }
+
+    fn delete_test_only(
+        &mut self, _access_key: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        Self::unsupported()
+    }
+
+    fn delete_all<AM: access_mode::AccessMode>(
+        &mut self, _access_key_prefix: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Vec<MptKeyValue>>> {
+        Self::unsupported()
+    }
+
+    fn compute_state_root(&mut self) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported()
+    }
+
+    fn get_state_root(&self) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported()
+    }
+
+    fn commit(
+        &mut self, _epoch: EpochId,
+    ) -> StorageResult<StateRootWithAuxInfo> {
+        Self::unsupported()
+    }
+}
+
+/// The accounts of the state before the block, shared by all the speculative
+/// states.
+pub(super) struct Speculation {
+    base_cache: Arc<HashMap<AddressWithSpace, AccountEntry>>,
+    /// All the accounts loaded into the cache of the speculative state.
+    loaded: Mutex<HashSet<AddressWithSpace>>,
+}
+
+impl Speculation {
+    /// Record that `address` is accessed, and return its entry if it is
+    /// cached in the state before the block.
+    pub(super) fn load(
+        &self, address: &AddressWithSpace,
+    ) -> Option<AccountEntry> {
+        self.loaded.lock().insert(*address);
+        self.base_cache.get(address).map(AccountEntry::clone_dirty)
+    }
+}
+
+struct SpeculativeResult<T> {
+    output: T,
+    /// The accounts accessed by the transaction.
+    accessed: HashSet<AddressWithSpace>,
+    written: Vec<(AddressWithSpace, AccountEntry)>,
+    /// The changes of the token counters in the world statistics, as
+    /// `(increased, decreased)` pairs.
+    token_changes: [(U256, U256); 6],
+}
+
+impl State {
+    /// Execute the transactions `0..count` in parallel and return their
+    /// outputs. The resulting state and outputs are the same as calling
+    /// `runner` on this state for each transaction in order.
+    pub fn execute_in_parallel<R: TransactionRunner>(
+        &mut self, count: usize, runner: &R,
+    ) -> DbResult<Vec<R::Output>> {
+        assert!(self.checkpoints.get_mut().is_empty());
+        let base_cache = Arc::new(mem::take(self.cache.get_mut()));
+        let speculative_results: Vec<_> = {
+            let state = &*self;
+            let base_cache = &base_cache;
+            (0..count)
+                .into_par_iter()
+                .map(|index| state.speculate(base_cache, runner, index))
+                .collect()
+        };
+        *self.cache.get_mut() = Arc::try_unwrap(base_cache)
+            .ok()
+            .expect("speculative states are dropped");
+
+        let mut written_in_block = HashSet::new();
+        let mut outputs = Vec::with_capacity(count);
+        for (index, result) in speculative_results.into_iter().enumerate() {
+            match result {
+                Some(result)
+                    if result.accessed.is_disjoint(&written_in_block) =>
+                {
+                    let cache = self.cache.get_mut();
+                    for (address, entry) in result.written {
+                        written_in_block.insert(address);
+                        cache.insert(address, entry);
+                    }
+                    self.apply_token_changes(&result.token_changes);
+                    outputs.push(result.output);
+                }
+                _ => {
+                    self.checkpoint();
+                    let output = runner.run(self, index);
+                    written_in_block.extend(
+                        self.checkpoints.get_mut().last().unwrap().keys(),
+                    );
+                    self.discard_checkpoint();
+                    outputs.push(output?);
+                }
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Execute the transaction at `index` on a speculative state. Returns
+    /// `None` if it can not be executed speculatively.
+    fn speculate<R: TransactionRunner>(
+        &self, base_cache: &Arc<HashMap<AddressWithSpace, AccountEntry>>,
+        runner: &R, index: usize,
+    ) -> Option<SpeculativeResult<R::Output>>
+    {
+        let mut state = SpeculativeState {
+            db: StateDb::new(SpeculativeStorage { db: &self.db }),
+            accounts_to_notify: Default::default(),
+            cache: Default::default(),
+            speculation: Some(Speculation {
+                base_cache: base_cache.clone(),
+                loaded: Default::default(),
+            }),
+            world_statistics: self.world_statistics,
+            world_statistics_checkpoints: Default::default(),
+            checkpoints: Default::default(),
+        };
+        let before = self.world_statistics;
+
+        state.checkpoint();
+        let output = runner.run(&mut state, index).ok()?;
+        let written_addresses: Vec<_> = state
+            .checkpoints
+            .get_mut()
+            .last()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        state.discard_checkpoint();
+
+        let after = state.world_statistics;
+        // Only the token counters are expected to be changed by transactions.
+        if after.interest_rate_per_block != before.interest_rate_per_block
+            || after.accumulate_interest_rate != before.accumulate_interest_rate
+            || after.last_distribute_block != before.last_distribute_block
+        {
+            return None;
+        }
+        let change = |after: U256, before: U256| {
+            if after >= before {
+                (after - before, U256::zero())
+            } else {
+                (U256::zero(), before - after)
+            }
+        };
+        let token_changes = [
+            change(after.total_issued_tokens, before.total_issued_tokens),
+            change(after.total_staking_tokens, before.total_staking_tokens),
+            change(after.total_storage_tokens, before.total_storage_tokens),
+            change(
+                after.total_pos_staking_tokens,
+                before.total_pos_staking_tokens,
+            ),
+            change(
+                after.distributable_pos_interest,
+                before.distributable_pos_interest,
+            ),
+            change(after.total_evm_tokens, before.total_evm_tokens),
+        ];
+
+        let mut accessed =
+            state.speculation.take().unwrap().loaded.into_inner();
+        let mut cache = state.cache.into_inner();
+        let mut written = Vec::with_capacity(written_addresses.len());
+        for address in written_addresses {
+            // The entry may be removed when a change is reverted.
+            if let Some(entry) = cache.remove(&address) {
+                written.push((address, entry));
+            }
+            // An account can be written without being loaded.
+            accessed.insert(address);
+        }
+
+        Some(SpeculativeResult {
+            output,
+            accessed,
+            written,
+            token_changes,
+        })
+    }
+
+    fn apply_token_changes(&mut self, changes: &[(U256, U256); 6]) {
+        let stat = &mut self.world_statistics;
+        let mut counters = [
+            &mut stat.total_issued_tokens,
+            &mut stat.total_staking_tokens,
+            &mut stat.total_storage_tokens,
+            &mut stat.total_pos_staking_tokens,
+            &mut stat.distributable_pos_interest,
+            &mut stat.total_evm_tokens,
+        ];
+        for (counter, &(increased, decreased)) in
+            counters.iter_mut().zip(changes.iter())
+        {
+            **counter = **counter + increased - decreased;
+        }
+    }
+}
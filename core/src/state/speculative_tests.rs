@@ -0,0 +1,178 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{State, StateGeneric, TransactionRunner};
+use crate::{
+    executive::{
+        contract_address, ExecutionOutcome, ExecutiveGeneric, TransactOptions,
+    },
+    machine::{new_machine_with_builtin, Machine},
+    test_helpers::get_state_for_genesis_write,
+    vm::{CreateContractAddress, Env, Spec},
+    vm_factory::VmFactory,
+};
+use cfx_parameters::consensus::ONE_CFX_IN_DRIP;
+use cfx_state::{state_trait::StateOpsTrait, CleanupMode, StateTrait};
+use cfx_statedb::Result as DbResult;
+use cfx_storage::{tests::new_state_manager_for_unit_test, StorageStateTrait};
+use cfx_types::{Address, AddressSpaceUtil, AddressWithSpace, U256};
+use keylib::{Generator, KeyPair, Random};
+use primitives::{
+    storage::STORAGE_LAYOUT_REGULAR_V0, transaction::Action, NativeTransaction,
+    SignedTransaction, Transaction,
+};
+use rustc_hex::FromHex;
+
+struct TestRunner<'a> {
+    transactions: &'a [SignedTransaction],
+    env: &'a Env,
+    machine: &'a Machine,
+    spec: &'a Spec,
+}
+
+impl<'a> TransactionRunner for TestRunner<'a> {
+    type Output = ExecutionOutcome;
+
+    fn run<S: StorageStateTrait>(
+        &self, state: &mut StateGeneric<S>, index: usize,
+    ) -> DbResult<ExecutionOutcome> {
+        ExecutiveGeneric::new(state, self.env, self.machine, self.spec)
+            .transact(
+                &self.transactions[index],
+                TransactOptions::with_no_tracing(),
+            )
+    }
+}
+
+fn call(
+    sender: &KeyPair, nonce: u64, to: Address, value: u64,
+) -> SignedTransaction {
+    Transaction::from(NativeTransaction {
+        nonce: nonce.into(),
+        gas_price: U256::one(),
+        gas: U256::from(100_000),
+        value: value.into(),
+        action: Action::Call(to),
+        storage_limit: 1000,
+        epoch_height: 0,
+        chain_id: 1,
+        data: vec![],
+    })
+    .sign(sender.secret())
+}
+
+/// Prepare a state with funded `senders` and a contract which increases
+/// its storage slot 0 on every call.
+fn prepare_state(
+    state: &mut State, spec: &Spec, senders: &[KeyPair],
+    counter: &AddressWithSpace,
+)
+{
+    for sender in senders {
+        state
+            .add_balance(
+                &sender.address().with_native_space(),
+                &(U256::from(ONE_CFX_IN_DRIP) * 100),
+                CleanupMode::NoEmpty,
+                spec.account_start_nonce,
+            )
+            .unwrap();
+    }
+    let admin = senders[0].address();
+    state
+        .new_contract_with_admin(
+            counter,
+            &admin,
+            U256::zero(),
+            spec.contract_start_nonce,
+            Some(STORAGE_LAYOUT_REGULAR_V0),
+        )
+        .unwrap();
+    // PUSH1 0 SLOAD PUSH1 1 ADD PUSH1 0 SSTORE STOP
+    let code = "60005460010160005500".from_hex().unwrap();
+    state.init_code(counter, code, admin).unwrap();
+}
+
+#[test]
+fn test_parallel_execution_matches_sequential() {
+    let mut env = Env::default();
+    env.gas_limit = U256::MAX;
+    let machine =
+        new_machine_with_builtin(Default::default(), VmFactory::new(1024 * 32));
+    let spec = machine.spec(env.number);
+
+    let senders: Vec<KeyPair> =
+        (0..8).map(|_| Random.generate().unwrap()).collect();
+    let unfunded = Random.generate().unwrap();
+    let counter = contract_address(
+        CreateContractAddress::FromSenderNonceAndCodeHash,
+        /* block_number = */ 0.into(),
+        &senders[0].address().with_native_space(),
+        &U256::zero(),
+        &[],
+    )
+    .0;
+    let shared_recipient = Random.generate().unwrap().address();
+
+    let mut transactions = vec![];
+    // Independent transfers.
+    for sender in &senders {
+        let recipient = Random.generate().unwrap().address();
+        transactions.push(call(sender, 0, recipient, 1000));
+    }
+    // Transactions depending on the transfers above.
+    transactions.push(call(&senders[0], 1, shared_recipient, 10));
+    for sender in &senders[1..4] {
+        transactions.push(call(sender, 1, shared_recipient, 10));
+    }
+    // Calls to the same contract.
+    for sender in &senders[4..8] {
+        transactions.push(call(sender, 1, counter.address, 0));
+    }
+    // Transactions which are not executed.
+    transactions.push(call(&senders[5], 5, shared_recipient, 10));
+    transactions.push(call(&unfunded, 0, shared_recipient, 10));
+
+    let runner = TestRunner {
+        transactions: &transactions,
+        env: &env,
+        machine: &machine,
+        spec: &spec,
+    };
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut sequential_state = get_state_for_genesis_write(&storage_manager);
+    prepare_state(&mut sequential_state, &spec, &senders, &counter);
+    let sequential_outcomes: Vec<_> = (0..transactions.len())
+        .map(|index| runner.run(&mut sequential_state, index).unwrap())
+        .collect();
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut parallel_state = get_state_for_genesis_write(&storage_manager);
+    prepare_state(&mut parallel_state, &spec, &senders, &counter);
+    let parallel_outcomes = parallel_state
+        .execute_in_parallel(transactions.len(), &runner)
+        .unwrap();
+
+    assert_eq!(
+        format!("{:?}", sequential_outcomes),
+        format!("{:?}", parallel_outcomes)
+    );
+    assert_eq!(
+        parallel_state.storage_at(&counter, &[0u8; 32]).unwrap(),
+        U256::from(4)
+    );
+    assert_eq!(
+        sequential_state.total_issued_tokens(),
+        parallel_state.total_issued_tokens()
+    );
+    assert_eq!(
+        sequential_state.total_storage_tokens(),
+        parallel_state.total_storage_tokens()
+    );
+    assert_eq!(
+        sequential_state.compute_state_root(None).unwrap(),
+        parallel_state.compute_state_root(None).unwrap()
+    );
+}
@@ -0,0 +1,422 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_parameters::block::MAX_BLOCK_SIZE_IN_BYTES;
+use cfx_types::H256;
+use metrics::{Gauge, GaugeUsize};
+use network::node_table::NodeId;
+use parking_lot::Mutex;
+use std::{
+    cmp::min,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+lazy_static! {
+    static ref PENDING_BODIES_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group(
+            "sync_download",
+            "catch_up_pending_bodies"
+        );
+    static ref INFLIGHT_BODIES_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group(
+            "sync_download",
+            "catch_up_inflight_bodies"
+        );
+    static ref BUFFERED_BODY_SIZE_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group(
+            "sync_download",
+            "catch_up_buffered_body_size"
+        );
+}
+
+/// The max number of batches requested from a peer and not received yet.
+/// More batches are assigned to other peers, so that the bodies are
+/// downloaded from multiple peers in parallel.
+const MAX_INFLIGHT_BATCHES_PER_PEER: usize = 4;
+
+/// Weight of the latest body in the moving average of body sizes, which is
+/// used to estimate the size of the bodies in flight.
+const BODY_SIZE_ALPHA: f64 = 0.05;
+
+struct InFlightBody {
+    height: u64,
+    /// The peer which the body is counted against. It is `None` after the
+    /// request timed out and the request manager resends it.
+    peer: Option<NodeId>,
+    requested_at: Instant,
+}
+
+struct DownloaderInner {
+    /// The blocks whose headers are verified but bodies are not requested
+    /// yet, ordered by height so that the bodies closer to the consensus
+    /// frontier are requested first.
+    pending: BTreeSet<(u64, H256)>,
+    pending_heights: HashMap<H256, u64>,
+
+    in_flight: HashMap<H256, InFlightBody>,
+    /// The number of in-flight bodies by height.
+    in_flight_heights: BTreeMap<u64, usize>,
+    /// The number of in-flight bodies counted against each peer.
+    peer_load: HashMap<NodeId, usize>,
+
+    /// The sizes of the bodies received out of order by height, i.e. the
+    /// bodies higher than a body still pending or in flight. They are kept
+    /// in the sync graph until their ancestors are received, so they are
+    /// counted in the memory bound until then.
+    buffered: BTreeMap<u64, usize>,
+    buffered_size: usize,
+
+    average_body_size: f64,
+}
+
+impl DownloaderInner {
+    fn new() -> Self {
+        DownloaderInner {
+            pending: Default::default(),
+            pending_heights: Default::default(),
+            in_flight: Default::default(),
+            in_flight_heights: Default::default(),
+            peer_load: Default::default(),
+            buffered: Default::default(),
+            buffered_size: 0,
+            // Start from the max block size to avoid requesting too many
+            // bodies before the size of bodies is known.
+            average_body_size: MAX_BLOCK_SIZE_IN_BYTES as f64,
+        }
+    }
+
+    /// The lowest height of the bodies not received yet.
+    fn frontier(&self) -> Option<u64> {
+        let pending = self.pending.iter().next().map(|(height, _)| *height);
+        let in_flight = self.in_flight_heights.keys().next().cloned();
+        match (pending, in_flight) {
+            (Some(a), Some(b)) => Some(min(a, b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Stop counting the buffered bodies lower than the frontier, because
+    /// their ancestors are all received.
+    fn release_buffered(&mut self) {
+        let released = match self.frontier() {
+            Some(frontier) => {
+                let ahead = self.buffered.split_off(&frontier);
+                std::mem::replace(&mut self.buffered, ahead)
+            }
+            None => std::mem::take(&mut self.buffered),
+        };
+        for size in released.values() {
+            self.buffered_size -= size;
+        }
+    }
+
+    fn estimated_size(&self, queued_size: usize) -> usize {
+        queued_size
+            + self.buffered_size
+            + (self.in_flight.len() as f64 * self.average_body_size) as usize
+    }
+
+    fn remove_pending(&mut self, hash: &H256) -> bool {
+        match self.pending_heights.remove(hash) {
+            Some(height) => self.pending.remove(&(height, *hash)),
+            None => false,
+        }
+    }
+
+    fn release_peer(&mut self, body: &mut InFlightBody) {
+        if let Some(peer) = body.peer.take() {
+            if let Some(load) = self.peer_load.get_mut(&peer) {
+                *load -= 1;
+                if *load == 0 {
+                    self.peer_load.remove(&peer);
+                }
+            }
+        }
+    }
+
+    fn remove_in_flight(&mut self, hash: &H256) -> Option<InFlightBody> {
+        let mut body = self.in_flight.remove(hash)?;
+        if let Some(count) = self.in_flight_heights.get_mut(&body.height) {
+            *count -= 1;
+            if *count == 0 {
+                self.in_flight_heights.remove(&body.height);
+            }
+        }
+        self.release_peer(&mut body);
+        Some(body)
+    }
+
+    fn update_gauges(&self) {
+        PENDING_BODIES_GAUGE.update(self.pending.len());
+        INFLIGHT_BODIES_GAUGE.update(self.in_flight.len());
+        BUFFERED_BODY_SIZE_GAUGE.update(self.buffered_size);
+    }
+}
+
+/// Downloads the bodies of the blocks whose headers are verified in
+/// `CatchUpSyncBlock` phase.
+///
+/// The bodies are requested in batches from multiple peers in parallel,
+/// instead of from the peer which responds the headers. The size of the
+/// bodies in flight, waiting in the recover public queue, and received out of
+/// order is bounded by `max_unprocessed_block_size`.
+pub struct CatchUpBodyDownloader {
+    inner: Mutex<DownloaderInner>,
+    max_unprocessed_size: usize,
+}
+
+impl CatchUpBodyDownloader {
+    pub fn new(max_unprocessed_size: usize) -> Self {
+        CatchUpBodyDownloader {
+            inner: Mutex::new(DownloaderInner::new()),
+            max_unprocessed_size,
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        *inner = DownloaderInner::new();
+        inner.update_gauges();
+    }
+
+    /// Queue the bodies of the blocks `(height, hash)` to download.
+    pub fn enqueue<I: IntoIterator<Item = (u64, H256)>>(&self, blocks: I) {
+        let mut inner = self.inner.lock();
+        for (height, hash) in blocks {
+            if inner.in_flight.contains_key(&hash)
+                || inner.pending_heights.contains_key(&hash)
+            {
+                continue;
+            }
+            inner.pending.insert((height, hash));
+            inner.pending_heights.insert(hash, height);
+        }
+        inner.update_gauges();
+    }
+
+    /// Take the queued bodies which are not requested yet.
+    pub fn take_pending(&self) -> Vec<H256> {
+        let mut inner = self.inner.lock();
+        let pending = std::mem::take(&mut inner.pending);
+        inner.pending_heights.clear();
+        inner.release_buffered();
+        inner.update_gauges();
+        pending.into_iter().map(|(_, hash)| hash).collect()
+    }
+
+    /// Assign the lowest pending bodies in batches of `batch_size` to the
+    /// least loaded of `peers`, until every peer has
+    /// `MAX_INFLIGHT_BATCHES_PER_PEER` batches in flight or the estimated
+    /// size reaches the bound. `queued_size` is the size of the received
+    /// blocks not processed yet.
+    pub fn next_requests(
+        &self, peers: &[NodeId], batch_size: usize, queued_size: usize,
+    ) -> Vec<(NodeId, Vec<H256>)> {
+        let mut inner = self.inner.lock();
+        let batch_size = batch_size.max(1);
+        let max_peer_load = batch_size * MAX_INFLIGHT_BATCHES_PER_PEER;
+        let now = Instant::now();
+        let mut requests = Vec::new();
+
+        while !inner.pending.is_empty() {
+            let used = inner.estimated_size(queued_size);
+            if used >= self.max_unprocessed_size {
+                break;
+            }
+            let available = ((self.max_unprocessed_size - used) as f64
+                / inner.average_body_size) as usize;
+            if available == 0 {
+                break;
+            }
+            let peer = match peers
+                .iter()
+                .map(|peer| {
+                    (inner.peer_load.get(peer).cloned().unwrap_or(0), peer)
+                })
+                .filter(|(load, _)| *load < max_peer_load)
+                .min_by_key(|(load, _)| *load)
+            {
+                Some((_, peer)) => *peer,
+                None => break,
+            };
+
+            let batch: Vec<(u64, H256)> = inner
+                .pending
+                .iter()
+                .take(min(batch_size, available))
+                .cloned()
+                .collect();
+            for (height, hash) in &batch {
+                inner.pending.remove(&(*height, *hash));
+                inner.pending_heights.remove(hash);
+                inner.in_flight.insert(
+                    *hash,
+                    InFlightBody {
+                        height: *height,
+                        peer: Some(peer),
+                        requested_at: now,
+                    },
+                );
+                *inner.in_flight_heights.entry(*height).or_insert(0) += 1;
+            }
+            *inner.peer_load.entry(peer).or_insert(0) += batch.len();
+            requests.push((
+                peer,
+                batch.into_iter().map(|(_, hash)| hash).collect(),
+            ));
+        }
+
+        inner.update_gauges();
+        requests
+    }
+
+    /// Handle the received bodies `(hash, size)`. The bodies received ahead
+    /// of the frontier are counted as buffered until the frontier passes
+    /// them.
+    pub fn on_received<I: IntoIterator<Item = (H256, usize)>>(
+        &self, blocks: I,
+    ) {
+        let mut inner = self.inner.lock();
+        for (hash, size) in blocks {
+            if let Some(body) = inner.remove_in_flight(&hash) {
+                let average = inner.average_body_size;
+                inner.average_body_size =
+                    average + BODY_SIZE_ALPHA * (size as f64 - average);
+                if inner.frontier().map_or(false, |f| body.height > f) {
+                    *inner.buffered.entry(body.height).or_insert(0) += size;
+                    inner.buffered_size += size;
+                }
+            } else {
+                // The block may be received through other requests.
+                inner.remove_pending(&hash);
+            }
+        }
+        inner.release_buffered();
+        inner.update_gauges();
+    }
+
+    /// Stop downloading the bodies which are removed from the sync graph.
+    pub fn remove<'a, I: IntoIterator<Item = &'a H256>>(&self, hashes: I) {
+        let mut inner = self.inner.lock();
+        for hash in hashes {
+            if !inner.remove_pending(hash) {
+                inner.remove_in_flight(hash);
+            }
+        }
+        inner.release_buffered();
+        inner.update_gauges();
+    }
+
+    /// Handle the bodies requested more than `timeout` ago. If the request
+    /// manager is still requesting a body (in `requested`), the body is no
+    /// longer counted against the peer. Otherwise, the body is queued to be
+    /// requested again.
+    pub fn expire(&self, timeout: Duration, requested: &HashSet<H256>) {
+        let mut inner = self.inner.lock();
+        let now = Instant::now();
+        let expired: Vec<H256> = inner
+            .in_flight
+            .iter()
+            .filter(|(_, body)| now - body.requested_at >= timeout)
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in expired {
+            if requested.contains(&hash) {
+                let mut body =
+                    inner.in_flight.remove(&hash).expect("body in flight");
+                inner.release_peer(&mut body);
+                body.requested_at = now;
+                inner.in_flight.insert(hash, body);
+            } else if let Some(body) = inner.remove_in_flight(&hash) {
+                inner.pending.insert((body.height, hash));
+                inner.pending_heights.insert(hash, body.height);
+            }
+        }
+        inner.update_gauges();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.pending.is_empty() && inner.in_flight.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(i: u64) -> H256 { H256::from_low_u64_be(i) }
+
+    #[test]
+    fn test_parallel_requests() {
+        let downloader =
+            CatchUpBodyDownloader::new(100 * MAX_BLOCK_SIZE_IN_BYTES);
+        downloader.enqueue((0..100).rev().map(|i| (i, hash(i))));
+        let peers = vec![NodeId::random(), NodeId::random()];
+
+        let requests = downloader.next_requests(&peers, 10, 0);
+        // every peer gets `MAX_INFLIGHT_BATCHES_PER_PEER` batches, and the
+        // lowest bodies are requested first
+        assert_eq!(requests.len(), 2 * MAX_INFLIGHT_BATCHES_PER_PEER);
+        assert_eq!(requests[0].1, (0..10).map(hash).collect::<Vec<_>>());
+        assert_ne!(requests[0].0, requests[1].0);
+        assert!(downloader.next_requests(&peers, 10, 0).is_empty());
+
+        // more bodies are requested after the bodies are received
+        downloader.on_received((0..10).map(|i| (hash(i), 1000)));
+        assert_eq!(downloader.next_requests(&peers, 10, 0).len(), 1);
+    }
+
+    #[test]
+    fn test_size_bound() {
+        let downloader =
+            CatchUpBodyDownloader::new(10 * MAX_BLOCK_SIZE_IN_BYTES);
+        downloader.enqueue((0..100).map(|i| (i, hash(i))));
+        let peers = vec![NodeId::random()];
+
+        let requests = downloader.next_requests(&peers, 4, 0);
+        let requested: usize = requests.iter().map(|(_, h)| h.len()).sum();
+        assert_eq!(requested, 10);
+
+        // the unprocessed blocks are counted as well
+        downloader.on_received((0..10).map(|i| (hash(i), 1000)));
+        assert!(downloader
+            .next_requests(&peers, 4, 10 * MAX_BLOCK_SIZE_IN_BYTES)
+            .is_empty());
+        assert!(!downloader.next_requests(&peers, 4, 0).is_empty());
+    }
+
+    #[test]
+    fn test_out_of_order_buffered() {
+        let downloader = CatchUpBodyDownloader::new(usize::MAX);
+        downloader.enqueue((0..4).map(|i| (i, hash(i))));
+        downloader.next_requests(&[NodeId::random()], 1, 0);
+
+        downloader.on_received(vec![(hash(2), 100), (hash(3), 100)]);
+        assert_eq!(downloader.inner.lock().buffered_size, 200);
+        downloader.on_received(vec![(hash(1), 100)]);
+        assert_eq!(downloader.inner.lock().buffered_size, 300);
+        // the buffered bodies are released once the lowest body is received
+        downloader.on_received(vec![(hash(0), 100)]);
+        assert_eq!(downloader.inner.lock().buffered_size, 0);
+        assert!(downloader.is_empty());
+    }
+
+    #[test]
+    fn test_expire() {
+        let downloader = CatchUpBodyDownloader::new(usize::MAX);
+        downloader.enqueue((0..2).map(|i| (i, hash(i))));
+        let peers = vec![NodeId::random()];
+        assert_eq!(downloader.next_requests(&peers, 1, 0).len(), 2);
+
+        // hash(0) is still requested by the request manager, while hash(1)
+        // is dropped and requested again
+        let requested = vec![hash(0)].into_iter().collect();
+        downloader.expire(Duration::from_secs(0), &requested);
+        let requests = downloader.next_requests(&peers, 1, 0);
+        assert_eq!(requests, vec![(peers[0], vec![hash(1)])]);
+    }
+}
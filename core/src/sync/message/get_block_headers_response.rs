@@ -6,8 +6,8 @@ use crate::{
     message::{Message, RequestId},
     sync::{
         message::{
-            metrics::BLOCK_HEADER_HANDLE_TIMER, Context, GetBlockHeaders,
-            Handleable,
+            metrics::{metric_headers_downloaded, BLOCK_HEADER_HANDLE_TIMER},
+            Context, GetBlockHeaders, Handleable,
         },
         synchronization_state::PeerFilter,
         Error, ErrorKind,
@@ -65,6 +65,11 @@ impl Handleable for GetBlockHeadersResponse {
             return Ok(());
         }
 
+        metric_headers_downloaded(
+            ctx.manager.phase_manager.get_current_phase().phase_type(),
+            self.headers.len(),
+        );
+
         let req = ctx.match_request(self.request_id)?;
         let delay = req.delay;
         let req = req.downcast_ref::<GetBlockHeaders>(
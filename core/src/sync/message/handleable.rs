@@ -14,15 +14,20 @@ pub struct Context<'a> {
     pub io: &'a dyn NetworkContext,
     pub node_id: NodeId,
     pub manager: &'a SynchronizationProtocolHandler,
+    // Size of the message in bytes, which is used to measure the throughput
+    // of the peer if the message is a response.
+    pub msg_size: usize,
 }
 
 impl<'a> Context<'a> {
     pub fn match_request(
         &self, request_id: u64,
     ) -> Result<RequestMessage, Error> {
-        self.manager
-            .request_manager
-            .match_request(&self.node_id, request_id)
+        self.manager.request_manager.match_response(
+            &self.node_id,
+            request_id,
+            self.msg_size,
+        )
     }
 
     pub fn send_response(&self, response: &dyn Message) -> Result<(), Error> {
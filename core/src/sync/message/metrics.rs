@@ -2,7 +2,9 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use metrics::{register_meter_with_group, Meter};
+use super::msgid;
+use crate::{message::MsgId, sync::SyncPhaseType};
+use metrics::{register_meter_with_group, Histogram, Meter, Sample};
 use std::sync::Arc;
 
 lazy_static! {
@@ -18,4 +20,132 @@ lazy_static! {
         register_meter_with_group("timer", "sync:recover_compact_block");
     pub static ref BLOCK_HEADER_HANDLE_TIMER: Arc<dyn Meter> =
         register_meter_with_group("timer", "sync::on_block_headers");
+    static ref BLOCKS_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("blocks");
+    static ref CMPCT_BLOCKS_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("compact_blocks");
+    static ref HEADERS_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("headers");
+    static ref TRANSACTIONS_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("transactions");
+    static ref SNAPSHOT_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("snapshot");
+    static ref OTHER_COMPRESSION: CompressionMetrics =
+        CompressionMetrics::new("other");
+    static ref CATCH_UP_SYNC_BLOCK_HEADER_DOWNLOAD: DownloadMetrics =
+        DownloadMetrics::new("catch_up_sync_block_header");
+    static ref CATCH_UP_FILL_BLOCK_BODY_DOWNLOAD: DownloadMetrics =
+        DownloadMetrics::new("catch_up_fill_block_body");
+    static ref CATCH_UP_SYNC_BLOCK_DOWNLOAD: DownloadMetrics =
+        DownloadMetrics::new("catch_up_sync_block");
+    static ref NORMAL_DOWNLOAD: DownloadMetrics =
+        DownloadMetrics::new("normal");
+    static ref OTHER_DOWNLOAD: DownloadMetrics = DownloadMetrics::new("other");
+}
+
+/// Bandwidth saved and compression ratio (compressed size in percentage of
+/// the original size) of the compressed messages of a type.
+struct CompressionMetrics {
+    saved_bytes: Arc<dyn Meter>,
+    ratio: Arc<dyn Histogram>,
+}
+
+impl CompressionMetrics {
+    fn new(name: &str) -> Self {
+        CompressionMetrics {
+            saved_bytes: register_meter_with_group(
+                "sync_compression",
+                &format!("{}_saved_bytes", name),
+            ),
+            ratio: Sample::ExpDecay(0.015).register_with_group(
+                "sync_compression",
+                &format!("{}_ratio", name),
+                1024,
+            ),
+        }
+    }
+}
+
+/// Updates the compression metrics of the message type of `msg_id`.
+pub fn metric_compression(msg_id: MsgId, size: usize, compressed_size: usize) {
+    let metrics: &CompressionMetrics = match msg_id {
+        msgid::GET_BLOCKS_RESPONSE
+        | msgid::GET_BLOCKS_WITH_PUBLIC_RESPONSE
+        | msgid::GET_BLOCK_TXN_RESPONSE
+        | msgid::NEW_BLOCK => &BLOCKS_COMPRESSION,
+        msgid::GET_CMPCT_BLOCKS_RESPONSE => &CMPCT_BLOCKS_COMPRESSION,
+        msgid::GET_BLOCK_HEADERS_RESPONSE => &HEADERS_COMPRESSION,
+        msgid::TRANSACTIONS
+        | msgid::GET_TRANSACTIONS_RESPONSE
+        | msgid::GET_TRANSACTIONS_FROM_TX_HASHES_RESPONSE => {
+            &TRANSACTIONS_COMPRESSION
+        }
+        msgid::GET_SNAPSHOT_MANIFEST_RESPONSE
+        | msgid::GET_SNAPSHOT_CHUNK_RESPONSE => &SNAPSHOT_COMPRESSION,
+        _ => &OTHER_COMPRESSION,
+    };
+
+    metrics
+        .saved_bytes
+        .mark(size.saturating_sub(compressed_size));
+    if size > 0 {
+        metrics.ratio.update((compressed_size * 100 / size) as u64);
+    }
+}
+
+/// Download throughput of the headers and blocks received in a sync phase.
+struct DownloadMetrics {
+    headers: Arc<dyn Meter>,
+    blocks: Arc<dyn Meter>,
+    block_bytes: Arc<dyn Meter>,
+}
+
+impl DownloadMetrics {
+    fn new(phase: &str) -> Self {
+        DownloadMetrics {
+            headers: register_meter_with_group(
+                "sync_download",
+                &format!("{}_headers", phase),
+            ),
+            blocks: register_meter_with_group(
+                "sync_download",
+                &format!("{}_blocks", phase),
+            ),
+            block_bytes: register_meter_with_group(
+                "sync_download",
+                &format!("{}_block_bytes", phase),
+            ),
+        }
+    }
+
+    fn of_phase(phase: SyncPhaseType) -> &'static DownloadMetrics {
+        match phase {
+            SyncPhaseType::CatchUpSyncBlockHeader => {
+                &CATCH_UP_SYNC_BLOCK_HEADER_DOWNLOAD
+            }
+            SyncPhaseType::CatchUpFillBlockBodyPhase => {
+                &CATCH_UP_FILL_BLOCK_BODY_DOWNLOAD
+            }
+            SyncPhaseType::CatchUpSyncBlock => &CATCH_UP_SYNC_BLOCK_DOWNLOAD,
+            SyncPhaseType::Normal => &NORMAL_DOWNLOAD,
+            SyncPhaseType::CatchUpRecoverBlockHeaderFromDB
+            | SyncPhaseType::CatchUpCheckpoint => &OTHER_DOWNLOAD,
+        }
+    }
+}
+
+/// Updates the download metrics of `phase` with the headers received from
+/// peers.
+pub fn metric_headers_downloaded(phase: SyncPhaseType, count: usize) {
+    DownloadMetrics::of_phase(phase).headers.mark(count);
+}
+
+/// Updates the download metrics of `phase` with the blocks received from
+/// peers, which are `size` bytes in total.
+pub fn metric_blocks_downloaded(
+    phase: SyncPhaseType, count: usize, size: usize,
+) {
+    let metrics = DownloadMetrics::of_phase(phase);
+    metrics.blocks.mark(count);
+    metrics.block_bytes.mark(size);
 }
@@ -52,6 +52,9 @@ pub use self::{
     heartbeat::Heartbeat,
     keys::{Key, KeyContainer},
     message::{handle_rlp_message, msgid},
+    metrics::{
+        metric_blocks_downloaded, metric_compression, metric_headers_downloaded,
+    },
     new_block::NewBlock,
     new_block_hashes::NewBlockHashes,
     snapshot_chunk_request::SnapshotChunkRequest,
@@ -109,6 +109,10 @@ impl Handleable for StatusV2 {
                 notified_capabilities: Default::default(),
                 throttling,
                 throttled_msgs: Default::default(),
+                score: ctx.io.get_peer_score(&ctx.node_id),
+                request_stats: Default::default(),
+                known_transactions: Default::default(),
+                advertised: ctx.io.get_peer_capabilities(&ctx.node_id),
             };
 
             peer_state
@@ -238,6 +242,10 @@ impl Handleable for StatusV3 {
                 notified_capabilities: Default::default(),
                 throttling,
                 throttled_msgs: Default::default(),
+                score: ctx.io.get_peer_score(&ctx.node_id),
+                request_stats: Default::default(),
+                known_transactions: Default::default(),
+                advertised: ctx.io.get_peer_capabilities(&ctx.node_id),
             };
 
             peer_state
@@ -38,7 +38,12 @@ impl Handleable for Throttled {
         );
 
         if let Some(request_id) = self.request_id {
-            let request = ctx.match_request(request_id)?;
+            // throttled response delivers nothing, which is not counted in
+            // the request statistics of the peer
+            let request = ctx
+                .manager
+                .request_manager
+                .match_request(&ctx.node_id, request_id)?;
             ctx.manager
                 .request_manager
                 .resend_request_to_another_peer(ctx.io, &request);
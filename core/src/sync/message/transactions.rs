@@ -76,6 +76,10 @@ impl Handleable for Transactions {
         if should_disconnect {
             bail!(ErrorKind::TooManyTrans);
         }
+        ctx.manager.syn.mark_transactions_known(
+            &ctx.node_id,
+            transactions.iter().map(|tx| &tx.hash),
+        );
 
         // The transaction pool will rely on the execution state information to
         // verify transaction validity. It may incorrectly accept/reject
@@ -148,6 +152,9 @@ impl Handleable for TransactionDigests {
                 }
             }
         }
+        ctx.manager
+            .syn
+            .mark_transactions_known(&ctx.node_id, &self.tx_hashes);
 
         // We will not request transactions when in the catch up mode, because
         // the transaction pool cannot process them correctly.
@@ -584,6 +591,13 @@ impl Handleable for GetTransactionsResponse {
             self.tx_hashes.len(),
             ctx.node_id
         );
+        ctx.manager.syn.mark_transactions_known(
+            &ctx.node_id,
+            self.transactions
+                .iter()
+                .map(|tx| &tx.hash)
+                .chain(self.tx_hashes.iter()),
+        );
 
         // The transaction pool will rely on the execution state information to
         // verify transaction validity. It may incorrectly accept/reject
@@ -663,6 +677,10 @@ impl Handleable for GetTransactionsFromTxHashesResponse {
             self.transactions.len(),
             ctx.node_id
         );
+        ctx.manager.syn.mark_transactions_known(
+            &ctx.node_id,
+            self.transactions.iter().map(|tx| &tx.hash),
+        );
 
         // The transaction pool will rely on the execution state information to
         // verify transaction validity. It may incorrectly accept/reject
@@ -1,6 +1,7 @@
 // Copyright 2019 Conflux Foundation. All rights reserved.
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
+mod catch_up_body_download;
 mod error;
 pub mod message;
 pub mod request_manager;
@@ -69,7 +70,7 @@ pub mod random {
 pub mod msg_sender {
     use super::message::msgid;
     use crate::message::MsgId;
-    use metrics::{register_meter_with_group, Meter};
+    use metrics::{register_meter_with_group, Gauge, GaugeUsize, Meter};
     use std::sync::Arc;
 
     pub const NULL: usize = !0;
@@ -314,9 +315,66 @@ pub mod msg_sender {
                 "network_connection_data_counter",
                 "get_transactions_from_tx_hashes_response_counter"
             );
+        /// Bytes of all the messages sent to propagate transactions, which
+        /// divided by `tx_propagate_set_size` is the bytes sent per unique
+        /// transaction.
+        static ref TX_PROPAGATION_SENT_BYTES_METER: Arc<dyn Meter> =
+            register_meter_with_group("tx_propagation", "sent_bytes");
+        static ref TX_PROPAGATION_ANNOUNCEMENT_UNIQUE_TX_METER: Arc<dyn Meter> =
+            register_meter_with_group(
+                "tx_propagation",
+                "announcement_unique_tx"
+            );
+        static ref TX_PROPAGATION_DIGESTS_UNIQUE_TX_METER: Arc<dyn Meter> =
+            register_meter_with_group("tx_propagation", "digests_unique_tx");
+        static ref TX_PROPAGATION_ANNOUNCEMENT_BYTES_PER_TX: Arc<dyn Gauge<usize>> =
+            GaugeUsize::register_with_group(
+                "tx_propagation",
+                "announcement_bytes_per_tx"
+            );
+        static ref TX_PROPAGATION_DIGESTS_BYTES_PER_TX: Arc<dyn Gauge<usize>> =
+            GaugeUsize::register_with_group(
+                "tx_propagation",
+                "digests_bytes_per_tx"
+            );
+    }
+
+    /// Marks the unique transactions propagated in a round, and updates the
+    /// bytes sent per unique transaction of the propagation mode, which is
+    /// the announcement mode if `announcement` is true and the legacy digests
+    /// mode otherwise. The mode is fixed for a node, so the metrics of nodes
+    /// running the two modes on the same workload can be compared directly.
+    pub fn metric_tx_propagation(announcement: bool, unique_tx_count: usize) {
+        let (unique_tx_meter, bytes_per_tx) = if announcement {
+            (
+                &TX_PROPAGATION_ANNOUNCEMENT_UNIQUE_TX_METER,
+                &TX_PROPAGATION_ANNOUNCEMENT_BYTES_PER_TX,
+            )
+        } else {
+            (
+                &TX_PROPAGATION_DIGESTS_UNIQUE_TX_METER,
+                &TX_PROPAGATION_DIGESTS_BYTES_PER_TX,
+            )
+        };
+        unique_tx_meter.mark(unique_tx_count);
+        let unique_tx_total = unique_tx_meter.count();
+        if unique_tx_total > 0 {
+            bytes_per_tx.update(
+                TX_PROPAGATION_SENT_BYTES_METER.count() / unique_tx_total,
+            );
+        }
     }
 
     pub fn metric_message(msg_id: MsgId, size: usize) {
+        match msg_id {
+            msgid::TRANSACTIONS
+            | msgid::TRANSACTION_DIGESTS
+            | msgid::GET_TRANSACTIONS_RESPONSE
+            | msgid::GET_TRANSACTIONS_FROM_TX_HASHES_RESPONSE => {
+                TX_PROPAGATION_SENT_BYTES_METER.mark(size)
+            }
+            _ => {}
+        }
         match msg_id {
             msgid::STATUS_V2 => ON_STATUS_METER.mark(size),
             msgid::STATUS_V3 => ON_STATUS_METER.mark(size),
@@ -34,7 +34,7 @@ pub use request_handler::{
     AsAny, Request, RequestHandler, RequestMessage, SynchronizationPeerRequest,
 };
 use std::{
-    cmp::Ordering,
+    cmp::{max, Ordering},
     collections::{binary_heap::BinaryHeap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
@@ -44,6 +44,7 @@ use tx_handler::{
     ReceivedTransactionContainer, SentTransactionContainer,
 };
 
+pub mod peer_stats;
 mod request_batcher;
 mod request_handler;
 pub mod tx_handler;
@@ -156,10 +157,6 @@ impl RequestManager {
         let inflight_pending_tx_index_maintain_timeout =
             protocol_config.inflight_pending_tx_index_maintain_timeout;
 
-        // FIXME: make sent_transaction_window_size to be 2^pow.
-        let sent_transaction_window_size =
-            protocol_config.tx_maintained_for_peer_timeout.as_millis()
-                / protocol_config.send_tx_period.as_millis();
         Self {
             received_transactions: Arc::new(RwLock::new(
                 ReceivedTransactionContainer::new(
@@ -172,7 +169,7 @@ impl RequestManager {
                 ),
             )),
             sent_transactions: RwLock::new(SentTransactionContainer::new(
-                sent_transaction_window_size as usize,
+                protocol_config.tx_maintained_for_peer_timeout,
             )),
             inflight_keys: Default::default(),
             waiting_requests: Default::default(),
@@ -600,7 +597,33 @@ impl RequestManager {
     pub fn match_request(
         &self, peer_id: &NodeId, request_id: u64,
     ) -> Result<RequestMessage, Error> {
-        self.request_handler.match_request(peer_id, request_id)
+        Ok(self
+            .request_handler
+            .match_request(peer_id, request_id)?
+            .message)
+    }
+
+    /// Match request with the response of `response_size` bytes, and update
+    /// the request statistics of the peer with the round trip time and the
+    /// throughput of the response.
+    pub fn match_response(
+        &self, peer_id: &NodeId, request_id: u64, response_size: usize,
+    ) -> Result<RequestMessage, Error> {
+        let matched =
+            self.request_handler.match_request(peer_id, request_id)?;
+        let rtt = matched.timed_req.send_time.elapsed();
+        let request = &matched.message.request;
+        let items = request_item_count(request);
+        self.syn.update_request_stats(peer_id, |stats| {
+            stats.on_response(
+                request.msg_id(),
+                request.msg_name(),
+                rtt,
+                response_size,
+                items,
+            )
+        });
+        Ok(matched.message)
     }
 
     /// Remove inflight keys when a header is received.
@@ -876,8 +899,11 @@ impl RequestManager {
         debug!("resend_timeout_requests: start");
         let timeout_requests =
             self.request_handler.process_timeout_requests(io);
-        for req in timeout_requests {
+        for (peer_id, req) in timeout_requests {
             debug!("Timeout requests: {:?}", req);
+            self.syn.update_request_stats(&peer_id, |stats| {
+                stats.on_timeout(req.request.msg_id(), req.request.msg_name())
+            });
             self.resend_request_to_another_peer(io, &req);
         }
     }
@@ -933,8 +959,8 @@ impl RequestManager {
             batcher.insert(delay, request);
         }
 
-        for (next_delay, request) in
-            batcher.get_batched_requests(prefer_archive_node_for_blocks)
+        for (next_delay, request) in batcher
+            .get_batched_requests(prefer_archive_node_for_blocks, &self.syn)
         {
             let mut filter = PeerFilter::new(request.msg_id());
             if let Some(cap) = request.required_capability() {
@@ -1062,6 +1088,24 @@ pub fn try_get_block_hashes(request: &Box<dyn Request>) -> Option<&Vec<H256>> {
     }
 }
 
+/// Return the number of items requested in `request`, e.g. the number of
+/// blocks or epochs, which is 1 for the requests not batched.
+fn request_item_count(request: &Box<dyn Request>) -> usize {
+    let any = request.as_any();
+    let count = if let Some(req) = any.downcast_ref::<GetBlockHeaders>() {
+        req.hashes.len()
+    } else if let Some(req) = any.downcast_ref::<GetBlocks>() {
+        req.hashes.len()
+    } else if let Some(req) = any.downcast_ref::<GetCompactBlocks>() {
+        req.hashes.len()
+    } else if let Some(req) = any.downcast_ref::<GetBlockHashesByEpoch>() {
+        req.epochs.len()
+    } else {
+        1
+    };
+    max(count, 1)
+}
+
 #[derive(Debug, DeriveMallocSizeOf)]
 struct TimedWaitingRequest {
     time_to_send: Instant,
@@ -0,0 +1,219 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::message::MsgId;
+use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
+use serde_derive::Serialize;
+use std::{
+    cmp::{max, min, Ordering},
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+/// Weight of the latest sample in the moving averages.
+const EWMA_ALPHA: f64 = 0.2;
+
+/// A peer responding in this RTT is half as likely to be selected as a peer
+/// responding immediately.
+const REFERENCE_RTT_MS: f64 = 500.0;
+
+/// Minimum weight in peer selection, so that a peer which failed recently
+/// still has a chance to be selected and recover its statistics.
+const MIN_SELECTION_WEIGHT: f64 = 0.05;
+
+/// Expected time for a peer to respond a batched request, which is used to
+/// adapt the batch size to the number of items the peers deliver per second.
+const TARGET_BATCH_RESPONSE_TIME: Duration = Duration::from_secs(1);
+
+/// Statistics of the requests of a type sent to a peer.
+#[derive(Clone, Debug, Default, Serialize, DeriveMallocSizeOf)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStats {
+    pub responses: u64,
+    pub timeouts: u64,
+    /// Moving average of the round trip time in milliseconds.
+    pub rtt_ms: f64,
+    /// Moving average of the response size over RTT in bytes per second.
+    pub throughput: f64,
+    /// Moving average of the requested items delivered per second.
+    pub items_per_sec: f64,
+    /// Moving average of the failures, where a timeout counts 1 and a
+    /// response counts 0.
+    pub failure_rate: f64,
+}
+
+fn ewma(avg: f64, sample: f64, first: bool) -> f64 {
+    if first {
+        sample
+    } else {
+        avg + EWMA_ALPHA * (sample - avg)
+    }
+}
+
+impl RequestStats {
+    fn on_response(&mut self, rtt: Duration, size: usize, items: usize) {
+        let first = self.responses == 0;
+        // avoid the division by zero for the responses within a millisecond
+        let secs = rtt.as_secs_f64().max(0.001);
+        self.rtt_ms = ewma(self.rtt_ms, rtt.as_secs_f64() * 1000.0, first);
+        self.throughput = ewma(self.throughput, size as f64 / secs, first);
+        self.items_per_sec =
+            ewma(self.items_per_sec, items as f64 / secs, first);
+        self.failure_rate = ewma(self.failure_rate, 0.0, false);
+        self.responses += 1;
+    }
+
+    fn on_timeout(&mut self) {
+        self.failure_rate = ewma(self.failure_rate, 1.0, false);
+        self.timeouts += 1;
+    }
+
+    /// Weight of the peer in random peer selection for requests of this type,
+    /// which is inversely related to the RTT, and decreased by the failures.
+    fn weight(&self) -> f64 {
+        let rtt_ms = if self.responses == 0 {
+            // unknown peers are neither preferred nor avoided
+            REFERENCE_RTT_MS
+        } else {
+            self.rtt_ms
+        };
+        let weight =
+            (1.0 - self.failure_rate) / (1.0 + rtt_ms / REFERENCE_RTT_MS);
+        weight.max(MIN_SELECTION_WEIGHT)
+    }
+}
+
+/// Statistics of the requests sent to a peer by request type, which are used
+/// to prefer the fast and reliable peers in peer selection, and to adapt the
+/// batch sizes of the resent requests.
+#[derive(Default, DeriveMallocSizeOf)]
+pub struct PeerRequestStats {
+    stats: HashMap<MsgId, RequestStats>,
+    #[ignore_malloc_size_of = "static strings"]
+    names: HashMap<MsgId, &'static str>,
+}
+
+impl PeerRequestStats {
+    pub fn get(&self, msg_id: MsgId) -> Option<&RequestStats> {
+        self.stats.get(&msg_id)
+    }
+
+    /// Updates the statistics with a response to the request of `msg_id`,
+    /// which is `size` bytes and delivers `items` of the requested items.
+    pub fn on_response(
+        &mut self, msg_id: MsgId, name: &'static str, rtt: Duration,
+        size: usize, items: usize,
+    )
+    {
+        self.names.insert(msg_id, name);
+        self.stats
+            .entry(msg_id)
+            .or_default()
+            .on_response(rtt, size, items);
+    }
+
+    pub fn on_timeout(&mut self, msg_id: MsgId, name: &'static str) {
+        self.names.insert(msg_id, name);
+        self.stats.entry(msg_id).or_default().on_timeout();
+    }
+
+    /// Weight of the peer in random peer selection for requests of `msg_id`.
+    pub fn weight(&self, msg_id: MsgId) -> f64 {
+        self.stats
+            .get(&msg_id)
+            .map_or_else(|| RequestStats::default().weight(), |s| s.weight())
+    }
+
+    /// Returns the statistics by request name for Debug RPC.
+    pub fn to_map(&self) -> BTreeMap<String, RequestStats> {
+        self.stats
+            .iter()
+            .map(|(msg_id, stats)| {
+                let name = self
+                    .names
+                    .get(msg_id)
+                    .map_or_else(|| format!("{}", msg_id), |n| n.to_string());
+                (name, stats.clone())
+            })
+            .collect()
+    }
+}
+
+/// Returns the batch size of requests that the peers are expected to respond
+/// in `TARGET_BATCH_RESPONSE_TIME`, according to the median of the items
+/// delivered per second by the peers. The batch size is between a quarter
+/// and 4 times of `default`, and is `default` if no peer has responded yet.
+pub fn adaptive_batch_size(
+    mut items_per_sec: Vec<f64>, default: usize,
+) -> usize {
+    if items_per_sec.is_empty() {
+        return default;
+    }
+
+    items_per_sec.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let median = items_per_sec[items_per_sec.len() / 2];
+    let size = (median * TARGET_BATCH_RESPONSE_TIME.as_secs_f64()) as usize;
+    min(max(size, max(default / 4, 1)), default * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::message::msgid;
+
+    #[test]
+    fn test_weight() {
+        let mut fast = PeerRequestStats::default();
+        let mut slow = PeerRequestStats::default();
+        let unknown = PeerRequestStats::default();
+        let msg_id = msgid::GET_BLOCKS;
+        for _ in 0..10 {
+            fast.on_response(
+                msg_id,
+                "GetBlocks",
+                Duration::from_millis(50),
+                1000,
+                10,
+            );
+            slow.on_response(
+                msg_id,
+                "GetBlocks",
+                Duration::from_secs(3),
+                1000,
+                10,
+            );
+        }
+        assert!(fast.weight(msg_id) > unknown.weight(msg_id));
+        assert!(unknown.weight(msg_id) > slow.weight(msg_id));
+        assert_eq!(
+            fast.weight(msgid::GET_BLOCK_HEADERS),
+            unknown.weight(msg_id)
+        );
+
+        let stats = fast.get(msg_id).unwrap();
+        assert_eq!(stats.responses, 10);
+        assert!((stats.rtt_ms - 50.0).abs() < 1e-6);
+        assert!((stats.throughput - 20000.0).abs() < 1e-6);
+        assert!((stats.items_per_sec - 200.0).abs() < 1e-6);
+
+        // timeouts decrease the weight
+        let weight = fast.weight(msg_id);
+        fast.on_timeout(msg_id, "GetBlocks");
+        assert!(fast.weight(msg_id) < weight);
+        for _ in 0..100 {
+            fast.on_timeout(msg_id, "GetBlocks");
+        }
+        assert_eq!(fast.weight(msg_id), MIN_SELECTION_WEIGHT);
+        assert_eq!(fast.get(msg_id).unwrap().timeouts, 101);
+        assert!(fast.to_map().contains_key("GetBlocks"));
+    }
+
+    #[test]
+    fn test_adaptive_batch_size() {
+        assert_eq!(adaptive_batch_size(vec![], 50), 50);
+        assert_eq!(adaptive_batch_size(vec![10.0, 100.0, 1000.0], 50), 100);
+        assert_eq!(adaptive_batch_size(vec![1.0], 50), 12);
+        assert_eq!(adaptive_batch_size(vec![1e6], 50), 200);
+    }
+}
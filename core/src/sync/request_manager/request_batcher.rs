@@ -1,7 +1,8 @@
 use crate::{
     sync::{
-        message::{GetBlockHashesByEpoch, GetBlockHeaders, GetBlocks},
-        request_manager::Request,
+        message::{msgid, GetBlockHashesByEpoch, GetBlockHeaders, GetBlocks},
+        request_manager::{peer_stats::adaptive_batch_size, Request},
+        synchronization_state::SynchronizationState,
     },
     NodeType,
 };
@@ -72,14 +73,28 @@ impl RequestBatcher {
     }
 
     /// Batch inserted requests according to their request types.
-    /// Requests with close delays are batched together.
+    /// Requests with close delays are batched together, and the batch sizes
+    /// are adapted to the number of items the peers deliver per second.
     pub fn get_batched_requests(
         mut self, prefer_archive_node_for_blocks: bool,
-    ) -> impl Iterator<Item = (Duration, Box<dyn Request>)> {
+        syn: &SynchronizationState,
+    ) -> impl Iterator<Item = (Duration, Box<dyn Request>)>
+    {
+        let header_batch_size = adaptive_batch_size(
+            syn.request_items_per_sec(msgid::GET_BLOCK_HEADERS),
+            DEFAULT_REQUEST_HEADER_BATCH_SIZE,
+        );
+        let block_batch_size = adaptive_batch_size(
+            syn.request_items_per_sec(msgid::GET_BLOCKS),
+            DEFAULT_REQUEST_BLOCK_BATCH_SIZE,
+        );
+        let epoch_batch_size = adaptive_batch_size(
+            syn.request_items_per_sec(msgid::GET_BLOCK_HASHES_BY_EPOCH),
+            DEFAULT_REQUEST_EPOCH_BATCH_SIZE,
+        );
+
         let mut requests = Vec::new();
-        for (delay, hashes) in
-            self.headers.batch_iter(DEFAULT_REQUEST_HEADER_BATCH_SIZE)
-        {
+        for (delay, hashes) in self.headers.batch_iter(header_batch_size) {
             requests.push((
                 delay,
                 Box::new(GetBlockHeaders {
@@ -93,9 +108,7 @@ impl RequestBatcher {
         } else {
             None
         };
-        for (delay, hashes) in
-            self.blocks.batch_iter(DEFAULT_REQUEST_BLOCK_BATCH_SIZE)
-        {
+        for (delay, hashes) in self.blocks.batch_iter(block_batch_size) {
             requests.push((
                 delay,
                 Box::new(GetBlocks {
@@ -106,9 +119,7 @@ impl RequestBatcher {
                 }) as Box<dyn Request>,
             ));
         }
-        for (delay, epochs) in
-            self.epochs.batch_iter(DEFAULT_REQUEST_EPOCH_BATCH_SIZE)
-        {
+        for (delay, epochs) in self.epochs.batch_iter(epoch_batch_size) {
             requests.push((
                 delay,
                 Box::new(GetBlockHashesByEpoch {
@@ -16,8 +16,8 @@ use cfx_parameters::sync::FAILED_REQUEST_RESEND_WAIT;
 use malloc_size_of::MallocSizeOf;
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use network::{
-    node_table::NodeId, ErrorKind as NetworkErrorKind, NetworkContext,
-    UpdateNodeOperation,
+    node_table::NodeId, reputation::ReputationEvent,
+    ErrorKind as NetworkErrorKind, NetworkContext, UpdateNodeOperation,
 };
 use parking_lot::Mutex;
 use std::{
@@ -75,7 +75,7 @@ impl RequestHandler {
     //      No need to let caller handle request resending;
     pub fn match_request(
         &self, peer_id: &NodeId, request_id: u64,
-    ) -> Result<RequestMessage, Error> {
+    ) -> Result<SynchronizationPeerRequest, Error> {
         let mut peers = self.peers.lock();
         if let Some(peer) = peers.get_mut(peer_id) {
             peer.match_request(request_id)
@@ -158,18 +158,21 @@ impl RequestHandler {
         timeout_requests
     }
 
+    /// Returns the timeout requests with the peers they were sent to.
     pub fn process_timeout_requests(
         &self, io: &dyn NetworkContext,
-    ) -> Vec<RequestMessage> {
+    ) -> Vec<(NodeId, RequestMessage)> {
         // Check if in-flight requests timeout
         let mut timeout_requests = Vec::new();
         let mut peers_to_disconnect = HashSet::new();
         let mut peers_to_send_pending_requests = HashSet::new();
         for sync_req in self.get_timeout_sync_requests() {
-            if let Ok(mut req) =
+            if let Ok(matched) =
                 self.match_request(&sync_req.peer_id, sync_req.request_id)
             {
+                let mut req = matched.message;
                 let peer_id = sync_req.peer_id.clone();
+                io.report_peer(&peer_id, ReputationEvent::Timeout);
                 if let Some(request_container) =
                     self.peers.lock().get_mut(&peer_id)
                 {
@@ -182,7 +185,7 @@ impl RequestHandler {
                     }
                 }
                 req.request.notify_timeout();
-                timeout_requests.push(req);
+                timeout_requests.push((peer_id, req));
             } else {
                 debug!("Timeout a removed request {:?}", sync_req);
             }
@@ -380,14 +383,14 @@ impl RequestContainer {
     //      handle the resending of the request for caller;
     pub fn match_request(
         &mut self, request_id: u64,
-    ) -> Result<RequestMessage, Error> {
+    ) -> Result<SynchronizationPeerRequest, Error> {
         let removed_req = self.remove_inflight_request(request_id);
         if let Some(removed_req) = removed_req {
             removed_req
                 .timed_req
                 .removed
                 .store(true, AtomicOrdering::Relaxed);
-            Ok(removed_req.message)
+            Ok(removed_req)
         } else {
             bail!(ErrorKind::RequestNotFound)
         }
@@ -513,6 +516,7 @@ impl RequestMessage {
 #[derive(Debug, DeriveMallocSizeOf)]
 pub struct TimedSyncRequests {
     pub peer_id: NodeId,
+    pub send_time: Instant,
     pub timeout_time: Instant,
     pub request_id: u64,
     pub removed: AtomicBool,
@@ -522,9 +526,11 @@ impl TimedSyncRequests {
     pub fn new(
         peer_id: NodeId, timeout: Duration, request_id: u64,
     ) -> TimedSyncRequests {
+        let now = Instant::now();
         TimedSyncRequests {
             peer_id,
-            timeout_time: Instant::now() + timeout,
+            send_time: now,
+            timeout_time: now + timeout,
             request_id,
             removed: AtomicBool::new(false),
         }
@@ -8,10 +8,13 @@ use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use metrics::{register_meter_with_group, Meter, MeterTimer};
 use network::node_table::NodeId;
 use primitives::{block::CompactBlock, SignedTransaction, TxPropagateId};
+use rand::Rng;
+use siphasher::sip::SipHasher24;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hasher,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 lazy_static! {
     static ref TX_FIRST_MISS_METER: Arc<dyn Meter> =
@@ -223,90 +226,82 @@ impl ReceivedTransactionContainer {
 }
 
 #[derive(DeriveMallocSizeOf)]
-struct SentTransactionContainerInner {
-    window_size: usize,
-    base_time_tick: usize,
-    next_time_tick: usize,
-    time_windowed_indices: Vec<Option<Vec<Arc<SignedTransaction>>>>,
-}
-
-impl SentTransactionContainerInner {
-    pub fn new(window_size: usize) -> Self {
-        let mut time_windowed_indices = Vec::new();
-        for _ in 0..window_size {
-            time_windowed_indices.push(None);
-        }
-
-        SentTransactionContainerInner {
-            window_size,
-            base_time_tick: 0,
-            next_time_tick: 0,
-            time_windowed_indices,
-        }
-    }
+struct SentTransactionWindow {
+    time: Instant,
+    transactions: Vec<Arc<SignedTransaction>>,
 }
 
+/// The transactions announced to peers in digests. A digest refers to the
+/// transactions by the window index and the positions in the window, so that
+/// the peer can request the transactions with the indices. Every digest can
+/// have its own window, and the windows are kept for `timeout` while the
+/// total number of transactions in the windows is within
+/// `MAX_SENT_TRANSACTIONS`.
+///
 /// This struct is not implemented as thread-safe since
 /// currently it is only used under protection of lock
 /// on SynchronizationState. Later we may refine the
 /// locking design to make it thread-safe.
 #[derive(DeriveMallocSizeOf)]
 pub struct SentTransactionContainer {
-    inner: SentTransactionContainerInner,
+    timeout: Duration,
+    /// The window index of the first window in `windows`.
+    base_window_index: usize,
+    windows: VecDeque<SentTransactionWindow>,
+    /// The total number of transactions in `windows`.
+    size: usize,
 }
 
 impl SentTransactionContainer {
-    pub fn new(window_size: usize) -> Self {
+    const MAX_SENT_TRANSACTIONS: usize = 1 << 22;
+
+    pub fn new(timeout: Duration) -> Self {
         SentTransactionContainer {
-            inner: SentTransactionContainerInner::new(window_size),
+            timeout,
+            base_window_index: 0,
+            windows: VecDeque::new(),
+            size: 0,
         }
     }
 
     pub fn get_transaction(
         &self, window_index: usize, index: usize,
     ) -> Option<Arc<SignedTransaction>> {
-        let inner = &self.inner;
-        if window_index >= inner.base_time_tick {
-            if window_index - inner.base_time_tick >= inner.window_size {
-                return None;
-            }
-        } else {
-            if std::usize::MAX - inner.base_time_tick + window_index + 1
-                >= inner.window_size
-            {
-                return None;
-            }
-        }
-
-        let transactions = inner.time_windowed_indices
-            [window_index % inner.window_size]
-            .as_ref();
-        if transactions.is_none() {
+        if window_index < self.base_window_index {
             return None;
         }
-
-        let transactions = transactions.unwrap();
-        if index >= transactions.len() {
-            return None;
-        }
-
-        Some(transactions[index].clone())
+        self.windows
+            .get(window_index - self.base_window_index)?
+            .transactions
+            .get(index)
+            .cloned()
     }
 
+    /// Append a window of `transactions` and return its window index.
     pub fn append_transactions(
         &mut self, transactions: Vec<Arc<SignedTransaction>>,
     ) -> usize {
-        let inner = &mut self.inner;
-
-        let base_window_index = inner.base_time_tick % inner.window_size;
-        let next_time_tick = inner.next_time_tick;
-        let next_window_index = next_time_tick % inner.window_size;
-        inner.time_windowed_indices[next_window_index] = Some(transactions);
-        if (next_window_index + 1) % inner.window_size == base_window_index {
-            inner.base_time_tick += 1;
+        let now = Instant::now();
+        self.size += transactions.len();
+        self.windows.push_back(SentTransactionWindow {
+            time: now,
+            transactions,
+        });
+        while let Some(window) = self.windows.front() {
+            if now.duration_since(window.time) < self.timeout
+                && self.size <= Self::MAX_SENT_TRANSACTIONS
+            {
+                break;
+            }
+            if self.windows.len() == 1 {
+                // keep the window just appended
+                break;
+            }
+            self.size -= window.transactions.len();
+            self.windows.pop_front();
+            self.base_window_index += 1;
         }
-        inner.next_time_tick += 1;
-        next_time_tick
+        self.base_window_index + self.windows.len() - 1
     }
 }
 
@@ -577,3 +572,135 @@ impl TransactionCacheContainer {
         }
     }
 }
+
+/// Rolling bloom filter of the transactions known by a peer, i.e. the
+/// transactions sent to or received from the peer recently, which are not
+/// propagated to the peer again.
+///
+/// The filter has two generations of `GENERATION_SIZE` transactions. When the
+/// current generation is full, the previous generation is dropped and the
+/// current one becomes the previous, so at least the latest `GENERATION_SIZE`
+/// transactions are remembered.
+#[derive(DeriveMallocSizeOf)]
+pub struct KnownTransactionFilter {
+    key1: u64,
+    key2: u64,
+    current: Vec<u64>,
+    previous: Vec<u64>,
+    current_count: usize,
+}
+
+impl Default for KnownTransactionFilter {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        KnownTransactionFilter {
+            key1: rng.gen(),
+            key2: rng.gen(),
+            current: vec![0; Self::GENERATION_BITS / 64],
+            previous: vec![0; Self::GENERATION_BITS / 64],
+            current_count: 0,
+        }
+    }
+}
+
+impl KnownTransactionFilter {
+    /// 16 bits per transaction with 11 hash functions, so the false positive
+    /// rate is about 0.05%.
+    const GENERATION_BITS: usize = 1 << 18;
+    const GENERATION_SIZE: usize = 16384;
+    const NUM_HASHES: u64 = 11;
+
+    fn bit_positions(&self, tx_hash: &H256) -> impl Iterator<Item = usize> {
+        let mut hasher = SipHasher24::new_with_keys(self.key1, self.key2);
+        hasher.write(tx_hash.as_ref());
+        let hash = hasher.finish();
+        // double hashing with the two halves of the hash
+        let (h1, h2) = (hash & 0xffffffff, (hash >> 32) | 1);
+        (0..Self::NUM_HASHES).map(move |i| {
+            (h1.wrapping_add(i.wrapping_mul(h2)) as usize)
+                % Self::GENERATION_BITS
+        })
+    }
+
+    pub fn contains(&self, tx_hash: &H256) -> bool {
+        [&self.current, &self.previous].iter().any(|bits| {
+            self.bit_positions(tx_hash)
+                .all(|pos| bits[pos / 64] & (1 << (pos % 64)) != 0)
+        })
+    }
+
+    pub fn insert(&mut self, tx_hash: &H256) {
+        if self.contains(tx_hash) {
+            return;
+        }
+        if self.current_count >= Self::GENERATION_SIZE {
+            std::mem::swap(&mut self.current, &mut self.previous);
+            for word in self.current.iter_mut() {
+                *word = 0;
+            }
+            self.current_count = 0;
+        }
+        let positions: Vec<usize> = self.bit_positions(tx_hash).collect();
+        for pos in positions {
+            self.current[pos / 64] |= 1 << (pos % 64);
+        }
+        self.current_count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_transaction_filter() {
+        let mut filter = KnownTransactionFilter::default();
+        let hashes: Vec<H256> = (0..KnownTransactionFilter::GENERATION_SIZE
+            * 2)
+            .map(|_| H256::random())
+            .collect();
+        for hash in &hashes[..KnownTransactionFilter::GENERATION_SIZE] {
+            filter.insert(hash);
+        }
+        assert!(hashes[..KnownTransactionFilter::GENERATION_SIZE]
+            .iter()
+            .all(|hash| filter.contains(hash)));
+        let false_positives = hashes[KnownTransactionFilter::GENERATION_SIZE..]
+            .iter()
+            .filter(|hash| filter.contains(hash))
+            .count();
+        assert!(false_positives < 100);
+
+        // the older generation is forgotten after two generations are filled
+        for hash in &hashes[KnownTransactionFilter::GENERATION_SIZE..] {
+            filter.insert(hash);
+        }
+        for _ in 0..100 {
+            filter.insert(&H256::random());
+        }
+        assert!(hashes[KnownTransactionFilter::GENERATION_SIZE..]
+            .iter()
+            .all(|hash| filter.contains(hash)));
+        let remembered = hashes[..KnownTransactionFilter::GENERATION_SIZE]
+            .iter()
+            .filter(|hash| filter.contains(hash))
+            .count();
+        assert!(remembered < 100);
+    }
+
+    #[test]
+    fn test_sent_transaction_windows() {
+        let mut sent = SentTransactionContainer::new(Duration::from_secs(60));
+        let window1 = sent.append_transactions(vec![]);
+        let window2 = sent.append_transactions(vec![]);
+        assert_eq!(window2, window1 + 1);
+        assert!(sent.get_transaction(window2, 0).is_none());
+
+        // expired windows are removed
+        let mut sent = SentTransactionContainer::new(Duration::from_secs(0));
+        sent.append_transactions(vec![]);
+        let window = sent.append_transactions(vec![]);
+        assert_eq!(sent.base_window_index, window);
+        assert_eq!(sent.windows.len(), 1);
+    }
+}
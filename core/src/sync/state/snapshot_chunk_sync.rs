@@ -143,6 +143,7 @@ impl Inner {
     )
     {
         let peers = PeerFilter::new(msgid::STATE_SYNC_CANDIDATE_REQUEST)
+            .with_snapshot()
             .select_all(&sync_handler.syn);
         if peers.is_empty() {
             return;
@@ -370,6 +371,7 @@ impl SnapshotChunkSync {
                 node_id: Default::default(),
                 io,
                 manager: sync_handler,
+                msg_size: 0,
             },
         );
 
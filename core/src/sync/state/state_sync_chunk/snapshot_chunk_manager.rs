@@ -152,6 +152,7 @@ impl SnapshotChunkManager {
     fn request_chunks(&mut self, ctx: &Context) {
         let chosen_peers = PeerFilter::new(msgid::GET_SNAPSHOT_CHUNK)
             .choose_from(&self.active_peers)
+            .with_snapshot()
             .select_n(
                 self.config.max_downloading_chunks
                     - self.downloading_chunks.len(),
@@ -241,6 +241,7 @@ impl SnapshotManifestManager {
 
         let available_peers = PeerFilter::new(msgid::GET_SNAPSHOT_MANIFEST)
             .choose_from(&self.active_peers)
+            .with_snapshot()
             .select_all(&sync_handler.syn);
         let maybe_peer = available_peers.choose(&mut thread_rng()).map(|p| *p);
         if let Some(peer) = maybe_peer {
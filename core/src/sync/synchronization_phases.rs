@@ -535,6 +535,7 @@ impl SynchronizationPhaseTrait for CatchUpSyncBlockPhase {
             self.graph.get_genesis_hash_and_height_in_current_era();
         *sync_handler.latest_epoch_requested.lock() =
             (cur_era_genesis_height, Instant::now(), 0, 0);
+        sync_handler.body_downloader.clear();
 
         sync_handler.request_epochs(io);
     }
@@ -572,6 +573,7 @@ impl SynchronizationPhaseTrait for NormalSyncPhase {
     )
     {
         info!("start phase {:?}", self.name());
+        sync_handler.finish_catch_up_body_download(io);
         sync_handler.request_missing_terminals(io);
     }
 }
@@ -11,12 +11,17 @@ use crate::{
     light_protocol::Provider as LightProvider,
     message::{decode_msg, Message, MsgId},
     sync::{
+        catch_up_body_download::CatchUpBodyDownloader,
         message::{
-            handle_rlp_message, msgid, Context, DynamicCapability,
-            GetBlockHeadersResponse, Heartbeat, NewBlockHashes, StatusV2,
-            StatusV3, TransactionDigests,
+            handle_rlp_message, metric_blocks_downloaded, metric_compression,
+            msgid, Context, DynamicCapability, GetBlockHeadersResponse,
+            Heartbeat, NewBlockHashes, StatusV2, StatusV3, TransactionDigests,
+            Transactions,
+        },
+        msg_sender::metric_tx_propagation,
+        request_manager::{
+            peer_stats::adaptive_batch_size, try_get_block_hashes, Request,
         },
-        request_manager::{try_get_block_hashes, Request},
         state::SnapshotChunkSync,
         synchronization_phases::{SyncPhaseType, SynchronizationPhaseManager},
         synchronization_state::PeerFilter,
@@ -35,7 +40,7 @@ use malloc_size_of::{new_malloc_size_ops, MallocSizeOf};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use metrics::{register_meter_with_group, Meter, MeterTimer};
 use network::{
-    node_table::NodeId, service::ProtocolVersion,
+    node_table::NodeId, reputation::ReputationEvent, service::ProtocolVersion,
     throttling::THROTTLING_SERVICE, Error as NetworkError, HandlerWorkType,
     NetworkContext, NetworkProtocolHandler, UpdateNodeOperation,
 };
@@ -92,6 +97,9 @@ const EPOCH_SYNC_RESTART_TIMEOUT_S: u64 = 60 * 10;
 const EPOCH_SYNC_MAX_INFLIGHT: u64 = 300;
 const EPOCH_SYNC_BATCH_SIZE: u64 = 30;
 const BLOCK_SYNC_MAX_INFLIGHT: usize = 1000;
+/// The max number of peers to download block bodies from in parallel in
+/// `CatchUpSyncBlock` phase.
+const CATCH_UP_BODY_SYNC_MAX_PEERS: usize = 16;
 
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub enum SyncHandlerWorkType {
@@ -369,6 +377,10 @@ pub struct SynchronizationProtocolHandler {
     #[ignore_malloc_size_of = "channels are not handled in MallocSizeOf"]
     pub recover_public_queue: Arc<AsyncTaskQueue<RecoverPublicTask>>,
 
+    // Parallel body download in `CatchUpSyncBlock` phase
+    #[ignore_malloc_size_of = "only stores block hashes"]
+    pub body_downloader: CatchUpBodyDownloader,
+
     // Worker task queue for local message
     #[ignore_malloc_size_of = "channels are not handled in MallocSizeOf"]
     local_message: AsyncTaskQueue<LocalMessageTask>,
@@ -410,6 +422,8 @@ pub struct ProtocolConfiguration {
     pub max_trans_count_received_in_catch_up: u64,
     pub min_peers_tx_propagation: usize,
     pub max_peers_tx_propagation: usize,
+    pub enable_tx_announcement: bool,
+    pub tx_announcement_size_threshold: usize,
     pub max_downloading_chunks: usize,
     pub test_mode: bool,
     pub dev_mode: bool,
@@ -453,6 +467,10 @@ impl SynchronizationProtocolHandler {
             recover_public_queue.clone(),
         ));
 
+        let body_downloader = CatchUpBodyDownloader::new(
+            protocol_config.max_unprocessed_block_size,
+        );
+
         let state_sync = Arc::new(SnapshotChunkSync::new(state_sync_config));
 
         Self {
@@ -471,6 +489,7 @@ impl SynchronizationProtocolHandler {
             ),
             phase_manager_lock: Mutex::new(0),
             recover_public_queue,
+            body_downloader,
             local_message: AsyncTaskQueue::new(
                 SyncHandlerWorkType::LocalMessage,
                 10000000000, // TODO: Set a better capacity.
@@ -578,6 +597,7 @@ impl SynchronizationProtocolHandler {
             node_id: *peer,
             io,
             manager: self,
+            msg_size: rlp.as_raw().len(),
         };
 
         if !handle_rlp_message(msg_id, &ctx, &rlp)? {
@@ -603,11 +623,15 @@ impl SynchronizationProtocolHandler {
         let reason = format!("{}", e.0);
         let error_reason = format!("{:?}", e);
         let mut op = None;
+        let mut event = None;
 
         // NOTE, DO NOT USE WILDCARD IN THE FOLLOWING MATCH STATEMENT!
         // COMPILER WILL HELP TO FIND UNHANDLED ERROR CASES.
         match e.0 {
-            ErrorKind::InvalidBlock => op = Some(UpdateNodeOperation::Failure),
+            ErrorKind::InvalidBlock => {
+                op = Some(UpdateNodeOperation::Failure);
+                event = Some(ReputationEvent::InvalidBlock);
+            }
             ErrorKind::InvalidGetBlockTxn(_) => {
                 op = Some(UpdateNodeOperation::Demotion)
             }
@@ -623,7 +647,8 @@ impl SynchronizationProtocolHandler {
             // TODO handle the unexpected response case (timeout or real invalid
             // message type)
             ErrorKind::UnexpectedResponse => {
-                op = Some(UpdateNodeOperation::Demotion)
+                op = Some(UpdateNodeOperation::Demotion);
+                event = Some(ReputationEvent::UselessResponse);
             }
             ErrorKind::RequestNotFound => disconnect = false,
             ErrorKind::InCatchUpMode(_) => {
@@ -678,6 +703,9 @@ impl SynchronizationProtocolHandler {
                     op = Some(UpdateNodeOperation::Remove)
                 }
                 network::ErrorKind::BadAddr => disconnect = false,
+                network::ErrorKind::Compression(_) => {
+                    op = Some(UpdateNodeOperation::Remove)
+                }
                 network::ErrorKind::Decoder(_) => {
                     op = Some(UpdateNodeOperation::Remove)
                 }
@@ -723,11 +751,34 @@ impl SynchronizationProtocolHandler {
             );
         }
 
+        if let Some(event) = event {
+            self.report_peer(io, peer, event);
+        }
+
         if disconnect {
             io.disconnect_peer(peer, op, reason.as_str());
         }
     }
 
+    /// Reports an event of the peer to update its reputation in network, and
+    /// caches the updated score for peer selection.
+    pub fn report_peer(
+        &self, io: &dyn NetworkContext, peer: &NodeId, event: ReputationEvent,
+    ) {
+        let score = io.report_peer(peer, event);
+        self.syn.update_peer_score(peer, score);
+    }
+
+    /// Refreshes the cached reputation scores, which decay over time in
+    /// network, and the capabilities advertised in discovery.
+    fn refresh_peer_scores(&self, io: &dyn NetworkContext) {
+        for (id, state) in self.syn.peers.read().iter() {
+            let mut state = state.write();
+            state.score = io.get_peer_score(id);
+            state.advertised = io.get_peer_capabilities(id);
+        }
+    }
+
     pub fn start_sync(&self, io: &dyn NetworkContext) {
         let current_phase_type =
             self.phase_manager.get_current_phase().phase_type();
@@ -1028,6 +1079,7 @@ impl SynchronizationProtocolHandler {
                 node_id: io.self_node_id(),
                 io,
                 manager: self,
+                msg_size: 0,
             };
 
             ctx.send_response(&block_headers_resp)
@@ -1044,6 +1096,17 @@ impl SynchronizationProtocolHandler {
         let mut need_to_relay = Vec::new();
         let mut received_blocks = HashSet::new();
         let mut dependent_hashes = HashSet::new();
+        let mut has_invalid_block = false;
+        let block_sizes: HashMap<H256, usize> = task
+            .blocks
+            .iter()
+            .map(|block| (block.hash(), block.approximated_rlp_size()))
+            .collect();
+        metric_blocks_downloaded(
+            self.phase_manager.get_current_phase().phase_type(),
+            block_sizes.len(),
+            block_sizes.values().sum(),
+        );
         for mut block in task.blocks {
             let hash = block.hash();
             if self.graph.contains_block(&hash) {
@@ -1109,10 +1172,28 @@ impl SynchronizationProtocolHandler {
                 // The requested block is correctly received
                 received_blocks.insert(hash);
             }
+            if insert_result.is_invalid() {
+                has_invalid_block = true;
+            }
             if insert_result.should_relay() {
                 need_to_relay.push(hash);
             }
         }
+        if has_invalid_block {
+            self.report_peer(
+                io,
+                &task.failed_peer,
+                ReputationEvent::InvalidBlock,
+            );
+        } else if !task.requested.is_empty() {
+            let event =
+                if task.requested.iter().any(|h| received_blocks.contains(h)) {
+                    ReputationEvent::GoodDelivery
+                } else {
+                    ReputationEvent::UselessResponse
+                };
+            self.report_peer(io, &task.failed_peer, event);
+        }
         let mut filter =
             PeerFilter::new(msgid::GET_BLOCKS).exclude(task.failed_peer);
         if let Some(preferred_note_type) =
@@ -1130,6 +1211,12 @@ impl SynchronizationProtocolHandler {
             task.delay,
             self.preferred_peer_node_type_for_get_block(),
         );
+        self.body_downloader.on_received(
+            received_blocks.iter().map(|hash| {
+                (*hash, block_sizes.get(hash).cloned().unwrap_or(0))
+            }),
+        );
+        self.request_catch_up_bodies(io);
         if self.graph.inner.read().locked_for_catchup {
             self.request_block_bodies(io);
             Ok(())
@@ -1365,45 +1452,20 @@ impl SynchronizationProtocolHandler {
 
         let mut short_ids_part: Vec<Vec<u8>> = vec![vec![]; lucky_peers.len()];
         let mut tx_hashes_part: Vec<H256> = vec![];
-        let (short_ids_transactions, tx_hashes_transactions) = {
-            let mut transactions = self.get_to_propagate_trans();
+        let (short_ids_transactions, tx_hashes_transactions): (
+            Vec<Arc<SignedTransaction>>,
+            Vec<Arc<SignedTransaction>>,
+        ) = {
+            let transactions = self.take_transactions_to_propagate();
             if transactions.is_empty() {
                 return;
             }
 
-            let mut total_tx_bytes = 0;
-            let mut short_ids_transactions: Vec<Arc<SignedTransaction>> =
-                Vec::new();
-            let mut tx_hashes_transactions: Vec<Arc<SignedTransaction>> =
-                Vec::new();
-
             let received_pool =
                 self.request_manager.received_transactions.read();
-            for (_, tx) in transactions.iter() {
-                total_tx_bytes += tx.rlp_size();
-                if total_tx_bytes >= MAX_TXS_BYTES_TO_PROPAGATE {
-                    break;
-                }
-                if received_pool.group_overflow_from_tx_hash(&tx.hash()) {
-                    tx_hashes_transactions.push(tx.clone());
-                } else {
-                    short_ids_transactions.push(tx.clone());
-                }
-            }
-
-            if short_ids_transactions.len() + tx_hashes_transactions.len()
-                != transactions.len()
-            {
-                for tx in short_ids_transactions.iter() {
-                    transactions.remove(&tx.hash);
-                }
-                for tx in tx_hashes_transactions.iter() {
-                    transactions.remove(&tx.hash);
-                }
-                self.set_to_propagate_trans(transactions);
-            }
-
-            (short_ids_transactions, tx_hashes_transactions)
+            transactions.into_iter().partition(|tx| {
+                !received_pool.group_overflow_from_tx_hash(&tx.hash())
+            })
         };
         debug!(
             "Send short ids:{}, Send tx hashes:{}",
@@ -1446,6 +1508,7 @@ impl SynchronizationProtocolHandler {
             lucky_peers.len()
         );
 
+        let unique_tx_count = sent_transactions.len();
         let window_index = self
             .request_manager
             .append_sent_transactions(sent_transactions);
@@ -1478,6 +1541,7 @@ impl SynchronizationProtocolHandler {
                 }
             }
         }
+        metric_tx_propagation(false, unique_tx_count);
 
         if resend_flag {
             let mut resend_transactions: HashMap<H256, Arc<SignedTransaction>> =
@@ -1492,6 +1556,227 @@ impl SynchronizationProtocolHandler {
         }
     }
 
+    /// Takes the transactions to propagate in this round, which are limited
+    /// by `MAX_TXS_BYTES_TO_PROPAGATE` in total, and leaves the others to the
+    /// next round.
+    fn take_transactions_to_propagate(&self) -> Vec<Arc<SignedTransaction>> {
+        let mut transactions = self.get_to_propagate_trans();
+        let mut total_tx_bytes = 0;
+        let mut to_propagate = Vec::new();
+        for tx in transactions.values() {
+            total_tx_bytes += tx.rlp_size();
+            if total_tx_bytes >= MAX_TXS_BYTES_TO_PROPAGATE {
+                break;
+            }
+            to_propagate.push(tx.clone());
+        }
+
+        if to_propagate.len() != transactions.len() {
+            for tx in &to_propagate {
+                transactions.remove(&tx.hash);
+            }
+            self.set_to_propagate_trans(transactions);
+        }
+        to_propagate
+    }
+
+    /// Propagates the new transactions to all the normal-phase peers, except
+    /// the transactions already known by each peer. The transactions not
+    /// larger than `tx_announcement_size_threshold` are pushed in full to
+    /// `push_peers`, and the others are announced by their short ids or
+    /// hashes and fetched by the peers on demand.
+    fn announce_transactions_to_peers(
+        &self, io: &dyn NetworkContext, push_peers: Vec<NodeId>,
+    ) {
+        let _timer = MeterTimer::time_func(PROPAGATE_TX_TIMER.as_ref());
+        let peers = PeerFilter::new(msgid::TRANSACTION_DIGESTS)
+            .with_cap(DynamicCapability::NormalPhase(true))
+            .select_all(&self.syn);
+        if peers.is_empty() {
+            return;
+        }
+        let transactions = self.take_transactions_to_propagate();
+        if transactions.is_empty() {
+            return;
+        }
+
+        let push_peers: HashSet<NodeId> = push_peers.into_iter().collect();
+        let size_threshold =
+            self.protocol_config.tx_announcement_size_threshold;
+        let group_overflow: HashSet<H256> = {
+            let received_pool =
+                self.request_manager.received_transactions.read();
+            transactions
+                .iter()
+                .filter(|tx| {
+                    received_pool.group_overflow_from_tx_hash(&tx.hash)
+                })
+                .map(|tx| tx.hash)
+                .collect()
+        };
+
+        // Peers that announce the same transactions share the window of sent
+        // transactions, which is the transactions announced by short ids
+        // followed by those announced by hashes.
+        let mut windows: HashMap<Vec<H256>, usize> = HashMap::new();
+        let mut sent_transactions: HashSet<H256> = HashSet::new();
+        let mut sent_tx_hashes: HashSet<H256> = HashSet::new();
+        let mut resend_transactions: HashMap<H256, Arc<SignedTransaction>> =
+            HashMap::new();
+        let mut push_count = 0;
+        let mut announce_count = 0;
+        for peer_id in peers {
+            let (pushed, short_ids_transactions, tx_hashes_transactions) = {
+                let peer_info = match self.syn.get_peer_info(&peer_id) {
+                    Ok(peer_info) => peer_info,
+                    Err(_) => continue,
+                };
+                let peer_info = peer_info.read();
+                let is_push_peer = push_peers.contains(&peer_id);
+                let mut pushed = Vec::new();
+                let mut short_ids_transactions = Vec::new();
+                let mut tx_hashes_transactions = Vec::new();
+                for tx in &transactions {
+                    if peer_info.known_transactions.contains(&tx.hash) {
+                        continue;
+                    }
+                    let small = tx.rlp_size() <= size_threshold;
+                    if is_push_peer && small {
+                        pushed.push(tx.clone());
+                    } else if small && !group_overflow.contains(&tx.hash) {
+                        short_ids_transactions.push(tx.clone());
+                    } else {
+                        tx_hashes_transactions.push(tx.clone());
+                    }
+                }
+                (pushed, short_ids_transactions, tx_hashes_transactions)
+            };
+
+            if !pushed.is_empty() {
+                let tx_msg = Transactions {
+                    transactions: pushed
+                        .iter()
+                        .map(|tx| tx.transaction.clone())
+                        .collect(),
+                };
+                match tx_msg.send(io, &peer_id) {
+                    Ok(_) => {
+                        trace!(
+                            "{:02} <- Transactions ({} entries)",
+                            peer_id,
+                            pushed.len()
+                        );
+                        push_count += pushed.len();
+                        self.syn.mark_transactions_known(
+                            &peer_id,
+                            pushed.iter().map(|tx| &tx.hash),
+                        );
+                        sent_transactions
+                            .extend(pushed.iter().map(|tx| tx.hash));
+                    }
+                    Err(e) => {
+                        warn!(
+                            "failed to push transactions to peer, id: {}, err: {}",
+                            peer_id, e
+                        );
+                        for tx in pushed {
+                            resend_transactions.insert(tx.hash, tx);
+                        }
+                    }
+                }
+            }
+
+            if short_ids_transactions.is_empty()
+                && tx_hashes_transactions.is_empty()
+            {
+                continue;
+            }
+            let window_transactions: Vec<Arc<SignedTransaction>> =
+                short_ids_transactions
+                    .iter()
+                    .chain(tx_hashes_transactions.iter())
+                    .cloned()
+                    .collect();
+            let window_key: Vec<H256> =
+                window_transactions.iter().map(|tx| tx.hash).collect();
+            let window_index = match windows.get(&window_key) {
+                Some(window_index) => *window_index,
+                None => {
+                    let window_index = self
+                        .request_manager
+                        .append_sent_transactions(window_transactions.clone());
+                    windows.insert(window_key, window_index);
+                    window_index
+                }
+            };
+
+            let (key1, key2) =
+                (rand::thread_rng().gen(), rand::thread_rng().gen());
+            let mut short_ids = Vec::new();
+            for tx in &short_ids_transactions {
+                TransactionDigests::append_short_id(
+                    &mut short_ids,
+                    key1,
+                    key2,
+                    &tx.hash,
+                );
+            }
+            let mut tx_hashes = Vec::new();
+            for tx in &tx_hashes_transactions {
+                TransactionDigests::append_tx_hash(&mut tx_hashes, tx.hash);
+            }
+            let tx_msg = TransactionDigests::new(
+                window_index,
+                key1,
+                key2,
+                short_ids,
+                tx_hashes,
+            );
+            match tx_msg.send(io, &peer_id) {
+                Ok(_) => {
+                    trace!(
+                        "{:02} <- TransactionDigests ({} entries)",
+                        peer_id,
+                        window_transactions.len()
+                    );
+                    announce_count += window_transactions.len();
+                    sent_tx_hashes.extend(
+                        tx_hashes_transactions.iter().map(|tx| tx.hash),
+                    );
+                    self.syn.mark_transactions_known(
+                        &peer_id,
+                        window_transactions.iter().map(|tx| &tx.hash),
+                    );
+                    sent_transactions
+                        .extend(window_transactions.iter().map(|tx| tx.hash));
+                }
+                Err(e) => {
+                    warn!(
+                        "failed to propagate transaction ids to peer, id: {}, err: {}",
+                        peer_id, e
+                    );
+                    for tx in window_transactions {
+                        resend_transactions.insert(tx.hash, tx);
+                    }
+                }
+            }
+        }
+
+        TX_HASHES_PROPAGATE_METER.mark(sent_tx_hashes.len());
+        TX_PROPAGATE_METER.mark(sent_transactions.len());
+        metric_tx_propagation(true, sent_transactions.len());
+        debug!(
+            "Pushed {} transactions and announced {} transactions to peers, {} unique transactions in total.",
+            push_count,
+            announce_count,
+            sent_transactions.len()
+        );
+
+        if !resend_transactions.is_empty() {
+            self.set_to_propagate_trans(resend_transactions);
+        }
+    }
+
     pub fn check_future_blocks(&self, io: &dyn NetworkContext) {
         let now_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -1564,7 +1849,11 @@ impl SynchronizationProtocolHandler {
         }
 
         let peers = self.select_peers_for_transactions();
-        self.propagate_transactions_to_peers(io, peers);
+        if self.protocol_config.enable_tx_announcement {
+            self.announce_transactions_to_peers(io, peers);
+        } else {
+            self.propagate_transactions_to_peers(io, peers);
+        }
     }
 
     pub fn remove_expired_flying_request(&self, io: &dyn NetworkContext) {
@@ -1575,6 +1864,16 @@ impl SynchronizationProtocolHandler {
             self.need_block_from_archive_node(),
         );
         self.handle_cancelled_requests(cancelled_requests);
+
+        if self.phase_manager.get_current_phase().phase_type()
+            == SyncPhaseType::CatchUpSyncBlock
+        {
+            self.body_downloader.expire(
+                self.protocol_config.blocks_request_timeout,
+                &self.request_manager.in_flight_blocks(),
+            );
+            self.request_catch_up_bodies(io);
+        }
     }
 
     /// Remove the blocks in `cancelled_requests` and their future set from sync
@@ -1594,6 +1893,7 @@ impl SynchronizationProtocolHandler {
                 }
             }
         }
+        self.body_downloader.remove(&to_remove_blocks);
         self.graph.remove_blocks_and_future(&to_remove_blocks);
     }
 
@@ -1605,7 +1905,10 @@ impl SynchronizationProtocolHandler {
         self.graph.data_man.cache_gc();
         self.graph
             .data_man
-            .database_gc(self.graph.consensus.best_epoch_number())
+            .database_gc(self.graph.consensus.best_epoch_number());
+        self.graph.data_man.notify_finalized_epoch(
+            self.graph.consensus.latest_finalized_epoch_number(),
+        );
     }
 
     fn log_statistics(&self) { self.graph.log_statistics(); }
@@ -1664,6 +1967,21 @@ impl SynchronizationProtocolHandler {
 
         DynamicCapability::NormalPhase(!catch_up_mode)
             .broadcast_with_peers(io, need_notify);
+
+        // Advertise in discovery whether the snapshot that syncing peers
+        // request is available, which is the case in the normal phase once
+        // the snapshot of the current era is made.
+        let snapshot = !catch_up_mode
+            && self
+                .graph
+                .data_man
+                .storage_manager
+                .get_storage_manager()
+                .get_snapshot_info_at_epoch(
+                    &self.graph.consensus.get_to_sync_epoch_id(),
+                )
+                .is_some();
+        io.set_snapshot_capability(snapshot);
     }
 
     pub fn request_missing_blocks(
@@ -1672,7 +1990,13 @@ impl SynchronizationProtocolHandler {
     )
     {
         let catch_up_mode = self.catch_up_mode();
-        if catch_up_mode {
+        if self.phase_manager.get_current_phase().phase_type()
+            == SyncPhaseType::CatchUpSyncBlock
+        {
+            // The headers are verified, so the bodies can be downloaded
+            // from any peers.
+            self.download_catch_up_bodies(io, hashes);
+        } else if catch_up_mode {
             self.request_blocks(io, peer_id, hashes);
         } else {
             self.request_manager
@@ -1680,6 +2004,66 @@ impl SynchronizationProtocolHandler {
         }
     }
 
+    fn download_catch_up_bodies(
+        &self, io: &dyn NetworkContext, mut hashes: Vec<H256>,
+    ) {
+        hashes.retain(|hash| !self.already_processed(hash));
+        let blocks = hashes.into_iter().filter_map(|hash| {
+            self.graph
+                .block_height_by_hash(&hash)
+                .map(|height| (height, hash))
+        });
+        self.body_downloader.enqueue(blocks);
+        self.request_catch_up_bodies(io);
+    }
+
+    /// Request the bodies queued in `body_downloader` from multiple peers in
+    /// parallel in `CatchUpSyncBlock` phase.
+    pub fn request_catch_up_bodies(&self, io: &dyn NetworkContext) {
+        if self.phase_manager.get_current_phase().phase_type()
+            != SyncPhaseType::CatchUpSyncBlock
+        {
+            return;
+        }
+        let preferred_node_type = self.preferred_peer_node_type_for_get_block();
+        let mut filter = PeerFilter::new(msgid::GET_BLOCKS);
+        if let Some(node_type) = preferred_node_type {
+            filter = filter.with_preferred_node_type(node_type);
+        }
+        let peers = filter.select_n(CATCH_UP_BODY_SYNC_MAX_PEERS, &self.syn);
+        // A peer responds at most `MAX_BLOCKS_TO_SEND` blocks in a batch.
+        let batch_size = min(
+            adaptive_batch_size(
+                self.syn.request_items_per_sec(msgid::GET_BLOCKS),
+                MAX_BLOCKS_TO_SEND as usize,
+            ),
+            MAX_BLOCKS_TO_SEND as usize,
+        );
+        let requests = self.body_downloader.next_requests(
+            &peers,
+            batch_size,
+            self.recover_public_queue.size(),
+        );
+        for (peer, hashes) in requests {
+            self.request_manager.request_blocks(
+                io,
+                Some(peer),
+                hashes,
+                self.request_block_need_public(),
+                None,
+                preferred_node_type,
+            );
+        }
+    }
+
+    /// Request the bodies left in `body_downloader` as in normal sync after
+    /// catch-up.
+    pub fn finish_catch_up_body_download(&self, io: &dyn NetworkContext) {
+        let pending = self.body_downloader.take_pending();
+        self.body_downloader.clear();
+        self.request_blocks(io, None, pending);
+    }
+
     pub fn request_blocks(
         &self, io: &dyn NetworkContext, peer_id: Option<NodeId>,
         mut hashes: Vec<H256>,
@@ -1881,6 +2265,18 @@ impl NetworkProtocolHandler for SynchronizationProtocolHandler {
         }
     }
 
+    fn on_message_compressed(
+        &self, msg_id: MsgId, size: usize, compressed_size: usize,
+    ) {
+        metric_compression(msg_id, size, compressed_size);
+    }
+
+    fn peer_stats(&self, node_id: &NodeId) -> Option<serde_json::Value> {
+        let peer = self.syn.peers.read().get(node_id)?.clone();
+        let stats = peer.read().request_stats.to_map();
+        serde_json::to_value(stats).ok()
+    }
+
     fn on_peer_connected(
         &self, io: &dyn NetworkContext, node_id: &NodeId,
         peer_protocol_version: ProtocolVersion,
@@ -1955,6 +2351,7 @@ impl NetworkProtocolHandler for SynchronizationProtocolHandler {
                         "sync heartbeat timeout", /* reason */
                     );
                 }
+                self.refresh_peer_scores(io);
             }
             EXPIRE_BLOCK_GC_TIMER => {
                 // remove expire blocks every `expire_block_gc_period`
@@ -1971,3 +2368,288 @@ impl NetworkProtocolHandler for SynchronizationProtocolHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SynchronizationProtocolHandler;
+    use crate::{
+        block_data_manager::DbType,
+        light_protocol::Provider as LightProvider,
+        message::{decode_msg, MsgId, SendQueuePriority},
+        sync::{
+            message::{
+                msgid, DynamicCapability, TransactionDigests, Transactions,
+            },
+            synchronization_state::SynchronizationPeerState,
+            utils::initialize_synchronization_graph,
+            ProtocolConfiguration, StateSyncConfiguration, SyncPhaseType,
+        },
+        NodeType,
+    };
+    use cfx_types::{Address, H256, U256};
+    use io::TimerToken;
+    use keylib::{Generator, KeyPair, Random};
+    use network::{
+        node_record::NodeCapabilities, node_table::NodeId,
+        reputation::ReputationEvent, service::ProtocolVersion,
+        Error as NetworkError, HandlerWorkType, NetworkContext, ProtocolId,
+        UpdateNodeOperation,
+    };
+    use parking_lot::Mutex;
+    use primitives::{
+        Action, NativeTransaction, SignedTransaction, Transaction,
+    };
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+        sync::{Arc, Weak},
+        time::{Duration, Instant},
+    };
+
+    /// Records the messages sent to peers instead of sending them.
+    #[derive(Default)]
+    struct RecordingNetworkContext {
+        sent: Mutex<Vec<(NodeId, Vec<u8>)>>,
+    }
+
+    impl RecordingNetworkContext {
+        fn take_transactions(&self, peer: &NodeId) -> Vec<Vec<H256>> {
+            self.take(peer, msgid::TRANSACTIONS, |rlp| {
+                let msg: Transactions = rlp.as_val().unwrap();
+                msg.transactions.iter().map(|tx| tx.hash()).collect()
+            })
+        }
+
+        /// Returns the number of short ids and the hashes of each
+        /// `TransactionDigests` sent to the peer.
+        fn take_digests(&self, peer: &NodeId) -> Vec<(usize, Vec<H256>)> {
+            self.take(peer, msgid::TRANSACTION_DIGESTS, |rlp| {
+                let msg: TransactionDigests = rlp.as_val().unwrap();
+                (msg.len(), msg.tx_hashes.clone())
+            })
+        }
+
+        fn take<T, F>(&self, peer: &NodeId, id: MsgId, decode: F) -> Vec<T>
+        where F: Fn(&rlp::Rlp) -> T {
+            let mut sent = self.sent.lock();
+            let mut taken = Vec::new();
+            sent.retain(|(node_id, msg)| {
+                let (msg_id, rlp) = decode_msg(msg).unwrap();
+                if node_id != peer || msg_id != id {
+                    return true;
+                }
+                taken.push(decode(&rlp));
+                false
+            });
+            taken
+        }
+    }
+
+    impl NetworkContext for RecordingNetworkContext {
+        fn get_protocol(&self) -> ProtocolId { *b"cfx" }
+
+        fn get_peer_connection_origin(
+            &self, _node_id: &NodeId,
+        ) -> Option<bool> {
+            Some(true)
+        }
+
+        fn send(
+            &self, node_id: &NodeId, msg: Vec<u8>,
+            _min_protocol_version: ProtocolVersion,
+            _version_valid_till: ProtocolVersion, _priority: SendQueuePriority,
+        ) -> Result<(), NetworkError>
+        {
+            self.sent.lock().push((*node_id, msg));
+            Ok(())
+        }
+
+        fn disconnect_peer(
+            &self, _node_id: &NodeId, _op: Option<UpdateNodeOperation>,
+            _reason: &str,
+        )
+        {
+        }
+
+        fn register_timer(
+            &self, _token: TimerToken, _delay: Duration,
+        ) -> Result<(), NetworkError> {
+            Ok(())
+        }
+
+        fn dispatch_work(&self, _work_type: HandlerWorkType) {}
+
+        fn insert_peer_node_tag(
+            &self, _peer: NodeId, _key: &str, _value: &str,
+        ) {
+        }
+
+        fn is_peer_self(&self, _node_id: &NodeId) -> bool { false }
+
+        fn self_node_id(&self) -> NodeId { NodeId::zero() }
+
+        fn report_peer(
+            &self, _node_id: &NodeId, _event: ReputationEvent,
+        ) -> f64 {
+            0.0
+        }
+
+        fn get_peer_score(&self, _node_id: &NodeId) -> f64 { 0.0 }
+
+        fn get_peer_capabilities(
+            &self, _node_id: &NodeId,
+        ) -> Option<NodeCapabilities> {
+            None
+        }
+
+        fn set_snapshot_capability(&self, _snapshot: bool) {}
+    }
+
+    fn new_test_tx(
+        sender: &KeyPair, nonce: usize, data_size: usize,
+    ) -> Arc<SignedTransaction> {
+        Arc::new(
+            Transaction::from(NativeTransaction {
+                nonce: U256::from(nonce),
+                gas_price: U256::from(1),
+                gas: U256::from(50000),
+                action: Action::Call(Address::random()),
+                value: U256::zero(),
+                storage_limit: 0,
+                epoch_height: 0,
+                chain_id: 1,
+                data: vec![0; data_size],
+            })
+            .sign(sender.secret()),
+        )
+    }
+
+    fn new_peer_state(node_id: NodeId) -> SynchronizationPeerState {
+        let mut peer_state = SynchronizationPeerState {
+            node_id,
+            node_type: NodeType::Full,
+            is_validator: false,
+            protocol_version: ProtocolVersion(3),
+            genesis_hash: H256::zero(),
+            best_epoch: 0,
+            latest_block_hashes: HashSet::new(),
+            received_transaction_count: 0,
+            heartbeat: Instant::now(),
+            capabilities: Default::default(),
+            notified_capabilities: Default::default(),
+            throttling: Default::default(),
+            throttled_msgs: Default::default(),
+            score: 0.0,
+            request_stats: Default::default(),
+            known_transactions: Default::default(),
+            advertised: None,
+        };
+        peer_state
+            .capabilities
+            .insert(DynamicCapability::NormalPhase(true));
+        peer_state
+    }
+
+    #[test]
+    fn test_announce_transactions_to_peers() {
+        let db_dir = "./test_announce_transactions.db/";
+        let (sync_graph, consensus, _, _) = initialize_synchronization_graph(
+            db_dir,
+            1,
+            1,
+            1,
+            1,
+            50000,
+            DbType::Rocksdb,
+        );
+        let light_provider = Arc::new(LightProvider::new(
+            consensus.clone(),
+            sync_graph.clone(),
+            Weak::new(),
+            consensus.txpool.clone(),
+            None,
+            NodeType::Full,
+        ));
+        let protocol_config = ProtocolConfiguration {
+            received_tx_index_maintain_timeout: Duration::from_secs(300),
+            inflight_pending_tx_index_maintain_timeout: Duration::from_secs(30),
+            tx_maintained_for_peer_timeout: Duration::from_secs(600),
+            enable_tx_announcement: true,
+            tx_announcement_size_threshold: 512,
+            ..Default::default()
+        };
+        let state_sync_config = StateSyncConfiguration {
+            max_downloading_chunks: 1,
+            candidate_request_timeout: Duration::from_secs(10),
+            chunk_request_timeout: Duration::from_secs(10),
+            manifest_request_timeout: Duration::from_secs(10),
+        };
+        let handler = SynchronizationProtocolHandler::new(
+            NodeType::Full,
+            protocol_config,
+            state_sync_config,
+            SyncPhaseType::Normal,
+            sync_graph,
+            light_provider,
+            consensus,
+        );
+
+        let (push_peer, small_known_peer, announce_peer) =
+            (NodeId::random(), NodeId::random(), NodeId::random());
+        for peer in &[push_peer, small_known_peer, announce_peer] {
+            handler.syn.peer_connected(*peer, new_peer_state(*peer));
+        }
+
+        let sender = Random.generate().unwrap();
+        let small_tx = new_test_tx(&sender, 0, 0);
+        let small_known_tx = new_test_tx(&sender, 1, 0);
+        let large_tx = new_test_tx(&sender, 2, 1024);
+        handler
+            .syn
+            .mark_transactions_known(&small_known_peer, &[small_known_tx.hash]);
+        let transactions: HashMap<H256, Arc<SignedTransaction>> =
+            vec![small_tx.clone(), small_known_tx.clone(), large_tx.clone()]
+                .into_iter()
+                .map(|tx| (tx.hash, tx))
+                .collect();
+        handler.set_to_propagate_trans(transactions.clone());
+
+        let io = RecordingNetworkContext::default();
+        handler.announce_transactions_to_peers(&io, vec![push_peer]);
+
+        // Small transactions are pushed in full to the push peers, and the
+        // large one is announced by hash.
+        let mut pushed = io.take_transactions(&push_peer);
+        assert_eq!(pushed.len(), 1);
+        pushed[0].sort();
+        let mut expected = vec![small_tx.hash, small_known_tx.hash];
+        expected.sort();
+        assert_eq!(pushed[0], expected);
+        assert_eq!(io.take_digests(&push_peer), vec![(0, vec![large_tx.hash])]);
+
+        // The other peers get the small transactions by short ids, except
+        // the transactions they already know.
+        assert!(io.take_transactions(&small_known_peer).is_empty());
+        assert_eq!(
+            io.take_digests(&small_known_peer),
+            vec![(1, vec![large_tx.hash])]
+        );
+        assert!(io.take_transactions(&announce_peer).is_empty());
+        assert_eq!(
+            io.take_digests(&announce_peer),
+            vec![(2, vec![large_tx.hash])]
+        );
+        assert!(io.sent.lock().is_empty());
+
+        // The transactions sent are known by the peers, and not propagated to
+        // them again.
+        handler.set_to_propagate_trans(transactions);
+        handler.announce_transactions_to_peers(&io, vec![announce_peer]);
+        assert!(io.sent.lock().is_empty());
+
+        drop(handler);
+        while let Err(e) = fs::remove_dir_all(db_dir) {
+            println!("failed to remove directory {}, err = {:?}", db_dir, e);
+        }
+    }
+}
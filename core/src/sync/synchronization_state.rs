@@ -8,18 +8,24 @@ use crate::{
     message::MsgId,
     sync::{
         message::{DynamicCapability, DynamicCapabilitySet},
-        random, Error, ErrorKind,
+        random,
+        request_manager::{
+            peer_stats::PeerRequestStats, tx_handler::KnownTransactionFilter,
+        },
+        Error, ErrorKind,
     },
     NodeType,
 };
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use network::{
-    node_table::NodeId, service::ProtocolVersion, Error as NetworkError,
+    node_record::NodeCapabilities, node_table::NodeId,
+    service::ProtocolVersion, Error as NetworkError,
     ErrorKind as NetworkErrorKind,
 };
 use parking_lot::RwLock;
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, Rng};
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
@@ -58,6 +64,24 @@ pub struct SynchronizationPeerState {
     // should not send requests to the remote peer. Otherwise, the remote peer
     // may disconnect the TCP connection.
     pub throttled_msgs: ThrottledManager<MsgId>,
+
+    // Reputation score of the peer in network, which is used to prefer the
+    // peers of high score in peer selection. It is refreshed periodically
+    // and when an event of the peer is reported.
+    pub score: f64,
+
+    // Statistics of the requests sent to the peer, which is used to prefer
+    // the fast and reliable peers in peer selection.
+    pub request_stats: PeerRequestStats,
+
+    // Transactions recently sent to or received from the peer, which are
+    // not propagated to the peer again.
+    pub known_transactions: KnownTransactionFilter,
+
+    // Capabilities advertised in the discovery record of the peer, if any.
+    // It is refreshed periodically along with the score.
+    #[ignore_malloc_size_of = "small and short-lived"]
+    pub advertised: Option<NodeCapabilities>,
 }
 
 impl SynchronizationPeerState {
@@ -167,6 +191,55 @@ impl SynchronizationState {
         }
     }
 
+    /// Updates the cached reputation score for the specified peer. It takes no
+    /// effect if the peer is in handshaking status or not found.
+    pub fn update_peer_score(&self, node_id: &NodeId, score: f64) {
+        if let Some(state) = self.peers.read().get(node_id) {
+            state.write().score = score;
+        }
+    }
+
+    /// Updates the request statistics for the specified peer. It takes no
+    /// effect if the peer is in handshaking status or not found.
+    pub fn update_request_stats<F>(&self, node_id: &NodeId, f: F)
+    where F: FnOnce(&mut PeerRequestStats) {
+        if let Some(state) = self.peers.read().get(node_id) {
+            f(&mut state.write().request_stats);
+        }
+    }
+
+    /// Marks the transactions as known by the specified peer, so they will
+    /// not be propagated to the peer again. It takes no effect if the peer is
+    /// in handshaking status or not found.
+    pub fn mark_transactions_known<'a, I>(
+        &self, node_id: &NodeId, tx_hashes: I,
+    ) where I: IntoIterator<Item = &'a H256> {
+        if let Some(state) = self.peers.read().get(node_id) {
+            let mut state = state.write();
+            for tx_hash in tx_hashes {
+                state.known_transactions.insert(tx_hash);
+            }
+        }
+    }
+
+    /// Returns the requested items delivered per second by the peers that
+    /// have responded to the requests of `msg_id`.
+    pub fn request_items_per_sec(&self, msg_id: MsgId) -> Vec<f64> {
+        self.peers
+            .read()
+            .values()
+            .filter_map(|state| {
+                let state = state.read();
+                let stats = state.request_stats.get(msg_id)?;
+                if stats.responses > 0 {
+                    Some(stats.items_per_sec)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Updates the heartbeat for the specified peer. It takes no effect if the
     /// peer is in handshaking status or not found.
     pub fn update_heartbeat(&self, node_id: &NodeId) {
@@ -266,19 +339,29 @@ impl SynchronizationState {
     }
 }
 
+/// Weight of a peer in random peer selection, where a peer with 20 more
+/// reputation score is twice as likely to be selected.
+fn reputation_weight(score: f64) -> f64 { 2f64.powf(score / 20.0) }
+
 #[derive(Default)]
 /// Filter peers that match ``all'' the provided conditions.
 pub struct PeerFilter<'a> {
+    msg_id: Option<MsgId>,
     throttle_msg_ids: Option<HashSet<MsgId>>,
     preferred_node_type: Option<NodeType>,
     excludes: Option<HashSet<NodeId>>,
     choose_from: Option<&'a HashSet<NodeId>>,
     cap: Option<DynamicCapability>,
     min_best_epoch: Option<u64>,
+    snapshot: bool,
 }
 
 impl<'a> PeerFilter<'a> {
-    pub fn new(msg_id: MsgId) -> Self { PeerFilter::default().throttle(msg_id) }
+    pub fn new(msg_id: MsgId) -> Self {
+        let mut filter = PeerFilter::default().throttle(msg_id);
+        filter.msg_id = Some(msg_id);
+        filter
+    }
 
     pub fn with_preferred_node_type(mut self, node_type: NodeType) -> Self {
         self.preferred_node_type = Some(node_type);
@@ -315,7 +398,26 @@ impl<'a> PeerFilter<'a> {
         self
     }
 
+    /// Exclude the peers that advertise they do not serve state snapshots in
+    /// discovery. Peers without records are not excluded.
+    pub fn with_snapshot(mut self) -> Self {
+        self.snapshot = true;
+        self
+    }
+
     pub fn select_all(self, syn: &SynchronizationState) -> Vec<NodeId> {
+        self.select_all_with_weight(syn)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Returns the matched peers with their weights in random selection,
+    /// according to the reputation score, and the request statistics if
+    /// the filter is created for a request.
+    fn select_all_with_weight(
+        self, syn: &SynchronizationState,
+    ) -> Vec<(NodeId, f64)> {
         let mut peers = Vec::new();
 
         let check_state = self.throttle_msg_ids.is_some()
@@ -373,20 +475,45 @@ impl<'a> PeerFilter<'a> {
                 }
             }
 
-            peers.push(*id);
+            let peer = peer.read();
+            if self.snapshot
+                && peer.advertised.as_ref().map_or(false, |c| !c.snapshot)
+            {
+                continue;
+            }
+            let mut weight = reputation_weight(peer.score);
+            if let Some(msg_id) = self.msg_id {
+                weight *= peer.request_stats.weight(msg_id);
+            }
+            peers.push((*id, weight));
         }
 
         peers
     }
 
+    /// Selects a peer randomly, where the peers of higher reputation score, and
+    /// the faster and more reliable peers for the request are more likely to
+    /// be selected.
     pub fn select(self, syn: &SynchronizationState) -> Option<NodeId> {
-        self.select_all(syn).choose(&mut random::new()).cloned()
+        self.select_all_with_weight(syn)
+            .choose_weighted(&mut random::new(), |(_, weight)| *weight)
+            .ok()
+            .map(|(id, _)| *id)
     }
 
+    /// Selects `n` peers randomly, where the peers of higher reputation score,
+    /// and the faster and more reliable peers for the request are more likely
+    /// to be selected.
     pub fn select_n(self, n: usize, syn: &SynchronizationState) -> Vec<NodeId> {
-        let mut peers = self.select_all(syn);
-        peers.shuffle(&mut random::new());
-        peers.truncate(n);
-        peers
+        // weighted random sampling without replacement, which takes the peers
+        // of the largest `u ^ (1 / weight)` for `u` uniformly in [0, 1)
+        let mut rng = random::new();
+        let mut peers: Vec<(NodeId, f64)> = self
+            .select_all_with_weight(syn)
+            .into_iter()
+            .map(|(id, weight)| (id, rng.gen::<f64>().powf(1.0 / weight)))
+            .collect();
+        peers.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        peers.into_iter().take(n).map(|(id, _)| id).collect()
     }
 }
@@ -258,6 +258,7 @@ pub fn initialize_synchronization_graph_with_data_manager(
             get_logs_filter_max_block_number_range: None,
             sync_state_starting_epoch: None,
             sync_state_epoch_gap: None,
+            gas_price_oracle: Default::default(),
         },
         txpool.clone(),
         statistics.clone(),
@@ -267,6 +268,7 @@ pub fn initialize_synchronization_graph_with_data_manager(
         notifications.clone(),
         ConsensusExecutionConfiguration {
             executive_trace: false,
+            parallel_execution: false,
         },
         verification_config.clone(),
         NodeType::Archive,
@@ -14,7 +14,10 @@ mod transaction_pool_inner;
 
 extern crate rand;
 
-pub use self::{impls::TreapMap, transaction_pool_inner::TransactionStatus};
+pub use self::{
+    impls::TreapMap,
+    transaction_pool_inner::{ReadyPriceBucket, TransactionStatus},
+};
 use crate::{
     block_data_manager::BlockDataManager, consensus::BestInformation,
     machine::Machine, state::State, verification::VerificationConfig,
@@ -29,7 +32,7 @@ use account_cache::AccountCache;
 use cfx_parameters::block::DEFAULT_TARGET_BLOCK_GAS_LIMIT;
 use cfx_statedb::{Result as StateDbResult, StateDb};
 use cfx_storage::{StateIndex, StorageManagerTrait};
-use cfx_types::{AddressWithSpace as Address, AllChainID, H256, U256};
+use cfx_types::{AddressWithSpace as Address, AllChainID, Space, H256, U256};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use metrics::{
     register_meter_with_group, Gauge, GaugeUsize, Lock, Meter, MeterTimer,
@@ -125,6 +128,11 @@ pub struct TransactionPool {
     set_tx_requests: Mutex<Vec<Arc<SignedTransaction>>>,
     recycle_tx_requests: Mutex<Vec<Arc<SignedTransaction>>>,
     machine: Arc<Machine>,
+    /// The ready price buckets of each space computed for a best block. They
+    /// are only recomputed when the best block changes, so estimating the gas
+    /// price does not walk the ready pool for every request.
+    ready_price_buckets_cache:
+        Mutex<HashMap<Space, (H256, Vec<ReadyPriceBucket>)>>,
 
     /// If it's `false`, operations on the tx pool will be ignored to save
     /// memory/CPU cost.
@@ -190,6 +198,7 @@ impl TransactionPool {
             set_tx_requests: Mutex::new(Default::default()),
             recycle_tx_requests: Mutex::new(Default::default()),
             machine,
+            ready_price_buckets_cache: Default::default(),
             ready_for_mining: AtomicBool::new(false),
         }
     }
@@ -667,6 +676,22 @@ impl TransactionPool {
         inner.total_unpacked()
     }
 
+    /// The ready transactions of `space` grouped by gas price, in the
+    /// descending order of gas price. The result is cached until the best
+    /// block changes.
+    pub fn ready_price_buckets(&self, space: Space) -> Vec<ReadyPriceBucket> {
+        let best_block_hash = self.consensus_best_info.lock().best_block_hash;
+        let mut cache = self.ready_price_buckets_cache.lock();
+        if let Some((block_hash, buckets)) = cache.get(&space) {
+            if *block_hash == best_block_hash {
+                return buckets.clone();
+            }
+        }
+        let buckets = self.inner.read().ready_price_buckets(space);
+        cache.insert(space, (best_block_hash, buckets.clone()));
+        buckets
+    }
+
     /// stats retrieves the length of ready and deferred pool.
     pub fn stats(&self) -> (usize, usize, usize, usize) {
         let inner = self.inner.read();
@@ -25,8 +25,8 @@ use primitives::{
 use rlp::*;
 use serde::Serialize;
 use std::{
-    cmp::{Ordering, Reverse},
-    collections::HashMap,
+    cmp::{max, min, Ordering, Reverse},
+    collections::{BTreeMap, HashMap},
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -38,6 +38,10 @@ lazy_static! {
 
 const FURTHEST_FUTURE_TRANSACTION_NONCE_OFFSET: u32 = 2000;
 
+/// The number of the highest bits kept when grouping gas prices into buckets,
+/// so the gas prices in a bucket differ by less than 1/8.
+const PRICE_BUCKET_SIGNIFICANT_BITS: usize = 4;
+
 lazy_static! {
     static ref TX_POOL_RECALCULATE: Arc<dyn Meter> =
         register_meter_with_group("timer", "tx_pool::recalculate");
@@ -336,6 +340,14 @@ impl SpacedReadyAccountPool {
 
     fn len(&self) -> usize { self.packing_pool.len() + self.waiting_pool.len() }
 
+    fn iter(&self) -> impl Iterator<Item = &Arc<SignedTransaction>> {
+        self.packing_pool
+            .treap
+            .iter()
+            .map(|(_, tx)| tx)
+            .chain(self.waiting_pool.iter().map(|(_, tx)| &tx.0))
+    }
+
     #[cfg(test)]
     fn top(&self) -> Option<Arc<SignedTransaction>> { self.packing_pool.top() }
 }
@@ -501,6 +513,13 @@ impl ReadyAccountPool {
 
     fn len(&self) -> usize { self.native_pool.len() + self.evm_pool.len() }
 
+    fn spaced_pool(&self, space: Space) -> &SpacedReadyAccountPool {
+        match space {
+            Space::Native => &self.native_pool,
+            Space::Ethereum => &self.evm_pool,
+        }
+    }
+
     fn get(
         &self, address: &AddressWithSpace,
     ) -> Option<Arc<SignedTransaction>> {
@@ -607,6 +626,24 @@ impl ReadyAccountPool {
     }
 }
 
+/// The ready transactions of a space whose gas prices are in
+/// `[min_price, max_price]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadyPriceBucket {
+    pub min_price: U256,
+    pub max_price: U256,
+    pub tx_count: usize,
+    /// The total gas limit of the transactions.
+    pub total_gas: U256,
+}
+
+/// Round the gas price down to its highest `PRICE_BUCKET_SIGNIFICANT_BITS`
+/// bits.
+fn price_bucket(price: &U256) -> U256 {
+    let shift = price.bits().saturating_sub(PRICE_BUCKET_SIGNIFICANT_BITS);
+    (*price >> shift) << shift
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum TransactionStatus {
@@ -688,6 +725,33 @@ impl TransactionPoolInner {
 
     pub fn total_received(&self) -> usize { self.total_received_count }
 
+    /// Group the ready transactions of `space` by their gas prices, in the
+    /// descending order of gas price. Only the first unpacked transaction of
+    /// each account is ready.
+    pub fn ready_price_buckets(&self, space: Space) -> Vec<ReadyPriceBucket> {
+        let mut buckets = BTreeMap::new();
+        for tx in self.ready_account_pool.spaced_pool(space).iter() {
+            let price = tx.gas_price();
+            let bucket = buckets.entry(price_bucket(price)).or_insert(
+                ReadyPriceBucket {
+                    min_price: *price,
+                    max_price: *price,
+                    tx_count: 0,
+                    total_gas: U256::zero(),
+                },
+            );
+            bucket.min_price = min(bucket.min_price, *price);
+            bucket.max_price = max(bucket.max_price, *price);
+            bucket.tx_count += 1;
+            bucket.total_gas += *tx.gas();
+        }
+        buckets
+            .into_iter()
+            .rev()
+            .map(|(_, bucket)| bucket)
+            .collect()
+    }
+
     pub fn total_unpacked(&self) -> usize { self.unpacked_transaction_count }
 
     pub fn get(&self, tx_hash: &H256) -> Option<Arc<SignedTransaction>> {
@@ -1288,19 +1352,15 @@ impl TransactionPoolInner {
         &self, address: Option<AddressWithSpace>,
     ) -> (Vec<Arc<SignedTransaction>>, Vec<Arc<SignedTransaction>>) {
         let ready_txs = match address {
-            Some(addr) => {
-                let spaced_pool = match addr.space {
-                    Space::Native => &self.ready_account_pool.native_pool,
-                    Space::Ethereum => &self.ready_account_pool.evm_pool,
-                };
-                spaced_pool
-                    .packing_pool
-                    .treap
-                    .iter()
-                    .filter(|address_tx| addr.address == *address_tx.0)
-                    .map(|(_, tx)| tx.clone())
-                    .collect()
-            }
+            Some(addr) => self
+                .ready_account_pool
+                .spaced_pool(addr.space)
+                .packing_pool
+                .treap
+                .iter()
+                .filter(|address_tx| addr.address == *address_tx.0)
+                .map(|(_, tx)| tx.clone())
+                .collect(),
             None => self
                 .ready_account_pool
                 .native_pool
@@ -1455,9 +1515,12 @@ impl TransactionPoolInner {
 
 #[cfg(test)]
 mod test_transaction_pool_inner {
-    use super::{DeferredPool, InsertResult, TxWithReadyInfo};
+    use super::{
+        price_bucket, DeferredPool, InsertResult, ReadyPriceBucket,
+        TxWithReadyInfo,
+    };
     use crate::transaction_pool::transaction_pool_inner::ReadyAccountPool;
-    use cfx_types::{Address, AddressSpaceUtil, U256};
+    use cfx_types::{Address, AddressSpaceUtil, Space, U256};
     use keylib::{Generator, KeyPair, Random};
     use primitives::{
         Action, NativeTransaction, SignedTransaction, Transaction,
@@ -1789,4 +1852,37 @@ mod test_transaction_pool_inner {
         assert_eq!(ready_pool.native_pool.packing_pool.len(), 0);
         assert_eq!(ready_pool.native_pool.waiting_pool.len(), 0);
     }
+
+    #[test]
+    fn test_ready_price_buckets() {
+        assert_eq!(price_bucket(&U256::from(0)), U256::from(0));
+        assert_eq!(price_bucket(&U256::from(15)), U256::from(15));
+        assert_eq!(price_bucket(&U256::from(17)), U256::from(16));
+        assert_eq!(price_bucket(&U256::from(1_000_000)), U256::from(983_040));
+
+        // The packing pool holds one transaction and the others wait.
+        let mut ready_pool = ReadyAccountPool::new(1, 1, 50001.into());
+        for gas_price in &[1000, 1010, 20, 21, 5] {
+            let sender = Random.generate().unwrap();
+            ready_pool.update(
+                &sender.address().with_native_space(),
+                Some(new_test_tx(&sender, 0, *gas_price, 0)),
+            );
+        }
+        let mut inner = super::TransactionPoolInner::new(1000, 1, 1, 0.into());
+        inner.ready_account_pool = ready_pool;
+        let bucket = |min_price: usize, max_price: usize, tx_count: usize| {
+            ReadyPriceBucket {
+                min_price: min_price.into(),
+                max_price: max_price.into(),
+                tx_count,
+                total_gas: U256::from(50000 * tx_count),
+            }
+        };
+        assert_eq!(
+            inner.ready_price_buckets(Space::Native),
+            vec![bucket(1000, 1010, 2), bucket(20, 21, 2), bucket(5, 5, 1)]
+        );
+        assert!(inner.ready_price_buckets(Space::Ethereum).is_empty());
+    }
 }
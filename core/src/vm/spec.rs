@@ -149,6 +149,15 @@ pub struct Spec {
     pub cip78b: bool,
     /// CIP-90: A Space that Fully EVM Compatible
     pub cip90: bool,
+    /// Enable `create2EVM` in the cross-space internal contract for
+    /// deterministic contract creation in the EVM space
+    pub cip_create2_evm: bool,
+    /// Enable the internal contract in the EVM space for read-only access to
+    /// the Conflux space
+    pub cip_core_space_reader: bool,
+    /// Keep a bounded history of state roots, pivot block hashes and PoS
+    /// block ids readable through the context internal contracts
+    pub cip_epoch_history: bool,
 }
 
 /// Wasm cost table
@@ -282,6 +291,9 @@ impl Spec {
             cip90: false,
             cip78a: false,
             cip78b: false,
+            cip_create2_evm: false,
+            cip_core_space_reader: false,
+            cip_epoch_history: false,
             evm_gas_ratio: 2,
         }
     }
@@ -299,6 +311,12 @@ impl Spec {
         spec.cip90 = number >= params.transition_numbers.cip90b;
         spec.cip78a = number >= params.transition_numbers.cip78a;
         spec.cip78b = number >= params.transition_numbers.cip78b;
+        spec.cip_create2_evm =
+            number >= params.transition_numbers.cip_create2_evm;
+        spec.cip_core_space_reader =
+            number >= params.transition_numbers.cip_core_space_reader;
+        spec.cip_epoch_history =
+            number >= params.transition_numbers.cip_epoch_history;
         spec
     }
 
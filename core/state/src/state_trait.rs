@@ -254,6 +254,13 @@ pub trait StateOpsTrait {
         owner: Address,
     ) -> DbResult<()>;
 
+    /// Sets a storage entry of an internal contract in the Conflux space
+    /// outside transactions. The entry is owned by the contract itself and
+    /// occupies no storage collateral.
+    fn set_system_storage(
+        &mut self, address: &Address, key: Vec<u8>, value: U256,
+    ) -> DbResult<()>;
+
     fn update_pos_status(
         &mut self, identifier: H256, number: u64,
     ) -> DbResult<()>;
@@ -22,7 +22,7 @@ pub use self::{
     statedb_ext::{
         StateDbExt, ACCUMULATE_INTEREST_RATE_KEY,
         DISTRIBUTABLE_POS_INTEREST_KEY, INTEREST_RATE_KEY,
-        LAST_DISTRIBUTE_BLOCK_KEY, TOTAL_BANK_TOKENS_KEY,
+        LAST_DISTRIBUTE_BLOCK_KEY, TOTAL_BANK_TOKENS_KEY, TOTAL_EVM_TOKENS_KEY,
         TOTAL_POS_STAKING_TOKENS_KEY, TOTAL_STORAGE_TOKENS_KEY,
         TOTAL_TOKENS_KEY,
     },
@@ -70,6 +70,14 @@ mod impls {
         fn get_original_storage_root_with_proof(
             &self, address: &AddressWithSpace,
         ) -> Result<(StorageRoot, StorageRootProof)>;
+
+        /// Read at most `limit` key/values prefixed by `key_prefix` in key
+        /// order starting from `start_key`, see
+        /// [`StorageStateTraitExt::read_range`].
+        fn get_original_range(
+            &self, key_prefix: StorageKeyWithSpace, start_key: Option<&[u8]>,
+            limit: usize,
+        ) -> Result<(Vec<MptKeyValue>, Option<Vec<u8>>)>;
     }
 
     pub trait StateDbCheckpointMethods {
@@ -548,6 +556,16 @@ mod impls {
                 .get_node_merkle_all_versions::<WithProof>(key)
                 .map_err(Into::into)
         }
+
+        fn get_original_range(
+            &self, key_prefix: StorageKeyWithSpace, start_key: Option<&[u8]>,
+            limit: usize,
+        ) -> Result<(Vec<MptKeyValue>, Option<Vec<u8>>)>
+        {
+            self.storage
+                .read_range(key_prefix, start_key, limit)
+                .map_err(Into::into)
+        }
     }
 
     impl<Storage: StorageStateTrait> StateDbCheckpointMethods for StateDb<Storage> {
@@ -154,9 +154,11 @@ impl State {
         ))
     }
 
+    /// A `TombStone` value means that the key is deleted in the delta tries,
+    /// which hides the value in the snapshot.
     fn get_from_all_tries<WithProof: StaticBool>(
         &self, access_key: StorageKeyWithSpace,
-    ) -> Result<(Option<Box<[u8]>>, StateProof)> {
+    ) -> Result<(MptValue<Box<[u8]>>, StateProof)> {
         let mut proof = StateProof::default();
 
         let (maybe_value, maybe_delta_proof) = self
@@ -168,14 +170,8 @@ impl State {
             )?;
         proof.with_delta(maybe_delta_proof);
 
-        match maybe_value {
-            MptValue::Some(value) => {
-                return Ok((Some(value), proof));
-            }
-            MptValue::TombStone => {
-                return Ok((None, proof));
-            }
-            _ => {}
+        if maybe_value != MptValue::None {
+            return Ok((maybe_value, proof));
         }
 
         // FIXME This is for the case of read-only access of the first snapshot
@@ -198,14 +194,8 @@ impl State {
 
                 proof.with_intermediate(maybe_proof);
 
-                match maybe_value {
-                    MptValue::Some(value) => {
-                        return Ok((Some(value), proof));
-                    }
-                    MptValue::TombStone => {
-                        return Ok((None, proof));
-                    }
-                    _ => {}
+                if maybe_value != MptValue::None {
+                    return Ok((maybe_value, proof));
                 }
             }
         }
@@ -214,7 +204,13 @@ impl State {
             self.get_from_snapshot::<WithProof>(&access_key.to_key_bytes())?;
         proof.with_snapshot(maybe_proof);
 
-        Ok((maybe_value, proof))
+        Ok((maybe_value.into(), proof))
+    }
+
+    fn delete_in_delta(
+        &mut self, access_key: StorageKeyWithSpace,
+    ) -> Result<()> {
+        self.set(access_key, MptValue::<Box<[u8]>>::TombStone.unwrap())
     }
 }
 
@@ -232,8 +228,14 @@ impl StateTrait for State {
     ) -> Result<Option<Box<[u8]>>> {
         self.ensure_temp_slab_for_db_load();
 
-        self.get_from_all_tries::<NoProof>(access_key)
-            .map(|(value, _)| value)
+        match self.get_from_all_tries::<NoProof>(access_key)?.0 {
+            MptValue::Some(value) => Ok(Some(value)),
+            MptValue::TombStone => Ok(None),
+            MptValue::None => match self.manager.forked_state() {
+                Some(forked_state) => forked_state.get(access_key),
+                None => Ok(None),
+            },
+        }
     }
 
     fn set(
@@ -257,7 +259,11 @@ impl StateTrait for State {
     }
 
     fn delete(&mut self, access_key: StorageKeyWithSpace) -> Result<()> {
-        self.set(access_key, MptValue::<Box<[u8]>>::TombStone.unwrap())?;
+        self.delete_in_delta(access_key)?;
+        if let Some(forked_state) = self.manager.forked_state() {
+            forked_state
+                .on_deleted(access_key, /* is_prefix = */ false)?;
+        }
         Ok(())
     }
 
@@ -399,7 +405,7 @@ impl StateTrait for State {
                 let storage_key = StorageKeyWithSpace::from_delta_mpt_key(&k);
                 // Only delete non-empty keys.
                 if v.len() > 0 && !AM::is_read_only() {
-                    self.delete(storage_key)?;
+                    self.delete_in_delta(storage_key)?;
                 }
                 let k = storage_key.to_key_bytes();
                 if !deleted_keys.contains(&k) {
@@ -417,13 +423,22 @@ impl StateTrait for State {
             let storage_key =
                 StorageKeyWithSpace::from_key_bytes::<SkipInputCheck>(&k);
             if !AM::is_read_only() {
-                self.delete(storage_key)?;
+                self.delete_in_delta(storage_key)?;
             }
             if !deleted_keys.contains(&k) {
                 result.push((k, v));
             }
         }
 
+        if !AM::is_read_only() {
+            if let Some(forked_state) = self.manager.forked_state() {
+                forked_state.on_deleted(
+                    access_key_prefix,
+                    /* is_prefix = */ true,
+                )?;
+            }
+        }
+
         if result.is_empty() {
             Ok(None)
         } else {
@@ -503,7 +518,13 @@ impl StateTraitExt for State {
         self.ensure_temp_slab_for_db_load();
 
         self.check_freshly_synced_snapshot("proof")?;
-        self.get_from_all_tries::<WithProof>(access_key)
+        let (maybe_value, proof) =
+            self.get_from_all_tries::<WithProof>(access_key)?;
+        let value = match maybe_value {
+            MptValue::Some(value) => Some(value),
+            _ => None,
+        };
+        Ok((value, proof))
     }
 
     fn get_node_merkle_all_versions<WithProof: StaticBool>(
@@ -639,9 +660,169 @@ impl StateTraitExt for State {
 
         Ok((triplet, proof))
     }
+
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace,
+        start_key: Option<&[u8]>, limit: usize,
+    ) -> Result<(Vec<MptKeyValue>, Option<Vec<u8>>)>
+    {
+        self.ensure_temp_slab_for_db_load();
+
+        let lower_bound_incl = access_key_prefix.to_key_bytes();
+        let upper_bound_excl =
+            to_key_prefix_iter_upper_bound(&lower_bound_incl);
+        let start_key = match start_key {
+            Some(start_key) if start_key > lower_bound_incl.as_slice() => {
+                start_key.to_vec()
+            }
+            _ => lower_bound_incl.clone(),
+        };
+
+        // Keys in delta tries are padded with the hashes of the storage keys,
+        // so unlike the snapshot, they can't be seeked to `start_key`. The
+        // delta tries only hold the changes since the last snapshot, so we
+        // traverse the prefix, and only sort the values needed for this page.
+        let mut delta_kvs = HashMap::new();
+        if let (Some(intermediate_trie), Some(intermediate_key_padding)) = (
+            self.maybe_intermediate_trie.as_ref(),
+            self.maybe_intermediate_trie_key_padding.as_ref(),
+        ) {
+            Self::traverse_delta(
+                intermediate_trie,
+                self.intermediate_trie_root.clone(),
+                &access_key_prefix
+                    .to_delta_mpt_key_bytes(intermediate_key_padding),
+                &start_key,
+                &mut delta_kvs,
+            )?;
+        }
+        // Values in the delta trie override the intermediate trie.
+        Self::traverse_delta(
+            &self.delta_trie,
+            self.delta_trie_root.clone(),
+            &access_key_prefix
+                .to_delta_mpt_key_bytes(&self.delta_trie_key_padding),
+            &start_key,
+            &mut delta_kvs,
+        )?;
+        let mut delta_kvs =
+            Self::first_delta_kvs(delta_kvs, limit.saturating_add(1))
+                .into_iter();
+
+        let mut kv_iterator = self.snapshot_db.snapshot_kv_iterator()?.take();
+        let mut snapshot_kvs = kv_iterator
+            .iter_range(
+                start_key.as_slice(),
+                upper_bound_excl.as_ref().map(|v| &**v),
+            )?
+            .take();
+
+        // Merge the two sorted sequences, delta values take precedence.
+        let mut result = Vec::new();
+        let mut next_key = None;
+        let mut maybe_delta_kv = delta_kvs.next();
+        let mut maybe_snapshot_kv = snapshot_kvs.next()?;
+        loop {
+            let (key, value) =
+                match (maybe_delta_kv.take(), maybe_snapshot_kv.take()) {
+                    (None, None) => break,
+                    (Some(delta_kv), None) => {
+                        maybe_delta_kv = delta_kvs.next();
+                        delta_kv
+                    }
+                    (None, Some(snapshot_kv)) => {
+                        maybe_snapshot_kv = snapshot_kvs.next()?;
+                        snapshot_kv
+                    }
+                    (Some(delta_kv), Some(snapshot_kv)) => {
+                        if delta_kv.0 < snapshot_kv.0 {
+                            maybe_delta_kv = delta_kvs.next();
+                            maybe_snapshot_kv = Some(snapshot_kv);
+                            delta_kv
+                        } else if delta_kv.0 == snapshot_kv.0 {
+                            maybe_delta_kv = delta_kvs.next();
+                            maybe_snapshot_kv = snapshot_kvs.next()?;
+                            delta_kv
+                        } else {
+                            maybe_delta_kv = Some(delta_kv);
+                            maybe_snapshot_kv = snapshot_kvs.next()?;
+                            snapshot_kv
+                        }
+                    }
+                };
+            // Skip the tombStone values from delta tries.
+            if value.len() == 0 {
+                continue;
+            }
+            if result.len() == limit {
+                next_key = Some(key);
+                break;
+            }
+            result.push((key, value));
+        }
+
+        Ok((result, next_key))
+    }
 }
 
 impl State {
+    /// Collect the key/value pairs under `delta_mpt_key_prefix` whose
+    /// original key bytes are not less than `start_key` into `kvs`, keyed by
+    /// the original key bytes.
+    fn traverse_delta(
+        mpt: &DeltaMpt, maybe_root_node: Option<NodeRefDeltaMpt>,
+        delta_mpt_key_prefix: &[u8], start_key: &[u8],
+        kvs: &mut HashMap<Vec<u8>, Box<[u8]>>,
+    ) -> Result<()>
+    {
+        let root_node = match maybe_root_node {
+            None => return Ok(()),
+            Some(root_node) => root_node,
+        };
+        // Traversal won't create any new nodes so it's fine to pass an empty
+        // owned_node_set.
+        let mut empty_owned_node_set: Option<OwnedNodeSet> =
+            Some(Default::default());
+        let maybe_kvs =
+            SubTrieVisitor::new(mpt, root_node, &mut empty_owned_node_set)?
+                .traversal(delta_mpt_key_prefix, delta_mpt_key_prefix)?;
+        for (k, v) in maybe_kvs.unwrap_or_default() {
+            let key =
+                StorageKeyWithSpace::from_delta_mpt_key(&k).to_key_bytes();
+            if key.as_slice() >= start_key {
+                kvs.insert(key, v);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the first `count` values of `kvs` in ascending order of keys,
+    /// together with the tombstones before the last of them. They are enough
+    /// to merge `count - 1` values with the snapshot and find the next key.
+    fn first_delta_kvs(
+        kvs: HashMap<Vec<u8>, Box<[u8]>>, count: usize,
+    ) -> Vec<MptKeyValue> {
+        let mut value_keys: Vec<&Vec<u8>> = kvs
+            .iter()
+            .filter(|(_, value)| value.len() != 0)
+            .map(|(key, _)| key)
+            .collect();
+        let max_key = if value_keys.len() > count {
+            Some(value_keys.select_nth_unstable(count - 1).1.to_vec())
+        } else {
+            None
+        };
+        let mut kvs: Vec<MptKeyValue> = kvs
+            .into_iter()
+            .filter(|(key, _)| {
+                max_key.as_ref().map_or(true, |max_key| key <= max_key)
+            })
+            .collect();
+        kvs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        kvs
+    }
+
     fn ensure_temp_slab_for_db_load(&self) {
         self.delta_trie.get_node_memory_manager().enlarge().ok();
     }
@@ -923,7 +1104,7 @@ use primitives::{
 use rustc_hex::ToHex;
 use std::{
     cell::UnsafeCell,
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     hint::unreachable_unchecked,
     sync::{atomic::Ordering, Arc},
 };
@@ -36,6 +36,9 @@ pub struct StateTrees {
 #[derive(MallocSizeOfDerive)]
 pub struct StateManager {
     storage_manager: Arc<StorageManager>,
+    /// The keys missing in the local state are read from it in fork mode.
+    #[ignore_malloc_size_of = "insignificant"]
+    forked_state: Option<Arc<dyn ForkedStateTrait>>,
     pub number_committed_nodes: AtomicUsize,
 }
 
@@ -51,10 +54,22 @@ impl StateManager {
 
         Ok(Self {
             storage_manager,
+            forked_state: None,
             number_committed_nodes: Default::default(),
         })
     }
 
+    pub fn with_forked_state(
+        mut self, forked_state: Arc<dyn ForkedStateTrait>,
+    ) -> Self {
+        self.forked_state = Some(forked_state);
+        self
+    }
+
+    pub fn forked_state(&self) -> Option<&Arc<dyn ForkedStateTrait>> {
+        self.forked_state.as_ref()
+    }
+
     pub fn log_usage(&self) {
         self.storage_manager.log_usage();
         debug!(
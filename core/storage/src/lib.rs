@@ -177,8 +177,8 @@ pub use self::{
         },
     },
     state::{
-        State as StorageState, StateTrait as StorageStateTrait,
-        StateTraitExt as StorageStateTraitExt,
+        ForkedStateTrait, State as StorageState,
+        StateTrait as StorageStateTrait, StateTraitExt as StorageStateTraitExt,
     },
     state_manager::{
         StateIndex, StateManager as StorageManager,
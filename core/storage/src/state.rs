@@ -53,6 +53,33 @@ pub trait StateTraitExt {
     fn get_node_merkle_all_versions<WithProof: StaticBool>(
         &self, access_key: StorageKeyWithSpace,
     ) -> Result<(NodeMerkleTriplet, NodeMerkleProof)>;
+
+    /// Read the key/value pairs prefixed by `access_key_prefix` in ascending
+    /// order of their key bytes, starting from `start_key` (inclusive).
+    ///
+    /// At most `limit` pairs are returned. If there are more pairs in the
+    /// range, the key of the first pair left out is returned, which can be
+    /// used as `start_key` to read the next page.
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace,
+        start_key: Option<&[u8]>, limit: usize,
+    ) -> Result<(Vec<MptKeyValue>, Option<Vec<u8>>)>;
+}
+
+/// The state of a remote chain which a local chain is forked from. It serves
+/// the keys which are not in the local state, so the local chain runs on top
+/// of the remote state without copying it.
+pub trait ForkedStateTrait: Send + Sync {
+    /// Read `access_key` from the remote state.
+    fn get(&self, access_key: StorageKeyWithSpace)
+        -> Result<Option<Box<[u8]>>>;
+
+    /// Called when `access_key` is deleted from the local state, or all the
+    /// keys prefixed by it if `is_prefix` is true. The deleted keys are no
+    /// longer read from the remote state.
+    fn on_deleted(
+        &self, access_key: StorageKeyWithSpace, is_prefix: bool,
+    ) -> Result<()>;
 }
 
 use super::{
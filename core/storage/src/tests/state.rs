@@ -562,6 +562,235 @@ fn test_set_delete_all() {
     assert_eq!(state_root, empty_state_root);
 }
 
+#[test]
+fn test_read_range() {
+    let state_manager = new_state_manager_for_unit_test();
+    let address = Address::from_low_u64_be(1);
+    let other_address = Address::from_low_u64_be(2);
+    let keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS / 10);
+    let (keys_0, keys_1) = (
+        &keys[0..TEST_NUMBER_OF_KEYS / 20],
+        &keys[TEST_NUMBER_OF_KEYS / 20..],
+    );
+    let mut expected = BTreeMap::new();
+
+    let mut state = state_manager.get_state_for_genesis_write();
+    for key in keys_0 {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state.set(storage_key, key[..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key.clone());
+        state
+            .set(
+                StorageKey::new_storage_key(&other_address, key)
+                    .with_native_space(),
+                key[..].into(),
+            )
+            .unwrap();
+    }
+    let mut epoch_id = H256::default();
+    epoch_id.as_bytes_mut()[0] = 1;
+    state.compute_state_root().unwrap();
+    state.commit(epoch_id).unwrap();
+
+    // Overwrite and delete some of the committed keys in the next epoch.
+    let mut state = state_manager
+        .get_state_for_next_epoch(StateIndex::new_for_test_only_delta_mpt(
+            &epoch_id,
+        ))
+        .unwrap()
+        .unwrap();
+    for (i, key) in keys_0.iter().enumerate() {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        if i % 3 == 0 {
+            state.delete(storage_key).unwrap();
+            expected.remove(&storage_key.to_key_bytes());
+        } else if i % 3 == 1 {
+            state.set(storage_key, key[1..].into()).unwrap();
+            expected.insert(storage_key.to_key_bytes(), key[1..].to_vec());
+        }
+    }
+    for key in keys_1 {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state.set(storage_key, key[..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key.clone());
+    }
+    epoch_id.as_bytes_mut()[0] = 2;
+    state.compute_state_root().unwrap();
+    state.commit(epoch_id).unwrap();
+
+    let state = state_manager
+        .get_state_no_commit(
+            StateIndex::new_for_test_only_delta_mpt(&epoch_id),
+            /* try_open = */ false,
+        )
+        .unwrap()
+        .unwrap();
+    let prefix = StorageKey::new_storage_root_key(&address).with_native_space();
+    assert_eq!(read_range_by_pages(&state, prefix, 7), expected);
+}
+
+// Keys of the read range are split across the tries:
+//                    [snapshot] [intermediate] [delta]
+// keys_0 are set in      X       1/3 overwritten  1/3 deleted, 1/3 overwritten
+// keys_1 are set in                   X           1/2 deleted
+// keys_2 are set in                               X
+#[test]
+fn test_read_range_across_tries() {
+    let snapshot_epoch_count = 1;
+    let state_manager =
+        new_state_manager_for_unit_test_with_snapshot_epoch_count(
+            snapshot_epoch_count,
+        );
+    let address = Address::from_low_u64_be(1);
+    let other_address = Address::from_low_u64_be(2);
+    let keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS / 10);
+    let portion = keys.len() / 3;
+    let (keys_0, keys_1, keys_2) = (
+        &keys[0..portion],
+        &keys[portion..portion * 2],
+        &keys[portion * 2..],
+    );
+    let mut expected = BTreeMap::new();
+
+    let mut state_0 = state_manager.get_state_for_genesis_write();
+    for key in keys_0 {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state_0.set(storage_key, key[..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key.clone());
+        state_0
+            .set(
+                StorageKey::new_storage_key(&other_address, key)
+                    .with_native_space(),
+                key[..].into(),
+            )
+            .unwrap();
+    }
+    let mut epoch_id_0 = H256::default();
+    epoch_id_0.as_bytes_mut()[0] = 1;
+    let root_0 = state_0.compute_state_root().unwrap();
+    state_0.commit(epoch_id_0).unwrap();
+
+    let mut state_1 = state_manager
+        .get_state_for_next_epoch(StateIndex::new_for_next_epoch(
+            &epoch_id_0,
+            &root_0,
+            1,
+            snapshot_epoch_count,
+        ))
+        .unwrap()
+        .unwrap();
+    for key in keys_0.iter().step_by(3) {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state_1.set(storage_key, key[1..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key[1..].to_vec());
+    }
+    for key in keys_1 {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state_1.set(storage_key, key[..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key.clone());
+    }
+    let mut epoch_id_1 = H256::default();
+    epoch_id_1.as_bytes_mut()[0] = 2;
+    let root_1 = state_1.compute_state_root().unwrap();
+    state_1.commit(epoch_id_1).unwrap();
+
+    let mut state_2 = state_manager
+        .get_state_for_next_epoch(StateIndex::new_for_next_epoch(
+            &epoch_id_1,
+            &root_1,
+            2,
+            snapshot_epoch_count,
+        ))
+        .unwrap()
+        .unwrap();
+    for (i, key) in keys_0.iter().enumerate() {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        if i % 3 == 1 {
+            state_2.delete(storage_key).unwrap();
+            expected.remove(&storage_key.to_key_bytes());
+        } else if i % 3 == 2 {
+            state_2.set(storage_key, key[2..].into()).unwrap();
+            expected.insert(storage_key.to_key_bytes(), key[2..].to_vec());
+        }
+    }
+    for key in keys_1.iter().step_by(2) {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state_2.delete(storage_key).unwrap();
+        expected.remove(&storage_key.to_key_bytes());
+    }
+    for key in keys_2 {
+        let storage_key =
+            StorageKey::new_storage_key(&address, key).with_native_space();
+        state_2.set(storage_key, key[..].into()).unwrap();
+        expected.insert(storage_key.to_key_bytes(), key.clone());
+    }
+    let mut epoch_id_2 = H256::default();
+    epoch_id_2.as_bytes_mut()[0] = 3;
+    let root_2 = state_2.compute_state_root().unwrap();
+    state_2.commit(epoch_id_2).unwrap();
+
+    let state = state_manager
+        .get_state_for_next_epoch(StateIndex::new_for_next_epoch(
+            &epoch_id_2,
+            &root_2,
+            3,
+            snapshot_epoch_count,
+        ))
+        .unwrap()
+        .unwrap();
+
+    // The keys deleted in the delta trie are still in the snapshot.
+    let deleted_key = StorageKey::new_storage_key(&address, &keys_0[1])
+        .with_native_space()
+        .to_key_bytes();
+    assert!(state
+        .get_from_snapshot::<NoProof>(&deleted_key)
+        .unwrap()
+        .0
+        .is_some());
+    assert!(!expected.contains_key(&deleted_key));
+
+    let prefix = StorageKey::new_storage_root_key(&address).with_native_space();
+    assert_eq!(read_range_by_pages(&state, prefix, 7), expected);
+    assert_eq!(read_range_by_pages(&state, prefix, 1), expected);
+}
+
+/// Read all the key/value pairs under `prefix` with `read_range`, `limit`
+/// values at a time.
+fn read_range_by_pages(
+    state: &State, prefix: StorageKeyWithSpace, limit: usize,
+) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    let mut read = BTreeMap::new();
+    let mut start_key = None;
+    loop {
+        let (kvs, next_key) = state
+            .read_range(
+                prefix,
+                start_key.as_ref().map(|k: &Vec<u8>| &k[..]),
+                limit,
+            )
+            .unwrap();
+        assert!(kvs.len() <= limit);
+        for (key, value) in kvs {
+            assert!(start_key.as_ref().map_or(true, |k| &key >= k));
+            assert!(read.insert(key, value.to_vec()).is_none());
+        }
+        match next_key {
+            None => break,
+            next_key => start_key = next_key,
+        }
+    }
+    read
+}
+
 #[test]
 fn test_set_order() {
     let mut rng = get_rng_for_test();
@@ -742,6 +971,7 @@ use crate::{
     state_manager::*,
     tests::{
         generate_keys, get_rng_for_test, new_state_manager_for_unit_test,
+        new_state_manager_for_unit_test_with_snapshot_epoch_count,
         FakeStateManager, TEST_NUMBER_OF_KEYS,
     },
     utils::access_mode,
@@ -758,6 +988,7 @@ use rand::{
 };
 use rlp::Rlp;
 use std::{
+    collections::BTreeMap,
     sync::Arc,
     thread,
     time::{Duration, Instant},
@@ -0,0 +1,74 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! CPU cost of the session encryption.
+//!
+//! `session_encryption/<size>` seals and opens a packet of the given size,
+//! which gives the encryption throughput of a core. `3000_tps` seals and opens
+//! the packets to propagate the transactions of one second at 3000 TPS to a
+//! single peer, i.e. the time of one iteration is the CPU time spent per
+//! second per peer. Multiply it by the number of peers to get the cost of a
+//! node.
+
+use bytes::BytesMut;
+use cfx_types::H256;
+use criterion::{
+    criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use network::encryption::{SessionCipher, SessionSecret};
+
+// size of a typical transfer transaction
+const TRANSACTION_SIZE: usize = 150;
+const TPS: usize = 3000;
+// number of transactions per propagation packet
+const TRANSACTIONS_PER_PACKET: usize = 50;
+
+fn cipher_pair() -> (SessionCipher, SessionCipher) {
+    let secret = SessionSecret::new(H256::random(), H256::random());
+    (
+        SessionCipher::new(&secret, true),
+        SessionCipher::new(&secret, false),
+    )
+}
+
+fn seal_and_open(
+    sender: &mut SessionCipher, receiver: &mut SessionCipher, packet: &[u8],
+) {
+    let mut data = packet.to_vec();
+    sender.seal(&mut data).unwrap();
+    let mut buf = BytesMut::from(data);
+    receiver.open(&mut buf).unwrap();
+}
+
+fn packet_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("session_encryption");
+    for size in &[256, 4 * 1024, 64 * 1024, 1024 * 1024] {
+        let packet = vec![0x5au8; *size];
+        let (mut sender, mut receiver) = cipher_pair();
+        group.throughput(Throughput::Bytes(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &packet,
+            |b, packet| {
+                b.iter(|| seal_and_open(&mut sender, &mut receiver, packet))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn tps_benchmark(c: &mut Criterion) {
+    let packet = vec![0x5au8; TRANSACTION_SIZE * TRANSACTIONS_PER_PACKET];
+    let (mut sender, mut receiver) = cipher_pair();
+    c.bench_function("3000_tps", move |b| {
+        b.iter(|| {
+            for _ in 0..TPS / TRANSACTIONS_PER_PACKET {
+                seal_and_open(&mut sender, &mut receiver, &packet);
+            }
+        })
+    });
+}
+
+criterion_group!(benches, packet_benchmark, tps_benchmark);
+criterion_main!(benches);
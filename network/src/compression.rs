@@ -0,0 +1,149 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{Error, ErrorKind};
+use std::{io::Read, str::FromStr};
+
+/// zstd level to balance the compression ratio and CPU cost at high TPS.
+const ZSTD_LEVEL: i32 = 1;
+
+/// Algorithm to compress the protocol packets of a session.
+///
+/// The algorithms supported by a node are exchanged in the Hello packets, and
+/// the id of the algorithm is set in the header of every compressed packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Snappy,
+    Zstd,
+}
+
+impl Compression {
+    pub fn id(&self) -> u8 {
+        match self {
+            Compression::Snappy => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Compression::Snappy),
+            2 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Parses the comma separated algorithms in the order of preference.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        let mut algorithms = Vec::new();
+        for algorithm in s.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+            let algorithm = algorithm.parse()?;
+            if !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        Ok(algorithms)
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Compression::Snappy => snap::raw::Encoder::new()
+                .compress_vec(data)
+                .map_err(|e| ErrorKind::Compression(e.to_string()).into()),
+            Compression::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)
+                .map_err(|e| ErrorKind::Compression(e.to_string()).into()),
+        }
+    }
+
+    /// Decompresses the data, and fails if the decompressed data is larger
+    /// than `max_size`, so that a small packet cannot use up the memory.
+    pub fn decompress(
+        &self, data: &[u8], max_size: usize,
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            Compression::Snappy => {
+                let size = snap::raw::decompress_len(data).map_err(|e| {
+                    Error::from_kind(ErrorKind::Compression(e.to_string()))
+                })?;
+                if size > max_size {
+                    bail!(ErrorKind::OversizedPacket);
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(data)
+                    .map_err(|e| ErrorKind::Compression(e.to_string()).into())
+            }
+            Compression::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(data)?;
+                let mut decompressed = Vec::new();
+                decoder
+                    .take(max_size as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|e| {
+                        Error::from_kind(ErrorKind::Compression(e.to_string()))
+                    })?;
+                if decompressed.len() > max_size {
+                    bail!(ErrorKind::OversizedPacket);
+                }
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "snappy" => Ok(Compression::Snappy),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "invalid compression {:?}, expected snappy or zstd",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_and_decompress() {
+        let data: Vec<u8> = (0..10000).map(|i| (i % 7) as u8).collect();
+
+        for compression in &[Compression::Snappy, Compression::Zstd] {
+            let compressed = compression.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(
+                compression.decompress(&compressed, data.len()).unwrap(),
+                data
+            );
+            assert!(compression
+                .decompress(&compressed, data.len() - 1)
+                .is_err());
+            assert!(compression.decompress(&[0xff; 8], data.len()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Compression::parse_list("zstd, snappy,zstd").unwrap(),
+            vec![Compression::Zstd, Compression::Snappy]
+        );
+        assert_eq!(Compression::parse_list("").unwrap(), vec![]);
+        assert!(Compression::parse_list("zstd,lz4").is_err());
+
+        for compression in &[Compression::Snappy, Compression::Zstd] {
+            assert_eq!(
+                Compression::from_id(compression.id()),
+                Some(*compression)
+            );
+        }
+        assert_eq!(Compression::from_id(0), None);
+        assert_eq!(Compression::from_id(3), None);
+    }
+}
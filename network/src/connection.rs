@@ -3,8 +3,10 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
+    encryption::{SessionCipher, SessionSecret, ENCRYPTION_OVERHEAD},
     io::{IoContext, StreamToken},
     throttling::THROTTLING_SERVICE,
+    transport::{MultiplexedStream, TransportProtocol, TransportStream},
     Error, ErrorKind,
 };
 use bytes::{Bytes, BytesMut};
@@ -12,11 +14,11 @@ use lazy_static::lazy_static;
 use metrics::{
     register_meter_with_group, Gauge, GaugeUsize, Histogram, Meter, Sample,
 };
-use mio::{tcp::*, *};
+use mio::{Poll, Ready, Token};
 use priority_send_queue::{PrioritySendQueue, SendQueuePriority};
 use serde_derive::Serialize;
 use std::{
-    io::{self, Read, Write},
+    io,
     net::SocketAddr,
     sync::{
         atomic::{AtomicBool, Ordering as AtomicOrdering},
@@ -83,11 +85,14 @@ pub enum WriteStatus {
     Complete,
 }
 
-const MAX_PAYLOAD_SIZE: usize = (1 << 24) - 1;
+pub(crate) const MAX_PAYLOAD_SIZE: usize = (1 << 24) - 1;
 
-pub trait GenericSocket: Read + Write {}
-
-impl GenericSocket for TcpStream {}
+/// Priorities of the send queue from the highest.
+const PRIORITIES: [SendQueuePriority; 3] = [
+    SendQueuePriority::High,
+    SendQueuePriority::Normal,
+    SendQueuePriority::Low,
+];
 
 /// Trait to assemble/load packet with extra information.
 /// E.g. prefix packet with length information
@@ -113,10 +118,15 @@ struct Packet {
     original_is_high_priority: bool,
     throttling_size: usize,
     creation_time: Instant,
+    // whether to encrypt the data before sending out, which is decided when
+    // the packet is queued.
+    encrypted: bool,
 }
 
 impl Packet {
-    fn new(data: Vec<u8>, priority: SendQueuePriority) -> Result<Self, Error> {
+    fn new(
+        data: Vec<u8>, priority: SendQueuePriority, encrypted: bool,
+    ) -> Result<Self, Error> {
         // update throttling
         let throttling_size = data.len();
         THROTTLING_SERVICE
@@ -131,15 +141,18 @@ impl Packet {
             original_is_high_priority: is_high_priority,
             throttling_size,
             creation_time: Instant::now(),
+            encrypted,
         })
     }
 
-    fn write(&mut self, writer: &mut dyn Write) -> Result<usize, Error> {
+    fn write<Socket: MultiplexedStream>(
+        &mut self, socket: &mut Socket, stream: usize,
+    ) -> Result<usize, Error> {
         if self.is_send_completed() {
             return Ok(0);
         }
 
-        let size = writer.write(&self.data[self.sending_pos..])?;
+        let size = socket.write(stream, &self.data[self.sending_pos..])?;
         self.sending_pos += size;
         Ok(size)
     }
@@ -167,30 +180,109 @@ impl Drop for Packet {
 #[allow(dead_code)]
 pub struct SendQueueStatus {
     queue_length: usize,
+    /// Size of the packet data after compression if compressed.
+    pub compressed_size: Option<usize>,
 }
 
-pub struct GenericConnection<Socket: GenericSocket> {
+/// Connection of a session, which sends the packets of each priority on a
+/// separate stream if the transport has multiple streams. Otherwise, the
+/// lowest priorities share the last stream.
+pub struct GenericConnection<Socket: MultiplexedStream> {
     /// Connection id (token)
     token: StreamToken,
     /// Network socket
     socket: Socket,
-    /// Receive buffer
-    recv_buf: BytesMut,
+    /// Receive buffer of each stream
+    recv_bufs: Vec<BytesMut>,
+    /// Only the first stream is read until the Hello packet received, so that
+    /// no protocol packet is handled before it.
+    multiplexed: bool,
     /// Packets that waiting for sending out.
     send_queue: PrioritySendQueue<Packet>,
-    /// Sending packet.
-    sending_packet: Option<Packet>,
+    /// Sending packet of each stream.
+    sending_packets: Vec<Option<Packet>>,
     /// Event flags this connection interested
     interest: Ready,
     /// Registered flag
     registered: AtomicBool,
     /// Assemble packet with extra information before sending out.
     assembler: Box<dyn PacketAssembler>,
+    /// Encrypt packets queued after encryption is enabled, and decrypt all
+    /// packets received since then. Each stream has its own cipher.
+    ciphers: Vec<SessionCipher>,
+    /// Packets queued after the Hello packet while the encryption is being
+    /// negotiated, which are held until the encryption is enabled. The remote
+    /// peer decrypts all the packets after the Hello packet, so none of them
+    /// could be sent in the clear.
+    held_packets: Option<Vec<(Packet, SendQueuePriority)>>,
 }
 
-impl<Socket: GenericSocket> GenericConnection<Socket> {
+impl<Socket: MultiplexedStream> GenericConnection<Socket> {
+    fn with_socket(
+        token: StreamToken, socket: Socket, assembler: Box<dyn PacketAssembler>,
+    ) -> Self {
+        let stream_count = socket.stream_count();
+        GenericConnection {
+            token,
+            socket,
+            recv_bufs: vec![BytesMut::new(); stream_count],
+            multiplexed: false,
+            send_queue: PrioritySendQueue::default(),
+            sending_packets: (0..stream_count).map(|_| None).collect(),
+            interest: Ready::hup() | Ready::readable(),
+            registered: AtomicBool::new(false),
+            assembler,
+            ciphers: Vec::new(),
+            held_packets: None,
+        }
+    }
+
+    /// Returns the stream to send the packets of the priority.
+    fn stream_of(&self, priority: SendQueuePriority) -> usize {
+        (priority as usize).min(self.socket.stream_count() - 1)
+    }
+
     /// Readable IO handler. Called when there is some data to be read.
-    pub fn readable(&mut self) -> io::Result<Option<Bytes>> {
+    pub fn readable(&mut self) -> Result<Option<Bytes>, Error> {
+        let stream_count = if self.multiplexed {
+            self.socket.stream_count()
+        } else {
+            1
+        };
+        for stream in 0..stream_count {
+            self.read_stream(stream)?;
+        }
+
+        for stream in 0..stream_count {
+            let mut packet =
+                match self.assembler.load(&mut self.recv_bufs[stream]) {
+                    Some(packet) => packet,
+                    None => continue,
+                };
+
+            if let Some(cipher) = self.ciphers.get_mut(stream) {
+                if let Err(e) = cipher.open(&mut packet) {
+                    debug!(
+                        "Failed to decrypt packet, token = {}, err = {:?}",
+                        self.token, e
+                    );
+                    return Err(e);
+                }
+            }
+
+            trace!(
+                "Packet received, token = {}, stream = {}, size = {}",
+                self.token,
+                stream,
+                packet.len()
+            );
+            return Ok(Some(packet.freeze()));
+        }
+
+        Ok(None)
+    }
+
+    fn read_stream(&mut self, stream: usize) -> Result<(), Error> {
         let mut buf: [u8; 1024] = [0; 1024];
 
         // Read until the socket has no data to read.
@@ -201,7 +293,7 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
         // caller should guarantee to read all packets in a loop when Readable
         // IO event triggered.
         loop {
-            match self.socket.read(&mut buf) {
+            match self.socket.read(stream, &mut buf) {
                 Ok(size) => {
                     trace!(
                         "Succeed to read socket data, token = {}, size = {}",
@@ -212,33 +304,23 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
                     if size == 0 {
                         break;
                     }
-                    self.recv_buf.extend_from_slice(&buf[0..size]);
+                    self.recv_bufs[stream].extend_from_slice(&buf[0..size]);
                 }
                 Err(e) => match e.kind() {
                     io::ErrorKind::Interrupted => continue,
                     io::ErrorKind::WouldBlock => break,
                     _ => {
                         debug!("Failed to read socket data, token = {}, err = {:?}", self.token, e);
-                        return Err(e);
+                        return Err(e.into());
                     }
                 },
             }
         }
 
-        let packet = self.assembler.load(&mut self.recv_buf);
-
-        if let Some(ref p) = packet {
-            trace!(
-                "Packet received, token = {}, size = {}",
-                self.token,
-                p.len()
-            );
-        }
-
-        Ok(packet.map(|p| p.freeze()))
+        Ok(())
     }
 
-    /// Send the specified data out immediately
+    /// Send the specified data out immediately on the first stream
     pub fn write_raw_data(
         &mut self, mut data: Vec<u8>,
     ) -> Result<usize, Error> {
@@ -249,8 +331,11 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
             data
         );
 
+        if let Some(cipher) = self.ciphers.first_mut() {
+            cipher.seal(&mut data)?;
+        }
         self.assembler.assemble(&mut data)?;
-        let size = self.socket.write(&data)?;
+        let size = self.socket.write(0, &data)?;
 
         trace!(
             "Succeed to send socket data, token = {}, size = {}",
@@ -262,35 +347,58 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
         Ok(size)
     }
 
-    /// Continue to send out the uncompleted packet, or pop new packet from
-    /// queue to send out.
-    fn write_next_from_queue(&mut self) -> Result<WriteStatus, Error> {
+    /// Pops the packet of the highest priority sent on the stream.
+    fn pop_packet(&mut self, stream: usize) -> Option<Packet> {
+        for priority in &PRIORITIES {
+            if self.stream_of(*priority) == stream {
+                if let Some(packet) =
+                    self.send_queue.pop_front_by_priority(*priority)
+                {
+                    return Some(packet);
+                }
+            }
+        }
+        None
+    }
+
+    /// Continue to send out the uncompleted packet of the stream, or pop new
+    /// packet from queue to send out.
+    fn write_next_from_queue(
+        &mut self, stream: usize,
+    ) -> Result<WriteStatus, Error> {
         // In case of last packet is all sent out.
-        if self.sending_packet.is_none() {
+        if self.sending_packets[stream].is_none() {
             // get packet from queue to send
-            let (mut packet, _) = match self.send_queue.pop_front() {
-                Some(item) => item,
+            let mut packet = match self.pop_packet(stream) {
+                Some(packet) => packet,
                 None => return Ok(WriteStatus::Complete),
             };
 
+            if packet.encrypted {
+                self.ciphers
+                    .get_mut(stream)
+                    .expect("cipher should be enabled for encrypted packet")
+                    .seal(&mut packet.data)?;
+            }
+
             // assemble packet to send, e.g. prefix length to packet
             self.assembler.assemble(&mut packet.data)?;
 
             trace!(
-                "Packet ready for sent, token = {}, size = {}",
+                "Packet ready for sent, token = {}, stream = {}, size = {}",
                 self.token,
+                stream,
                 packet.data.len()
             );
 
-            self.sending_packet = Some(packet);
+            self.sending_packets[stream] = Some(packet);
         }
 
-        let packet = self
-            .sending_packet
+        let packet = self.sending_packets[stream]
             .as_mut()
             .expect("should pop packet from send queue");
 
-        let size = packet.write(&mut self.socket)?;
+        let size = packet.write(&mut self.socket, stream)?;
         if size == 0 {
             WRITABLE_ZERO_COUNTER.mark(1);
         }
@@ -305,7 +413,7 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
         WRITABLE_COUNTER.mark(1);
         if packet.is_send_completed() {
             trace!("Packet sent, token = {}", self.token);
-            self.sending_packet = None;
+            self.sending_packets[stream] = None;
 
             WRITABLE_PACKET_COUNTER.mark(1);
 
@@ -319,9 +427,16 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
     pub fn writable<Message: Sync + Send + Clone + 'static>(
         &mut self, io: &IoContext<Message>,
     ) -> Result<WriteStatus, Error> {
-        let status = self.write_next_from_queue()?;
+        let mut status = WriteStatus::Complete;
+        for stream in 0..self.socket.stream_count() {
+            if self.write_next_from_queue(stream)? == WriteStatus::Ongoing {
+                status = WriteStatus::Ongoing;
+            }
+        }
 
-        if self.sending_packet.is_none() && self.send_queue.is_empty() {
+        if self.sending_packets.iter().all(Option::is_none)
+            && self.send_queue.is_empty()
+        {
             self.interest.remove(Ready::writable());
         }
         NETWORK_SEND_QUEUE_SIZE.update(self.send_queue.len());
@@ -337,14 +452,22 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
     {
         if !data.is_empty() {
             let size = data.len();
-            if self.assembler.is_oversized(size) {
+            let encrypted =
+                self.is_encrypted() || self.held_packets.is_some();
+            let overhead = if encrypted { ENCRYPTION_OVERHEAD } else { 0 };
+            if self.assembler.is_oversized(size + overhead) {
                 return Err(ErrorKind::OversizedPacket.into());
             }
 
             trace!("Sending packet, token = {}, size = {}", self.token, size);
 
-            let packet = Packet::new(data, priority)?;
-            self.send_queue.push_back(packet, priority);
+            let packet = Packet::new(data, priority, encrypted)?;
+            match self.held_packets {
+                Some(ref mut held_packets) => {
+                    held_packets.push((packet, priority))
+                }
+                None => self.send_queue.push_back(packet, priority),
+            }
 
             SEND_METER.mark(size);
             match priority {
@@ -367,26 +490,54 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
 
         Ok(SendQueueStatus {
             queue_length: self.send_queue.len(),
+            compressed_size: None,
         })
     }
 
     pub fn is_sending(&self) -> bool { self.interest.is_writable() }
+
+    /// Holds the packets queued from now on until the encryption is enabled,
+    /// which is called right after the Hello packet is queued. The Disconnect
+    /// packets written immediately are not held, since the session is closed
+    /// right after them.
+    pub fn hold_until_encrypted(&mut self) {
+        self.held_packets = Some(Vec::new());
+    }
+
+    /// Encrypts the packets queued from now on and decrypts the packets
+    /// received from now on. The held packets are queued to send encrypted,
+    /// and the packets queued before holding are sent in the clear.
+    pub fn enable_encryption(
+        &mut self, secret: &SessionSecret, originated: bool,
+    ) {
+        self.ciphers = (0..self.socket.stream_count())
+            .map(|stream| SessionCipher::for_stream(secret, originated, stream))
+            .collect();
+        let held_packets = self.held_packets.take().unwrap_or_default();
+        if !held_packets.is_empty() {
+            for (packet, priority) in held_packets {
+                self.send_queue.push_back(packet, priority);
+            }
+            self.interest.insert(Ready::writable());
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool { !self.ciphers.is_empty() }
+
+    /// Reads all the streams from now on, which is enabled once the Hello
+    /// packet received on the first stream.
+    pub fn enable_multiplexing(&mut self) { self.multiplexed = true; }
 }
 
-pub type Connection = GenericConnection<TcpStream>;
+pub type Connection = GenericConnection<Box<dyn TransportStream>>;
 
 impl Connection {
-    pub fn new(token: StreamToken, socket: TcpStream) -> Self {
-        Connection {
+    pub fn new(token: StreamToken, socket: Box<dyn TransportStream>) -> Self {
+        Connection::with_socket(
             token,
             socket,
-            recv_buf: BytesMut::new(),
-            send_queue: PrioritySendQueue::default(),
-            sending_packet: None,
-            interest: Ready::hup() | Ready::readable(),
-            registered: AtomicBool::new(false),
-            assembler: Box::new(PacketWithLenAssembler::default()),
-        }
+            Box::new(PacketWithLenAssembler::default()),
+        )
     }
 
     /// Register this connection with the IO event loop.
@@ -401,12 +552,7 @@ impl Connection {
             self.token,
             reg
         );
-        if let Err(e) = event_loop.register(
-            &self.socket,
-            reg,
-            self.interest,
-            PollOpt::edge(),
-        ) {
+        if let Err(e) = self.socket.register(reg, self.interest, event_loop) {
             trace!(
                 "Failed to register socket, token = {}, reg = {:?}, err = {:?}",
                 self.token,
@@ -431,8 +577,8 @@ impl Connection {
         if !self.registered.load(AtomicOrdering::SeqCst) {
             self.register_socket(reg, event_loop)
         } else {
-            event_loop
-                .reregister(&self.socket, reg, self.interest, PollOpt::edge())
+            self.socket
+                .reregister(reg, self.interest, event_loop)
                 .unwrap_or_else(|e| {
                     trace!("Failed to reregister socket, token = {}, reg = {:?}, err = {:?}", self.token, reg, e);
                 });
@@ -444,12 +590,14 @@ impl Connection {
     /// handler.
     pub fn deregister_socket(&self, event_loop: &Poll) -> io::Result<()> {
         trace!("Connection deregister, token = {}", self.token);
-        event_loop.deregister(&self.socket).ok();
+        self.socket.deregister(event_loop).ok();
         Ok(())
     }
 
     pub fn token(&self) -> StreamToken { self.token }
 
+    pub fn transport(&self) -> TransportProtocol { self.socket.protocol() }
+
     /// Get remote peer address
     pub fn remote_addr(&self) -> io::Result<SocketAddr> {
         self.socket.peer_addr()
@@ -465,11 +613,14 @@ impl Connection {
     pub fn details(&self) -> ConnectionDetails {
         ConnectionDetails {
             token: self.token,
-            recv_buf: self.recv_buf.len(),
+            transport: self.socket.protocol().to_string(),
+            recv_buf: self.recv_bufs.iter().map(BytesMut::len).sum(),
             sending_buf: self
-                .sending_packet
-                .as_ref()
-                .map_or(0, |p| p.data.len() - p.sending_pos),
+                .sending_packets
+                .iter()
+                .flatten()
+                .map(|p| p.data.len() - p.sending_pos)
+                .sum(),
             priority_queue_normal: self
                 .send_queue
                 .len_by_priority(SendQueuePriority::Normal),
@@ -478,6 +629,7 @@ impl Connection {
                 .len_by_priority(SendQueuePriority::High),
             interest: format!("{:?}", self.interest),
             registered: self.registered.load(AtomicOrdering::SeqCst),
+            encrypted: self.is_encrypted(),
         }
     }
 }
@@ -487,12 +639,14 @@ impl Connection {
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionDetails {
     pub token: StreamToken,
+    pub transport: String,
     pub recv_buf: usize,
     pub sending_buf: usize,
     pub priority_queue_normal: usize,
     pub priority_queue_high: usize,
     pub interest: String,
     pub registered: bool,
+    pub encrypted: bool,
 }
 
 /// Assembler that prefix packet with length information.
@@ -590,7 +744,7 @@ impl PacketAssembler for PacketWithLenAssembler {
 mod tests {
     use super::*;
     use crate::io::*;
-    use mio::Ready;
+    use cfx_types::H256;
     use std::{
         cmp,
         io::{Read, Result, Write},
@@ -659,25 +813,61 @@ mod tests {
         }
     }
 
-    impl GenericSocket for TestSocket {}
+    impl MultiplexedStream for TestSocket {
+        fn stream_count(&self) -> usize { 1 }
+
+        fn read(&mut self, _stream: usize, buf: &mut [u8]) -> Result<usize> {
+            Read::read(self, buf)
+        }
+
+        fn write(&mut self, _stream: usize, buf: &[u8]) -> Result<usize> {
+            Write::write(self, buf)
+        }
+    }
+
+    /// Socket with a stream for each priority.
+    struct TestStreams {
+        streams: Vec<TestSocket>,
+    }
+
+    impl MultiplexedStream for TestStreams {
+        fn stream_count(&self) -> usize { self.streams.len() }
+
+        fn read(&mut self, stream: usize, buf: &mut [u8]) -> Result<usize> {
+            Read::read(&mut self.streams[stream], buf)
+        }
+
+        fn write(&mut self, stream: usize, buf: &[u8]) -> Result<usize> {
+            Write::write(&mut self.streams[stream], buf)
+        }
+    }
 
     type TestConnection = GenericConnection<TestSocket>;
 
     impl TestConnection {
         fn new() -> Self {
-            TestConnection {
-                token: 1_234_567_890usize,
-                socket: TestSocket::new(),
-                send_queue: PrioritySendQueue::default(),
-                sending_packet: None,
-                recv_buf: BytesMut::new(),
-                interest: Ready::hup() | Ready::readable(),
-                registered: AtomicBool::new(false),
-                assembler: Box::new(PacketWithLenAssembler::new(1, None)),
-            }
+            TestConnection::with_socket(
+                1_234_567_890usize,
+                TestSocket::new(),
+                Box::new(PacketWithLenAssembler::new(1, None)),
+            )
         }
     }
 
+    fn multiplexed_connection(
+        buf_size: usize,
+    ) -> GenericConnection<TestStreams> {
+        GenericConnection::with_socket(
+            1_234_567_890usize,
+            TestStreams {
+                streams: (0..3)
+                    .map(|_| TestSocket::with_buf(buf_size))
+                    .collect(),
+            },
+            Box::new(PacketWithLenAssembler::new(1, None)),
+        )
+    }
+
     fn test_io() -> IoContext<i32> {
         IoContext::new(IoChannel::disconnected(), 0)
     }
@@ -695,7 +885,8 @@ mod tests {
     fn connection_write_is_buffered() {
         let mut connection = TestConnection::new();
         connection.socket = TestSocket::with_buf(10);
-        let packet = Packet::new(vec![0; 60], SendQueuePriority::High).unwrap();
+        let packet =
+            Packet::new(vec![0; 60], SendQueuePriority::High, false).unwrap();
         connection
             .send_queue
             .push_back(packet, SendQueuePriority::High);
@@ -705,7 +896,7 @@ mod tests {
         assert!(status.is_ok());
         assert_eq!(0, connection.send_queue.len());
 
-        let sending_packet = connection.sending_packet.unwrap();
+        let sending_packet = connection.sending_packets[0].take().unwrap();
         assert_eq!(sending_packet.data.len(), 61);
         assert_eq!(sending_packet.sending_pos, 10);
     }
@@ -738,6 +929,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn connection_encrypted() {
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        let mut sender = TestConnection::new();
+        let mut receiver = TestConnection::new();
+
+        // packets queued before encryption enabled are sent in the clear
+        sender
+            .send(&test_io(), vec![1, 2], SendQueuePriority::High)
+            .unwrap();
+        sender.enable_encryption(&secret, true);
+        receiver.enable_encryption(&secret, false);
+        sender
+            .send(&test_io(), vec![3, 4, 5], SendQueuePriority::High)
+            .unwrap();
+        sender.writable(&test_io()).unwrap();
+        sender.writable(&test_io()).unwrap();
+
+        let mut buf = BytesMut::from(&sender.socket.write_buf[..]);
+        assert_eq!(&sender.assembler.load(&mut buf).unwrap()[..], &[1, 2]);
+        receiver.socket.read_buf = buf.to_vec();
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[3, 4, 5]);
+
+        // tampered packets are rejected
+        let mut data = vec![6, 7];
+        sender.ciphers[0].seal(&mut data).unwrap();
+        data[0] ^= 1;
+        sender.assembler.assemble(&mut data).unwrap();
+        receiver.socket.read_buf.extend_from_slice(&data);
+        assert!(receiver.readable().is_err());
+    }
+
+    #[test]
+    fn connection_holds_packets_until_encrypted() {
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        let mut sender = TestConnection::new();
+        let mut receiver = TestConnection::new();
+
+        // a ping queued after the Hello is held before the remote Hello read
+        sender
+            .send(&test_io(), vec![0x80, 1], SendQueuePriority::High)
+            .unwrap();
+        sender.hold_until_encrypted();
+        sender
+            .send(&test_io(), vec![0x02, 2], SendQueuePriority::High)
+            .unwrap();
+        while sender.writable(&test_io()).unwrap() == WriteStatus::Ongoing {}
+        assert!(sender.send_queue.is_empty());
+        assert!(!sender.is_sending());
+
+        // the remote peer decrypts all the packets after the Hello
+        receiver.socket.read_buf = sender.socket.write_buf.clone();
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[0x80, 1]);
+        assert!(receiver.readable().unwrap().is_none());
+        receiver.enable_encryption(&secret, false);
+
+        // the ping is sent encrypted once the encryption is enabled
+        sender.enable_encryption(&secret, true);
+        assert!(sender.is_sending());
+        while sender.writable(&test_io()).unwrap() == WriteStatus::Ongoing {}
+        receiver.socket.read_buf = sender.socket.write_buf.clone();
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[0x02, 2]);
+    }
+
+    #[test]
+    fn connection_streams_by_priority() {
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        let mut sender = multiplexed_connection(30);
+        let mut receiver = multiplexed_connection(0);
+        sender.enable_encryption(&secret, true);
+        receiver.enable_encryption(&secret, false);
+
+        // the low priority packet does not block the high priority one
+        sender
+            .send(&test_io(), vec![1; 60], SendQueuePriority::Low)
+            .unwrap();
+        sender
+            .send(&test_io(), vec![2; 5], SendQueuePriority::High)
+            .unwrap();
+        assert_eq!(sender.writable(&test_io()).unwrap(), WriteStatus::Ongoing);
+        assert!(sender.sending_packets[0].is_none());
+        assert!(sender.sending_packets[2].is_some());
+        while sender.writable(&test_io()).unwrap() == WriteStatus::Ongoing {}
+        assert!(sender.send_queue.is_empty());
+        assert!(!sender.is_sending());
+
+        for stream in 0..3 {
+            receiver.socket.streams[stream].read_buf =
+                sender.socket.streams[stream].write_buf.clone();
+        }
+
+        // only the first stream is read before multiplexing enabled
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[2; 5]);
+        assert!(receiver.readable().unwrap().is_none());
+        receiver.enable_multiplexing();
+        assert_eq!(&receiver.readable().unwrap().unwrap()[..], &[1; 60][..]);
+        assert!(receiver.readable().unwrap().is_none());
+    }
+
     #[test]
     fn test_assembler_oversized() {
         let assembler = PacketWithLenAssembler::default();
@@ -5,10 +5,12 @@
 use crate::{
     hash::keccak,
     node_database::NodeDatabase,
+    node_record::{NodeCapabilities, NodeRecord},
     node_table::{NodeId, *},
     service::{UdpIoContext, MAX_DATAGRAM_SIZE, UDP_PROTOCOL_DISCOVERY},
-    DiscoveryConfiguration, Error, ErrorKind, IpFilter, ThrottlingReason,
-    NODE_TAG_ARCHIVE, NODE_TAG_NODE_TYPE,
+    DiscoveryConfiguration, Error, ErrorKind, IpFilter, ProtocolInfo,
+    ThrottlingReason, NODE_TAG_ARCHIVE, NODE_TAG_FULL, NODE_TAG_LIGHT,
+    NODE_TAG_NODE_TYPE,
 };
 use cfx_bytes::Bytes;
 use cfx_types::{H256, H520};
@@ -85,6 +87,11 @@ pub struct Discovery {
     ping_throttling: TimeWindowBucket<IpAddr>,
     find_nodes_throttling: TimeWindowBucket<IpAddr>,
 
+    // Capabilities of the local node advertised in the PING/PONG packets,
+    // and the signed record of them, which is renewed before expiration.
+    capabilities: Option<NodeCapabilities>,
+    record: Option<NodeRecord>,
+
     config: DiscoveryConfiguration,
 }
 
@@ -92,8 +99,18 @@ impl Discovery {
     pub fn new(
         key: &KeyPair, public: NodeEndpoint, ip_filter: IpFilter,
         config: DiscoveryConfiguration,
+        capabilities: Option<NodeCapabilities>,
     ) -> Discovery
     {
+        // Light nodes only look up the nodes that could serve them.
+        let peer_tags = match &capabilities {
+            Some(c) if c.node_type == NODE_TAG_LIGHT => vec![
+                (NODE_TAG_NODE_TYPE.into(), NODE_TAG_FULL.into()),
+                (NODE_TAG_NODE_TYPE.into(), NODE_TAG_ARCHIVE.into()),
+            ],
+            _ => Vec::new(),
+        };
+
         Discovery {
             id: key.public().clone(),
             id_hash: keccak(key.public()),
@@ -110,6 +127,7 @@ impl Discovery {
             disc_option: DiscoveryOption {
                 general: true,
                 archive: false,
+                peer_tags,
             },
             ping_throttling: TimeWindowBucket::new(
                 config.throttling_interval,
@@ -119,10 +137,95 @@ impl Discovery {
                 config.throttling_interval,
                 config.throttling_limit_find_nodes,
             ),
+            capabilities,
+            record: None,
             config,
         }
     }
 
+    /// Update the protocols advertised in the node record, which is signed
+    /// again with a new sequence number.
+    pub fn set_protocols(&mut self, protocols: Vec<ProtocolInfo>) {
+        if let Some(capabilities) = self.capabilities.as_mut() {
+            capabilities.protocols = protocols;
+        }
+    }
+
+    /// Update whether the local node serves state snapshots, which is
+    /// advertised in the node record.
+    pub fn set_snapshot(&mut self, snapshot: bool) {
+        if let Some(capabilities) = self.capabilities.as_mut() {
+            capabilities.snapshot = snapshot;
+        }
+    }
+
+    /// Returns the record of local node capabilities, which is signed again
+    /// if changed or half of its time to live has passed.
+    fn local_record(&mut self) -> Option<NodeRecord> {
+        let capabilities = self.capabilities.as_ref()?;
+        let ttl = self.config.record_ttl;
+        let prev_seq = match &self.record {
+            Some(record)
+                if record.capabilities == *capabilities
+                    && record.remaining_ttl() > ttl / 2 =>
+            {
+                return Some(record.clone())
+            }
+            Some(record) => record.seq,
+            None => 0,
+        };
+
+        // Use the time as sequence number, so that records signed after
+        // restart are still newer than the previous ones.
+        let seq = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .max(prev_seq + 1);
+        match NodeRecord::new(seq, ttl, capabilities.clone(), &self.secret) {
+            Ok(record) => {
+                self.record = Some(record.clone());
+                Some(record)
+            }
+            Err(e) => {
+                warn!("Error signing node record: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Decode the optional node record at `index` of a PING/PONG packet, and
+    /// returns the record if it is signed by the sender and not expired.
+    fn decode_record(
+        rlp: &Rlp, index: usize, node_id: &NodeId,
+    ) -> Result<Option<NodeRecord>, Error> {
+        if rlp.item_count()? <= index {
+            return Ok(None);
+        }
+
+        let record: NodeRecord = rlp.val_at(index)?;
+        if !record.verify(node_id) {
+            debug!("Invalid node record signature, node_id={:#x}", node_id);
+            return Err(ErrorKind::BadProtocol.into());
+        }
+
+        if record.is_expired() {
+            return Ok(None);
+        }
+
+        Ok(Some(record))
+    }
+
+    /// Returns whether the node of the `record` is in another chain.
+    fn is_other_chain(&self, record: &Option<NodeRecord>) -> bool {
+        match (&self.capabilities, record) {
+            (Some(local), Some(record)) => {
+                local.chain_id != record.capabilities.chain_id
+            }
+            _ => false,
+        }
+    }
+
     fn is_allowed(&self, entry: &NodeEntry) -> bool {
         entry.endpoint.is_allowed(&self.ip_filter) && entry.id != self.id
     }
@@ -163,11 +266,15 @@ impl Discovery {
     fn ping(
         &mut self, uio: &UdpIoContext, node: &NodeEntry,
     ) -> Result<(), Error> {
-        let mut rlp = RlpStream::new_list(4);
+        let record = self.local_record();
+        let mut rlp = RlpStream::new_list(4 + record.is_some() as usize);
         rlp.append(&DISCOVER_PROTOCOL_VERSION);
         self.public_endpoint.to_rlp_list(&mut rlp);
         node.endpoint.to_rlp_list(&mut rlp);
         rlp.append(&self.config.expire_timestamp());
+        if let Some(record) = record {
+            rlp.append(&record);
+        }
         let hash = self.send_packet(
             uio,
             PACKET_PING,
@@ -270,8 +377,11 @@ impl Discovery {
         let ping_to = NodeEndpoint::from_rlp(&rlp.at(2)?)?;
         let timestamp: u64 = rlp.val_at(3)?;
         self.check_timestamp(timestamp)?;
+        let record = Self::decode_record(rlp, 4, node_id)?;
 
-        let mut response = RlpStream::new_list(3);
+        let local_record = self.local_record();
+        let mut response =
+            RlpStream::new_list(3 + local_record.is_some() as usize);
         let pong_to = NodeEndpoint {
             address: from.clone(),
             udp_port: ping_from.udp_port,
@@ -287,6 +397,9 @@ impl Discovery {
 
         response.append(&echo_hash);
         response.append(&self.config.expire_timestamp());
+        if let Some(local_record) = local_record {
+            response.append(&local_record);
+        }
         self.send_packet(uio, PACKET_PONG, from, &response.drain())?;
 
         let entry = NodeEntry {
@@ -298,10 +411,14 @@ impl Discovery {
             debug!("Got bad address: {:?}", entry);
         } else if !self.is_allowed(&entry) {
             debug!("Address not allowed: {:?}", entry);
+        } else if self.is_other_chain(&record) {
+            debug!("Ping from node of another chain: {:?}", entry);
         } else {
-            uio.node_db
-                .write()
-                .note_success(node_id, None, false /* trusted_only */);
+            let mut node_db = uio.node_db.write();
+            node_db.note_success(node_id, None, false /* trusted_only */);
+            if let Some(record) = record {
+                node_db.set_record(*node_id, record);
+            }
         }
         Ok(())
     }
@@ -316,6 +433,7 @@ impl Discovery {
         let echo_hash: H256 = rlp.val_at(1)?;
         let timestamp: u64 = rlp.val_at(2)?;
         self.check_timestamp(timestamp)?;
+        let record = Self::decode_record(rlp, 3, node_id)?;
 
         let expected_node = match self.in_flight_pings.entry(*node_id) {
             Entry::Occupied(entry) => {
@@ -338,7 +456,15 @@ impl Discovery {
         };
 
         if let Some(node) = expected_node {
-            uio.node_db.write().insert_with_conditional_promotion(node);
+            if self.is_other_chain(&record) {
+                debug!("Got Pong from node of another chain: {:?}", node);
+                return Ok(());
+            }
+            let mut node_db = uio.node_db.write();
+            node_db.insert_with_conditional_promotion(node);
+            if let Some(record) = record {
+                node_db.set_record(*node_id, record);
+            }
             Ok(())
         } else {
             debug!("Got unexpected Pong from {:?} ; request not found", &from);
@@ -501,7 +627,13 @@ impl Discovery {
         let mut tried_count = 0;
 
         if self.disc_option.general {
-            tried_count += self.discover_without_tag(uio);
+            if self.disc_option.peer_tags.is_empty() {
+                tried_count += self.discover_without_tag(uio);
+            } else {
+                for (key, value) in self.disc_option.peer_tags.clone() {
+                    tried_count += self.discover_with_tag(uio, &key, &value);
+                }
+            }
         }
 
         if self.disc_option.archive {
@@ -545,6 +677,7 @@ impl Discovery {
 
     pub fn round(&mut self, uio: &UdpIoContext) {
         self.check_expired(uio, Instant::now());
+        uio.node_db.write().remove_expired_records();
         self.update_new_nodes(uio);
 
         if self.discovery_round.is_some() {
@@ -671,6 +804,9 @@ pub struct DiscoveryOption {
     pub general: bool,
     // discover archive nodes
     pub archive: bool,
+    // tags of the nodes to discover instead of all nodes, e.g. light nodes
+    // only discover full and archive nodes
+    pub peer_tags: Vec<(String, String)>,
 }
 
 #[derive(RlpEncodable, RlpDecodable)]
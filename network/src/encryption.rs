@@ -0,0 +1,363 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{node_table::NodeId, Error, ErrorKind};
+use bytes::BytesMut;
+use cfx_types::H256;
+use chacha20poly1305::{
+    aead::{AeadInPlace, NewAead},
+    ChaCha20Poly1305, Key, Nonce, Tag,
+};
+use keccak_hash::keccak;
+use std::{collections::HashMap, str::FromStr};
+
+/// Number of bytes an encrypted packet is longer than the plain packet, i.e.
+/// the size of the authentication tag.
+pub const ENCRYPTION_OVERHEAD: usize = 16;
+
+/// Whether to encrypt the protocol packets of a session.
+///
+/// Encryption is advertised in the handshake, and used only if both peers
+/// enable it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEncryption {
+    /// Never encrypt. Peers that require encryption will disconnect.
+    Disabled,
+    /// Encrypt if the remote peer enables encryption as well.
+    Preferred,
+    /// Encrypt, and disconnect the remote peer if it does not enable
+    /// encryption.
+    Required,
+}
+
+impl FromStr for SessionEncryption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "disabled" => Ok(SessionEncryption::Disabled),
+            "preferred" => Ok(SessionEncryption::Preferred),
+            "required" => Ok(SessionEncryption::Required),
+            _ => Err(format!(
+                "invalid session encryption {:?}, expected disabled, \
+                 preferred or required",
+                s
+            )),
+        }
+    }
+}
+
+/// Session encryption of the network, which could be overridden per peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEncryptionConfig {
+    pub default: SessionEncryption,
+    pub peers: HashMap<NodeId, SessionEncryption>,
+}
+
+impl Default for SessionEncryptionConfig {
+    fn default() -> Self {
+        SessionEncryptionConfig {
+            default: SessionEncryption::Disabled,
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl SessionEncryptionConfig {
+    /// Creates the config from the default encryption of the network, and the
+    /// optional comma separated `<node id>:<encryption>` overrides of peers.
+    pub fn new(default: &str, peers: Option<&str>) -> Result<Self, String> {
+        let mut config = SessionEncryptionConfig {
+            default: default.parse()?,
+            peers: HashMap::new(),
+        };
+
+        for peer in peers.into_iter().flat_map(|p| p.split(',')) {
+            let peer = peer.trim();
+            if peer.is_empty() {
+                continue;
+            }
+
+            let (id, encryption) = match peer.find(':') {
+                Some(idx) => (&peer[..idx], &peer[idx + 1..]),
+                None => {
+                    return Err(format!(
+                        "invalid peer session encryption {:?}, expected \
+                         <node id>:<encryption>",
+                        peer
+                    ))
+                }
+            };
+            let id = NodeId::from_str(id.trim_start_matches("0x"))
+                .map_err(|e| format!("invalid node id {:?}: {:?}", id, e))?;
+            config.peers.insert(id, encryption.parse()?);
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the session encryption for the specified peer.
+    pub fn get(&self, id: &NodeId) -> SessionEncryption {
+        self.peers.get(id).cloned().unwrap_or(self.default)
+    }
+}
+
+/// Secret shared by the two peers of a session, i.e. the random nonces
+/// exchanged in the ECIES encrypted handshake, and the ECDH secret of the
+/// ephemeral keys exchanged in the Hello packets.
+///
+/// The nonces could be recovered from the recorded handshake once the node
+/// key is leaked, while the ephemeral keys are dropped after the session keys
+/// are derived, which keeps the past sessions secret.
+#[derive(Clone, Copy)]
+pub struct SessionSecret {
+    initiator_nonce: H256,
+    responder_nonce: H256,
+    ephemeral_secret: H256,
+}
+
+impl SessionSecret {
+    pub fn new(initiator_nonce: H256, responder_nonce: H256) -> Self {
+        SessionSecret {
+            initiator_nonce,
+            responder_nonce,
+            ephemeral_secret: H256::zero(),
+        }
+    }
+
+    /// Returns the secret with the ECDH secret of the ephemeral keys.
+    pub fn with_ephemeral_secret(mut self, secret: H256) -> Self {
+        self.ephemeral_secret = secret;
+        self
+    }
+
+    fn derive_key(&self, label: &[u8]) -> H256 {
+        let mut data = Vec::with_capacity(3 * H256::len_bytes() + label.len());
+        data.extend_from_slice(self.initiator_nonce.as_bytes());
+        data.extend_from_slice(self.responder_nonce.as_bytes());
+        data.extend_from_slice(self.ephemeral_secret.as_bytes());
+        data.extend_from_slice(label);
+        keccak(data)
+    }
+}
+
+/// ChaCha20-Poly1305 encryption of session packets.
+///
+/// Each direction has its own key derived from the session secret. The nonce
+/// is the number of packets sent (or received) in that direction, so packets
+/// have to be opened in the order they are sealed, and any packet dropped,
+/// replayed or reordered fails the authentication. Streams of a multiplexed
+/// connection have their own keys, since packets are only ordered in a
+/// stream.
+pub struct SessionCipher {
+    sealing: ChaCha20Poly1305,
+    opening: ChaCha20Poly1305,
+    sealed: u64,
+    opened: u64,
+}
+
+impl SessionCipher {
+    pub fn new(secret: &SessionSecret, originated: bool) -> Self {
+        Self::for_stream(secret, originated, 0)
+    }
+
+    /// Creates the cipher of a stream of the connection. The keys of the
+    /// first stream are the same as a connection with only one stream.
+    pub fn for_stream(
+        secret: &SessionSecret, originated: bool, stream: usize,
+    ) -> Self {
+        let label = |direction: &[u8]| {
+            let mut label = direction.to_vec();
+            if stream > 0 {
+                label.extend_from_slice(format!("/{}", stream).as_bytes());
+            }
+            label
+        };
+        let initiator_key = secret.derive_key(&label(b"initiator"));
+        let responder_key = secret.derive_key(&label(b"responder"));
+        let (sealing_key, opening_key) = if originated {
+            (initiator_key, responder_key)
+        } else {
+            (responder_key, initiator_key)
+        };
+
+        SessionCipher {
+            sealing: ChaCha20Poly1305::new(Key::from_slice(
+                sealing_key.as_bytes(),
+            )),
+            opening: ChaCha20Poly1305::new(Key::from_slice(
+                opening_key.as_bytes(),
+            )),
+            sealed: 0,
+            opened: 0,
+        }
+    }
+
+    fn nonce(counter: u64) -> Nonce {
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&counter.to_le_bytes());
+        nonce
+    }
+
+    /// Encrypts the packet in place and appends the authentication tag.
+    pub fn seal(&mut self, data: &mut Vec<u8>) -> Result<(), Error> {
+        let tag = self
+            .sealing
+            .encrypt_in_place_detached(&Self::nonce(self.sealed), &[], data)
+            .map_err(|_| Error::from_kind(ErrorKind::Auth))?;
+        data.extend_from_slice(&tag);
+        self.sealed += 1;
+        Ok(())
+    }
+
+    /// Verifies the authentication tag and decrypts the packet in place.
+    pub fn open(&mut self, data: &mut BytesMut) -> Result<(), Error> {
+        if data.len() < ENCRYPTION_OVERHEAD {
+            bail!(ErrorKind::Auth);
+        }
+
+        let tag_pos = data.len() - ENCRYPTION_OVERHEAD;
+        let tag = Tag::clone_from_slice(&data[tag_pos..]);
+        data.truncate(tag_pos);
+        self.opening
+            .decrypt_in_place_detached(
+                &Self::nonce(self.opened),
+                &[],
+                &mut data[..],
+                &tag,
+            )
+            .map_err(|_| Error::from_kind(ErrorKind::Auth))?;
+        self.opened += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keylib::{crypto::ecdh, Generator, Random};
+
+    fn cipher_pair() -> (SessionCipher, SessionCipher) {
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        (
+            SessionCipher::new(&secret, true),
+            SessionCipher::new(&secret, false),
+        )
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let (mut initiator, mut responder) = cipher_pair();
+
+        for i in 0..3 {
+            let plain = vec![i as u8; 100];
+            let mut data = plain.clone();
+            initiator.seal(&mut data).unwrap();
+            assert_eq!(data.len(), plain.len() + ENCRYPTION_OVERHEAD);
+            assert_ne!(&data[..plain.len()], &plain[..]);
+
+            let mut buf = BytesMut::from(&data[..]);
+            responder.open(&mut buf).unwrap();
+            assert_eq!(&buf[..], &plain[..]);
+        }
+
+        let mut data = vec![];
+        responder.seal(&mut data).unwrap();
+        let mut buf = BytesMut::from(&data[..]);
+        initiator.open(&mut buf).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_packets() {
+        let (mut initiator, mut responder) = cipher_pair();
+
+        // tampered
+        let mut data = vec![1, 2, 3];
+        initiator.seal(&mut data).unwrap();
+        data[0] ^= 1;
+        assert!(responder.open(&mut BytesMut::from(&data[..])).is_err());
+
+        // reordered
+        let (mut initiator, mut responder) = cipher_pair();
+        let mut first = vec![1, 2, 3];
+        let mut second = vec![4, 5, 6];
+        initiator.seal(&mut first).unwrap();
+        initiator.seal(&mut second).unwrap();
+        assert!(responder.open(&mut BytesMut::from(&second[..])).is_err());
+
+        // sealed by self, or too short
+        let (mut initiator, _) = cipher_pair();
+        let mut data = vec![1, 2, 3];
+        initiator.seal(&mut data).unwrap();
+        assert!(initiator.open(&mut BytesMut::from(&data[..])).is_err());
+        assert!(initiator.open(&mut BytesMut::from(&[0u8; 15][..])).is_err());
+    }
+
+    #[test]
+    fn test_stream_keys() {
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        let mut initiator = SessionCipher::for_stream(&secret, true, 1);
+        let mut responder = SessionCipher::for_stream(&secret, false, 1);
+        let mut other_stream = SessionCipher::for_stream(&secret, false, 2);
+
+        let mut data = vec![1, 2, 3];
+        initiator.seal(&mut data).unwrap();
+        assert!(other_stream.open(&mut BytesMut::from(&data[..])).is_err());
+        let mut buf = BytesMut::from(&data[..]);
+        responder.open(&mut buf).unwrap();
+        assert_eq!(&buf[..], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ephemeral_secret() {
+        let initiator_key = Random.generate().unwrap();
+        let responder_key = Random.generate().unwrap();
+        let initiator_shared =
+            ecdh::agree(initiator_key.secret(), responder_key.public())
+                .unwrap();
+        let responder_shared =
+            ecdh::agree(responder_key.secret(), initiator_key.public())
+                .unwrap();
+        assert_eq!(*initiator_shared, *responder_shared);
+
+        let secret = SessionSecret::new(H256::random(), H256::random());
+        let mut initiator = SessionCipher::new(
+            &secret.with_ephemeral_secret(*initiator_shared),
+            true,
+        );
+        let mut responder = SessionCipher::new(
+            &secret.with_ephemeral_secret(*responder_shared),
+            false,
+        );
+        let mut data = vec![1, 2, 3];
+        initiator.seal(&mut data).unwrap();
+        let mut buf = BytesMut::from(&data[..]);
+        responder.open(&mut buf).unwrap();
+        assert_eq!(&buf[..], &[1, 2, 3]);
+
+        // the nonces only are not enough to open the packets
+        let mut data = vec![1, 2, 3];
+        initiator.seal(&mut data).unwrap();
+        let mut nonces_only = SessionCipher::new(&secret, false);
+        assert!(nonces_only.open(&mut BytesMut::from(&data[..])).is_err());
+    }
+
+    #[test]
+    fn test_config() {
+        let id = NodeId::random();
+        let peers = format!("0x{:x}:required, {:x}:disabled", id, id);
+        let config =
+            SessionEncryptionConfig::new("preferred", Some(&peers)).unwrap();
+        assert_eq!(config.get(&id), SessionEncryption::Disabled);
+        assert_eq!(config.get(&NodeId::random()), SessionEncryption::Preferred);
+
+        assert!(SessionEncryptionConfig::new("on", None).is_err());
+        assert!(SessionEncryptionConfig::new("disabled", Some("abc")).is_err());
+        assert!(
+            SessionEncryptionConfig::new("disabled", Some("abc:required"))
+                .is_err()
+        );
+    }
+}
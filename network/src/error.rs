@@ -170,6 +170,11 @@ error_chain! {
             display("Packet is too large"),
         }
 
+        Compression(reason: String) {
+            description("Compression error"),
+            display("Compression error: reason={}", reason),
+        }
+
         Io(err: io::Error) {
             description("IO Error"),
             display("Unexpected IO error: {}", err),
@@ -19,13 +19,16 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
-    connection::Connection, node_table::NodeId, service::HostMetadata, Error,
-    ErrorKind,
+    connection::Connection,
+    encryption::{SessionEncryption, SessionEncryptionConfig, SessionSecret},
+    node_table::NodeId,
+    service::HostMetadata,
+    transport::TransportStream,
+    Error, ErrorKind,
 };
 use cfx_types::{Public, H256};
 use io::{IoContext, StreamToken};
 use keylib::{crypto::ecies, Secret};
-use mio::tcp::TcpStream;
 use priority_send_queue::SendQueuePriority;
 use std::{
     sync::atomic::{AtomicBool, Ordering},
@@ -36,6 +39,17 @@ const AUTH_PACKET_SIZE: usize = 209;
 const ACK_OF_AUTH_PACKET_SIZE: usize = 177;
 const ACK_OF_ACK_PACKET_SIZE: usize = 145;
 const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Magic in the tail of the handshake nonce to advertise that the node
+/// supports the extended Hello items. Old nodes take it as random bytes, and
+/// a random nonce of an old node has the magic by chance with a negligible
+/// probability.
+const NONCE_MAGIC: &[u8; 8] = b"cfxhello";
+/// Position of the capability flags in the nonce, i.e. the byte before the
+/// magic. The flags could not be tampered with since the nonces are exchanged
+/// in ECIES encrypted packets.
+const NONCE_FLAGS_POS: usize = 32 - NONCE_MAGIC.len() - 1;
+/// Capability flag that the node enables the session encryption.
+const FLAG_ENCRYPTION: u8 = 0x01;
 
 // used for test purpose only to bypass the cryptography
 pub static BYPASS_CRYPTOGRAPHY: AtomicBool = AtomicBool::new(false);
@@ -64,29 +78,54 @@ pub struct Handshake {
     pub state: HandshakeState,
     /// nonce for verification
     nonce: H256,
+    /// nonce of the remote node, which is not available if cryptography is
+    /// bypassed
+    remote_nonce: Option<H256>,
 }
 
 impl Handshake {
     /// Create a new handshake object
     pub fn new(
-        token: StreamToken, id: Option<&NodeId>, socket: TcpStream,
+        token: StreamToken, id: Option<&NodeId>,
+        socket: Box<dyn TransportStream>,
     ) -> Self {
         Handshake {
             id: id.cloned().unwrap_or_else(NodeId::default),
             connection: Connection::new(token, socket),
             state: HandshakeState::New,
-            nonce: H256::random(),
+            nonce: Self::new_nonce(),
+            remote_nonce: None,
         }
     }
 
+    /// Generates a random nonce with the magic in the tail.
+    fn new_nonce() -> H256 {
+        let mut nonce = H256::random();
+        nonce[H256::len_bytes() - NONCE_MAGIC.len()..]
+            .copy_from_slice(NONCE_MAGIC);
+        nonce
+    }
+
+    /// Sets the capability flags in the nonce for the remote node, which
+    /// should be called before the nonce is sent.
+    fn set_flags(&mut self, encryption: &SessionEncryptionConfig) {
+        let mut flags = 0;
+        if encryption.get(&self.id) != SessionEncryption::Disabled {
+            flags |= FLAG_ENCRYPTION;
+        }
+        self.nonce[NONCE_FLAGS_POS] = flags;
+    }
+
     /// Start a handshake
     pub fn start<Message>(
         &mut self, io: &IoContext<Message>, host: &HostMetadata,
+        encryption: &SessionEncryptionConfig,
     ) -> Result<(), Error>
     where Message: Send + Clone + Sync + 'static {
         io.register_timer(self.connection.token(), HANDSHAKE_TIMEOUT)?;
 
         if !self.id.is_zero() {
+            self.set_flags(encryption);
             self.write_auth(io, host.id())?;
         } else {
             self.state = HandshakeState::ReadingAuth;
@@ -98,9 +137,55 @@ impl Handshake {
     /// Check if handshake is complete
     pub fn done(&self) -> bool { self.state == HandshakeState::StartSession }
 
+    /// Returns the secret shared with the remote node once the handshake is
+    /// complete, which is known by the two nodes only because the nonces are
+    /// exchanged in ECIES encrypted packets.
+    pub fn session_secret(&self, originated: bool) -> Option<SessionSecret> {
+        if !self.done() {
+            return None;
+        }
+
+        let remote_nonce = self.remote_nonce?;
+        let (initiator_nonce, responder_nonce) = if originated {
+            (self.nonce, remote_nonce)
+        } else {
+            (remote_nonce, self.nonce)
+        };
+        Some(SessionSecret::new(initiator_nonce, responder_nonce))
+    }
+
+    /// Returns whether the remote node supports the extended Hello items,
+    /// which is advertised in the nonce and thus known once the nonce is
+    /// received.
+    pub fn remote_extended_hello(&self) -> bool {
+        self.remote_flags().is_some()
+    }
+
+    /// Returns whether the remote node enables the session encryption, which
+    /// is advertised in the nonce.
+    pub fn remote_encryption(&self) -> bool {
+        self.remote_flags()
+            .map_or(false, |flags| flags & FLAG_ENCRYPTION != 0)
+    }
+
+    fn remote_flags(&self) -> Option<u8> {
+        self.remote_nonce.as_ref().and_then(Self::nonce_flags)
+    }
+
+    /// Returns the capability flags in the nonce, or `None` if the nonce does
+    /// not have the magic.
+    fn nonce_flags(nonce: &H256) -> Option<u8> {
+        if nonce.as_bytes().ends_with(NONCE_MAGIC) {
+            Some(nonce[NONCE_FLAGS_POS])
+        } else {
+            None
+        }
+    }
+
     /// Readable IO handler. Drives the state change.
     pub fn readable<Message>(
         &mut self, io: &IoContext<Message>, host: &HostMetadata,
+        encryption: &SessionEncryptionConfig,
     ) -> Result<bool, Error>
     where Message: Send + Clone + Sync + 'static {
         trace!("handshake readable enter, state = {:?}", self.state);
@@ -123,7 +208,7 @@ impl Handshake {
                 {
                     self.read_node_id(io, host.id(), &data)?;
                 } else {
-                    self.read_auth(io, host.secret(), &data)?;
+                    self.read_auth(io, host.secret(), encryption, &data)?;
                 }
             }
             HandshakeState::ReadingAckofAuth => {
@@ -168,7 +253,8 @@ impl Handshake {
 
     /// Parse, validate and confirm auth message
     fn read_auth<Message>(
-        &mut self, io: &IoContext<Message>, secret: &Secret, data: &[u8],
+        &mut self, io: &IoContext<Message>, secret: &Secret,
+        encryption: &SessionEncryptionConfig, data: &[u8],
     ) -> Result<(), Error>
     where Message: Send + Clone + Sync + 'static {
         trace!(
@@ -189,6 +275,8 @@ impl Handshake {
 
         let (remote_public, remote_nonce) = auth.split_at(NodeId::len_bytes());
         self.id.assign_from_slice(remote_public);
+        self.remote_nonce = Some(H256::from_slice(remote_nonce));
+        self.set_flags(encryption);
 
         self.write_ack_of_auth(io, remote_nonce)
     }
@@ -264,6 +352,7 @@ impl Handshake {
             debug!("failed to read ack of auth, nonce mismatch");
             return Err(ErrorKind::BadProtocol.into());
         }
+        self.remote_nonce = Some(H256::from_slice(remote_nonce));
 
         self.write_ack_of_ack(io, remote_nonce)
     }
@@ -314,3 +403,20 @@ impl Handshake {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_magic() {
+        let mut nonce = Handshake::new_nonce();
+        assert!(Handshake::nonce_flags(&nonce).is_some());
+        assert_ne!(nonce, Handshake::new_nonce());
+        assert_eq!(Handshake::nonce_flags(&H256::random()), None);
+
+        nonce[NONCE_FLAGS_POS] = FLAG_ENCRYPTION;
+        assert_eq!(Handshake::nonce_flags(&nonce), Some(FLAG_ENCRYPTION));
+        assert_eq!(&nonce[NONCE_FLAGS_POS + 1..], NONCE_MAGIC);
+    }
+}
@@ -0,0 +1,812 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{
+    node_table::NodeId, service::NetworkServiceInner, NetworkIoMessage, PeerId,
+    ProtocolId,
+};
+use io::{IoChannel, IoContext};
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::{
+    cmp::{max, min, Ordering},
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    sync::{Arc, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Maximum time for the delivery thread to wait before checking whether the
+/// in-memory network is dropped.
+const MAX_DELIVERY_WAIT: Duration = Duration::from_millis(100);
+
+/// In-memory transport that connects the network services of the nodes in the
+/// same process, so that the protocols of a cluster could be tested without
+/// sockets and processes.
+///
+/// Instead of TCP sessions, links between nodes are created and removed
+/// explicitly, and could be blocked by partitions. Protocol packets are passed
+/// to the IO event loop of the remote node directly. Packets of a link are
+/// delivered in order after the latency of the link, and the packets due at
+/// the same time are delivered in the order they are sent, so the delivery
+/// order only depends on the order of sending. The latency is measured by the
+/// wall-clock time, or by a manual clock which is only advanced by
+/// `advance_clock`, so that tests with latency do not depend on the real time.
+#[derive(Clone)]
+pub struct InMemoryNetwork {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    nodes: RwLock<HashMap<NodeId, Endpoint>>,
+    next_index: Mutex<PeerId>,
+    links: Mutex<Links>,
+    queue: Mutex<PacketQueue>,
+    queue_cond: Condvar,
+}
+
+struct Endpoint {
+    /// Index of the node, which is used as the stream token of the packets
+    /// sent by the node, so that they are handled in order by the remote IO
+    /// event loop.
+    index: PeerId,
+    channel: IoChannel<NetworkIoMessage>,
+    service: Weak<NetworkServiceInner>,
+}
+
+#[derive(Default)]
+struct Links {
+    /// Connected links by the ordered node pairs, and the originators.
+    connected: HashMap<(NodeId, NodeId), NodeId>,
+    /// Links removed by the partitions, which are connected again once the
+    /// partitions are healed.
+    severed: HashMap<(NodeId, NodeId), NodeId>,
+    /// Partition group of nodes. Nodes of different groups cannot connect.
+    groups: HashMap<NodeId, usize>,
+    latencies: HashMap<(NodeId, NodeId), Duration>,
+    default_latency: Duration,
+}
+
+enum Clock {
+    /// Wall-clock time since the network is created.
+    System(Instant),
+    /// Time advanced by `InMemoryNetwork::advance_clock` only.
+    Manual(Duration),
+}
+
+impl Clock {
+    fn now(&self) -> Duration {
+        match self {
+            Clock::System(start) => start.elapsed(),
+            Clock::Manual(now) => *now,
+        }
+    }
+}
+
+struct PacketQueue {
+    clock: Clock,
+    packets: BinaryHeap<Packet>,
+    next_seq: u64,
+    /// Number of the queued packets of a directed link, and the time to
+    /// deliver the last one.
+    in_flight: HashMap<(NodeId, NodeId), (usize, Duration)>,
+}
+
+struct Packet {
+    /// Time to deliver the packet by the clock of the queue.
+    deliver_at: Duration,
+    seq: u64,
+    from: NodeId,
+    to: NodeId,
+    protocol: ProtocolId,
+    data: Vec<u8>,
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // earliest packet first in the max heap
+        other
+            .deliver_at
+            .cmp(&self.deliver_at)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for Packet {}
+
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+fn link_key(a: &NodeId, b: &NodeId) -> (NodeId, NodeId) {
+    if a < b {
+        (*a, *b)
+    } else {
+        (*b, *a)
+    }
+}
+
+impl Links {
+    fn same_group(&self, a: &NodeId, b: &NodeId) -> bool {
+        self.groups.get(a) == self.groups.get(b)
+    }
+
+    fn latency(&self, from: &NodeId, to: &NodeId) -> Duration {
+        self.latencies
+            .get(&(*from, *to))
+            .cloned()
+            .unwrap_or(self.default_latency)
+    }
+}
+
+impl Inner {
+    fn io(&self, id: &NodeId) -> Option<IoContext<NetworkIoMessage>> {
+        let nodes = self.nodes.read();
+        let endpoint = nodes.get(id)?;
+        endpoint.service.upgrade()?;
+        Some(IoContext::new(endpoint.channel.clone(), 0))
+    }
+
+    /// Notifies both nodes of a link that is connected or disconnected. The
+    /// notification is handled by the remote IO event loop in the same order
+    /// as the packets of the link, so it should be sent with the `links` lock
+    /// held to happen before or after all the packets of the link.
+    fn notify(&self, a: &NodeId, b: &NodeId, originator: Option<&NodeId>) {
+        let nodes = self.nodes.read();
+        let (a_endpoint, b_endpoint) = match (nodes.get(a), nodes.get(b)) {
+            (Some(a_endpoint), Some(b_endpoint)) => (a_endpoint, b_endpoint),
+            _ => return,
+        };
+
+        for (endpoint, peer, peer_endpoint) in
+            &[(a_endpoint, b, b_endpoint), (b_endpoint, a, a_endpoint)]
+        {
+            if endpoint.service.upgrade().is_none() {
+                continue;
+            }
+            let message = match originator {
+                Some(originator) => {
+                    let service = match peer_endpoint.service.upgrade() {
+                        Some(service) => service,
+                        None => continue,
+                    };
+                    let (protocols, pos_public_key, address) =
+                        service.in_memory_hello();
+                    NetworkIoMessage::InMemoryConnected {
+                        peer: **peer,
+                        index: peer_endpoint.index,
+                        address,
+                        originated: *peer != originator,
+                        protocols,
+                        pos_public_key,
+                    }
+                }
+                None => NetworkIoMessage::InMemoryDisconnected { peer: **peer },
+            };
+            let io = IoContext::new(endpoint.channel.clone(), 0);
+            if let Err(e) = io.handle(peer_endpoint.index, 0, message) {
+                warn!("Error notifying in-memory link: err={}", e);
+            }
+        }
+    }
+
+    fn deliver(&self, packet: Packet) {
+        // packets are dropped if the link is disconnected in flight
+        let links = self.links.lock();
+        if !links
+            .connected
+            .contains_key(&link_key(&packet.from, &packet.to))
+        {
+            return;
+        }
+
+        let from_index = match self.nodes.read().get(&packet.from) {
+            Some(endpoint) => endpoint.index,
+            None => return,
+        };
+        let io = match self.io(&packet.to) {
+            Some(io) => io,
+            None => return,
+        };
+        let message = NetworkIoMessage::HandleProtocolMessage {
+            protocol: packet.protocol,
+            peer: from_index,
+            node_id: packet.from,
+            data: packet.data,
+        };
+        if let Err(e) = io.handle(from_index, 0, message) {
+            warn!("Error delivering in-memory packet: err={}", e);
+        }
+    }
+
+    /// Delivers the queued packets that are due, or waits for the next one.
+    fn deliver_due_packet(&self) {
+        let mut queue = self.queue.lock();
+        let now = queue.clock.now();
+        let wait = match queue.packets.peek() {
+            Some(packet) if packet.deliver_at <= now => {
+                let packet = queue.packets.pop().expect("peeked");
+                drop(queue);
+
+                let link = (packet.from, packet.to);
+                self.deliver(packet);
+
+                // the packet is still in flight until delivered, so that the
+                // packets sent meanwhile are queued after it
+                let mut queue = self.queue.lock();
+                let remaining = match queue.in_flight.get_mut(&link) {
+                    Some((count, _)) => {
+                        *count -= 1;
+                        *count
+                    }
+                    None => 0,
+                };
+                if remaining == 0 {
+                    queue.in_flight.remove(&link);
+                }
+                return;
+            }
+            // the manual clock notifies once advanced
+            Some(packet) => match queue.clock {
+                Clock::System(_) => {
+                    min(packet.deliver_at - now, MAX_DELIVERY_WAIT)
+                }
+                Clock::Manual(_) => MAX_DELIVERY_WAIT,
+            },
+            None => MAX_DELIVERY_WAIT,
+        };
+        self.queue_cond.wait_for(&mut queue, wait);
+    }
+}
+
+impl InMemoryNetwork {
+    pub fn new() -> Self { Self::with_clock(Clock::System(Instant::now())) }
+
+    /// Creates a network whose latency is measured by a manual clock, which
+    /// starts at zero and is advanced by `advance_clock`.
+    pub fn with_manual_clock() -> Self {
+        Self::with_clock(Clock::Manual(Duration::from_secs(0)))
+    }
+
+    fn with_clock(clock: Clock) -> Self {
+        let inner = Arc::new(Inner {
+            nodes: RwLock::new(HashMap::new()),
+            next_index: Mutex::new(0),
+            links: Mutex::new(Links::default()),
+            queue: Mutex::new(PacketQueue {
+                clock,
+                packets: BinaryHeap::new(),
+                next_seq: 0,
+                in_flight: HashMap::new(),
+            }),
+            queue_cond: Condvar::new(),
+        });
+
+        let weak = Arc::downgrade(&inner);
+        thread::Builder::new()
+            .name("in-memory network".into())
+            .spawn(move || {
+                while let Some(inner) = weak.upgrade() {
+                    inner.deliver_due_packet();
+                }
+            })
+            .expect("in-memory network thread spawn error");
+
+        InMemoryNetwork { inner }
+    }
+
+    /// Attaches the network service of a node, which is called when the
+    /// service is initialized.
+    pub(crate) fn register(
+        &self, service: &Arc<NetworkServiceInner>,
+        channel: IoChannel<NetworkIoMessage>,
+    )
+    {
+        let index = {
+            let mut next_index = self.inner.next_index.lock();
+            let index = *next_index;
+            *next_index += 1;
+            index
+        };
+        let id = *service.metadata.id();
+        debug!("Register in-memory node {:?}, index = {}", id, index);
+        self.inner.nodes.write().insert(
+            id,
+            Endpoint {
+                index,
+                channel,
+                service: Arc::downgrade(service),
+            },
+        );
+    }
+
+    /// Returns the ids of the nodes attached.
+    pub fn nodes(&self) -> Vec<NodeId> {
+        self.inner
+            .nodes
+            .read()
+            .iter()
+            .filter(|(_, endpoint)| endpoint.service.upgrade().is_some())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Detaches a node, e.g. when the node is stopped, and disconnects all its
+    /// links.
+    pub fn remove_node(&self, id: &NodeId) {
+        let mut links = self.inner.links.lock();
+        let peers: Vec<NodeId> = links
+            .connected
+            .keys()
+            .filter_map(|(a, b)| {
+                if a == id {
+                    Some(*b)
+                } else if b == id {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for peer in peers {
+            links.connected.remove(&link_key(id, &peer));
+            self.inner.notify(id, &peer, None);
+        }
+        links.severed.retain(|(a, b), _| a != id && b != id);
+        links.groups.remove(id);
+        self.inner.nodes.write().remove(id);
+    }
+
+    /// Connects two nodes as if `originator` dialed `peer`. Returns false if
+    /// either node is not attached, they are already connected, or they are
+    /// in different partitions.
+    pub fn connect(&self, originator: &NodeId, peer: &NodeId) -> bool {
+        if originator == peer
+            || self.inner.io(originator).is_none()
+            || self.inner.io(peer).is_none()
+        {
+            return false;
+        }
+
+        let mut links = self.inner.links.lock();
+        let key = link_key(originator, peer);
+        if links.connected.contains_key(&key)
+            || !links.same_group(originator, peer)
+        {
+            return false;
+        }
+
+        debug!("Connect in-memory link {:?} -> {:?}", originator, peer);
+        links.connected.insert(key, *originator);
+        self.inner.notify(originator, peer, Some(originator));
+        true
+    }
+
+    /// Disconnects two nodes. Returns false if they are not connected.
+    pub fn disconnect(&self, a: &NodeId, b: &NodeId) -> bool {
+        let mut links = self.inner.links.lock();
+        if links.connected.remove(&link_key(a, b)).is_none() {
+            return false;
+        }
+
+        debug!("Disconnect in-memory link {:?} - {:?}", a, b);
+        self.inner.notify(a, b, None);
+        true
+    }
+
+    pub fn is_connected(&self, a: &NodeId, b: &NodeId) -> bool {
+        self.inner
+            .links
+            .lock()
+            .connected
+            .contains_key(&link_key(a, b))
+    }
+
+    /// Sets the latency of the packets sent from `from` to `to`.
+    pub fn set_latency(&self, from: &NodeId, to: &NodeId, latency: Duration) {
+        self.inner
+            .links
+            .lock()
+            .latencies
+            .insert((*from, *to), latency);
+    }
+
+    /// Advances the manual clock, and delivers the packets due.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network is not created by `with_manual_clock`.
+    pub fn advance_clock(&self, duration: Duration) {
+        let mut queue = self.inner.queue.lock();
+        match &mut queue.clock {
+            Clock::Manual(now) => *now += duration,
+            Clock::System(_) => {
+                panic!("advance the system clock of in-memory network")
+            }
+        }
+        self.inner.queue_cond.notify_one();
+    }
+
+    /// Returns the number of the packets sent from `from` to `to` that are
+    /// not delivered yet due to the latency.
+    pub fn in_flight(&self, from: &NodeId, to: &NodeId) -> usize {
+        self.inner
+            .queue
+            .lock()
+            .in_flight
+            .get(&(*from, *to))
+            .map_or(0, |(count, _)| *count)
+    }
+
+    /// Sets the latency of the links without a latency set explicitly.
+    pub fn set_default_latency(&self, latency: Duration) {
+        self.inner.links.lock().default_latency = latency;
+    }
+
+    /// Splits the nodes into partitions, and disconnects the links between
+    /// partitions. The nodes not in `groups` form another partition. Any
+    /// previous partitions are replaced.
+    pub fn partition(&self, groups: &[Vec<NodeId>]) {
+        let mut links = self.inner.links.lock();
+        links.groups.clear();
+        for (i, group) in groups.iter().enumerate() {
+            for id in group {
+                // group 0 is reserved for the nodes not specified
+                links.groups.insert(*id, i + 1);
+            }
+        }
+
+        let severed: Vec<((NodeId, NodeId), NodeId)> = links
+            .connected
+            .iter()
+            .filter(|((a, b), _)| !links.same_group(a, b))
+            .map(|(key, originator)| (*key, *originator))
+            .collect();
+        for (key, originator) in severed {
+            debug!("Partition in-memory link {:?} - {:?}", key.0, key.1);
+            links.connected.remove(&key);
+            links.severed.insert(key, originator);
+            self.inner.notify(&key.0, &key.1, None);
+        }
+    }
+
+    /// Removes the partitions, and connects again the links disconnected by
+    /// the partitions.
+    pub fn heal(&self) {
+        let mut links = self.inner.links.lock();
+        links.groups.clear();
+        let severed: Vec<((NodeId, NodeId), NodeId)> =
+            links.severed.drain().collect();
+        for (key, originator) in severed {
+            if links.connected.contains_key(&key) {
+                continue;
+            }
+            let peer = if originator == key.0 { key.1 } else { key.0 };
+            debug!("Heal in-memory link {:?} -> {:?}", originator, peer);
+            links.connected.insert(key, originator);
+            self.inner.notify(&originator, &peer, Some(&originator));
+        }
+    }
+
+    /// Sends a protocol packet, which is dropped silently if the nodes are not
+    /// connected, as the TCP transport does without a session.
+    pub(crate) fn send(
+        &self, from: &NodeId, to: &NodeId, protocol: ProtocolId, data: Vec<u8>,
+    ) {
+        let links = self.inner.links.lock();
+        if !links.connected.contains_key(&link_key(from, to)) {
+            trace!("Drop in-memory packet to disconnected peer {:?}", to);
+            return;
+        }
+        let latency = links.latency(from, to);
+
+        let mut queue = self.inner.queue.lock();
+        let now = queue.clock.now();
+        let link = (*from, *to);
+        if latency == Duration::from_secs(0)
+            && !queue.in_flight.contains_key(&link)
+        {
+            drop(queue);
+            drop(links);
+            self.inner.deliver(Packet {
+                deliver_at: now,
+                seq: 0,
+                from: *from,
+                to: *to,
+                protocol,
+                data,
+            });
+            return;
+        }
+
+        // packets of a link are delivered in order even if the latency is
+        // lowered
+        let deliver_at = match queue.in_flight.get(&link) {
+            Some((_, last)) => max(now + latency, *last),
+            None => now + latency,
+        };
+        let in_flight = queue.in_flight.entry(link).or_insert((0, deliver_at));
+        in_flight.0 += 1;
+        in_flight.1 = deliver_at;
+        let seq = queue.next_seq;
+        queue.next_seq += 1;
+        queue.packets.push(Packet {
+            deliver_at,
+            seq,
+            from: *from,
+            to: *to,
+            protocol,
+            data,
+        });
+        self.inner.queue_cond.notify_one();
+    }
+}
+
+impl Default for InMemoryNetwork {
+    fn default() -> Self { InMemoryNetwork::new() }
+}
+
+impl fmt::Debug for InMemoryNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "InMemoryNetwork {{ nodes: {}, links: {} }}",
+            self.inner.nodes.read().len(),
+            self.inner.links.lock().connected.len()
+        )
+    }
+}
+
+impl PartialEq for InMemoryNetwork {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::ProtocolVersion, DiscoveryConfiguration, HandlerWorkType,
+        NetworkConfiguration, NetworkContext, NetworkProtocolHandler,
+        NetworkService,
+    };
+    use diem_crypto::{PrivateKey, Uniform};
+    use diem_types::validator_config::{
+        ConsensusPrivateKey, ConsensusPublicKey, ConsensusVRFPrivateKey,
+        ConsensusVRFPublicKey,
+    };
+    use io::TimerToken;
+    use priority_send_queue::SendQueuePriority;
+
+    const PROTOCOL: ProtocolId = *b"tst";
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        connected: Mutex<Vec<(NodeId, ProtocolVersion)>>,
+        disconnected: Mutex<Vec<NodeId>>,
+        messages: Mutex<Vec<(NodeId, Vec<u8>)>>,
+    }
+
+    impl NetworkProtocolHandler for RecordingHandler {
+        fn minimum_supported_version(&self) -> ProtocolVersion {
+            ProtocolVersion(1)
+        }
+
+        fn initialize(&self, _io: &dyn NetworkContext) {}
+
+        fn on_message(
+            &self, _io: &dyn NetworkContext, node_id: &NodeId, data: &[u8],
+        ) {
+            self.messages.lock().push((*node_id, data.to_vec()));
+        }
+
+        fn on_peer_connected(
+            &self, _io: &dyn NetworkContext, node_id: &NodeId,
+            peer_protocol_version: ProtocolVersion,
+            _pos_public_key: Option<(
+                ConsensusPublicKey,
+                ConsensusVRFPublicKey,
+            )>,
+        )
+        {
+            self.connected
+                .lock()
+                .push((*node_id, peer_protocol_version));
+        }
+
+        fn on_peer_disconnected(
+            &self, _io: &dyn NetworkContext, node_id: &NodeId,
+        ) {
+            self.disconnected.lock().push(*node_id);
+        }
+
+        fn on_timeout(&self, _io: &dyn NetworkContext, _timer: TimerToken) {}
+
+        fn send_local_message(
+            &self, _io: &dyn NetworkContext, _message: Vec<u8>,
+        ) {
+        }
+
+        fn on_work_dispatch(
+            &self, _io: &dyn NetworkContext, _work_type: HandlerWorkType,
+        ) {
+        }
+    }
+
+    struct TestNode {
+        service: NetworkService,
+        handler: Arc<RecordingHandler>,
+    }
+
+    impl TestNode {
+        fn new(network: &InMemoryNetwork) -> TestNode {
+            let mut config =
+                NetworkConfiguration::new(1, DiscoveryConfiguration::default());
+            config.config_path = None;
+            config.nat_enabled = false;
+            config.in_memory = Some(network.clone());
+
+            let private_key = ConsensusPrivateKey::generate_for_testing();
+            let vrf_private_key =
+                ConsensusVRFPrivateKey::generate_for_testing();
+            let mut service = NetworkService::new(config);
+            service
+                .initialize((
+                    private_key.public_key(),
+                    vrf_private_key.public_key(),
+                ))
+                .unwrap();
+            let handler = Arc::new(RecordingHandler::default());
+            service
+                .register_protocol(
+                    handler.clone(),
+                    PROTOCOL,
+                    ProtocolVersion(1),
+                )
+                .unwrap();
+            TestNode { service, handler }
+        }
+
+        fn id(&self) -> NodeId {
+            *self.service.net_key_pair().unwrap().public()
+        }
+
+        fn send(&self, to: &NodeId, msg: Vec<u8>) {
+            self.service
+                .with_context(self.handler.clone(), PROTOCOL, |io| {
+                    io.send(
+                        to,
+                        msg,
+                        ProtocolVersion(1),
+                        ProtocolVersion(u8::MAX),
+                        SendQueuePriority::High,
+                    )
+                    .unwrap()
+                })
+                .unwrap();
+        }
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_connect_and_send() {
+        let network = InMemoryNetwork::new();
+        let a = TestNode::new(&network);
+        let b = TestNode::new(&network);
+        assert_eq!(network.nodes().len(), 2);
+
+        assert!(network.connect(&a.id(), &b.id()));
+        assert!(!network.connect(&b.id(), &a.id()));
+        wait_until(|| b.handler.connected.lock().len() == 1);
+        wait_until(|| a.handler.connected.lock().len() == 1);
+        assert_eq!(
+            a.service
+                .inner
+                .as_ref()
+                .unwrap()
+                .get_peer_connection_origin(&b.id()),
+            Some(true)
+        );
+        assert_eq!(
+            b.service
+                .inner
+                .as_ref()
+                .unwrap()
+                .get_peer_connection_origin(&a.id()),
+            Some(false)
+        );
+
+        for i in 0..100u8 {
+            a.send(&b.id(), vec![i]);
+        }
+        wait_until(|| b.handler.messages.lock().len() == 100);
+        let received: Vec<u8> = b
+            .handler
+            .messages
+            .lock()
+            .iter()
+            .map(|(_, m)| m[0])
+            .collect();
+        assert_eq!(received, (0..100u8).collect::<Vec<_>>());
+
+        assert!(network.disconnect(&a.id(), &b.id()));
+        wait_until(|| a.handler.disconnected.lock().len() == 1);
+        wait_until(|| b.handler.disconnected.lock().len() == 1);
+
+        // packets to disconnected peers are dropped, and the packets of a
+        // link are handled in order
+        a.send(&b.id(), vec![0]);
+        assert!(network.connect(&a.id(), &b.id()));
+        wait_until(|| a.handler.connected.lock().len() == 2);
+        a.send(&b.id(), vec![1]);
+        wait_until(|| b.handler.messages.lock().len() == 101);
+        assert_eq!(b.handler.messages.lock()[100].1, vec![1]);
+    }
+
+    #[test]
+    fn test_latency_keeps_order() {
+        let network = InMemoryNetwork::with_manual_clock();
+        let a = TestNode::new(&network);
+        let b = TestNode::new(&network);
+        assert!(network.connect(&a.id(), &b.id()));
+        wait_until(|| a.handler.connected.lock().len() == 1);
+
+        network.set_latency(&a.id(), &b.id(), Duration::from_millis(200));
+        a.send(&b.id(), vec![0]);
+        // lowering the latency does not reorder the packets in flight
+        network.set_latency(&a.id(), &b.id(), Duration::from_millis(0));
+        a.send(&b.id(), vec![1]);
+        assert_eq!(network.in_flight(&a.id(), &b.id()), 2);
+
+        network.advance_clock(Duration::from_millis(199));
+        assert_eq!(network.in_flight(&a.id(), &b.id()), 2);
+        network.advance_clock(Duration::from_millis(1));
+        wait_until(|| b.handler.messages.lock().len() == 2);
+        wait_until(|| network.in_flight(&a.id(), &b.id()) == 0);
+        let received: Vec<u8> = b
+            .handler
+            .messages
+            .lock()
+            .iter()
+            .map(|(_, m)| m[0])
+            .collect();
+        assert_eq!(received, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_partition_and_heal() {
+        let network = InMemoryNetwork::new();
+        let a = TestNode::new(&network);
+        let b = TestNode::new(&network);
+        let c = TestNode::new(&network);
+        assert!(network.connect(&a.id(), &b.id()));
+        assert!(network.connect(&b.id(), &c.id()));
+        wait_until(|| b.handler.connected.lock().len() == 2);
+
+        network.partition(&[vec![a.id(), b.id()], vec![c.id()]]);
+        assert!(network.is_connected(&a.id(), &b.id()));
+        assert!(!network.is_connected(&b.id(), &c.id()));
+        assert!(!network.connect(&a.id(), &c.id()));
+        wait_until(|| c.handler.disconnected.lock().len() == 1);
+
+        network.heal();
+        assert!(network.is_connected(&b.id(), &c.id()));
+        wait_until(|| c.handler.connected.lock().len() == 2);
+        assert!(network.connect(&a.id(), &c.id()));
+    }
+}
@@ -37,20 +37,28 @@ pub type ProtocolId = [u8; PROTOCOL_ID_SIZE];
 pub type HandlerWorkType = u8;
 pub type PeerId = usize;
 
+mod compression;
 mod connection;
 mod discovery;
+pub mod encryption;
 mod error;
 mod handshake;
+pub mod in_memory;
 mod ip;
 mod ip_utils;
 mod node_database;
+pub mod node_record;
 pub mod node_table;
+pub mod reputation;
+mod reserved_peers;
 pub mod service;
 mod session;
 mod session_manager;
 pub mod throttling;
+pub mod transport;
 
 pub use crate::{
+    compression::Compression,
     error::{DisconnectReason, Error, ErrorKind, ThrottlingReason},
     ip::SessionIpLimitConfig,
     node_table::Node,
@@ -60,13 +68,18 @@ pub use crate::{
 pub use io::TimerToken;
 
 use crate::{
+    encryption::SessionEncryptionConfig,
+    in_memory::InMemoryNetwork,
+    node_record::NodeCapabilities,
     node_table::NodeId,
+    reputation::{ReputationConfig, ReputationEvent},
     service::{
         ProtocolVersion, DEFAULT_CONNECTION_LIFETIME_FOR_PROMOTION,
         DEFAULT_DISCOVERY_REFRESH_TIMEOUT, DEFAULT_DISCOVERY_ROUND_TIMEOUT,
         DEFAULT_FAST_DISCOVERY_REFRESH_TIMEOUT, DEFAULT_HOUSEKEEPING_TIMEOUT,
         DEFAULT_NODE_TABLE_TIMEOUT,
     },
+    transport::QuicConfiguration,
 };
 use cfx_addr::Network;
 use diem_types::validator_config::{ConsensusPublicKey, ConsensusVRFPublicKey};
@@ -86,6 +99,7 @@ use std::{
 pub const NODE_TAG_NODE_TYPE: &str = "node_type";
 pub const NODE_TAG_ARCHIVE: &str = "archive";
 pub const NODE_TAG_FULL: &str = "full";
+pub const NODE_TAG_LIGHT: &str = "light";
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NetworkConfiguration {
@@ -119,6 +133,8 @@ pub struct NetworkConfiguration {
     pub max_handshakes: usize,
     /// List of reserved node addresses.
     pub reserved_nodes: Vec<String>,
+    /// Only connect to the reserved nodes
+    pub reserved_only: bool,
     /// IP filter
     pub ip_filter: IpFilter,
     /// Timeout duration for initiating peer connection management
@@ -139,6 +155,24 @@ pub struct NetworkConfiguration {
     /// Maximum number of P2P nodes for subnet B (ip/16).
     pub subnet_quota: usize,
     pub session_ip_limit_config: SessionIpLimitConfig,
+    /// Encryption of the protocol packets, by default and per peer
+    pub session_encryption: SessionEncryptionConfig,
+    /// Compression algorithms of the protocol packets in the order of
+    /// preference, and the minimum size of packets to compress
+    pub session_compression: Vec<Compression>,
+    pub session_compression_threshold: usize,
+    /// Decay and ban of the peer reputation
+    pub reputation: ReputationConfig,
+    /// Connect to the nodes in the same process through the in-memory network
+    /// instead of TCP and UDP, which is only used in tests
+    pub in_memory: Option<InMemoryNetwork>,
+    /// Capabilities of the local node advertised in discovery. If set, the
+    /// nodes that advertise they cannot serve the local node are not
+    /// connected to.
+    pub node_capabilities: Option<NodeCapabilities>,
+    /// Accept and create sessions through QUIC as well if set, which is
+    /// advertised in the node record. Requires the `quic` feature.
+    pub quic: Option<QuicConfiguration>,
 
     pub discovery_config: DiscoveryConfiguration,
 }
@@ -164,6 +198,7 @@ impl NetworkConfiguration {
             max_incoming_peers: 0,
             max_handshakes: 0,
             reserved_nodes: Vec::new(),
+            reserved_only: false,
             ip_filter: IpFilter::default(),
             housekeeping_timeout: DEFAULT_HOUSEKEEPING_TIMEOUT,
             discovery_refresh_timeout: DEFAULT_DISCOVERY_REFRESH_TIMEOUT,
@@ -176,6 +211,13 @@ impl NetworkConfiguration {
             test_mode: false,
             subnet_quota: 32,
             session_ip_limit_config: SessionIpLimitConfig::default(),
+            session_encryption: SessionEncryptionConfig::default(),
+            session_compression: Vec::new(),
+            session_compression_threshold: 1024,
+            reputation: ReputationConfig::default(),
+            in_memory: None,
+            node_capabilities: None,
+            quic: None,
             discovery_config,
         }
     }
@@ -212,6 +254,8 @@ pub struct DiscoveryConfiguration {
     pub throttling_interval: Duration,
     pub throttling_limit_ping: usize,
     pub throttling_limit_find_nodes: usize,
+    /// Time to live of the node record advertised in discovery
+    pub record_ttl: Duration,
 }
 
 impl DiscoveryConfiguration {
@@ -262,6 +306,21 @@ pub enum NetworkIoMessage {
         node_id: NodeId,
         data: Vec<u8>,
     },
+    /// A peer is connected through the in-memory network
+    InMemoryConnected {
+        peer: NodeId,
+        /// Index of the peer in the in-memory network
+        index: PeerId,
+        address: SocketAddr,
+        /// Whether the link is originated by this node
+        originated: bool,
+        protocols: Vec<ProtocolInfo>,
+        pos_public_key: Option<(ConsensusPublicKey, ConsensusVRFPublicKey)>,
+    },
+    /// A peer is disconnected from the in-memory network
+    InMemoryDisconnected {
+        peer: NodeId,
+    },
 }
 
 pub trait NetworkProtocolHandler: Sync + Send {
@@ -288,6 +347,19 @@ pub trait NetworkProtocolHandler: Sync + Send {
     fn on_work_dispatch(
         &self, _io: &dyn NetworkContext, _work_type: HandlerWorkType,
     );
+
+    /// Called when a message is compressed before sent, which is mainly used
+    /// for metrics.
+    fn on_message_compressed(
+        &self, _msg_id: u16, _size: usize, _compressed_size: usize,
+    ) {
+    }
+
+    /// Returns the statistics of the specified peer maintained by the
+    /// protocol handler, which is shown in the session details of Debug RPC.
+    fn peer_stats(&self, _node_id: &NodeId) -> Option<serde_json::Value> {
+        None
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -325,6 +397,24 @@ pub trait NetworkContext {
     fn is_peer_self(&self, _node_id: &NodeId) -> bool;
 
     fn self_node_id(&self) -> NodeId;
+
+    /// Reports an event that affects the reputation of the peer, which is
+    /// disconnected and banned if its score drops to the threshold. Returns
+    /// the updated score of the peer.
+    fn report_peer(&self, node_id: &NodeId, event: ReputationEvent) -> f64;
+
+    /// Returns the reputation score of the peer.
+    fn get_peer_score(&self, node_id: &NodeId) -> f64;
+
+    /// Returns the capabilities advertised in the unexpired discovery record
+    /// of the peer, or `None` if the peer has not advertised any.
+    fn get_peer_capabilities(
+        &self, node_id: &NodeId,
+    ) -> Option<NodeCapabilities>;
+
+    /// Sets whether the local node serves state snapshots, which is
+    /// advertised in its discovery record.
+    fn set_snapshot_capability(&self, snapshot: bool);
 }
 
 #[derive(Debug, Clone)]
@@ -4,11 +4,16 @@
 
 use crate::{
     ip::{NodeIpLimit, NodeTagIndex, ValidateInsertResult},
+    node_record::NodeRecord,
     node_table::{Node, NodeContact, NodeEntry, NodeId, NodeTable},
     IpFilter,
 };
 use io::StreamToken;
-use std::{collections::HashSet, net::IpAddr, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    time::Duration,
+};
 
 const TRUSTED_NODES_FILE: &str = "trusted_nodes.json";
 const UNTRUSTED_NODES_FILE: &str = "untrusted_nodes.json";
@@ -102,6 +107,11 @@ pub struct NodeDatabase {
     // 3. remove tag indices when demote a node
     // 4. remove tag indices when delete a trusted node
     trusted_node_tag_index: NodeTagIndex,
+
+    // Latest node records received from UDP discovery, which are kept in
+    // memory until expired. The capabilities in the records are also set as
+    // node tags, so as to sample nodes of desired capability.
+    records: HashMap<NodeId, NodeRecord>,
 }
 
 impl NodeDatabase {
@@ -120,6 +130,7 @@ impl NodeDatabase {
             blacklisted_lifetime: Duration::from_secs(7 * 24 * 3600),
             ip_limit,
             trusted_node_tag_index,
+            records: HashMap::new(),
         };
 
         db.init(false /* trusted */);
@@ -566,6 +577,95 @@ impl NodeDatabase {
         );
     }
 
+    /// Remove the tag of the specified node if the tag value is `value`.
+    pub fn remove_tag(&mut self, id: &NodeId, key: &str, value: &str) {
+        let trusted = self.trusted_nodes.contains(id);
+        let node = if trusted {
+            self.trusted_nodes.get_mut(id)
+        } else {
+            self.untrusted_nodes.get_mut(id)
+        };
+        let node = match node {
+            Some(node) => node,
+            None => return,
+        };
+
+        if node.tags.get(key).map(String::as_str) != Some(value) {
+            return;
+        }
+        node.tags.remove(key);
+
+        // tag index is only maintained for trusted node
+        if !trusted || !self.ip_limit.is_enabled() {
+            return;
+        }
+
+        let subnet = self
+            .ip_limit
+            .subnet(id)
+            .expect("node index should always exist");
+        self.trusted_node_tag_index.remove(
+            id,
+            subnet,
+            &key.into(),
+            &value.into(),
+        );
+    }
+
+    /// Update the record of the specified node if it is not older than the
+    /// existing one, and set the capabilities in the record as node tags.
+    /// The record is ignored if the node is not in database.
+    pub fn set_record(&mut self, id: NodeId, record: NodeRecord) {
+        if self.get(&id, false /* trusted_only */).is_none() {
+            return;
+        }
+
+        let new_tags = record.capabilities.tags();
+        if let Some(old) = self.records.get(&id) {
+            if old.seq > record.seq {
+                return;
+            }
+
+            let stale_tags: Vec<(&str, String)> = old
+                .capabilities
+                .tags()
+                .into_iter()
+                .filter(|tag| !new_tags.contains(tag))
+                .collect();
+            for (key, value) in stale_tags {
+                self.remove_tag(&id, key, &value);
+            }
+        }
+
+        for (key, value) in new_tags {
+            self.set_tag(id, key, &value);
+        }
+        self.records.insert(id, record);
+    }
+
+    /// Get the unexpired record of the specified node.
+    pub fn get_record(&self, id: &NodeId) -> Option<&NodeRecord> {
+        self.records.get(id).filter(|record| !record.is_expired())
+    }
+
+    /// Remove the expired records and the node tags set by them.
+    pub fn remove_expired_records(&mut self) {
+        let expired: Vec<NodeId> = self
+            .records
+            .iter()
+            .filter(|(_, record)| record.is_expired())
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            if let Some(record) = self.records.remove(&id) {
+                for (key, value) in record.capabilities.tags() {
+                    self.remove_tag(&id, key, &value);
+                }
+            }
+        }
+    }
+
     /// Set the specified node to blacklisted.
     pub fn set_blacklisted(&mut self, id: &NodeId) {
         // update the last failure time
@@ -615,7 +715,12 @@ impl NodeDatabase {
 #[cfg(test)]
 mod tests {
     use super::NodeDatabase;
-    use crate::node_table::{NodeEndpoint, NodeEntry, NodeId};
+    use crate::{
+        node_record::{NodeCapabilities, NodeRecord, NODE_TAG_SNAPSHOT},
+        node_table::{NodeEndpoint, NodeEntry, NodeId},
+        NODE_TAG_ARCHIVE, NODE_TAG_FULL, NODE_TAG_NODE_TYPE,
+    };
+    use cfxkey::{Generator, Random};
     use std::{str::FromStr, time::Duration};
 
     fn new_entry(addr: &str) -> NodeEntry {
@@ -797,4 +902,72 @@ mod tests {
         assert_eq!(db.evaluate_blacklisted(&n.id), false);
         assert_eq!(db.get(&n.id, false), None);
     }
+
+    #[test]
+    fn test_set_record() {
+        let mut db = NodeDatabase::new(None, 2);
+
+        let key = Random.generate().unwrap();
+        let mut entry = new_entry("127.0.0.1:999");
+        entry.id = key.public().clone();
+        db.insert_trusted(entry.clone());
+
+        let archive = NodeCapabilities {
+            node_type: NODE_TAG_ARCHIVE.into(),
+            protocols: vec![],
+            snapshot: true,
+            chain_id: 1,
+            transports: vec![],
+        };
+        let record =
+            NodeRecord::new(2, Duration::from_secs(60), archive, key.secret())
+                .unwrap();
+        db.set_record(entry.id, record.clone());
+        assert_eq!(db.get_record(&entry.id), Some(&record));
+        let sampled = db.sample_trusted_node_ids_with_tag(
+            1,
+            &NODE_TAG_NODE_TYPE.into(),
+            &NODE_TAG_ARCHIVE.into(),
+        );
+        assert!(sampled.contains(&entry.id));
+
+        // older record is ignored
+        let full = NodeCapabilities {
+            node_type: NODE_TAG_FULL.into(),
+            protocols: vec![],
+            snapshot: false,
+            chain_id: 1,
+            transports: vec![],
+        };
+        let older = NodeRecord::new(
+            1,
+            Duration::from_secs(60),
+            full.clone(),
+            key.secret(),
+        )
+        .unwrap();
+        db.set_record(entry.id, older);
+        assert_eq!(db.get_record(&entry.id), Some(&record));
+
+        // newer record replaces the tags
+        let newer =
+            NodeRecord::new(3, Duration::from_secs(60), full, key.secret())
+                .unwrap();
+        db.set_record(entry.id, newer.clone());
+        let tags = &db.get(&entry.id, true).unwrap().tags;
+        assert_eq!(
+            tags.get(NODE_TAG_NODE_TYPE).map(String::as_str),
+            Some(NODE_TAG_FULL)
+        );
+        assert!(!tags.contains_key(NODE_TAG_SNAPSHOT));
+
+        // expired record is removed with its tags
+        let mut expired = newer;
+        expired.seq = 4;
+        expired.expire_timestamp = 0;
+        db.set_record(entry.id, expired);
+        assert_eq!(db.get_record(&entry.id), None);
+        db.remove_expired_records();
+        assert!(db.get(&entry.id, true).unwrap().tags.is_empty());
+    }
 }
@@ -0,0 +1,326 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{
+    hash::keccak, node_table::NodeId, transport::TransportInfo, Error,
+    ProtocolId, ProtocolInfo, NODE_TAG_LIGHT, NODE_TAG_NODE_TYPE,
+};
+use cfx_types::{H256, H520};
+use cfxkey::{recover, sign, Secret};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Node tag of the nodes that serve state snapshots.
+pub const NODE_TAG_SNAPSHOT: &str = "snapshot";
+/// Node tag of the chain id of the nodes.
+pub const NODE_TAG_CHAIN_ID: &str = "chain_id";
+
+/// Capabilities of a node advertised in discovery, so that nodes can tell
+/// whether a peer could serve them before connecting to it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NodeCapabilities {
+    /// Node type, e.g. `NODE_TAG_ARCHIVE`, `NODE_TAG_FULL` or
+    /// `NODE_TAG_LIGHT`.
+    pub node_type: String,
+    /// Supported protocols and their latest versions.
+    pub protocols: Vec<ProtocolInfo>,
+    /// Whether the node serves state snapshots.
+    pub snapshot: bool,
+    pub chain_id: u64,
+    /// Transports other than TCP that the node accepts sessions on, in the
+    /// order of preference. TCP on the node endpoint is always accepted.
+    pub transports: Vec<TransportInfo>,
+}
+
+impl NodeCapabilities {
+    /// Returns the node tags of the capabilities, which are used to look up
+    /// the nodes of some capability in the node database.
+    pub fn tags(&self) -> Vec<(&'static str, String)> {
+        let mut tags = vec![(NODE_TAG_CHAIN_ID, self.chain_id.to_string())];
+        if !self.node_type.is_empty() {
+            tags.push((NODE_TAG_NODE_TYPE, self.node_type.clone()));
+        }
+        if self.snapshot {
+            tags.push((NODE_TAG_SNAPSHOT, true.to_string()));
+        }
+        tags
+    }
+
+    /// Returns whether the peer with these capabilities could serve the local
+    /// node, i.e. it is of the same chain, supports any local protocol of the
+    /// minimum version, and is not a light node if the local node is.
+    pub fn can_serve(
+        &self, local: &NodeCapabilities,
+        minimum_peer_protocol_version: &[ProtocolInfo],
+    ) -> bool
+    {
+        if self.chain_id != local.chain_id {
+            return false;
+        }
+        if local.node_type == NODE_TAG_LIGHT && self.node_type == NODE_TAG_LIGHT
+        {
+            return false;
+        }
+        self.protocols.iter().any(|p| {
+            minimum_peer_protocol_version
+                .iter()
+                .any(|m| m.protocol == p.protocol && m.version <= p.version)
+        })
+    }
+
+    /// Returns whether the node could serve light nodes over `protocol`, i.e.
+    /// it supports the protocol and is not a light node itself.
+    pub fn serves_light_nodes(&self, protocol: &ProtocolId) -> bool {
+        self.node_type != NODE_TAG_LIGHT
+            && self.protocols.iter().any(|p| p.protocol == *protocol)
+    }
+}
+
+impl Encodable for NodeCapabilities {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        // transports are appended only if any, to be compatible with the
+        // nodes that do not support them
+        let item_count = if self.transports.is_empty() { 4 } else { 5 };
+        s.begin_list(item_count)
+            .append(&self.node_type)
+            .append_list(&self.protocols)
+            .append(&self.snapshot)
+            .append(&self.chain_id);
+        if item_count == 5 {
+            s.append_list(&self.transports);
+        }
+    }
+}
+
+impl Decodable for NodeCapabilities {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 && item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(NodeCapabilities {
+            node_type: rlp.val_at(0)?,
+            protocols: rlp.list_at(1)?,
+            snapshot: rlp.val_at(2)?,
+            chain_id: rlp.val_at(3)?,
+            transports: if item_count == 5 {
+                rlp.list_at(4)?
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
+
+/// Node record signed by the node, which carries the node capabilities in
+/// the discovery packets. A record is valid until `expire_timestamp`, and a
+/// record of greater `seq` replaces the previous one of the same node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRecord {
+    pub seq: u64,
+    /// Unix timestamp in seconds when the record expires.
+    pub expire_timestamp: u64,
+    pub capabilities: NodeCapabilities,
+    pub signature: H520,
+}
+
+impl NodeRecord {
+    /// Creates a record signed by `secret` which expires after `ttl`.
+    pub fn new(
+        seq: u64, ttl: Duration, capabilities: NodeCapabilities,
+        secret: &Secret,
+    ) -> Result<Self, Error>
+    {
+        let expire_timestamp = unix_now() + ttl.as_secs();
+        let hash = Self::signing_hash(seq, expire_timestamp, &capabilities);
+        let signature = sign(secret, &hash)?;
+        Ok(NodeRecord {
+            seq,
+            expire_timestamp,
+            capabilities,
+            signature: signature.into(),
+        })
+    }
+
+    fn signing_hash(
+        seq: u64, expire_timestamp: u64, capabilities: &NodeCapabilities,
+    ) -> H256 {
+        let mut s = RlpStream::new_list(3);
+        s.append(&seq)
+            .append(&expire_timestamp)
+            .append(capabilities);
+        keccak(s.out())
+    }
+
+    /// Returns whether the record is signed by the node `id`.
+    pub fn verify(&self, id: &NodeId) -> bool {
+        let hash = Self::signing_hash(
+            self.seq,
+            self.expire_timestamp,
+            &self.capabilities,
+        );
+        match recover(&self.signature.into(), &hash) {
+            Ok(signer) => signer == *id,
+            Err(_) => false,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool { self.expire_timestamp < unix_now() }
+
+    /// Returns the remaining time before the record expires.
+    pub fn remaining_ttl(&self) -> Duration {
+        Duration::from_secs(self.expire_timestamp.saturating_sub(unix_now()))
+    }
+}
+
+impl Encodable for NodeRecord {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4)
+            .append(&self.seq)
+            .append(&self.expire_timestamp)
+            .append(&self.capabilities)
+            .append(&self.signature);
+    }
+}
+
+impl Decodable for NodeRecord {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(NodeRecord {
+            seq: rlp.val_at(0)?,
+            expire_timestamp: rlp.val_at(1)?,
+            capabilities: rlp.val_at(2)?,
+            signature: rlp.val_at(3)?,
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::ProtocolVersion, transport::TransportProtocol, NODE_TAG_FULL,
+    };
+    use cfxkey::{Generator, Random};
+
+    fn capabilities(node_type: &str, chain_id: u64) -> NodeCapabilities {
+        NodeCapabilities {
+            node_type: node_type.into(),
+            protocols: vec![ProtocolInfo {
+                protocol: *b"cfx",
+                version: ProtocolVersion(3),
+            }],
+            snapshot: node_type != NODE_TAG_LIGHT,
+            chain_id,
+            transports: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = Random.generate().unwrap();
+        let record = NodeRecord::new(
+            1,
+            Duration::from_secs(60),
+            capabilities(NODE_TAG_FULL, 1029),
+            key.secret(),
+        )
+        .unwrap();
+        assert!(record.verify(key.public()));
+        assert!(!record.is_expired());
+
+        let decoded: NodeRecord = rlp::decode(&rlp::encode(&record)).unwrap();
+        assert_eq!(decoded, record);
+        assert!(decoded.verify(key.public()));
+
+        let other = Random.generate().unwrap();
+        assert!(!record.verify(other.public()));
+
+        let mut tampered = record.clone();
+        tampered.capabilities.node_type = NODE_TAG_LIGHT.into();
+        assert!(!tampered.verify(key.public()));
+
+        let mut expired = record;
+        expired.expire_timestamp = unix_now() - 1;
+        assert!(expired.is_expired());
+    }
+
+    #[test]
+    fn test_transports() {
+        let key = Random.generate().unwrap();
+        let mut caps = capabilities(NODE_TAG_FULL, 1029);
+        let without_transports = rlp::encode(&caps);
+        caps.transports.push(TransportInfo {
+            protocol: TransportProtocol::Quic,
+            port: 32324,
+        });
+
+        let record = NodeRecord::new(
+            1,
+            Duration::from_secs(60),
+            caps.clone(),
+            key.secret(),
+        )
+        .unwrap();
+        let decoded: NodeRecord = rlp::decode(&rlp::encode(&record)).unwrap();
+        assert_eq!(decoded.capabilities.transports, caps.transports);
+        assert!(decoded.verify(key.public()));
+
+        // capabilities of the nodes that do not support transports
+        let decoded: NodeCapabilities =
+            rlp::decode(&without_transports).unwrap();
+        assert!(decoded.transports.is_empty());
+    }
+
+    #[test]
+    fn test_can_serve() {
+        let minimum_versions = vec![ProtocolInfo {
+            protocol: *b"cfx",
+            version: ProtocolVersion(2),
+        }];
+        let local = capabilities(NODE_TAG_LIGHT, 1029);
+
+        assert!(capabilities(NODE_TAG_FULL, 1029)
+            .can_serve(&local, &minimum_versions));
+        assert!(!capabilities(NODE_TAG_FULL, 1)
+            .can_serve(&local, &minimum_versions));
+        assert!(!capabilities(NODE_TAG_LIGHT, 1029)
+            .can_serve(&local, &minimum_versions));
+
+        let mut old_version = capabilities(NODE_TAG_FULL, 1029);
+        old_version.protocols[0].version = ProtocolVersion(1);
+        assert!(!old_version.can_serve(&local, &minimum_versions));
+
+        let full = capabilities(NODE_TAG_FULL, 1029);
+        assert!(capabilities(NODE_TAG_LIGHT, 1029)
+            .can_serve(&full, &minimum_versions));
+    }
+
+    #[test]
+    fn test_serves_light_nodes() {
+        assert!(capabilities(NODE_TAG_FULL, 1029).serves_light_nodes(b"cfx"));
+        assert!(!capabilities(NODE_TAG_FULL, 1029).serves_light_nodes(b"cfl"));
+        assert!(!capabilities(NODE_TAG_LIGHT, 1029).serves_light_nodes(b"cfx"));
+    }
+
+    #[test]
+    fn test_tags() {
+        let tags = capabilities(NODE_TAG_FULL, 1029).tags();
+        assert!(tags.contains(&(NODE_TAG_CHAIN_ID, "1029".into())));
+        assert!(tags.contains(&(NODE_TAG_NODE_TYPE, NODE_TAG_FULL.into())));
+        assert!(tags.contains(&(NODE_TAG_SNAPSHOT, "true".into())));
+
+        let tags = capabilities(NODE_TAG_LIGHT, 1029).tags();
+        assert!(!tags.iter().any(|(key, _)| *key == NODE_TAG_SNAPSHOT));
+    }
+}
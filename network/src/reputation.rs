@@ -0,0 +1,439 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::node_table::NodeId;
+use serde_derive::{Deserialize, Serialize};
+use serde_json;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{self, Duration, Instant, SystemTime},
+};
+
+const BANNED_NODES_FILE: &str = "banned_nodes.json";
+
+/// Upper bound of the score, so that a peer cannot accumulate enough good
+/// deliveries to misbehave for a long time without being banned.
+pub const MAX_PEER_SCORE: f64 = 100.0;
+
+/// Scores decayed closer to zero are not tracked anymore.
+const MIN_TRACKED_SCORE: f64 = 0.1;
+
+/// Longer bans, e.g. requested through RPC, are shortened to this duration.
+const MAX_BAN_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 3600);
+
+/// Event of a peer that affects its reputation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationEvent {
+    /// The peer delivered the requested data.
+    GoodDelivery,
+    /// The peer responded without any of the requested data.
+    UselessResponse,
+    /// The peer did not respond to a request in time.
+    Timeout,
+    /// The peer sent an invalid block.
+    InvalidBlock,
+}
+
+impl ReputationEvent {
+    pub fn weight(&self) -> f64 {
+        match self {
+            ReputationEvent::GoodDelivery => 1.0,
+            ReputationEvent::UselessResponse => -5.0,
+            ReputationEvent::Timeout => -10.0,
+            ReputationEvent::InvalidBlock => -50.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReputationConfig {
+    /// Duration in which a score decays to half of its value.
+    pub score_half_life: Duration,
+    /// Peers are banned once their score drops to the threshold.
+    pub ban_score: f64,
+    pub ban_duration: Duration,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        ReputationConfig {
+            score_half_life: Duration::from_secs(1800),
+            ban_score: -100.0,
+            ban_duration: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Reputation of a peer for Debug RPC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerScore {
+    pub node_id: NodeId,
+    pub score: f64,
+    /// Unix timestamp in seconds until which the peer is banned.
+    pub banned_until: Option<u64>,
+}
+
+struct Score {
+    value: f64,
+    updated: Instant,
+}
+
+impl Score {
+    fn decayed(&self, half_life: Duration, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let half_lives = elapsed.as_secs_f64() / half_life.as_secs_f64();
+        self.value * 0.5f64.powf(half_lives)
+    }
+}
+
+/// Reputation table maintains the scores of peers, and bans the peers whose
+/// score drops to the threshold for a while.
+///
+/// Scores are changed by the weighted events reported by the protocol
+/// handlers, and decay exponentially towards zero over time, so that both
+/// the misbehaviour and good deliveries of a peer are forgiven or forgotten
+/// eventually. Scores are kept in memory, while the bans are persisted in the
+/// same directory as the node tables so that they survive restarts.
+pub struct ReputationTable {
+    config: ReputationConfig,
+    scores: HashMap<NodeId, Score>,
+    bans: HashMap<NodeId, SystemTime>,
+    path: Option<PathBuf>,
+}
+
+impl ReputationTable {
+    pub fn new(dir: Option<String>, config: ReputationConfig) -> Self {
+        let path = dir.map(|dir| {
+            let mut buf = PathBuf::from(dir);
+            buf.push(BANNED_NODES_FILE);
+            buf
+        });
+
+        let mut table = ReputationTable {
+            config,
+            scores: HashMap::new(),
+            bans: HashMap::new(),
+            path,
+        };
+
+        table.load_from_file();
+        table
+    }
+
+    /// Returns the current score of the specified peer.
+    pub fn score(&self, id: &NodeId) -> f64 {
+        self.scores.get(id).map_or(0.0, |score| {
+            score.decayed(self.config.score_half_life, Instant::now())
+        })
+    }
+
+    /// Updates the score of the specified peer with `event`, and bans the peer
+    /// if its score drops to the threshold. Returns the updated score, and
+    /// whether the peer is banned due to the event.
+    pub fn note(&mut self, id: &NodeId, event: ReputationEvent) -> (f64, bool) {
+        if self.is_banned(id) {
+            return (self.score(id), false);
+        }
+
+        let now = Instant::now();
+        let value = (self.score(id) + event.weight()).min(MAX_PEER_SCORE);
+        if value > self.config.ban_score {
+            self.scores.insert(
+                *id,
+                Score {
+                    value,
+                    updated: now,
+                },
+            );
+            return (value, false);
+        }
+
+        debug!(
+            "Ban peer for low reputation, id = {:?}, score = {}, event = {:?}",
+            id, value, event
+        );
+        let duration = self.config.ban_duration;
+        self.ban(id, duration);
+        (value, true)
+    }
+
+    /// Checks if the specified peer is banned. Expired ban will be removed.
+    pub fn is_banned(&mut self, id: &NodeId) -> bool {
+        match self.bans.get(id) {
+            Some(until) if *until > SystemTime::now() => true,
+            Some(_) => {
+                self.bans.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Bans the specified peer for `duration`, at most `MAX_BAN_DURATION`,
+    /// and resets its score so that the peer starts over when the ban
+    /// expires. The bans are persisted immediately.
+    pub fn ban(&mut self, id: &NodeId, duration: Duration) {
+        self.scores.remove(id);
+        let duration = duration.min(MAX_BAN_DURATION);
+        self.bans.insert(*id, SystemTime::now() + duration);
+        self.save();
+    }
+
+    /// Lifts the ban of the specified peer. Returns false if not banned.
+    pub fn unban(&mut self, id: &NodeId) -> bool {
+        let banned = self.bans.remove(id).is_some();
+        if banned {
+            self.save();
+        }
+        banned
+    }
+
+    /// Returns the scores of all tracked and banned peers.
+    pub fn scores(&self) -> Vec<PeerScore> {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let mut scores: HashMap<NodeId, PeerScore> = self
+            .scores
+            .iter()
+            .map(|(id, score)| {
+                let peer_score = PeerScore {
+                    node_id: *id,
+                    score: score.decayed(self.config.score_half_life, now),
+                    banned_until: None,
+                };
+                (*id, peer_score)
+            })
+            .collect();
+
+        for (id, until) in &self.bans {
+            if *until <= system_now {
+                continue;
+            }
+            scores
+                .entry(*id)
+                .or_insert_with(|| PeerScore {
+                    node_id: *id,
+                    score: 0.0,
+                    banned_until: None,
+                })
+                .banned_until = until
+                .duration_since(time::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs());
+        }
+
+        scores.into_iter().map(|(_, score)| score).collect()
+    }
+
+    fn load_from_file(&mut self) {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return,
+        };
+
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("banned nodes file not found: {:?}", e);
+                return;
+            }
+        };
+        let res: Result<json::BannedNodes, _> = serde_json::from_reader(file);
+        match res {
+            Ok(banned) => {
+                for node in banned.nodes {
+                    if let Some(until) = time::UNIX_EPOCH
+                        .checked_add(Duration::from_secs(node.until))
+                    {
+                        self.bans.insert(node.id, until);
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Error reading banned nodes file: {:?}", e);
+            }
+        }
+    }
+
+    /// Persists the bans, and removes the expired bans and the scores that
+    /// decayed close to zero.
+    pub fn save(&mut self) {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let half_life = self.config.score_half_life;
+        self.scores.retain(|_, score| {
+            score.decayed(half_life, now).abs() >= MIN_TRACKED_SCORE
+        });
+        self.bans.retain(|_, until| *until > system_now);
+
+        let path = match self.path {
+            Some(ref path) => Path::new(path),
+            None => return,
+        };
+
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Error creating banned nodes directory: {:?}", e);
+                return;
+            }
+        }
+
+        let nodes = self
+            .bans
+            .iter()
+            .filter_map(|(id, until)| {
+                let until = until.duration_since(time::UNIX_EPOCH).ok()?;
+                Some(json::BannedNode {
+                    id: *id,
+                    until: until.as_secs(),
+                })
+            })
+            .collect();
+        let banned = json::BannedNodes { nodes };
+
+        match fs::File::create(&path) {
+            Ok(file) => {
+                if let Err(e) = serde_json::to_writer_pretty(file, &banned) {
+                    warn!("Error writing banned nodes file: {:?}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Error creating banned nodes file: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Drop for ReputationTable {
+    fn drop(&mut self) { self.save(); }
+}
+
+mod json {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct BannedNodes {
+        pub nodes: Vec<BannedNode>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct BannedNode {
+        pub id: NodeId,
+        pub until: u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, mem};
+
+    fn new_table() -> ReputationTable {
+        ReputationTable::new(None, ReputationConfig::default())
+    }
+
+    #[test]
+    fn test_note_and_ban() {
+        let mut table = new_table();
+        let id = NodeId::random();
+
+        assert_eq!(table.note(&id, ReputationEvent::GoodDelivery).1, false);
+        assert!(table.score(&id) > 0.9);
+        assert_eq!(table.note(&id, ReputationEvent::Timeout).1, false);
+        assert!(table.score(&id) < -8.9);
+
+        // the 2nd invalid block gets the peer banned
+        assert_eq!(table.note(&id, ReputationEvent::InvalidBlock).1, false);
+        assert_eq!(table.note(&id, ReputationEvent::InvalidBlock).1, true);
+        assert!(table.is_banned(&id));
+        assert_eq!(table.score(&id), 0.0);
+
+        // events are ignored during the ban
+        assert_eq!(table.note(&id, ReputationEvent::InvalidBlock).1, false);
+        let scores = table.scores();
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].node_id, id);
+        assert!(scores[0].banned_until.is_some());
+
+        assert!(table.unban(&id));
+        assert!(!table.unban(&id));
+        assert!(!table.is_banned(&id));
+        assert!(table.scores().is_empty());
+    }
+
+    #[test]
+    fn test_max_score() {
+        let mut table = new_table();
+        let id = NodeId::random();
+        for _ in 0..200 {
+            table.note(&id, ReputationEvent::GoodDelivery);
+        }
+        assert!(table.score(&id) <= MAX_PEER_SCORE);
+        assert!(table.score(&id) > MAX_PEER_SCORE - 1.0);
+    }
+
+    #[test]
+    fn test_decay() {
+        let config = ReputationConfig {
+            score_half_life: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let mut table = ReputationTable::new(None, config);
+        let id = NodeId::random();
+        table.scores.insert(
+            id,
+            Score {
+                value: -40.0,
+                updated: Instant::now() - Duration::from_secs(2),
+            },
+        );
+        assert!((table.score(&id) + 10.0).abs() < 0.1);
+
+        // decayed scores are removed
+        table.scores.get_mut(&id).unwrap().updated =
+            Instant::now() - Duration::from_secs(20);
+        table.save();
+        assert!(table.scores.is_empty());
+    }
+
+    #[test]
+    fn test_ban_persisted() {
+        let id = NodeId::random();
+        let dir = env::temp_dir()
+            .join(format!("reputation_test_{:x}", id.to_low_u64_be()));
+        let dir_str = dir.to_str().map(|s| s.to_string());
+
+        // the tables are not dropped, as if the node crashed
+        let mut table =
+            ReputationTable::new(dir_str.clone(), ReputationConfig::default());
+        table.ban(&id, Duration::from_secs(u64::MAX));
+        let until = table.scores()[0].banned_until.unwrap();
+        mem::forget(table);
+
+        let mut table =
+            ReputationTable::new(dir_str.clone(), ReputationConfig::default());
+        assert!(table.is_banned(&id));
+        assert_eq!(table.scores()[0].banned_until, Some(until));
+        assert!(table.unban(&id));
+        mem::forget(table);
+
+        let mut table =
+            ReputationTable::new(dir_str, ReputationConfig::default());
+        assert!(!table.is_banned(&id));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ban_expiration() {
+        let mut table = new_table();
+        let id = NodeId::random();
+        table.ban(&id, Duration::from_secs(0));
+        assert!(!table.is_banned(&id));
+        assert!(table.bans.is_empty());
+    }
+}
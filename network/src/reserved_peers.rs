@@ -0,0 +1,249 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::node_table::{Node, NodeId};
+use serde_json;
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const RESERVED_NODES_FILE: &str = "reserved_nodes.json";
+
+/// Reserved peers are always connected, and are exempt from the limits of
+/// incoming and outgoing sessions as well as the IP and subnet quotas. In the
+/// reserved-only mode, sessions are established with reserved peers only.
+///
+/// The reserved peers and the mode could be changed at runtime, and are
+/// persisted in the same directory as the node tables so that the changes
+/// survive restarts. The reserved nodes in the configuration are always added
+/// at startup.
+pub struct ReservedPeers {
+    nodes: HashMap<NodeId, Node>,
+    reserved_only: bool,
+    path: Option<PathBuf>,
+}
+
+impl ReservedPeers {
+    pub fn new(
+        dir: Option<String>, config_nodes: &[String], reserved_only: bool,
+    ) -> Self {
+        let path = dir.map(|dir| {
+            let mut buf = PathBuf::from(dir);
+            buf.push(RESERVED_NODES_FILE);
+            buf
+        });
+
+        let mut peers = ReservedPeers {
+            nodes: HashMap::new(),
+            reserved_only,
+            path,
+        };
+
+        peers.load_from_file();
+        for url in config_nodes {
+            match Node::from_str(url) {
+                Ok(node) => {
+                    peers.nodes.insert(node.id, node);
+                }
+                Err(e) => debug!("Error parsing node id: {}: {:?}", url, e),
+            }
+        }
+
+        peers
+    }
+
+    pub fn contains(&self, id: &NodeId) -> bool { self.nodes.contains_key(id) }
+
+    pub fn get(&self, id: &NodeId) -> Option<&Node> { self.nodes.get(id) }
+
+    pub fn ids(&self) -> Vec<NodeId> { self.nodes.keys().cloned().collect() }
+
+    pub fn nodes(&self) -> Vec<Node> { self.nodes.values().cloned().collect() }
+
+    /// Checks if any reserved peer is located at the specified IP address,
+    /// which is used to admit the incoming connections before the node id of
+    /// remote peer is known.
+    pub fn contains_ip(&self, ip: &IpAddr) -> bool {
+        self.nodes
+            .values()
+            .any(|node| node.endpoint.address.ip() == *ip)
+    }
+
+    pub fn reserved_only(&self) -> bool { self.reserved_only }
+
+    /// Adds or updates a reserved peer. Returns false if the peer is already
+    /// reserved with the same endpoint.
+    pub fn insert(&mut self, node: Node) -> bool {
+        if let Some(cur) = self.nodes.get(&node.id) {
+            if cur.endpoint == node.endpoint {
+                return false;
+            }
+        }
+
+        self.nodes.insert(node.id, node);
+        self.save();
+        true
+    }
+
+    /// Removes a reserved peer. Returns false if the peer is not reserved.
+    pub fn remove(&mut self, id: &NodeId) -> bool {
+        if self.nodes.remove(id).is_none() {
+            return false;
+        }
+
+        self.save();
+        true
+    }
+
+    pub fn set_reserved_only(&mut self, reserved_only: bool) {
+        if self.reserved_only != reserved_only {
+            self.reserved_only = reserved_only;
+            self.save();
+        }
+    }
+
+    fn load_from_file(&mut self) {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return,
+        };
+
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("reserved nodes file not found: {:?}", e);
+                return;
+            }
+        };
+        let res: Result<json::ReservedNodes, _> = serde_json::from_reader(file);
+        match res {
+            Ok(reserved) => {
+                for url in reserved.nodes {
+                    match Node::from_str(&url) {
+                        Ok(node) => {
+                            self.nodes.insert(node.id, node);
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Error parsing reserved node {}: {:?}",
+                                url, e
+                            )
+                        }
+                    }
+                }
+                self.reserved_only = reserved.reserved_only;
+            }
+            Err(e) => {
+                warn!("Error reading reserved nodes file: {:?}", e);
+            }
+        }
+    }
+
+    fn save(&self) {
+        let path = match self.path {
+            Some(ref path) => Path::new(path),
+            None => return,
+        };
+
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Error creating reserved nodes directory: {:?}", e);
+                return;
+            }
+        }
+
+        let reserved = json::ReservedNodes {
+            reserved_only: self.reserved_only,
+            nodes: self.nodes.values().map(|node| node.to_string()).collect(),
+        };
+
+        match fs::File::create(&path) {
+            Ok(file) => {
+                if let Err(e) = serde_json::to_writer_pretty(file, &reserved) {
+                    warn!("Error writing reserved nodes file: {:?}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Error creating reserved nodes file: {:?}", e);
+            }
+        }
+    }
+}
+
+mod json {
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReservedNodes {
+        pub reserved_only: bool,
+        pub nodes: Vec<String>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn node_url(port: u16) -> (NodeId, String) {
+        let id = NodeId::random();
+        (id, format!("cfxnode://{:x}@127.0.0.1:{}", id, port))
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let (id, url) = node_url(32323);
+        let mut peers = ReservedPeers::new(None, &[url.clone()], false);
+        assert!(peers.contains(&id));
+        assert!(peers.contains_ip(&"127.0.0.1".parse().unwrap()));
+        assert!(!peers.contains_ip(&"127.0.0.2".parse().unwrap()));
+
+        assert!(!peers.insert(Node::from_str(&url).unwrap()));
+        let (id2, url2) = node_url(32324);
+        assert!(peers.insert(Node::from_str(&url2).unwrap()));
+        assert_eq!(peers.ids().len(), 2);
+
+        assert!(peers.remove(&id));
+        assert!(!peers.remove(&id));
+        assert_eq!(peers.ids(), vec![id2]);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let dir = env::temp_dir().join(format!(
+            "reserved_peers_test_{:x}",
+            NodeId::random().to_low_u64_be()
+        ));
+        let dir_str = dir.to_str().map(|s| s.to_string());
+
+        let (config_id, config_url) = node_url(32323);
+        let (id, url) = node_url(32324);
+        {
+            let mut peers = ReservedPeers::new(
+                dir_str.clone(),
+                &[config_url.clone()],
+                false,
+            );
+            assert!(peers.insert(Node::from_str(&url).unwrap()));
+            assert!(peers.remove(&config_id));
+            peers.set_reserved_only(true);
+        }
+
+        // nodes in the configuration are added again
+        let peers = ReservedPeers::new(dir_str.clone(), &[config_url], false);
+        assert!(peers.reserved_only());
+        assert!(peers.contains(&id));
+        assert!(peers.contains(&config_id));
+
+        let peers = ReservedPeers::new(dir_str, &[], false);
+        assert_eq!(peers.ids(), vec![id]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
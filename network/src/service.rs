@@ -8,7 +8,7 @@ use std::{
     fmt::Formatter,
     fs,
     io::{self, Read, Write},
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
     path::{Path, PathBuf},
     str::FromStr,
     sync::{atomic::Ordering as AtomicOrdering, Arc},
@@ -16,7 +16,7 @@ use std::{
 };
 
 use keccak_hash::keccak;
-use mio::{tcp::*, udp::*, *};
+use mio::{udp::*, *};
 use parity_path::restrict_permissions_owner;
 use parking_lot::{Mutex, RwLock};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -41,13 +41,20 @@ use crate::{
     io::*,
     ip_utils::{map_external_address, select_public_address},
     node_database::NodeDatabase,
+    node_record::NodeCapabilities,
     node_table::*,
     parse_msg_id_leb128_2_bytes_at_most,
+    reputation::{PeerScore, ReputationEvent, ReputationTable},
+    reserved_peers::ReservedPeers,
     session::{self, Session, SessionData, SessionDetails},
     session_manager::SessionManager,
+    transport::{
+        QuicConfiguration, TcpTransport, Transport, TransportFallback,
+        TransportInfo, TransportProtocol, TransportStream,
+    },
     Error, ErrorKind, HandlerWorkType, IpFilter, NatType, NetworkConfiguration,
     NetworkContext as NetworkContextTrait, NetworkIoMessage,
-    NetworkProtocolHandler, PeerInfo, ProtocolId, ProtocolInfo,
+    NetworkProtocolHandler, PeerId, PeerInfo, ProtocolId, ProtocolInfo,
     UpdateNodeOperation, NODE_TAG_ARCHIVE, NODE_TAG_NODE_TYPE,
 };
 
@@ -69,6 +76,8 @@ const DISCOVERY_ROUND: TimerToken = SYS_TIMER + 6;
 const NODE_TABLE: TimerToken = SYS_TIMER + 7;
 const SEND_DELAYED_MESSAGES: TimerToken = SYS_TIMER + 8;
 const CHECK_SESSIONS: TimerToken = SYS_TIMER + 9;
+const QUIC_ENDPOINT: StreamToken = SYS_TIMER + 10;
+const TRANSPORT_TIMER: TimerToken = SYS_TIMER + 11;
 const HANDLER_TIMER: TimerToken = LAST_SESSION + 256;
 const STOP_NET_POLL: TimerToken = HANDLER_TIMER + 1;
 
@@ -230,6 +239,12 @@ impl NetworkService {
                 .as_ref()
                 .unwrap()
                 .register_handler(inner.clone())?;
+            if let Some(ref network) = self.config.in_memory {
+                network.register(
+                    &inner,
+                    self.io_service.as_ref().unwrap().channel(),
+                );
+            }
             self.inner = Some(inner);
         }
         Ok(())
@@ -371,21 +386,37 @@ impl NetworkService {
         &self, node_id: Option<NodeId>,
     ) -> Option<Vec<SessionDetails>> {
         let inner = self.inner.as_ref()?;
-        match node_id {
-            None => Some(
-                inner
-                    .sessions
-                    .all()
-                    .iter()
-                    .map(|s| s.read().details())
-                    .collect(),
-            ),
+        let mut sessions = match node_id {
+            None => inner
+                .sessions
+                .all()
+                .iter()
+                .map(|s| s.read().details())
+                .collect(),
             Some(id) => {
                 let session = inner.sessions.get_by_id(&id)?;
                 let details = session.read().details();
-                Some(vec![details])
+                vec![details]
+            }
+        };
+
+        let handlers = inner.handlers.read();
+        for details in &mut sessions {
+            let id = match details.node_id {
+                Some(id) => id,
+                None => continue,
+            };
+            for (protocol, handler) in handlers.iter() {
+                if let Some(stats) = handler.peer_stats(&id) {
+                    details.protocol_stats.insert(
+                        String::from_utf8_lossy(protocol).into_owned(),
+                        stats,
+                    );
+                }
             }
         }
+
+        Some(sessions)
     }
 
     pub fn disconnect_node(
@@ -409,8 +440,86 @@ impl NetworkService {
     pub fn save_node_db(&self) {
         if let Some(inner) = &self.inner {
             inner.node_db.write().save();
+            inner.reputation.write().save();
+        }
+    }
+
+    pub fn get_peer_scores(&self) -> Vec<PeerScore> {
+        match self.inner {
+            Some(ref inner) => inner.reputation.read().scores(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Bans the specified node for `duration`, or the configured duration if
+    /// not specified, and disconnects it if connected.
+    pub fn ban_node(&self, id: &NodeId, duration: Option<Duration>) -> bool {
+        if self.inner.is_none() || self.io_service.is_none() {
+            return false;
+        }
+        let inner = self.inner.as_ref().unwrap();
+        let duration = duration.unwrap_or(self.config.reputation.ban_duration);
+        inner.reputation.write().ban(id, duration);
+        let io = IoContext::new(self.io_service.as_ref().unwrap().channel(), 0);
+        inner.kill_connection(id, &io, true, None, "banned");
+        true
+    }
+
+    /// Lifts the ban of the specified node. Returns false if not banned.
+    pub fn unban_node(&self, id: &NodeId) -> bool {
+        match self.inner {
+            Some(ref inner) => inner.reputation.write().unban(id),
+            None => false,
         }
     }
+
+    /// Add a reserved peer with node URL, e.g. cfxnode://<id>@<ip>:<port>
+    pub fn add_reserved_peer(&self, url: &str) -> Result<(), Error> {
+        if let Some(ref inner) = self.inner {
+            inner.add_reserved_node(url)
+        } else {
+            Err("Network service not started yet!".into())
+        }
+    }
+
+    /// Remove a reserved peer, which is disconnected in the reserved-only
+    /// mode. Returns false if not reserved.
+    pub fn remove_reserved_peer(&self, id: &NodeId) -> bool {
+        if self.inner.is_none() || self.io_service.is_none() {
+            return false;
+        }
+        let inner = self.inner.as_ref().unwrap();
+        let mut reserved_peers = inner.reserved_peers.write();
+        if !reserved_peers.remove(id) {
+            return false;
+        }
+        if reserved_peers.reserved_only() {
+            drop(reserved_peers);
+            let io =
+                IoContext::new(self.io_service.as_ref().unwrap().channel(), 0);
+            inner.kill_connection(id, &io, true, None, "reserved only");
+        }
+        true
+    }
+
+    /// Enable or disable the reserved-only mode. All the non-reserved peers
+    /// are disconnected when enabled.
+    pub fn set_reserved_only(&self, reserved_only: bool) -> bool {
+        if self.inner.is_none() || self.io_service.is_none() {
+            return false;
+        }
+        let inner = self.inner.as_ref().unwrap();
+        inner
+            .reserved_peers
+            .write()
+            .set_reserved_only(reserved_only);
+        if reserved_only {
+            let io =
+                IoContext::new(self.io_service.as_ref().unwrap().channel(), 0);
+            inner.drop_unreserved_peers(&io);
+        }
+        true
+    }
 }
 
 type SharedSession = Arc<RwLock<Session>>;
@@ -447,7 +556,9 @@ pub struct NetworkServiceInner {
     pub metadata: HostMetadata,
     pub config: NetworkConfiguration,
     udp_socket: Mutex<UdpSocket>,
-    tcp_listener: Mutex<TcpListener>,
+    /// Transports to accept and create the sessions by their stream tokens,
+    /// where TCP is always the first.
+    transports: Vec<(StreamToken, Box<dyn Transport>)>,
     udp_channel: RwLock<UdpChannel>,
     discovery: Mutex<Option<Discovery>>,
     handlers:
@@ -455,15 +566,29 @@ pub struct NetworkServiceInner {
     timers: RwLock<HashMap<TimerToken, ProtocolTimer>>,
     timer_counter: RwLock<usize>,
     pub node_db: RwLock<NodeDatabase>,
-    reserved_nodes: RwLock<HashSet<NodeId>>,
+    pub reputation: RwLock<ReputationTable>,
+    pub reserved_peers: RwLock<ReservedPeers>,
     dropped_nodes: RwLock<HashSet<NodeId>>,
 
+    /// Peers connected over TCP after failing to connect over the transports
+    /// they advertise.
+    transport_fallback: RwLock<TransportFallback>,
+    /// Peers connected through the in-memory network
+    in_memory_peers: RwLock<HashMap<NodeId, InMemoryPeer>>,
+
     is_consortium: bool,
 
     /// Delayed message queue and corresponding latency
     delayed_queue: Option<DelayedQueue>,
 }
 
+struct InMemoryPeer {
+    index: PeerId,
+    address: SocketAddr,
+    originated: bool,
+    protocols: Vec<ProtocolInfo>,
+}
+
 struct DelayedQueue {
     queue: Mutex<BinaryHeap<DelayMessageContext>>,
     latencies: RwLock<HashMap<NodeId, Duration>>,
@@ -522,6 +647,11 @@ impl NetworkServiceInner {
     ) -> Result<NetworkServiceInner, Error>
     {
         let mut listen_address = match config.listen_address {
+            // Nodes in the in-memory network only need a local port for the
+            // node endpoint, and are not reachable through sockets.
+            None if config.in_memory.is_some() => SocketAddr::V4(
+                SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0),
+            ),
             None => SocketAddr::V4(SocketAddrV4::new(
                 Ipv4Addr::new(0, 0, 0, 0),
                 DEFAULT_PORT,
@@ -556,13 +686,23 @@ impl NetworkServiceInner {
 
         info!("Self node id: {:?}", *keys.public());
 
-        let tcp_listener = TcpListener::bind(&listen_address)?;
+        let tcp_transport = TcpTransport::bind(&listen_address)?;
         listen_address = SocketAddr::new(
             listen_address.ip(),
-            tcp_listener.local_addr()?.port(),
+            tcp_transport.local_addr()?.port(),
         );
         debug!("Listening at {:?}", listen_address);
-        let udp_port = config.udp_port.unwrap_or_else(|| listen_address.port());
+        let mut transports: Vec<(StreamToken, Box<dyn Transport>)> =
+            vec![(TCP_ACCEPT, Box::new(tcp_transport))];
+        if let Some(ref quic) = config.quic {
+            let transport = bind_quic(quic, listen_address.ip())?;
+            debug!("QUIC listening at {:?}", transport.local_addr()?);
+            transports.push((QUIC_ENDPOINT, transport));
+        }
+        let udp_port = match config.in_memory {
+            Some(_) => config.udp_port.unwrap_or(0),
+            None => config.udp_port.unwrap_or_else(|| listen_address.port()),
+        };
         let local_endpoint = NodeEndpoint {
             address: listen_address,
             udp_port,
@@ -581,7 +721,7 @@ impl NetworkServiceInner {
                     address: public_address,
                     udp_port: local_endpoint.udp_port,
                 };
-                if config.nat_enabled {
+                if config.nat_enabled && config.in_memory.is_none() {
                     match map_external_address(&local_endpoint, &NatType::Any) {
                         Some(endpoint) => {
                             info!(
@@ -602,6 +742,18 @@ impl NetworkServiceInner {
             },
         };
 
+        // advertise the transports other than TCP, which is always accepted
+        // on the node endpoint
+        let mut capabilities = config.node_capabilities.clone();
+        if let Some(ref mut capabilities) = capabilities {
+            for (_, transport) in &transports[1..] {
+                capabilities.transports.push(TransportInfo {
+                    protocol: transport.protocol(),
+                    port: transport.local_addr()?.port(),
+                });
+            }
+        }
+
         let allow_ips = config.ip_filter.clone();
         let discovery = {
             if config.discovery_enabled {
@@ -610,6 +762,7 @@ impl NetworkServiceInner {
                     public_endpoint.clone(),
                     allow_ips,
                     config.discovery_config.clone(),
+                    capabilities,
                 ))
             } else {
                 None
@@ -632,7 +785,7 @@ impl NetworkServiceInner {
             udp_channel: RwLock::new(UdpChannel::new()),
             discovery: Mutex::new(discovery),
             udp_socket: Mutex::new(udp_socket),
-            tcp_listener: Mutex::new(tcp_listener),
+            transports,
             sessions: SessionManager::new(
                 FIRST_SESSION,
                 MAX_SESSIONS,
@@ -644,11 +797,21 @@ impl NetworkServiceInner {
             timers: RwLock::new(HashMap::new()),
             timer_counter: RwLock::new(HANDLER_TIMER),
             node_db: RwLock::new(NodeDatabase::new(
-                nodes_path,
+                nodes_path.clone(),
                 config.subnet_quota,
             )),
-            reserved_nodes: RwLock::new(HashSet::new()),
+            reputation: RwLock::new(ReputationTable::new(
+                nodes_path.clone(),
+                config.reputation.clone(),
+            )),
+            reserved_peers: RwLock::new(ReservedPeers::new(
+                nodes_path,
+                &config.reserved_nodes,
+                config.reserved_only,
+            )),
             dropped_nodes: RwLock::new(HashSet::new()),
+            transport_fallback: RwLock::new(TransportFallback::default()),
+            in_memory_peers: RwLock::new(HashMap::new()),
             is_consortium: config.is_consortium,
             delayed_queue: None,
         };
@@ -657,11 +820,11 @@ impl NetworkServiceInner {
             inner.add_boot_node(n);
         }
 
-        let reserved_nodes = config.reserved_nodes.clone();
-        for n in reserved_nodes {
-            if let Err(e) = inner.add_reserved_node(&n) {
-                debug!("Error parsing node id: {}: {:?}", n, e);
-            }
+        for n in inner.reserved_peers.read().nodes() {
+            inner.node_db.write().insert_trusted(NodeEntry {
+                id: n.id,
+                endpoint: n.endpoint,
+            });
         }
 
         Ok(inner)
@@ -684,6 +847,14 @@ impl NetworkServiceInner {
     pub fn add_latency(
         &self, peer: NodeId, latency_ms: f64,
     ) -> Result<(), Error> {
+        if let Some(ref network) = self.config.in_memory {
+            network.set_latency(
+                self.metadata.id(),
+                &peer,
+                Duration::from_millis(latency_ms as u64),
+            );
+            return Ok(());
+        }
         match self.delayed_queue {
             Some(ref queue) => {
                 let mut latencies = queue.latencies.write();
@@ -714,13 +885,13 @@ impl NetworkServiceInner {
         }
     }
 
-    fn add_reserved_node(&mut self, id: &str) -> Result<(), Error> {
+    fn add_reserved_node(&self, id: &str) -> Result<(), Error> {
         let n = Node::from_str(id)?;
         self.node_db.write().insert_trusted(NodeEntry {
             id: n.id.clone(),
             endpoint: n.endpoint.clone(),
         });
-        self.reserved_nodes.write().insert(n.id);
+        self.reserved_peers.write().insert(n);
         Ok(())
     }
 
@@ -809,7 +980,7 @@ impl NetworkServiceInner {
     fn on_housekeeping(&self, io: &IoContext<NetworkIoMessage>) {
         if self.is_consortium {
             unimplemented!();
-        } else {
+        } else if self.config.in_memory.is_none() {
             self.connect_peers(io);
         }
         self.drop_peers(io);
@@ -825,13 +996,32 @@ impl NetworkServiceInner {
 
         let self_id = self.metadata.id().clone();
 
-        let sampled_archive_nodes = self.sample_archive_nodes();
+        let (reserved_nodes, reserved_only) = {
+            let reserved_peers = self.reserved_peers.read();
+            (reserved_peers.ids(), reserved_peers.reserved_only())
+        };
+
+        // Only reserved peers are connected in the reserved-only mode.
+        let sampled_archive_nodes = if reserved_only {
+            HashSet::new()
+        } else {
+            self.retain_capable_nodes(self.sample_archive_nodes())
+        };
 
         let (handshake_count, egress_count, ingress_count) =
             self.sessions.stat();
         let samples;
         {
-            let egress_attempt_count = if self.config.max_outgoing_peers
+            // Reserved peers are not counted in the maximum outgoing peers.
+            let reserved_egress_count = reserved_nodes
+                .iter()
+                .filter(|id| self.get_peer_connection_origin(id) == Some(true))
+                .count();
+            let egress_count =
+                egress_count.saturating_sub(reserved_egress_count);
+            let egress_attempt_count = if reserved_only {
+                0
+            } else if self.config.max_outgoing_peers
                 > egress_count + sampled_archive_nodes.len()
             {
                 self.config.max_outgoing_peers
@@ -840,17 +1030,16 @@ impl NetworkServiceInner {
             } else {
                 0
             };
-            samples = self.node_db.read().sample_trusted_node_ids(
+            let sampled_nodes = self.node_db.read().sample_trusted_node_ids(
                 egress_attempt_count as u32,
                 &self.config.ip_filter,
             );
+            samples = self.retain_capable_nodes(sampled_nodes);
         }
 
-        let reserved_nodes = self.reserved_nodes.read();
         // Try to connect all reserved peers and trusted peers
         let nodes = reserved_nodes
-            .iter()
-            .cloned()
+            .into_iter()
             .chain(sampled_archive_nodes)
             .chain(samples);
 
@@ -880,6 +1069,28 @@ impl NetworkServiceInner {
         }
     }
 
+    /// Retain the nodes that could serve the local node, or have not
+    /// advertised their capabilities in discovery.
+    fn retain_capable_nodes(
+        &self, mut nodes: HashSet<NodeId>,
+    ) -> HashSet<NodeId> {
+        let local = match &self.config.node_capabilities {
+            Some(local) => local,
+            None => return nodes,
+        };
+
+        let node_db = self.node_db.read();
+        let minimum_peer_protocol_version =
+            self.metadata.minimum_peer_protocol_version.read();
+        nodes.retain(|id| match node_db.get_record(id) {
+            Some(record) => record
+                .capabilities
+                .can_serve(local, &minimum_peer_protocol_version),
+            None => true,
+        });
+        nodes
+    }
+
     /// Sample archive nodes for outgoing connections if not enough.
     fn sample_archive_nodes(&self) -> HashSet<NodeId> {
         if self.config.max_outgoing_peers_archive == 0 {
@@ -928,14 +1139,50 @@ impl NetworkServiceInner {
         }
     }
 
+    // Kill connections of all peers that are not reserved
+    fn drop_unreserved_peers(&self, io: &IoContext<NetworkIoMessage>) {
+        let node_ids: Vec<NodeId> = self
+            .sessions
+            .all()
+            .iter()
+            .filter_map(|s| s.read().id().cloned())
+            .collect();
+        let unreserved_nodes: Vec<NodeId> = {
+            let reserved_peers = self.reserved_peers.read();
+            node_ids
+                .into_iter()
+                .filter(|id| !reserved_peers.contains(id))
+                .collect()
+        };
+
+        for node_id in unreserved_nodes {
+            self.kill_connection(&node_id, io, true, None, "reserved only");
+        }
+    }
+
     fn connect_peer(&self, id: &NodeId, io: &IoContext<NetworkIoMessage>) {
         if self.sessions.contains_node(id) {
             trace!("Abort connect. Node already connected");
             return;
         }
 
+        if self.reputation.write().is_banned(id) {
+            debug!("Abort connect. Node banned");
+            return;
+        }
+
+        let reserved_address = self
+            .reserved_peers
+            .read()
+            .get(id)
+            .map(|node| node.endpoint.address);
+
         let (socket, address) = {
-            let address = {
+            let address = if let Some(address) = reserved_address {
+                // reserved node may be evicted from node table due to the
+                // subnet quota
+                address
+            } else {
                 // outgoing connection must pick node from trusted node table
                 if let Some(node) = self.node_db.read().get(id, true) {
                     node.endpoint.address
@@ -945,14 +1192,40 @@ impl NetworkServiceInner {
                 }
             };
 
-            if !self.sessions.is_ip_allowed(&address.ip()) {
+            if reserved_address.is_none()
+                && !self.sessions.is_ip_allowed(&address.ip())
+            {
                 debug!("cannot create outgoing connection to node, id = {:?}, address = {:?}", id, address);
                 return;
             }
 
-            match TcpStream::connect(&address) {
+            let (mut transport, mut target) =
+                self.select_transport(id, address);
+            let mut result = transport.connect(&target);
+            if result.is_err() && transport.protocol() != TransportProtocol::Tcp
+            {
+                debug!(
+                    "{}: can't connect to {:?} through {}, fall back to TCP",
+                    id,
+                    target,
+                    transport.protocol()
+                );
+                self.transport_fallback
+                    .write()
+                    .note_failure(id, Instant::now());
+                transport = self.tcp_transport();
+                target = address;
+                result = transport.connect(&target);
+            }
+            let address = target;
+            match result {
                 Ok(socket) => {
-                    trace!("{}: connecting to {:?}", id, address);
+                    trace!(
+                        "{}: connecting to {:?} through {}",
+                        id,
+                        address,
+                        transport.protocol()
+                    );
                     (socket, address)
                 }
                 Err(e) => {
@@ -995,6 +1268,14 @@ impl NetworkServiceInner {
                 });
             }
         }
+        for (id, peer) in self.in_memory_peers.read().iter() {
+            peers.push(PeerInfo {
+                id: peer.index,
+                nodeid: *id,
+                addr: peer.address,
+                protocols: peer.protocols.clone(),
+            });
+        }
 
         debug!("get_peer_info: leave, {} peers retrieved", peers.len());
 
@@ -1007,14 +1288,167 @@ impl NetworkServiceInner {
                 let sess = session.read();
                 Some(sess.originated())
             }
-            None => None,
+            None => self
+                .in_memory_peers
+                .read()
+                .get(node_id)
+                .map(|peer| peer.originated),
+        }
+    }
+
+    /// Returns the protocols, the pos public key and the address of this node
+    /// for the peers connected through the in-memory network, which are
+    /// exchanged in the Hello message for TCP sessions.
+    pub(crate) fn in_memory_hello(
+        &self,
+    ) -> (
+        Vec<ProtocolInfo>,
+        Option<(ConsensusPublicKey, ConsensusVRFPublicKey)>,
+        SocketAddr,
+    ) {
+        (
+            self.metadata.protocols.read().clone(),
+            self.sessions.self_pos_public_key.clone(),
+            self.metadata.local_address,
+        )
+    }
+
+    fn on_in_memory_peer_connected(
+        &self, io: &IoContext<NetworkIoMessage>, peer: &NodeId, index: PeerId,
+        address: SocketAddr, originated: bool, protocols: &[ProtocolInfo],
+        pos_public_key: &Option<(ConsensusPublicKey, ConsensusVRFPublicKey)>,
+    )
+    {
+        // Keep the protocols supported by both sides as the Hello message does
+        let protocols: Vec<ProtocolInfo> = protocols
+            .iter()
+            .filter(|c| {
+                self.metadata
+                    .minimum_peer_protocol_version
+                    .read()
+                    .iter()
+                    .any(|hc| {
+                        hc.protocol == c.protocol && hc.version <= c.version
+                    })
+            })
+            .cloned()
+            .collect();
+        debug!(
+            "In-memory peer connected, peer = {:?}, protocols = {:?}",
+            peer, protocols
+        );
+        self.in_memory_peers.write().insert(
+            *peer,
+            InMemoryPeer {
+                index,
+                address,
+                originated,
+                protocols: protocols.clone(),
+            },
+        );
+
+        let handlers = self.handlers.read().clone();
+        for protocol in &protocols {
+            if let Some(handler) = handlers.get(&protocol.protocol).cloned() {
+                let network_context =
+                    NetworkContext::new(io, handler, protocol.protocol, self);
+                network_context.protocol_handler().on_peer_connected(
+                    &network_context,
+                    peer,
+                    protocol.version,
+                    pos_public_key.clone(),
+                );
+            }
+        }
+    }
+
+    fn on_in_memory_peer_disconnected(
+        &self, io: &IoContext<NetworkIoMessage>, peer: &NodeId,
+    ) {
+        let removed = match self.in_memory_peers.write().remove(peer) {
+            Some(removed) => removed,
+            None => return,
+        };
+        debug!("In-memory peer disconnected, peer = {:?}", peer);
+
+        let handlers = self.handlers.read().clone();
+        for protocol in &removed.protocols {
+            if let Some(handler) = handlers.get(&protocol.protocol).cloned() {
+                let network_context =
+                    NetworkContext::new(io, handler, protocol.protocol, self);
+                network_context
+                    .protocol_handler()
+                    .on_peer_disconnected(&network_context, peer);
+            }
         }
     }
 
+    /// Returns the transport of the protocol if enabled.
+    fn transport(&self, protocol: TransportProtocol) -> Option<&dyn Transport> {
+        self.transports
+            .iter()
+            .find(|(_, transport)| transport.protocol() == protocol)
+            .map(|(_, transport)| &**transport)
+    }
+
+    fn transport_by_token(&self, token: StreamToken) -> Option<&dyn Transport> {
+        self.transports
+            .iter()
+            .find(|(t, _)| *t == token)
+            .map(|(_, transport)| &**transport)
+    }
+
+    /// Selects the transport to connect to the node at `address`, i.e. the
+    /// first transport advertised in the node record that is enabled locally,
+    /// or TCP if none.
+    fn select_transport(
+        &self, id: &NodeId, address: SocketAddr,
+    ) -> (&dyn Transport, SocketAddr) {
+        let advertised = self
+            .node_db
+            .read()
+            .get_record(id)
+            .map(|record| record.capabilities.transports.clone())
+            .unwrap_or_default();
+        if !advertised.is_empty()
+            && !self
+                .transport_fallback
+                .write()
+                .is_fallback(id, Instant::now())
+        {
+            for info in &advertised {
+                if let Some(transport) = self.transport(info.protocol) {
+                    return (transport, info.address(address.ip()));
+                }
+            }
+        }
+        (self.tcp_transport(), address)
+    }
+
+    fn tcp_transport(&self) -> &dyn Transport {
+        self.transport(TransportProtocol::Tcp)
+            .expect("TCP transport should be enabled")
+    }
+
     fn start(&self, io: &IoContext<NetworkIoMessage>) -> Result<(), Error> {
+        if self.config.in_memory.is_some() {
+            // Links are managed by the in-memory network, so neither UDP nor
+            // any transport is used.
+            return Ok(());
+        }
         self.initialize_udp_protocols(io)?;
         io.register_stream(UDP_MESSAGE)?;
-        io.register_stream(TCP_ACCEPT)?;
+        for (token, _) in &self.transports {
+            io.register_stream(*token)?;
+        }
+        if let Some(interval) = self
+            .transports
+            .iter()
+            .filter_map(|(_, transport)| transport.timer_interval())
+            .min()
+        {
+            io.register_timer(TRANSPORT_TIMER, interval)?;
+        }
         Ok(())
     }
 
@@ -1022,11 +1456,24 @@ impl NetworkServiceInner {
     // 1. proactively connect to a peer;
     // 2. passively connected by a peer;
     fn create_connection(
-        &self, socket: TcpStream, address: SocketAddr, id: Option<&NodeId>,
-        io: &IoContext<NetworkIoMessage>,
+        &self, socket: Box<dyn TransportStream>, address: SocketAddr,
+        id: Option<&NodeId>, io: &IoContext<NetworkIoMessage>,
     ) -> Result<(), Error>
     {
-        match self.sessions.create(socket, address, id, io, self) {
+        // Ingress connection is regarded as reserved by the IP address, and
+        // checked again by the node id after handshake.
+        let reserved = {
+            let reserved_peers = self.reserved_peers.read();
+            match id {
+                Some(id) => reserved_peers.contains(id),
+                None => reserved_peers.contains_ip(&address.ip()),
+            }
+        };
+
+        match self
+            .sessions
+            .create(socket, address, id, reserved, io, self)
+        {
             Ok(token) => {
                 debug!("new session created, token = {}, address = {:?}, id = {:?}", token, address, id);
                 if let Some(id) = id {
@@ -1225,10 +1672,18 @@ impl NetworkServiceInner {
         }
     }
 
-    fn accept(&self, io: &IoContext<NetworkIoMessage>) {
+    fn accept(&self, stream: StreamToken, io: &IoContext<NetworkIoMessage>) {
+        let transport = match self.transport_by_token(stream) {
+            Some(transport) => transport,
+            None => return,
+        };
+        if let Err(e) = transport.readable() {
+            debug!("Error reading {} transport: {:?}", transport.protocol(), e);
+        }
+
         trace!("Accepting incoming connection");
         loop {
-            let (socket, address) = match self.tcp_listener.lock().accept() {
+            let (socket, address) = match transport.accept() {
                 Ok((sock, addr)) => (sock, addr),
                 Err(e) => {
                     if e.kind() != io::ErrorKind::WouldBlock {
@@ -1271,6 +1726,17 @@ impl NetworkServiceInner {
                 }
                 deregister = remote || sess.done();
                 failure_id = sess.id().cloned();
+                if sess.originated()
+                    && sess.is_handshaking()
+                    && sess.transport() != TransportProtocol::Tcp
+                {
+                    // E.g. the QUIC handshake timed out since UDP is blocked.
+                    if let Some(id) = sess.id() {
+                        self.transport_fallback
+                            .write()
+                            .note_failure(id, Instant::now());
+                    }
+                }
                 debug!(
                     "kill connection by token, deregister = {}, reason = {:?}, session = {:?}, op = {:?}",
                     deregister, reason, *sess, op
@@ -1319,11 +1785,47 @@ impl NetworkServiceInner {
         }
     }
 
+    /// Updates the reputation of the specified peer, and disconnects the peer
+    /// if banned. Returns the updated score of the peer.
+    fn report_peer(
+        &self, node_id: &NodeId, event: ReputationEvent,
+        io: &IoContext<NetworkIoMessage>,
+    ) -> f64
+    {
+        if node_id == self.metadata.id() {
+            return 0.0;
+        }
+
+        let (score, banned) = self.reputation.write().note(node_id, event);
+        if banned {
+            self.kill_connection(
+                node_id,
+                io,
+                true,
+                None,
+                "banned for low reputation", // reason
+            );
+        }
+        score
+    }
+
     fn kill_connection(
         &self, node_id: &NodeId, io: &IoContext<NetworkIoMessage>,
         remote: bool, op: Option<UpdateNodeOperation>, reason: &str,
     ) -> bool
     {
+        if let Some(ref network) = self.config.in_memory {
+            if self.in_memory_peers.read().contains_key(node_id) {
+                debug!(
+                    "kill in-memory connection, peer = {:?}, reason = {:?}",
+                    node_id, reason
+                );
+                // The handlers are notified once the link is disconnected.
+                network.disconnect(self.metadata.id(), node_id);
+                return true;
+            }
+        }
+
         let mut to_disconnect: Vec<ProtocolId> = Vec::new();
         let mut deregister = false;
         let mut token = 0;
@@ -1544,7 +2046,7 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
     ) {
         match stream {
             FIRST_SESSION..=LAST_SESSION => self.session_readable(stream, io),
-            TCP_ACCEPT => self.accept(io),
+            TCP_ACCEPT | QUIC_ENDPOINT => self.accept(stream, io),
             UDP_MESSAGE => self.udp_readable(io),
             _ => panic!("Received unknown readable token"),
         }
@@ -1630,8 +2132,14 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                 trace!("Refreshing node table");
                 self.try_promote_untrusted();
                 self.node_db.write().save();
+                self.reputation.write().save();
             }
             CHECK_SESSIONS => self.on_check_sessions(io),
+            TRANSPORT_TIMER => {
+                for (_, transport) in &self.transports {
+                    transport.on_timeout();
+                }
+            }
             SEND_DELAYED_MESSAGES => {
                 if let Some(ref queue) = self.delayed_queue {
                     queue.send_delayed_messages(self);
@@ -1703,6 +2211,10 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                         },
                     );
                 }
+                let protocols = self.metadata.protocols.read().clone();
+                if let Some(discovery) = self.discovery.lock().as_mut() {
+                    discovery.set_protocols(protocols);
+                }
                 info!(
                     "Protocol {:?} version {:?} registered.",
                     protocol, version
@@ -1770,6 +2282,25 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                     warn!("Work is handled by unknown handler");
                 }
             }
+            NetworkIoMessage::InMemoryConnected {
+                ref peer,
+                index,
+                address,
+                originated,
+                ref protocols,
+                ref pos_public_key,
+            } => self.on_in_memory_peer_connected(
+                io,
+                peer,
+                *index,
+                *address,
+                *originated,
+                protocols,
+                pos_public_key,
+            ),
+            NetworkIoMessage::InMemoryDisconnected { ref peer } => {
+                self.on_in_memory_peer_disconnected(io, peer)
+            }
         }
     }
 
@@ -1785,15 +2316,12 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                         .expect("Error registering socket");
                 }
             }
-            TCP_ACCEPT => {
-                event_loop
-                    .register(
-                        &*self.tcp_listener.lock(),
-                        Token(TCP_ACCEPT),
-                        Ready::all(),
-                        PollOpt::edge(),
-                    )
-                    .expect("Error registering stream");
+            TCP_ACCEPT | QUIC_ENDPOINT => {
+                if let Some(transport) = self.transport_by_token(stream) {
+                    transport
+                        .register(reg, event_loop)
+                        .expect("Error registering transport");
+                }
             }
             UDP_MESSAGE => {
                 event_loop
@@ -1844,14 +2372,13 @@ impl IoHandler<NetworkIoMessage> for NetworkServiceInner {
                         .expect("Error updating socket");
                 }
             }
-            TCP_ACCEPT => event_loop
-                .reregister(
-                    &*self.tcp_listener.lock(),
-                    Token(TCP_ACCEPT),
-                    Ready::all(),
-                    PollOpt::edge(),
-                )
-                .expect("Error reregistering stream"),
+            TCP_ACCEPT | QUIC_ENDPOINT => {
+                if let Some(transport) = self.transport_by_token(stream) {
+                    transport
+                        .reregister(reg, event_loop)
+                        .expect("Error reregistering transport");
+                }
+            }
             UDP_MESSAGE => {
                 let udp_socket = self.udp_socket.lock();
                 let udp_channel = self.udp_channel.read();
@@ -1967,6 +2494,31 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
 
     fn self_node_id(&self) -> NodeId { *self.network_service.metadata.id() }
 
+    fn report_peer(&self, node_id: &NodeId, event: ReputationEvent) -> f64 {
+        self.network_service.report_peer(node_id, event, self.io)
+    }
+
+    fn get_peer_score(&self, node_id: &NodeId) -> f64 {
+        self.network_service.reputation.read().score(node_id)
+    }
+
+    fn get_peer_capabilities(
+        &self, node_id: &NodeId,
+    ) -> Option<NodeCapabilities> {
+        self.network_service
+            .node_db
+            .read()
+            .get_record(node_id)
+            .map(|record| record.capabilities.clone())
+    }
+
+    fn set_snapshot_capability(&self, snapshot: bool) {
+        if let Some(discovery) = self.network_service.discovery.lock().as_mut()
+        {
+            discovery.set_snapshot(snapshot);
+        }
+    }
+
     /// Message is sent through this method.
     fn send(
         &self, node_id: &NodeId, msg: Vec<u8>,
@@ -1988,6 +2540,38 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
             return Ok(());
         }
 
+        if let Some(ref network) = self.network_service.config.in_memory {
+            let peer_version = self
+                .network_service
+                .in_memory_peers
+                .read()
+                .get(node_id)
+                .and_then(|peer| {
+                    peer.protocols
+                        .iter()
+                        .find(|p| p.protocol == self.protocol)
+                        .map(|p| p.version)
+                });
+            if let Some(peer_version) = peer_version {
+                if min_protocol_version > peer_version {
+                    bail!(ErrorKind::SendUnsupportedMessage {
+                        protocol: self.protocol,
+                        msg_id: parse_msg_id_leb128_2_bytes_at_most(&mut &*msg),
+                        peer_protocol_version: Some(peer_version),
+                        min_supported_version: None,
+                    });
+                }
+                trace!("Sending {} bytes to {} in memory", msg.len(), node_id);
+                network.send(
+                    self.network_service.metadata.id(),
+                    node_id,
+                    self.protocol,
+                    msg,
+                );
+                return Ok(());
+            }
+        }
+
         let session = self.network_service.sessions.get_by_id(node_id);
         trace!("Sending {} bytes to {}", msg.len(), node_id);
         if let Some(session) = session {
@@ -2022,7 +2606,13 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
                     trace!("register delayed timer delay:{:?} ts_to_send:{:?} length:{}", latency, ts_to_send, queue.len());
                 }
                 None => {
-                    session.write().send_packet(
+                    let size = msg.len();
+                    let msg_id = if msg.is_empty() {
+                        None
+                    } else {
+                        Some(parse_msg_id_leb128_2_bytes_at_most(&mut &*msg))
+                    };
+                    let status = session.write().send_packet(
                         self.io,
                         Some(self.protocol),
                         min_protocol_version,
@@ -2030,6 +2620,15 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
                         msg,
                         priority,
                     )?;
+                    if let (Some(msg_id), Some(compressed_size)) =
+                        (msg_id, status.compressed_size)
+                    {
+                        self.handler.on_message_compressed(
+                            msg_id,
+                            size,
+                            compressed_size,
+                        );
+                    }
                 }
             }
             // TODO: Handle result from send_packet()
@@ -2076,6 +2675,24 @@ impl<'a> NetworkContextTrait for NetworkContext<'a> {
     }
 }
 
+#[cfg(feature = "quic")]
+fn bind_quic(
+    config: &QuicConfiguration, ip: IpAddr,
+) -> Result<Box<dyn Transport>, Error> {
+    Ok(Box::new(crate::transport::QuicTransport::bind(config, ip)?))
+}
+
+#[cfg(not(feature = "quic"))]
+fn bind_quic(
+    _config: &QuicConfiguration, _ip: IpAddr,
+) -> Result<Box<dyn Transport>, Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "QUIC transport is not supported, build with the quic feature",
+    )
+    .into())
+}
+
 fn save_key(path: &Path, key: &Secret) {
     let mut path_buf = PathBuf::from(path);
     if let Err(e) = fs::create_dir_all(path_buf.as_path()) {
@@ -3,11 +3,17 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
-    connection::{Connection, ConnectionDetails, SendQueueStatus, WriteStatus},
+    compression::Compression,
+    connection::{
+        Connection, ConnectionDetails, SendQueueStatus, WriteStatus,
+        MAX_PAYLOAD_SIZE,
+    },
+    encryption::{SessionEncryption, SessionSecret},
     handshake::Handshake,
     node_table::{NodeEndpoint, NodeEntry, NodeId},
     parse_msg_id_leb128_2_bytes_at_most,
     service::{NetworkServiceInner, ProtocolVersion},
+    transport::{TransportProtocol, TransportStream},
     DisconnectReason, Error, ErrorKind, ProtocolId, ProtocolInfo,
     SessionMetadata, UpdateNodeOperation, PROTOCOL_ID_SIZE,
 };
@@ -15,11 +21,13 @@ use bytes::Bytes;
 use diem_crypto::{bls::BLS_PUBLIC_KEY_LENGTH, ValidCryptoMaterial};
 use diem_types::validator_config::{ConsensusPublicKey, ConsensusVRFPublicKey};
 use io::*;
-use mio::{tcp::*, *};
+use keylib::{crypto::ecdh, Generator, Public, Random, Secret};
+use mio::{Poll, Token};
 use priority_send_queue::SendQueuePriority;
 use rlp::{Rlp, RlpStream};
 use serde_derive::Serialize;
 use std::{
+    collections::BTreeMap,
     convert::TryFrom,
     fmt,
     net::SocketAddr,
@@ -27,16 +35,26 @@ use std::{
     time::{Duration, Instant},
 };
 
-/// Peer session over TCP connection, including outgoing and incoming sessions.
+/// Peer session over a transport connection, TCP by default, including
+/// outgoing and incoming sessions.
 ///
 /// When a session created, 2 peers handshake with each other to exchange the
 /// node id based on asymmetric cryptography. After handshake, peers send HELLO
 /// packet to exchange the supported protocols. Then, session is ready to send
 /// and receive protocol packets.
 ///
-/// Conflux do not use AES based encrypted connection to send protocol packets.
-/// This is because that Conflux has high TPS, and the encryption/decryption
-/// workloads are very heavy (about 20% CPU time in 3000 TPS).
+/// By default, Conflux do not use encrypted connection to send protocol
+/// packets. This is because that Conflux has high TPS, and the
+/// encryption/decryption workloads are very heavy (about 20% CPU time in 3000
+/// TPS with AES). If both peers enable session encryption in the handshake,
+/// the protocol packets after Hello are encrypted with ChaCha20-Poly1305,
+/// using keys derived from the handshake nonces and the ephemeral keys
+/// exchanged in the Hello packets. Packets queued after the Hello packet are
+/// held until the remote Hello packet is received and encryption is enabled.
+///
+/// Similarly, the protocol packets larger than a threshold are compressed with
+/// the first algorithm in the order of local preference that is supported by
+/// the remote peer as well.
 pub struct Session {
     /// Session information
     pub metadata: SessionMetadata,
@@ -55,12 +73,28 @@ pub struct Session {
     last_read: Instant,
     last_write: (Instant, WriteStatus),
     pos_public_key: Option<(ConsensusPublicKey, ConsensusVRFPublicKey)>,
+    /// Secret shared with the remote peer after handshake, which is used to
+    /// encrypt the protocol packets.
+    session_secret: Option<SessionSecret>,
+    /// Whether the remote peer advertises the extended Hello items in the
+    /// handshake.
+    extended_hello: bool,
+    /// Whether the remote peer enables the session encryption in the
+    /// handshake.
+    remote_encryption: bool,
+    /// Secret of the ephemeral key sent in Hello, which is dropped once the
+    /// encryption keys are derived.
+    ephemeral_secret: Option<Secret>,
+    /// Algorithm to compress the protocol packets sent to the remote peer.
+    compression: Option<Compression>,
+    /// Protocol packets smaller than the threshold are not compressed.
+    compression_threshold: usize,
 }
 
 /// Session state.
 enum State {
     /// Handshake to exchange node id.
-    /// When handshake completed, the underlying TCP connection instance of
+    /// When handshake completed, the underlying connection instance of
     /// handshake will also be moved to the state `State::Session`.
     Handshake(MovableWrapper<Handshake>),
     /// Ready to send Hello or protocol packets.
@@ -103,8 +137,9 @@ impl Session {
     /// Create a new instance of `Session`, which starts to handshake with
     /// remote peer.
     pub fn new<Message: Send + Sync + Clone + 'static>(
-        io: &IoContext<Message>, socket: TcpStream, address: SocketAddr,
-        id: Option<&NodeId>, peer_header_version: u8, token: StreamToken,
+        io: &IoContext<Message>, socket: Box<dyn TransportStream>,
+        address: SocketAddr, id: Option<&NodeId>, peer_header_version: u8,
+        token: StreamToken,
         host: &NetworkServiceInner,
         pos_public_key: Option<(ConsensusPublicKey, ConsensusVRFPublicKey)>,
     ) -> Result<Session, Error>
@@ -112,7 +147,7 @@ impl Session {
         let originated = id.is_some();
 
         let mut handshake = Handshake::new(token, id, socket);
-        handshake.start(io, &host.metadata)?;
+        handshake.start(io, &host.metadata, &host.config.session_encryption)?;
 
         Ok(Session {
             metadata: SessionMetadata {
@@ -129,6 +164,12 @@ impl Session {
             last_read: Instant::now(),
             last_write: (Instant::now(), WriteStatus::Complete),
             pos_public_key,
+            session_secret: None,
+            extended_hello: false,
+            remote_encryption: false,
+            ephemeral_secret: None,
+            compression: None,
+            compression_threshold: 0,
         })
     }
 
@@ -146,6 +187,10 @@ impl Session {
 
     pub fn is_ready(&self) -> bool { self.had_hello.is_some() }
 
+    pub fn is_handshaking(&self) -> bool {
+        matches!(self.state, State::Handshake(_))
+    }
+
     pub fn expired(&self) -> bool { self.expired.is_some() }
 
     pub fn set_expired(&mut self) { self.expired = Some(Instant::now()); }
@@ -170,6 +215,10 @@ impl Session {
 
     pub fn token(&self) -> StreamToken { self.connection().token() }
 
+    pub fn transport(&self) -> TransportProtocol {
+        self.connection().transport()
+    }
+
     pub fn address(&self) -> SocketAddr { self.address }
 
     /// Register event loop for the underlying connection.
@@ -220,9 +269,48 @@ impl Session {
                 return Err(self.send_disconnect(DisconnectReason::Blacklisted));
             }
 
+            // refuse incoming session if the node is banned for low reputation
+            if host.reputation.write().is_banned(&id) {
+                return Err(self.send_disconnect(DisconnectReason::Custom(
+                    "banned".into(),
+                )));
+            }
+
+            // refuse incoming session of non-reserved node in reserved-only
+            // mode, or count it in the session limits if admitted as reserved
+            // by the IP address
+            let (reserved, reserved_only) = {
+                let reserved_peers = host.reserved_peers.read();
+                (reserved_peers.contains(&id), reserved_peers.reserved_only())
+            };
+            if !reserved && reserved_only {
+                return Err(self.send_disconnect(DisconnectReason::Custom(
+                    "reserved only".into(),
+                )));
+            }
+            if !reserved {
+                if let Err(reason) = host
+                    .sessions
+                    .revoke_ingress_exemption(self.token(), &self.address.ip())
+                {
+                    debug!(
+                        "failed to admit ingress session, reason = {:?}, session = {:?}",
+                        reason, self
+                    );
+                    return Err(self.send_disconnect(
+                        DisconnectReason::Custom("too many peers".into()),
+                    ));
+                }
+            }
+
             self.metadata.id = Some(id);
         }
 
+        self.session_secret =
+            wrapper.get().session_secret(self.metadata.originated);
+        self.extended_hello = wrapper.get().remote_extended_hello();
+        self.remote_encryption = wrapper.get().remote_encryption();
+
         // write HELLO packet to remote peer
         self.state = State::Session(wrapper.take().connection);
         self.write_hello(io, host)?;
@@ -249,7 +337,11 @@ impl Session {
             State::Handshake(ref mut h) => {
                 let h = h.get_mut();
 
-                if !h.readable(io, &host.metadata)? {
+                if !h.readable(
+                    io,
+                    &host.metadata,
+                    &host.config.session_encryption,
+                )? {
                     return Ok(SessionDataWithDisconnectInfo {
                         session_data: SessionData::None,
                         token_to_disconnect: None,
@@ -269,7 +361,19 @@ impl Session {
                 })
             }
             State::Session(ref mut c) => match c.readable()? {
-                Some(data) => Ok(self.read_packet(data, host)?),
+                Some(data) => {
+                    let data = self.read_packet(data, host)?;
+                    if let SessionData::Ready { .. } = data.session_data {
+                        // send the packets held until the encryption enabled
+                        if self.connection().is_sending() {
+                            io.update_registration(self.token())
+                                .unwrap_or_else(|e| {
+                                    debug!("Token registration error: {:?}", e)
+                                });
+                        }
+                    }
+                    Ok(data)
+                }
                 None => Ok(SessionDataWithDisconnectInfo {
                     session_data: SessionData::None,
                     token_to_disconnect: None,
@@ -282,7 +386,21 @@ impl Session {
     fn read_packet(
         &mut self, data: Bytes, host: &NetworkServiceInner,
     ) -> Result<SessionDataWithDisconnectInfo, Error> {
-        let packet = SessionPacket::parse(data)?;
+        let mut packet = SessionPacket::parse(data)?;
+
+        if let Some(compression) = packet.compression {
+            // only the algorithms in Hello could be used by remote peer
+            if !host.config.session_compression.contains(&compression) {
+                debug!(
+                    "Received packet compressed with {:?} not supported, session = {:?}",
+                    compression, self
+                );
+                bail!(ErrorKind::BadProtocol);
+            }
+            packet.data = compression
+                .decompress(&packet.data, MAX_PAYLOAD_SIZE)?
+                .into();
+        }
 
         // For protocol packet, the Hello packet should already been received.
         // So that dispatch it to the corresponding protocol handler.
@@ -311,6 +429,8 @@ impl Session {
                 // Handle Hello packet to exchange protocols
                 let rlp = Rlp::new(&packet.data);
                 let pos_public_key = self.read_hello(&rlp, host)?;
+                // protocol packets on the other streams follow the Hello
+                self.connection_mut().enable_multiplexing();
                 Ok(SessionDataWithDisconnectInfo {
                     session_data: SessionData::Ready { pos_public_key },
                     token_to_disconnect,
@@ -456,10 +576,24 @@ impl Session {
             host.node_db.write().insert_with_token(entry, self.token());
         }
 
+        let item_count = rlp.item_count()?;
+        let remote_ephemeral_public: Vec<u8> = if item_count == 6 {
+            rlp.val_at(4)?
+        } else {
+            Vec::new()
+        };
+        let remote_compression: Vec<u8> = if item_count == 6 {
+            rlp.list_at(5)?
+        } else {
+            Vec::new()
+        };
+        self.negotiate_encryption(&remote_ephemeral_public, host)?;
+        self.negotiate_compression(&remote_compression, host);
+
         self.had_hello = Some(Instant::now());
-        match rlp.item_count()? {
+        match item_count {
             3 => Ok(None),
-            4 => {
+            4 | 6 => {
                 // FIXME(lpl): Verify keys.
                 let pos_public_key_bytes: Vec<u8> = rlp.val_at(3)?;
                 trace!("pos_public_key_bytes: {:?}", pos_public_key_bytes);
@@ -493,12 +627,125 @@ impl Session {
         }
     }
 
+    /// Returns the session encryption configured for the remote peer, which
+    /// is always disabled if the handshake cryptography is bypassed.
+    fn session_encryption(
+        &self, host: &NetworkServiceInner,
+    ) -> SessionEncryption {
+        match (self.session_secret.as_ref(), self.metadata.id.as_ref()) {
+            (Some(_), Some(id)) => host.config.session_encryption.get(id),
+            _ => SessionEncryption::Disabled,
+        }
+    }
+
+    /// Returns whether both peers enable the session encryption in the
+    /// handshake.
+    fn encryption_enabled(&self, host: &NetworkServiceInner) -> bool {
+        self.remote_encryption
+            && self.session_encryption(host) != SessionEncryption::Disabled
+    }
+
+    /// Enables the encryption of the packets after Hello if both peers enable
+    /// it in the handshake, where the flags could not be tampered with. If
+    /// encryption is required but not enabled by the remote peer, disconnect
+    /// it.
+    fn negotiate_encryption(
+        &mut self, remote_ephemeral_public: &[u8], host: &NetworkServiceInner,
+    ) -> Result<(), Error> {
+        match (self.session_encryption(host), self.remote_encryption) {
+            (SessionEncryption::Disabled, _)
+            | (SessionEncryption::Preferred, false) => Ok(()),
+            (SessionEncryption::Required, false) => {
+                debug!(
+                    "Session encryption not enabled by remote peer, session = {:?}",
+                    self
+                );
+                Err(self.send_disconnect(DisconnectReason::Custom(
+                    "session encryption required".into(),
+                )))
+            }
+            (_, true) => {
+                if remote_ephemeral_public.len() != Public::len_bytes() {
+                    debug!(
+                        "Invalid ephemeral public key in Hello, session = {:?}",
+                        self
+                    );
+                    bail!(ErrorKind::BadProtocol);
+                }
+                let ephemeral_secret = self
+                    .ephemeral_secret
+                    .take()
+                    .expect("should have ephemeral key after Hello sent");
+                let shared = ecdh::agree(
+                    &ephemeral_secret,
+                    &Public::from_slice(remote_ephemeral_public),
+                )?;
+                let secret = self
+                    .session_secret
+                    .expect("should have session secret after handshake")
+                    .with_ephemeral_secret(*shared);
+                let originated = self.metadata.originated;
+                self.connection_mut().enable_encryption(&secret, originated);
+                debug!("Session encryption enabled, session = {:?}", self);
+                Ok(())
+            }
+        }
+    }
+
+    /// Selects the first compression algorithm in the order of local
+    /// preference that is supported by the remote peer as well.
+    fn negotiate_compression(
+        &mut self, remote_compression: &[u8], host: &NetworkServiceInner,
+    ) {
+        self.compression = host
+            .config
+            .session_compression
+            .iter()
+            .find(|c| remote_compression.contains(&c.id()))
+            .cloned();
+        self.compression_threshold = host.config.session_compression_threshold;
+        debug!(
+            "Session compression negotiated: {:?}, session = {:?}",
+            self.compression, self
+        );
+    }
+
+    /// Compresses the data of protocol packet if compression is negotiated and
+    /// the data is not smaller than the threshold. The data is sent as is if
+    /// compression fails or does not make it smaller.
+    fn compress(
+        &self, protocol: &Option<ProtocolId>, data: Vec<u8>,
+    ) -> (Vec<u8>, Option<Compression>) {
+        let compression = match self.compression {
+            Some(compression)
+                if protocol.is_some()
+                    && data.len() >= self.compression_threshold =>
+            {
+                compression
+            }
+            _ => return (data, None),
+        };
+
+        match compression.compress(&data) {
+            Ok(compressed) if compressed.len() < data.len() => {
+                (compressed, Some(compression))
+            }
+            Ok(_) => (data, None),
+            Err(e) => {
+                debug!("Failed to compress packet, err = {:?}", e);
+                (data, None)
+            }
+        }
+    }
+
     /// Assemble a packet with specified protocol id, packet id and data.
     /// Return concrete error if session is expired or the protocol id is
     /// invalid.
     fn prepare_packet(
         &self, protocol: Option<ProtocolId>, packet_id: u8, data: Vec<u8>,
-    ) -> Result<Vec<u8>, Error> {
+        compression: Option<Compression>,
+    ) -> Result<Vec<u8>, Error>
+    {
         if protocol.is_some() && self.had_hello.is_none() {
             debug!(
                 "Sending to unconfirmed session {}, protocol: {:?}, packet: {}",
@@ -519,6 +766,7 @@ impl Session {
             packet_id,
             self.metadata.peer_header_version,
             protocol,
+            compression,
             data,
         ))
     }
@@ -565,8 +813,13 @@ impl Session {
             min_proto_version,
             &data,
         )?;
-        let packet = self.prepare_packet(protocol, packet_id, data)?;
-        self.connection_mut().send(io, packet, priority)
+        let (data, compression) = self.compress(&protocol, data);
+        let compressed_size = compression.map(|_| data.len());
+        let packet =
+            self.prepare_packet(protocol, packet_id, data, compression)?;
+        let mut status = self.connection_mut().send(io, packet, priority)?;
+        status.compressed_size = compressed_size;
+        Ok(status)
     }
 
     /// Send a packet to remote peer immediately.
@@ -580,7 +833,9 @@ impl Session {
             min_proto_version,
             &data,
         )?;
-        let packet = self.prepare_packet(protocol, packet_id, data)?;
+        let (data, compression) = self.compress(&protocol, data);
+        let packet =
+            self.prepare_packet(protocol, packet_id, data, compression)?;
         self.connection_mut().write_raw_data(packet)
     }
 
@@ -601,7 +856,26 @@ impl Session {
         &mut self, io: &IoContext<Message>, host: &NetworkServiceInner,
     ) -> Result<(), Error> {
         debug!("Sending Hello, session = {:?}", self);
-        let mut rlp = RlpStream::new_list(4);
+        // ephemeral key to agree on a secret that could not be recovered
+        // from the recorded handshake
+        let ephemeral_public = if self.encryption_enabled(host) {
+            let key = Random.generate()?;
+            self.ephemeral_secret = Some(key.secret().clone());
+            key.public().as_bytes().to_vec()
+        } else {
+            Vec::new()
+        };
+        let compression: Vec<u8> = host
+            .config
+            .session_compression
+            .iter()
+            .map(|c| c.id())
+            .collect();
+        // the extended items are appended only if the remote peer advertises
+        // them in the handshake, since the old peers reject the Hello packets
+        // with more than 4 items
+        let item_count = if self.extended_hello { 6 } else { 4 };
+        let mut rlp = RlpStream::new_list(item_count);
         rlp.append(&host.metadata.network_id);
         rlp.append_list(&*host.metadata.protocols.read());
         host.metadata.public_endpoint.to_rlp_list(&mut rlp);
@@ -611,6 +885,10 @@ impl Session {
             &mut self.pos_public_key.as_ref().unwrap().1.to_bytes().to_vec(),
         );
         rlp.append(&key_bytes);
+        if self.extended_hello {
+            rlp.append(&ephemeral_public);
+            rlp.append_list::<u8, u8>(&compression);
+        }
         self.send_packet(
             io,
             None,
@@ -618,8 +896,13 @@ impl Session {
             PACKET_HELLO,
             rlp.drain(),
             SendQueuePriority::High,
-        )
-        .map(|_| ())
+        )?;
+        // the remote peer decrypts all the packets after the Hello, so the
+        // packets are held until the encryption is enabled on its Hello
+        if self.ephemeral_secret.is_some() {
+            self.connection_mut().hold_until_encrypted();
+        }
+        Ok(())
     }
 
     /// Writable IO handler. Sends pending packets.
@@ -639,6 +922,7 @@ impl Session {
             node_id: self.metadata.id,
             address: self.address,
             connection: self.connection().details(),
+            compression: self.compression.map(|c| format!("{:?}", c)),
             status: if let Some(time) = self.expired {
                 format!("expired ({:?})", time.elapsed())
             } else if let Some(time) = self.had_hello {
@@ -649,6 +933,7 @@ impl Session {
             last_read: format!("{:?}", self.last_read.elapsed()),
             last_write: format!("{:?}", self.last_write.0.elapsed()),
             last_write_status: format!("{:?}", self.last_write.1),
+            protocol_stats: BTreeMap::new(),
         }
     }
 
@@ -692,10 +977,14 @@ pub struct SessionDetails {
     pub node_id: Option<NodeId>,
     pub address: SocketAddr,
     pub connection: ConnectionDetails,
+    pub compression: Option<String>,
     pub status: String,
     pub last_read: String,
     pub last_write: String,
     pub last_write_status: String,
+    /// Statistics of the peer provided by the protocol handlers, keyed by
+    /// protocol name.
+    pub protocol_stats: BTreeMap<String, serde_json::Value>,
 }
 
 /// MovableWrapper is a util to move a value out of a struct.
@@ -739,10 +1028,13 @@ impl<T> MovableWrapper<T> {
 ///
 /// The header format is:
 /// [  extensions (0 to more bytes) || protocol (0 or 3 bytes if protocol_flag)
-///   || reserved (3 bit), has_extension (1 bit), header_version (3 bit),
-///      protocol_flag (1 bit)
+///   || reserved (1 bit), compression (2 bit), has_extension (1 bit),
+///      header_version (3 bit), protocol_flag (1 bit)
 ///   || packet_id]
 ///
+/// The compression is the id of the algorithm to compress the data, or 0 if
+/// not compressed.
+///
 /// The protocol format is:
 ///     [ protocol_id (3 bytes)]
 ///
@@ -757,13 +1049,14 @@ struct SessionPacket {
     pub data: Bytes,
     pub header_version: u8,
     pub extensions: Vec<Vec<u8>>,
+    pub compression: Option<Compression>,
 }
 
 impl SessionPacket {
     // data + Option<protocol> + protocol_flag + packet_id
     fn assemble(
         id: u8, header_version: u8, protocol: Option<ProtocolId>,
-        mut data: Vec<u8>,
+        compression: Option<Compression>, mut data: Vec<u8>,
     ) -> Vec<u8>
     {
         let mut protocol_flag = 0;
@@ -772,7 +1065,9 @@ impl SessionPacket {
             protocol_flag = 1;
         }
 
-        let header_byte = (header_version << 1) + protocol_flag;
+        let compression_id = compression.map_or(0, |c| c.id());
+        let header_byte =
+            (compression_id << 5) + (header_version << 1) + protocol_flag;
         data.push(header_byte);
         data.push(id);
 
@@ -802,6 +1097,16 @@ impl SessionPacket {
             return Err(ErrorKind::BadProtocol.into());
         }
         let has_extension = (header_byte & 0x10) >> 4;
+        let compression = match (header_byte & 0x60) >> 5 {
+            0 => None,
+            id => match Compression::from_id(id) {
+                Some(compression) => Some(compression),
+                None => {
+                    debug!("unsupported compression {}", id);
+                    return Err(ErrorKind::BadProtocol.into());
+                }
+            },
+        };
 
         // without protocol
         if protocol_flag == 0 {
@@ -819,6 +1124,7 @@ impl SessionPacket {
                 protocol: None,
                 data,
                 extensions,
+                compression,
             });
         }
 
@@ -847,6 +1153,7 @@ impl SessionPacket {
             header_version,
             data,
             extensions,
+            compression,
         })
     }
 
@@ -888,17 +1195,32 @@ mod tests {
 
     #[test]
     fn test_packet_assemble() {
-        let packet =
-            SessionPacket::assemble(5, PACKET_HEADER_VERSION, None, vec![1, 3]);
+        let packet = SessionPacket::assemble(
+            5,
+            PACKET_HEADER_VERSION,
+            None,
+            None,
+            vec![1, 3],
+        );
         assert_eq!(packet, vec![1, 3, 0, 5]);
 
         let packet = SessionPacket::assemble(
             6,
             PACKET_HEADER_VERSION,
             Some([8; 3]),
+            None,
             vec![2, 4],
         );
         assert_eq!(packet, vec![2, 4, 8, 8, 8, 1, 6]);
+
+        let packet = SessionPacket::assemble(
+            6,
+            PACKET_HEADER_VERSION,
+            Some([8; 3]),
+            Some(Compression::Zstd),
+            vec![2, 4],
+        );
+        assert_eq!(packet, vec![2, 4, 8, 8, 8, 0x41, 6]);
     }
 
     #[test]
@@ -925,6 +1247,7 @@ mod tests {
                 protocol: None,
                 data: vec![1, 2].into(),
                 extensions: vec![],
+                compression: None,
             }
         );
 
@@ -948,7 +1271,18 @@ mod tests {
                 protocol: Some([3; 3]),
                 data: vec![1, 9].into(),
                 extensions: vec![],
+                compression: None,
             }
         );
+
+        // compressed user packet
+        let packet =
+            SessionPacket::parse(vec![1, 9, 3, 3, 3, 0x21, PACKET_USER].into())
+                .unwrap();
+        assert_eq!(packet.compression, Some(Compression::Snappy));
+        assert_eq!(packet.data, vec![1, 9]);
+
+        // compression not supported
+        assert!(SessionPacket::parse(vec![1, 9, 0x60, 20].into()).is_err());
     }
 }
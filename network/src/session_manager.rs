@@ -7,11 +7,11 @@ use crate::{
     node_table::NodeId,
     service::NetworkServiceInner,
     session::{Session, PACKET_HEADER_VERSION},
+    transport::TransportStream,
     NetworkIoMessage,
 };
 use diem_types::validator_config::{ConsensusPublicKey, ConsensusVRFPublicKey};
 use io::IoContext;
-use mio::net::TcpStream;
 use parking_lot::RwLock;
 use slab::Slab;
 use std::{
@@ -32,6 +32,8 @@ use std::{
 ///
 /// The session manager also limits the maximum number of incoming TCP
 /// connections, so as to establish some trusted outgoing connections.
+///
+/// Sessions of reserved peers are exempt from both limits.
 pub struct SessionManager {
     sessions: RwLock<Slab<Arc<RwLock<Session>>>>,
     capacity: usize,
@@ -48,6 +50,8 @@ pub struct SessionManager {
     /// session indices
     node_id_index: RwLock<HashMap<NodeId, usize>>,
     ip_limit: RwLock<Box<dyn SessionIpLimit>>,
+    /// indices of sessions that are not counted in the limits
+    exempted: RwLock<HashSet<usize>>,
     tag_index: RwLock<SessionTagIndex>,
     /// pos public key
     pub self_pos_public_key:
@@ -73,6 +77,7 @@ impl SessionManager {
             cur_ingress_sessions: AtomicUsize::new(0),
             node_id_index: RwLock::new(HashMap::new()),
             ip_limit: RwLock::new(new_session_ip_limit(ip_limit_config)),
+            exempted: Default::default(),
             tag_index: Default::default(),
             self_pos_public_key,
         }
@@ -150,11 +155,16 @@ impl SessionManager {
         self.ip_limit.read().is_allowed(ip)
     }
 
-    /// Creates a new session with specified TCP socket. It is egress connection
+    /// Creates a new session with specified transport connection. It is egress
+    /// connection
     /// if the `id` is not `None`, otherwise it is ingress connection.
+    ///
+    /// The session of a `reserved` peer is not limited by the maximum number
+    /// of ingress sessions and the node IP policy.
     pub fn create(
-        &self, socket: TcpStream, address: SocketAddr, id: Option<&NodeId>,
-        io: &IoContext<NetworkIoMessage>, host: &NetworkServiceInner,
+        &self, socket: Box<dyn TransportStream>, address: SocketAddr,
+        id: Option<&NodeId>, reserved: bool, io: &IoContext<NetworkIoMessage>,
+        host: &NetworkServiceInner,
     ) -> Result<usize, String>
     {
         debug!(
@@ -168,7 +178,7 @@ impl SessionManager {
 
         // limits ingress sessions whose node id is `None`.
         let ingress = self.cur_ingress_sessions.load(Ordering::Relaxed);
-        if id.is_none() && ingress >= self.max_ingress_sessions {
+        if id.is_none() && !reserved && ingress >= self.max_ingress_sessions {
             debug!("SessionManager.create: leave on maximum ingress sessions reached");
             return Err(format!(
                 "maximum ingress sessions reached, current = {}, max = {}",
@@ -191,7 +201,7 @@ impl SessionManager {
 
         // validate against node IP policy.
         let ip = address.ip();
-        if !ip_limit.is_allowed(&ip) {
+        if !reserved && !ip_limit.is_allowed(&ip) {
             debug!("SessionManager.create: leave on IP policy limited");
             return Err(format!(
                 "IP policy limited, nodeId = {:?}, addr = {:?}",
@@ -229,10 +239,14 @@ impl SessionManager {
             node_id_index.insert(node_id.clone(), index);
         }
 
-        assert!(ip_limit.add(ip));
+        if reserved {
+            self.exempted.write().insert(index);
+        } else {
+            assert!(ip_limit.add(ip));
 
-        if id.is_none() {
-            self.cur_ingress_sessions.fetch_add(1, Ordering::Relaxed);
+            if id.is_none() {
+                self.cur_ingress_sessions.fetch_add(1, Ordering::Relaxed);
+            }
         }
 
         debug!("SessionManager.create: leave");
@@ -258,10 +272,12 @@ impl SessionManager {
                 }
             }
 
-            assert!(self.ip_limit.write().remove(&session.address().ip()));
+            if !self.exempted.write().remove(&session.token()) {
+                assert!(self.ip_limit.write().remove(&session.address().ip()));
 
-            if !session.metadata.originated {
-                self.cur_ingress_sessions.fetch_sub(1, Ordering::Relaxed);
+                if !session.metadata.originated {
+                    self.cur_ingress_sessions.fetch_sub(1, Ordering::Relaxed);
+                }
             }
 
             self.tag_index.write().remove(session.token());
@@ -310,6 +326,37 @@ impl SessionManager {
 
         Ok(token_to_disconnect)
     }
+
+    /// Counts an exempted ingress session in the limits, which is admitted
+    /// by the IP address of a reserved peer, but turns out to be another node
+    /// after handshake.
+    /// Return error if the limits are reached.
+    pub fn revoke_ingress_exemption(
+        &self, idx: usize, ip: &IpAddr,
+    ) -> Result<(), String> {
+        let mut ip_limit = self.ip_limit.write();
+        let mut exempted = self.exempted.write();
+        if !exempted.contains(&idx) {
+            return Ok(());
+        }
+
+        let ingress = self.cur_ingress_sessions.load(Ordering::Relaxed);
+        if ingress >= self.max_ingress_sessions {
+            return Err(format!(
+                "maximum ingress sessions reached, current = {}, max = {}",
+                ingress, self.max_ingress_sessions
+            ));
+        }
+
+        if !ip_limit.add(*ip) {
+            return Err(format!("IP policy limited, addr = {:?}", ip));
+        }
+
+        self.cur_ingress_sessions.fetch_add(1, Ordering::Relaxed);
+        exempted.remove(&idx);
+
+        Ok(())
+    }
 }
 
 #[derive(Default)]
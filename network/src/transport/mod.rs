@@ -0,0 +1,281 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Transports of the peer sessions.
+//!
+//! A transport listens for and creates the connections to remote peers, and a
+//! connection carries one or more independent streams of bytes. TCP is the
+//! default transport with a single stream, so the packets of all priorities
+//! share one stream. QUIC (behind the `quic` feature) opens a stream per
+//! priority of the send queue, so that a large low priority packet, e.g. a
+//! snapshot chunk, does not block the high priority packets behind it.
+//!
+//! The transports other than TCP are advertised in the discovery records, and
+//! used to connect to the peers that advertise them as well. If a peer fails to
+//! connect over such a transport, e.g. the UDP port of QUIC is blocked by a
+//! firewall, it is connected over TCP for a while instead.
+
+#[cfg(feature = "quic")]
+mod quic;
+mod tcp;
+
+#[cfg(feature = "quic")]
+pub use self::quic::QuicTransport;
+pub use self::tcp::TcpTransport;
+
+use crate::node_table::NodeId;
+use mio::{Poll, Ready, Token};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// Peers failed to connect over the transports other than TCP are connected
+/// over TCP for this long.
+const FALLBACK_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Protocol of a transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportProtocol {
+    Tcp,
+    Quic,
+}
+
+impl TransportProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportProtocol::Tcp => "tcp",
+            TransportProtocol::Quic => "quic",
+        }
+    }
+}
+
+impl fmt::Display for TransportProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransportProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(TransportProtocol::Tcp),
+            "quic" => Ok(TransportProtocol::Quic),
+            _ => Err(format!("Unknown transport protocol: {}", s)),
+        }
+    }
+}
+
+/// Transport advertised in the node record, which accepts connections on the
+/// port of the node endpoint IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportInfo {
+    pub protocol: TransportProtocol,
+    pub port: u16,
+}
+
+impl TransportInfo {
+    /// Returns the address to connect to the transport of the node at `ip`.
+    pub fn address(&self, ip: IpAddr) -> SocketAddr {
+        SocketAddr::new(ip, self.port)
+    }
+}
+
+impl Encodable for TransportInfo {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2)
+            .append(&self.protocol.as_str())
+            .append(&self.port);
+    }
+}
+
+impl Decodable for TransportInfo {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let protocol: String = rlp.val_at(0)?;
+        Ok(TransportInfo {
+            protocol: protocol
+                .parse()
+                .map_err(|_| DecoderError::Custom("unknown transport"))?,
+            port: rlp.val_at(1)?,
+        })
+    }
+}
+
+/// Configuration of the QUIC transport. QUIC connections are always secured
+/// with TLS, but the peers are authenticated by the session handshake as on
+/// TCP, so the certificate could be self-signed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuicConfiguration {
+    /// UDP port to accept the QUIC connections on, which is different from
+    /// the discovery port.
+    pub port: u16,
+    /// Path of the TLS certificate chain in PEM format.
+    pub cert_path: String,
+    /// Path of the TLS private key in PEM format.
+    pub key_path: String,
+    /// Connections are closed if idle for this long.
+    pub idle_timeout: Duration,
+}
+
+/// Independent streams of bytes of a connection. Bytes of a stream are
+/// delivered in order, while bytes of different streams are not.
+pub trait MultiplexedStream {
+    /// Returns the number of streams, which are indexed from 0.
+    fn stream_count(&self) -> usize;
+
+    /// Reads from the stream. Returns `WouldBlock` if no data available, or
+    /// 0 if the stream is closed by the remote peer.
+    fn read(&mut self, stream: usize, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes to the stream. Returns the number of bytes written, which may
+    /// be 0 if the stream is blocked by the flow control.
+    fn write(&mut self, stream: usize, buf: &[u8]) -> io::Result<usize>;
+}
+
+impl<T: MultiplexedStream + ?Sized> MultiplexedStream for Box<T> {
+    fn stream_count(&self) -> usize { (**self).stream_count() }
+
+    fn read(&mut self, stream: usize, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read(stream, buf)
+    }
+
+    fn write(&mut self, stream: usize, buf: &[u8]) -> io::Result<usize> {
+        (**self).write(stream, buf)
+    }
+}
+
+/// Connection of a transport, which is registered with the IO event loop by
+/// the token of the session.
+pub trait TransportStream: MultiplexedStream + Send + Sync {
+    fn protocol(&self) -> TransportProtocol;
+
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+
+    fn register(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()>;
+
+    fn reregister(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()>;
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()>;
+}
+
+/// Transport that connects to the remote peers and accepts the incoming
+/// connections, which is registered with the IO event loop by its own token.
+pub trait Transport: Send + Sync {
+    fn protocol(&self) -> TransportProtocol;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Starts to connect to the remote peer. The connection may not be
+    /// established yet when returned, and its streams become writable once
+    /// it is.
+    fn connect(
+        &self, address: &SocketAddr,
+    ) -> io::Result<Box<dyn TransportStream>>;
+
+    /// Accepts an established incoming connection. Returns `WouldBlock` if
+    /// there is none.
+    fn accept(&self) -> io::Result<(Box<dyn TransportStream>, SocketAddr)>;
+
+    fn register(&self, reg: Token, poll: &Poll) -> io::Result<()>;
+
+    fn reregister(&self, reg: Token, poll: &Poll) -> io::Result<()>;
+
+    /// Handles the readable event of the transport before accepting the
+    /// incoming connections, e.g. to receive the packets of the connections
+    /// that share the transport socket.
+    fn readable(&self) -> io::Result<()> { Ok(()) }
+
+    /// Interval of the timer to drive the transport, e.g. for the loss
+    /// recovery of QUIC, or `None` if not needed.
+    fn timer_interval(&self) -> Option<Duration> { None }
+
+    fn on_timeout(&self) {}
+}
+
+/// Peers failed to connect over the transports other than TCP, which are
+/// connected over TCP until the fallback expires.
+#[derive(Default)]
+pub struct TransportFallback {
+    expiries: HashMap<NodeId, Instant>,
+}
+
+impl TransportFallback {
+    /// Notes that the peer failed to connect over a transport other than TCP.
+    pub fn note_failure(&mut self, id: &NodeId, now: Instant) {
+        self.expiries.insert(*id, now + FALLBACK_TIMEOUT);
+    }
+
+    /// Returns whether the peer should be connected over TCP.
+    pub fn is_fallback(&mut self, id: &NodeId, now: Instant) -> bool {
+        match self.expiries.get(id) {
+            Some(expiry) if *expiry > now => true,
+            Some(_) => {
+                self.expiries.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_transport_info_rlp() {
+        let info = TransportInfo {
+            protocol: TransportProtocol::Quic,
+            port: 32324,
+        };
+        let decoded: TransportInfo = rlp::decode(&rlp::encode(&info)).unwrap();
+        assert_eq!(decoded, info);
+        assert_eq!(
+            info.address(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            "127.0.0.1:32324".parse().unwrap()
+        );
+
+        let mut s = RlpStream::new_list(2);
+        s.append(&"sctp").append(&32324u16);
+        assert!(rlp::decode::<TransportInfo>(&s.out()).is_err());
+    }
+
+    #[test]
+    fn test_transport_protocol_from_str() {
+        for protocol in &[TransportProtocol::Tcp, TransportProtocol::Quic] {
+            assert_eq!(protocol.as_str().parse(), Ok(*protocol));
+        }
+        assert!("udp".parse::<TransportProtocol>().is_err());
+    }
+
+    #[test]
+    fn test_transport_fallback() {
+        let mut fallback = TransportFallback::default();
+        let id = NodeId::random();
+        let now = Instant::now();
+        assert!(!fallback.is_fallback(&id, now));
+
+        fallback.note_failure(&id, now);
+        assert!(fallback.is_fallback(&id, now));
+        assert!(fallback.is_fallback(&id, now + FALLBACK_TIMEOUT / 2));
+        assert!(!fallback.is_fallback(&NodeId::random(), now));
+
+        assert!(!fallback.is_fallback(&id, now + FALLBACK_TIMEOUT));
+        assert!(fallback.expiries.is_empty());
+    }
+}
@@ -0,0 +1,774 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{
+    MultiplexedStream, QuicConfiguration, Transport, TransportProtocol,
+    TransportStream,
+};
+use bytes_1::{Bytes, BytesMut};
+use mio::{
+    net::UdpSocket, Poll, PollOpt, Ready, Registration, SetReadiness, Token,
+};
+use parking_lot::Mutex;
+use quinn_proto::{
+    ClientConfig, Connection, ConnectionHandle, DatagramEvent, Dir,
+    EndpointConfig, Event, IdleTimeout, ReadError, ReadableError, ServerConfig,
+    StreamEvent, StreamId, TransportConfig, VarInt, WriteError,
+};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, PrivateKey, ServerName,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    error::Error,
+    fs::File,
+    io::{self, BufReader},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Application protocol negotiated in the TLS handshake.
+const APPLICATION_PROTOCOL: &[u8] = b"conflux";
+/// Server name of the TLS handshake, which is not verified.
+const SERVER_NAME: &str = "conflux";
+/// Number of the streams of a connection, i.e. one for each priority of the
+/// send queue.
+const STREAM_COUNT: usize = 3;
+/// Flow control limits of the data received on a stream and a connection.
+const MAX_STREAM_DATA: u32 = 4 * 1024 * 1024;
+const MAX_DATA: u32 = STREAM_COUNT as u32 * MAX_STREAM_DATA;
+/// Maximum number of connections of the endpoint, including the incoming
+/// connections not accepted yet.
+const MAX_CONNECTIONS: usize = 2048;
+const MAX_DATAGRAM_SIZE: usize = 65535;
+const TIMER_INTERVAL: Duration = Duration::from_millis(10);
+
+/// QUIC transport, whose connections have a stream for each priority of the
+/// send queue. All the connections share the UDP socket of the endpoint.
+///
+/// Each peer sends on the unidirectional streams opened by itself, and the
+/// streams are prioritized by the priorities of the send queue, so that the
+/// packets of a higher priority are neither queued nor blocked by a lost
+/// packet behind the lower priority ones.
+pub struct QuicTransport {
+    endpoint: Arc<Mutex<Endpoint>>,
+}
+
+impl QuicTransport {
+    pub fn bind(config: &QuicConfiguration, ip: IpAddr) -> io::Result<Self> {
+        let mut transport_config = TransportConfig::default();
+        transport_config
+            .max_concurrent_uni_streams(VarInt::from_u32(STREAM_COUNT as u32))
+            .max_concurrent_bidi_streams(VarInt::from_u32(0))
+            .stream_receive_window(VarInt::from_u32(MAX_STREAM_DATA))
+            .receive_window(VarInt::from_u32(MAX_DATA))
+            .max_idle_timeout(Some(
+                IdleTimeout::try_from(config.idle_timeout).map_err(to_io)?,
+            ));
+        let transport_config = Arc::new(transport_config);
+
+        let (cert_chain, key) = load_cert(&config.cert_path, &config.key_path)?;
+        let mut server_crypto = rustls::ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(to_io)?
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .map_err(to_io)?;
+        server_crypto.alpn_protocols = vec![APPLICATION_PROTOCOL.to_vec()];
+        let mut server_config =
+            ServerConfig::with_crypto(Arc::new(server_crypto));
+        server_config.transport = transport_config.clone();
+        server_config
+            .concurrent_connections(MAX_CONNECTIONS as u32)
+            .migration(false);
+
+        // peers are authenticated by the session handshake instead
+        let mut client_crypto = rustls::ClientConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(to_io)?
+            .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+            .with_no_client_auth();
+        client_crypto.alpn_protocols = vec![APPLICATION_PROTOCOL.to_vec()];
+        let mut client_config = ClientConfig::new(Arc::new(client_crypto));
+        client_config.transport = transport_config;
+
+        let socket = UdpSocket::bind(&SocketAddr::new(ip, config.port))?;
+        let local_addr = socket.local_addr()?;
+        Ok(QuicTransport {
+            endpoint: Arc::new(Mutex::new(Endpoint {
+                socket,
+                local_addr,
+                endpoint: quinn_proto::Endpoint::new(
+                    Arc::new(EndpointConfig::default()),
+                    Some(Arc::new(server_config)),
+                ),
+                client_config,
+                connections: HashMap::new(),
+                incoming: VecDeque::new(),
+            })),
+        })
+    }
+}
+
+impl Transport for QuicTransport {
+    fn protocol(&self) -> TransportProtocol { TransportProtocol::Quic }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.endpoint.lock().local_addr)
+    }
+
+    fn connect(
+        &self, address: &SocketAddr,
+    ) -> io::Result<Box<dyn TransportStream>> {
+        let mut endpoint = self.endpoint.lock();
+        if endpoint.connections.len() >= MAX_CONNECTIONS {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "too many QUIC connections",
+            ));
+        }
+
+        let client_config = endpoint.client_config.clone();
+        let (handle, conn) = endpoint
+            .endpoint
+            .connect(client_config, *address, SERVER_NAME)
+            .map_err(to_io)?;
+
+        let mut connection = QuicConnection::new(conn, *address);
+        let registration = connection.take_registration();
+        endpoint.connections.insert(handle, connection);
+        endpoint.drive(handle);
+
+        Ok(Box::new(QuicStream {
+            endpoint: self.endpoint.clone(),
+            handle,
+            peer: *address,
+            registration,
+        }))
+    }
+
+    fn accept(&self) -> io::Result<(Box<dyn TransportStream>, SocketAddr)> {
+        let mut endpoint = self.endpoint.lock();
+        while let Some(handle) = endpoint.incoming.pop_front() {
+            let connection = match endpoint.connections.get_mut(&handle) {
+                Some(c) if !c.conn.is_closed() => c,
+                _ => continue,
+            };
+            let registration = connection.take_registration();
+            let peer = connection.peer;
+            let stream = QuicStream {
+                endpoint: self.endpoint.clone(),
+                handle,
+                peer,
+                registration,
+            };
+            return Ok((Box::new(stream), peer));
+        }
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+
+    fn register(&self, reg: Token, poll: &Poll) -> io::Result<()> {
+        let endpoint = self.endpoint.lock();
+        poll.register(&endpoint.socket, reg, Ready::readable(), PollOpt::edge())
+    }
+
+    fn reregister(&self, reg: Token, poll: &Poll) -> io::Result<()> {
+        let endpoint = self.endpoint.lock();
+        poll.reregister(
+            &endpoint.socket,
+            reg,
+            Ready::readable(),
+            PollOpt::edge(),
+        )
+    }
+
+    fn readable(&self) -> io::Result<()> { self.endpoint.lock().recv() }
+
+    fn timer_interval(&self) -> Option<Duration> { Some(TIMER_INTERVAL) }
+
+    fn on_timeout(&self) { self.endpoint.lock().on_timeout() }
+}
+
+struct Endpoint {
+    socket: UdpSocket,
+    local_addr: SocketAddr,
+    endpoint: quinn_proto::Endpoint,
+    client_config: ClientConfig,
+    connections: HashMap<ConnectionHandle, QuicConnection>,
+    /// Incoming connections established but not accepted yet.
+    incoming: VecDeque<ConnectionHandle>,
+}
+
+impl Endpoint {
+    /// Receives the packets until the socket would block, and sends the
+    /// packets of the connections in response.
+    fn recv(&mut self) -> io::Result<()> {
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            };
+            let data = BytesMut::from(&buf[..len]);
+            match self.endpoint.handle(Instant::now(), from, None, None, data) {
+                Some((handle, DatagramEvent::NewConnection(conn))) => {
+                    trace!("New QUIC connection from {}", from);
+                    self.connections
+                        .insert(handle, QuicConnection::new(conn, from));
+                }
+                Some((handle, DatagramEvent::ConnectionEvent(event))) => {
+                    if let Some(connection) = self.connections.get_mut(&handle)
+                    {
+                        connection.conn.handle_event(event);
+                        // acknowledgements and flow control updates may
+                        // unblock the writing
+                        connection.blocked = false;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // e.g. version negotiation and stateless reset
+        while let Some(transmit) = self.endpoint.poll_transmit() {
+            self.socket
+                .send_to(&transmit.contents, &transmit.destination)
+                .ok();
+        }
+
+        let handles: Vec<ConnectionHandle> =
+            self.connections.keys().cloned().collect();
+        for handle in handles {
+            self.drive(handle);
+        }
+        Ok(())
+    }
+
+    /// Processes the events of the connection, sends its pending packets and
+    /// notifies the session of the connection state. Packets that would block
+    /// the socket are dropped, and recovered by the loss recovery of QUIC as
+    /// if they were lost in the network.
+    fn drive(&mut self, handle: ConnectionHandle) {
+        let Endpoint {
+            socket,
+            endpoint,
+            connections,
+            incoming,
+            ..
+        } = self;
+        let connection = match connections.get_mut(&handle) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        let now = Instant::now();
+        while let Some(event) = connection.conn.poll_endpoint_events() {
+            if let Some(event) = endpoint.handle_event(handle, event) {
+                connection.conn.handle_event(event);
+            }
+        }
+
+        while let Some(event) = connection.conn.poll() {
+            match event {
+                Event::Connected => {
+                    connection.open_streams(now);
+                    if connection.conn.side().is_server() {
+                        incoming.push_back(handle);
+                    }
+                }
+                Event::ConnectionLost { reason } => {
+                    debug!(
+                        "QUIC connection to {} lost: {:?}",
+                        connection.peer, reason
+                    );
+                }
+                Event::Stream(StreamEvent::Opened { dir: Dir::Uni }) => {
+                    while connection.conn.streams().accept(Dir::Uni).is_some() {
+                    }
+                    connection.readable = [true; STREAM_COUNT];
+                }
+                Event::Stream(StreamEvent::Readable { id }) => {
+                    if let Some(stream) = connection.recv_stream_index(id) {
+                        connection.readable[stream] = true;
+                    }
+                }
+                Event::Stream(StreamEvent::Writable { .. }) => {
+                    connection.blocked = false;
+                }
+                _ => {}
+            }
+        }
+
+        while let Some(transmit) = connection.conn.poll_transmit(now, 1) {
+            if let Err(e) =
+                socket.send_to(&transmit.contents, &transmit.destination)
+            {
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    debug!("Failed to send QUIC packet: {:?}", e);
+                }
+                break;
+            }
+        }
+
+        connection.update_readiness();
+    }
+
+    /// Handles the expired timers of the connections, and removes the drained
+    /// connections that are no longer used by any session.
+    fn on_timeout(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<ConnectionHandle> = self
+            .connections
+            .iter()
+            .filter(|(_, c)| c.timeout.map_or(false, |t| t <= now))
+            .map(|(handle, _)| *handle)
+            .collect();
+        for handle in expired {
+            if let Some(connection) = self.connections.get_mut(&handle) {
+                connection.conn.handle_timeout(now);
+                connection.blocked = false;
+            }
+            self.drive(handle);
+        }
+
+        // drained connections held by sessions are kept until dropped, so
+        // that the sessions are notified
+        self.connections.retain(|_, c| {
+            !c.conn.is_drained() || (c.registration.is_none() && !c.dropped)
+        });
+        let connections = &self.connections;
+        self.incoming.retain(|h| connections.contains_key(h));
+    }
+}
+
+struct QuicConnection {
+    conn: Connection,
+    peer: SocketAddr,
+    /// Registration of the connection with the event loop, which is taken by
+    /// the stream once the connection is created or accepted.
+    registration: Option<Registration>,
+    readiness: SetReadiness,
+    /// Events the session is interested in.
+    interest: Ready,
+    /// Streams opened by the local peer to send, by the priority.
+    send_streams: Vec<StreamId>,
+    /// Whether the stream opened by the remote peer may have data to read.
+    readable: [bool; STREAM_COUNT],
+    /// Whether the last write is blocked by the flow control, and not
+    /// writable until more packets are received.
+    blocked: bool,
+    /// Time to handle the timeout of the connection.
+    timeout: Option<Instant>,
+    /// Whether the stream is dropped by the session.
+    dropped: bool,
+}
+
+impl QuicConnection {
+    fn new(conn: Connection, peer: SocketAddr) -> Self {
+        let (registration, readiness) = Registration::new2();
+        QuicConnection {
+            conn,
+            peer,
+            registration: Some(registration),
+            readiness,
+            interest: Ready::empty(),
+            send_streams: Vec::with_capacity(STREAM_COUNT),
+            readable: [false; STREAM_COUNT],
+            blocked: false,
+            timeout: None,
+            dropped: false,
+        }
+    }
+
+    fn take_registration(&mut self) -> Registration {
+        self.registration
+            .take()
+            .expect("registration should be taken only once")
+    }
+
+    /// Opens the streams to send once the connection is established, where
+    /// the stream of a higher priority is sent first.
+    fn open_streams(&mut self, now: Instant) {
+        for stream in 0..STREAM_COUNT {
+            match self.conn.streams().open(Dir::Uni) {
+                Some(id) => {
+                    self.conn
+                        .send_stream(id)
+                        .set_priority(-(stream as i32))
+                        .ok();
+                    self.send_streams.push(id);
+                }
+                None => {
+                    debug!("QUIC streams not allowed by {}", self.peer);
+                    self.conn.close(
+                        now,
+                        VarInt::from_u32(1),
+                        Bytes::from_static(b"streams not allowed"),
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the id of the unidirectional stream opened by the remote peer.
+    fn recv_stream_id(&self, stream: usize) -> StreamId {
+        StreamId::new(!self.conn.side(), Dir::Uni, stream as u64)
+    }
+
+    fn recv_stream_index(&self, id: StreamId) -> Option<usize> {
+        (0..STREAM_COUNT).find(|s| self.recv_stream_id(*s) == id)
+    }
+
+    /// Notifies the session of the events by the connection state.
+    fn update_readiness(&mut self) {
+        self.timeout = self.conn.poll_timeout();
+        if self.dropped {
+            return;
+        }
+
+        let mut ready = Ready::empty();
+        if self.conn.is_closed() {
+            ready |= Ready::hup();
+        } else {
+            if self.readable.iter().any(|r| *r) {
+                ready |= Ready::readable();
+            }
+            if !self.send_streams.is_empty() && !self.blocked {
+                ready |= Ready::writable();
+            }
+        }
+
+        if let Err(e) = self.readiness.set_readiness(ready & self.interest) {
+            debug!("Failed to set QUIC connection readiness: {:?}", e);
+        }
+    }
+}
+
+/// Connection of the QUIC transport held by a session.
+struct QuicStream {
+    endpoint: Arc<Mutex<Endpoint>>,
+    handle: ConnectionHandle,
+    peer: SocketAddr,
+    registration: Registration,
+}
+
+impl QuicStream {
+    fn set_interest(&self, interest: Ready) {
+        if let Some(connection) =
+            self.endpoint.lock().connections.get_mut(&self.handle)
+        {
+            connection.interest = interest;
+            connection.update_readiness();
+        }
+    }
+}
+
+impl MultiplexedStream for QuicStream {
+    fn stream_count(&self) -> usize { STREAM_COUNT }
+
+    fn read(&mut self, stream: usize, buf: &mut [u8]) -> io::Result<usize> {
+        let mut endpoint = self.endpoint.lock();
+        let connection = endpoint
+            .connections
+            .get_mut(&self.handle)
+            .ok_or_else(closed)?;
+        let stream_id = connection.recv_stream_id(stream);
+        let mut recv_stream = connection.conn.recv_stream(stream_id);
+        let mut chunks = match recv_stream.read(true) {
+            Ok(chunks) => chunks,
+            // not opened by the remote peer yet
+            Err(ReadableError::UnknownStream) => {
+                connection.readable[stream] = false;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            Err(e) => return Err(to_io(e)),
+        };
+
+        let result = match chunks.next(buf.len()) {
+            Ok(Some(chunk)) => {
+                buf[..chunk.bytes.len()].copy_from_slice(&chunk.bytes);
+                Ok(chunk.bytes.len())
+            }
+            Ok(None) => Ok(0),
+            Err(ReadError::Blocked) => {
+                connection.readable[stream] = false;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Err(e) => Err(to_io(e)),
+        };
+        // send the flow control updates if needed
+        if chunks.finalize().should_transmit() {
+            endpoint.drive(self.handle);
+        }
+        result
+    }
+
+    fn write(&mut self, stream: usize, buf: &[u8]) -> io::Result<usize> {
+        let mut endpoint = self.endpoint.lock();
+        let connection = endpoint
+            .connections
+            .get_mut(&self.handle)
+            .ok_or_else(closed)?;
+        let stream_id = match connection.send_streams.get(stream) {
+            Some(id) => *id,
+            // not established yet
+            None => return Ok(0),
+        };
+
+        let written = match connection.conn.send_stream(stream_id).write(buf) {
+            Ok(written) => written,
+            Err(WriteError::Blocked) => 0,
+            Err(e) => return Err(to_io(e)),
+        };
+        if written < buf.len() {
+            connection.blocked = true;
+        }
+
+        endpoint.drive(self.handle);
+        Ok(written)
+    }
+}
+
+impl TransportStream for QuicStream {
+    fn protocol(&self) -> TransportProtocol { TransportProtocol::Quic }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> { Ok(self.peer) }
+
+    fn register(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()> {
+        poll.register(&self.registration, reg, interest, PollOpt::edge())?;
+        self.set_interest(interest);
+        Ok(())
+    }
+
+    fn reregister(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()> {
+        poll.reregister(&self.registration, reg, interest, PollOpt::edge())?;
+        self.set_interest(interest);
+        Ok(())
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        poll.deregister(&self.registration)
+    }
+}
+
+impl Drop for QuicStream {
+    fn drop(&mut self) {
+        let mut endpoint = self.endpoint.lock();
+        if let Some(connection) = endpoint.connections.get_mut(&self.handle) {
+            connection.dropped = true;
+            connection.conn.close(
+                Instant::now(),
+                VarInt::from_u32(0),
+                Bytes::new(),
+            );
+            // removed by the timer once drained
+            endpoint.drive(self.handle);
+        }
+    }
+}
+
+/// Accepts any certificate of the server, since peers are authenticated by
+/// the session handshake.
+struct SkipServerVerification;
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self, _end_entity: &Certificate, _intermediates: &[Certificate],
+        _server_name: &ServerName, _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8], _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error>
+    {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Loads the certificate chain and the PKCS#8 or RSA private key in PEM
+/// format.
+fn load_cert(
+    cert_path: &str, key_path: &str,
+) -> io::Result<(Vec<Certificate>, PrivateKey)> {
+    let cert_chain =
+        rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))?;
+    if cert_chain.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no certificate in {}", cert_path),
+        ));
+    }
+
+    let mut reader = BufReader::new(File::open(key_path)?);
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key)) => break key,
+            Some(_) => continue,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no private key in {}", key_path),
+                ))
+            }
+        }
+    };
+
+    Ok((
+        cert_chain.into_iter().map(Certificate).collect(),
+        PrivateKey(key),
+    ))
+}
+
+fn to_io<E: Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "QUIC connection closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        thread,
+    };
+
+    fn bind() -> QuicTransport {
+        let testdata =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/transport/testdata");
+        let config = QuicConfiguration {
+            port: 0,
+            cert_path: format!("{}/quic_cert.pem", testdata),
+            key_path: format!("{}/quic_key.pem", testdata),
+            idle_timeout: Duration::from_secs(10),
+        };
+        QuicTransport::bind(&config, IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+    }
+
+    /// Drives the transports until `done` returns a value. The loopback
+    /// packets are delivered as soon as sent, so that it takes a few rounds
+    /// only, except the retransmission of the lost ones.
+    fn drive_until<T>(
+        transports: &[&QuicTransport], mut done: impl FnMut() -> Option<T>,
+    ) -> T {
+        for _ in 0..1000 {
+            for transport in transports {
+                transport.readable().unwrap();
+                transport.on_timeout();
+            }
+            if let Some(value) = done() {
+                return value;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("QUIC transports not done");
+    }
+
+    fn read_all(
+        stream: &mut dyn TransportStream, index: usize, len: usize,
+    ) -> Option<Vec<u8>> {
+        let mut data = vec![0; len];
+        let mut read = 0;
+        while read < len {
+            match stream.read(index, &mut data[read..]) {
+                Ok(0) => return None,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return None,
+                Err(e) => panic!("failed to read: {:?}", e),
+            }
+        }
+        Some(data)
+    }
+
+    #[test]
+    fn test_connect_and_accept() {
+        let server = bind();
+        let client = bind();
+        let server_addr = server.local_addr().unwrap();
+
+        let mut outgoing = client.connect(&server_addr).unwrap();
+        assert_eq!(outgoing.protocol(), TransportProtocol::Quic);
+        assert_eq!(outgoing.peer_addr().unwrap(), server_addr);
+        let (mut incoming, peer) =
+            drive_until(&[&server, &client], || server.accept().ok());
+        assert_eq!(peer, client.local_addr().unwrap());
+        assert_eq!(incoming.stream_count(), STREAM_COUNT);
+
+        // the streams are independent and delivered in order
+        let low: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let high = b"high priority".to_vec();
+        let mut written = 0;
+        drive_until(&[&server, &client], || {
+            written += outgoing.write(2, &low[written..]).unwrap();
+            Some(()).filter(|_| written == low.len())
+        });
+        assert_eq!(outgoing.write(0, &high).unwrap(), high.len());
+        let received = drive_until(&[&server, &client], || {
+            read_all(&mut *incoming, 0, high.len())
+        });
+        assert_eq!(received, high);
+        let mut received = Vec::new();
+        drive_until(&[&server, &client], || {
+            let mut buf = [0; 4096];
+            loop {
+                match incoming.read(2, &mut buf) {
+                    Ok(n) => received.extend_from_slice(&buf[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => panic!("failed to read: {:?}", e),
+                }
+            }
+            Some(()).filter(|_| received.len() == low.len())
+        });
+        assert_eq!(received, low);
+
+        // and the other direction
+        assert_eq!(incoming.write(1, &high).unwrap(), high.len());
+        let received = drive_until(&[&server, &client], || {
+            read_all(&mut *outgoing, 1, high.len())
+        });
+        assert_eq!(received, high);
+
+        // the remote peer is notified once the stream is dropped
+        drop(outgoing);
+        drive_until(&[&server, &client], || {
+            let mut buf = [0; 16];
+            match incoming.read(0, &mut buf) {
+                Err(e) if e.kind() != io::ErrorKind::WouldBlock => Some(()),
+                _ if server
+                    .endpoint
+                    .lock()
+                    .connections
+                    .values()
+                    .all(|c| c.conn.is_closed()) =>
+                {
+                    Some(())
+                }
+                _ => None,
+            }
+        });
+    }
+
+    #[test]
+    fn test_load_cert() {
+        assert!(load_cert("not_exist.pem", "not_exist.pem").is_err());
+        let testdata =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/transport/testdata");
+        let cert_path = format!("{}/quic_cert.pem", testdata);
+        // the certificate has no private key
+        assert!(load_cert(&cert_path, &cert_path).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{MultiplexedStream, Transport, TransportProtocol, TransportStream};
+use mio::{
+    tcp::{TcpListener, TcpStream},
+    Poll, PollOpt, Ready, Token,
+};
+use std::{
+    io::{self, Read, Write},
+    net::SocketAddr,
+};
+
+/// TCP transport, whose connections have a single stream.
+pub struct TcpTransport {
+    listener: TcpListener,
+}
+
+impl TcpTransport {
+    pub fn bind(address: &SocketAddr) -> io::Result<Self> {
+        Ok(TcpTransport {
+            listener: TcpListener::bind(address)?,
+        })
+    }
+}
+
+impl Transport for TcpTransport {
+    fn protocol(&self) -> TransportProtocol { TransportProtocol::Tcp }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    fn connect(
+        &self, address: &SocketAddr,
+    ) -> io::Result<Box<dyn TransportStream>> {
+        Ok(Box::new(TcpStream::connect(address)?))
+    }
+
+    fn accept(&self) -> io::Result<(Box<dyn TransportStream>, SocketAddr)> {
+        let (socket, address) = self.listener.accept()?;
+        Ok((Box::new(socket), address))
+    }
+
+    fn register(&self, reg: Token, poll: &Poll) -> io::Result<()> {
+        poll.register(&self.listener, reg, Ready::all(), PollOpt::edge())
+    }
+
+    fn reregister(&self, reg: Token, poll: &Poll) -> io::Result<()> {
+        poll.reregister(&self.listener, reg, Ready::all(), PollOpt::edge())
+    }
+}
+
+impl MultiplexedStream for TcpStream {
+    fn stream_count(&self) -> usize { 1 }
+
+    fn read(&mut self, _stream: usize, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+
+    fn write(&mut self, _stream: usize, buf: &[u8]) -> io::Result<usize> {
+        Write::write(self, buf)
+    }
+}
+
+impl TransportStream for TcpStream {
+    fn protocol(&self) -> TransportProtocol { TransportProtocol::Tcp }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> { TcpStream::peer_addr(self) }
+
+    fn register(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()> {
+        poll.register(self, reg, interest, PollOpt::edge())
+    }
+
+    fn reregister(
+        &self, reg: Token, interest: Ready, poll: &Poll,
+    ) -> io::Result<()> {
+        poll.reregister(self, reg, interest, PollOpt::edge())
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        poll.deregister(self)
+    }
+}
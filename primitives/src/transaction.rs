@@ -262,6 +262,36 @@ impl NativeTransaction {
             public: None,
         }
     }
+
+    /// Fake sign the transactions of impersonated senders in dev mode.
+    pub fn fake_sign_impersonated(
+        self, from: AddressWithSpace,
+    ) -> SignedTransaction {
+        let rs = impersonated_signature(&from);
+        SignedTransaction {
+            transaction: TransactionWithSignature {
+                transaction: TransactionWithSignatureSerializePart {
+                    unsigned: Transaction::Native(self),
+                    r: rs,
+                    s: rs,
+                    v: 0,
+                },
+                hash: H256::zero(),
+                rlp_size: None,
+            }
+            .compute_hash(),
+            sender: from.address,
+            public: None,
+        }
+    }
+}
+
+/// The `r` and `s` of the fake signatures of impersonated senders. As for
+/// phantom transactions, the sender address is used so that transactions
+/// with matching fields from different senders have different hashes, and
+/// the zero address is mapped to one to keep the signature non-zero.
+fn impersonated_signature(from: &AddressWithSpace) -> U256 {
+    U256::from(from.address.as_ref()).max(U256::one())
 }
 
 /// Replay protection logic for v part of transaction's signature
@@ -325,6 +355,28 @@ impl Eip155Transaction {
         }
     }
 
+    /// Fake sign the transactions of impersonated senders in dev mode.
+    pub fn fake_sign_impersonated(
+        self, from: AddressWithSpace,
+    ) -> SignedTransaction {
+        let rs = impersonated_signature(&from);
+        SignedTransaction {
+            transaction: TransactionWithSignature {
+                transaction: TransactionWithSignatureSerializePart {
+                    unsigned: Transaction::Ethereum(self),
+                    r: rs,
+                    s: rs,
+                    v: 0,
+                },
+                hash: H256::zero(),
+                rlp_size: None,
+            }
+            .compute_hash(),
+            sender: from.address,
+            public: None,
+        }
+    }
+
     /// Fake sign call requests in `eth_call`.
     // `fake_sign_phantom` will use zero signature when the sender is the
     // zero address, and that will fail basic signature verification.
@@ -179,6 +179,10 @@ class RpcClient:
     def gas_price(self) -> int:
         return int(self.node.cfx_gasPrice(), 0)
 
+    def gas_price_estimates(self) -> dict:
+        estimates = self.node.cfx_gasPriceEstimates()
+        return {k: int(v, 0) for k, v in estimates.items()}
+
     def get_block_reward_info(self, epoch: str):
         reward = self.node.cfx_getBlockRewardInfo(epoch)
         convert_b32_address_field_to_hex(reward, "author")
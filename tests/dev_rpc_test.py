@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+"""Test the chain control RPCs of the dev mode: snapshots, block time,
+account overrides and impersonation.
+"""
+from conflux.address import hex_to_b32_address
+from conflux.rpc import RpcClient
+from test_framework.test_framework import ConfluxTestFramework
+from test_framework.util import *
+
+
+class DevRpcTest(ConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 1
+        self.conf_parameters["mode"] = '"dev"'
+        self.conf_parameters["public_evm_rpc_apis"] = '"evm,dev"'
+        self.conf_parameters["evm_chain_id"] = str(10)
+        self.conf_parameters["evm_transaction_block_ratio"] = str(1)
+
+    def setup_network(self):
+        self.setup_nodes()
+
+    def run_test(self):
+        self.rpc = RpcClient(self.nodes[0])
+        self.test_snapshot_and_revert()
+        self.test_increase_time()
+        self.test_impersonation()
+        self.test_eth_impersonation()
+
+    def test_snapshot_and_revert(self):
+        node = self.nodes[0]
+        addr = self.rpc.rand_addr()
+        snapshot = node.evm_snapshot()
+        node.dev_setBalance(hex_to_b32_address(addr), hex(10 ** 18))
+        assert_equal(self.rpc.get_balance(addr), 10 ** 18)
+
+        later_snapshot = node.evm_snapshot()
+        node.dev_setBalance(hex_to_b32_address(addr), hex(2 * 10 ** 18))
+        assert_equal(self.rpc.get_balance(addr), 2 * 10 ** 18)
+
+        assert node.evm_revert(later_snapshot)
+        assert_equal(self.rpc.get_balance(addr), 10 ** 18)
+        assert node.evm_revert(snapshot)
+        assert_equal(self.rpc.get_balance(addr), 0)
+        # The snapshot reverted to is removed.
+        assert not node.evm_revert(snapshot)
+        self.log.info("Pass snapshot and revert")
+
+    def test_increase_time(self):
+        node = self.nodes[0]
+        offset = node.evm_increaseTime(3600)
+        assert offset >= 3600
+        block_hash = node.evm_mine()
+        timestamp = int(self.rpc.block_by_hash(block_hash)["timestamp"], 0)
+        assert timestamp >= int(time.time()) + 3600 - 60
+        # The offset is not changed by an overflowing increase.
+        assert_raises_rpc_error(None, "overflows", node.evm_increaseTime, 2 ** 64 - 1)
+        assert_equal(node.evm_increaseTime(0), offset)
+        self.log.info("Pass increase time")
+
+    def test_impersonation(self):
+        node = self.nodes[0]
+        receiver = self.rpc.rand_addr()
+        senders = [self.rpc.rand_addr(), self.rpc.rand_addr()]
+        tx_hashes = []
+        for sender in senders:
+            sender = hex_to_b32_address(sender)
+            tx = {
+                "from": sender,
+                "to": hex_to_b32_address(receiver),
+                "gas": hex(21000),
+                "gasPrice": hex(1),
+                "value": hex(100),
+            }
+            assert_raises_rpc_error(None, "not impersonated", node.dev_sendTransaction, tx)
+            node.dev_impersonateAccount(sender)
+            node.dev_setBalance(sender, hex(10 ** 18))
+            tx_hashes.append(node.dev_sendTransaction(tx))
+
+        # The transactions with identical fields from different senders have
+        # different hashes.
+        assert tx_hashes[0] != tx_hashes[1]
+        for tx_hash, sender in zip(tx_hashes, senders):
+            receipt = node.cfx_getTransactionReceipt(tx_hash)
+            assert_equal(receipt["outcomeStatus"], "0x0")
+            assert_equal(receipt["from"], hex_to_b32_address(sender))
+        assert_equal(self.rpc.get_balance(receiver), 200)
+
+        node.dev_stopImpersonatingAccount(hex_to_b32_address(senders[0]))
+        tx["from"] = hex_to_b32_address(senders[0])
+        assert_raises_rpc_error(None, "not impersonated", node.dev_sendTransaction, tx)
+        self.log.info("Pass impersonation")
+
+    def test_eth_impersonation(self):
+        node = self.nodes[0]
+        receiver = self.rpc.rand_addr()
+        sender = self.rpc.rand_addr()
+        tx = {
+            "from": sender,
+            "to": receiver,
+            "gas": hex(21000),
+            "gasPrice": hex(1),
+            "value": hex(100),
+        }
+        assert_raises_rpc_error(None, "not impersonated", node.eth_sendTransaction, tx)
+        # The eSpace dev RPCs are only served with the eSpace RPCs.
+        assert_raises_rpc_error(-32601, None, node.rpc.hardhat_impersonateAccount, sender)
+
+        node.ethrpc.hardhat_impersonateAccount(sender)
+        node.ethrpc.hardhat_setBalance(sender, hex(10 ** 18))
+        tx_hash = node.eth_sendTransaction(tx)
+        receipt = node.eth_getTransactionReceipt(tx_hash)
+        assert_equal(receipt["status"], "0x1")
+        assert_equal(receipt["from"].lower(), sender.lower())
+        assert_equal(int(node.eth_getBalance(receiver), 0), 100)
+        self.log.info("Pass eSpace impersonation")
+
+
+if __name__ == '__main__':
+    DevRpcTest().main()
@@ -43,3 +43,9 @@ class TestGasPrice(RpcClient):
         # median of prices
         assert_equal(self.gas_price(), 1)
 
+    def test_estimates(self):
+        estimates = self.gas_price_estimates()
+        assert_greater_than(estimates["slow"], 0)
+        assert_greater_than(estimates["normal"], estimates["slow"] - 1)
+        assert_greater_than(estimates["fast"], estimates["normal"] - 1)
+        assert_equal(estimates["normal"], self.gas_price())
@@ -0,0 +1,171 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfxcore::{
+    block_data_manager::{
+        address_tx_index::address_tx_index_entries, db_manager::DBManager,
+        DataVersionTuple,
+    },
+    pow::PowComputer,
+};
+use std::sync::Arc;
+
+fn open_db(db_path: &str) -> std::io::Result<Arc<db::SystemDB>> {
+    let db_config = db::db_config(
+        std::path::Path::new(db_path),
+        None,
+        db::DatabaseCompactionProfile::default(),
+        cfxcore::db::NUM_COLUMNS,
+        false,
+    );
+
+    db::open_database(db_path, &db_config)
+}
+
+/// Index the transactions of the epoch `epoch_number` in the address
+/// transaction index. Return `false` if the epoch has not been executed or
+/// its blocks or execution results have been garbage collected.
+fn index_epoch(db_manager: &DBManager, epoch_number: u64) -> bool {
+    let epoch_set =
+        match db_manager.executed_epoch_set_hashes_from_db(epoch_number) {
+            Some(epoch_set) => epoch_set,
+            None => return false,
+        };
+    let pivot_hash = match epoch_set.last() {
+        Some(hash) => *hash,
+        None => return false,
+    };
+
+    let mut entries = Vec::new();
+    for block_hash in &epoch_set {
+        let transactions = match db_manager.block_body_from_db(block_hash) {
+            Some(transactions) => transactions,
+            None => return false,
+        };
+        let receipts = match db_manager
+            .block_execution_result_from_db(block_hash)
+        {
+            Some(DataVersionTuple(pivot, result)) if pivot == pivot_hash => {
+                result.block_receipts
+            }
+            _ => return false,
+        };
+        // Traces only exist if the node runs with `executive_trace`.
+        let traces = match db_manager.block_traces_from_db(block_hash) {
+            Some(DataVersionTuple(pivot, traces)) if pivot == pivot_hash => {
+                Some(traces)
+            }
+            _ => None,
+        };
+        entries.extend(address_tx_index_entries(
+            epoch_number,
+            &pivot_hash,
+            block_hash,
+            &transactions,
+            &receipts.receipts,
+            traces.as_ref().map(|traces| &traces.0[..]),
+        ));
+    }
+    db_manager.insert_epoch_address_tx_entries_to_db(epoch_number, entries);
+    true
+}
+
+struct Config {
+    db_path: String,
+    from_epoch: u64,
+    to_epoch: u64,
+}
+
+// from /src/main.rs
+fn from_str_validator<T: std::str::FromStr>(arg: String) -> Result<(), String> {
+    match arg.parse::<T>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(arg),
+    }
+}
+
+fn parse_config() -> Config {
+    let matches = clap::App::new("cfx-address-tx-index")
+        .version("0.1")
+        .about(
+"Build the address transaction index of executed epochs from your local
+blockchain db. The node must be stopped while this runs.
+
+The epochs of an address are returned in the order they are first indexed,
+so this should be run for all the epochs before `persist_address_tx_index` is
+enabled, from the earliest epoch to the latest epoch. Indexing an epoch again
+replaces its entries.
+Example usage:
+    cfx-address-tx-index
+        --db-path ./run/blockchain_db
+        --from-epoch 1
+        --to-epoch 1000000")
+        .arg(
+            clap::Arg::with_name("db-path")
+                .long("db-path")
+                .value_name("PATH")
+                .help("Specifies local blockchain db directory")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            clap::Arg::with_name("from-epoch")
+                .long("from-epoch")
+                .value_name("NUM")
+                .help("Sets the first epoch to index")
+                .takes_value(true)
+                .required(true)
+                .validator(from_str_validator::<u64>),
+        )
+        .arg(
+            clap::Arg::with_name("to-epoch")
+                .long("to-epoch")
+                .value_name("NUM")
+                .help("Sets the last epoch to index")
+                .takes_value(true)
+                .required(true)
+                .validator(from_str_validator::<u64>),
+        )
+        .get_matches();
+
+    let db_path = matches.value_of("db-path").unwrap();
+    let from_epoch = matches
+        .value_of("from-epoch")
+        .unwrap()
+        .parse::<u64>()
+        .unwrap();
+    let to_epoch = matches
+        .value_of("to-epoch")
+        .unwrap()
+        .parse::<u64>()
+        .unwrap();
+
+    Config {
+        db_path: String::from(db_path),
+        from_epoch,
+        to_epoch,
+    }
+}
+
+fn main() {
+    let config = parse_config();
+    let db = open_db(&config.db_path).unwrap();
+    // Block headers are not loaded, so the PoW computer is never used.
+    let db_manager =
+        DBManager::new_from_rocksdb(db, Arc::new(PowComputer::new(false)));
+
+    for epoch_number in config.from_epoch..=config.to_epoch {
+        if !index_epoch(&db_manager, epoch_number) {
+            eprintln!(
+                "Epoch {} is not available, stop indexing. Epochs {}..{} have been indexed.",
+                epoch_number, config.from_epoch, epoch_number
+            );
+            std::process::exit(1);
+        }
+        if epoch_number % 10000 == 0 {
+            eprintln!("Indexed epoch {}", epoch_number);
+        }
+    }
+    eprintln!("Indexed epochs {}..={}", config.from_epoch, config.to_epoch);
+}
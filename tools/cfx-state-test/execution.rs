@@ -0,0 +1,298 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Execute eSpace transactions on an in-memory state and compute the
+//! Ethereum state root and logs hash of the result.
+
+use crate::{fixture::AccountState, trie::secure_trie_root};
+use cfx_internal_common::StateRootWithAuxInfo;
+use cfx_state::{state_trait::StateOpsTrait, CleanupMode, StateTrait};
+use cfx_statedb::{Result as DbResult, StateDbGeneric};
+use cfx_storage::{
+    utils::access_mode, MptKeyValue, Result as StorageResult, StorageStateTrait,
+};
+use cfx_types::{Address, AddressSpaceUtil, Space, H256, U256};
+use cfxcore::{
+    executive::{
+        gas_required_for, ExecutionOutcome, ExecutiveGeneric, TransactOptions,
+    },
+    machine::Machine,
+    state::StateGeneric,
+    vm::{Env, Spec},
+};
+use keccak_hash::{keccak, KECCAK_EMPTY};
+use parking_lot::Mutex;
+use primitives::{
+    storage::STORAGE_LAYOUT_REGULAR_V0, Action, CodeInfo, EpochId, LogEntry,
+    SignedTransaction, SkipInputCheck, StorageKey, StorageKeyWithSpace,
+    StorageValue, MERKLE_NULL_NODE,
+};
+use rlp::{Rlp, RlpStream};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+/// A storage which keeps all the key-values in memory. The clones share the
+/// same key-values, so the state committed by one `State` can be read by
+/// another.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    entries: Arc<Mutex<BTreeMap<Vec<u8>, Box<[u8]>>>>,
+}
+
+impl StorageStateTrait for MemoryStorage {
+    fn get(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        Ok(self.entries.lock().get(&access_key.to_key_bytes()).cloned())
+    }
+
+    fn set(
+        &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
+    ) -> StorageResult<()> {
+        self.entries.lock().insert(access_key.to_key_bytes(), value);
+        Ok(())
+    }
+
+    fn delete(&mut self, access_key: StorageKeyWithSpace) -> StorageResult<()> {
+        self.entries.lock().remove(&access_key.to_key_bytes());
+        Ok(())
+    }
+
+    fn delete_test_only(
+        &mut self, access_key: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Box<[u8]>>> {
+        Ok(self.entries.lock().remove(&access_key.to_key_bytes()))
+    }
+
+    fn delete_all<AM: access_mode::AccessMode>(
+        &mut self, access_key_prefix: StorageKeyWithSpace,
+    ) -> StorageResult<Option<Vec<MptKeyValue>>> {
+        let prefix = access_key_prefix.to_key_bytes();
+        let mut entries = self.entries.lock();
+        let key_values: Vec<MptKeyValue> = entries
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        if !AM::is_read_only() {
+            for (key, _) in &key_values {
+                entries.remove(key);
+            }
+        }
+        if key_values.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(key_values))
+        }
+    }
+
+    // The Conflux state root is not used.
+    fn compute_state_root(&mut self) -> StorageResult<StateRootWithAuxInfo> {
+        self.get_state_root()
+    }
+
+    fn get_state_root(&self) -> StorageResult<StateRootWithAuxInfo> {
+        Ok(StateRootWithAuxInfo::genesis(&MERKLE_NULL_NODE))
+    }
+
+    fn commit(
+        &mut self, _epoch: EpochId,
+    ) -> StorageResult<StateRootWithAuxInfo> {
+        self.get_state_root()
+    }
+}
+
+type MemoryState = StateGeneric<MemoryStorage>;
+
+fn open_state(storage: &MemoryStorage) -> DbResult<MemoryState> {
+    StateGeneric::new(StateDbGeneric::new(storage.clone()))
+}
+
+/// Create a storage with the given eSpace accounts.
+pub fn build_pre_state(
+    alloc: &BTreeMap<Address, AccountState>,
+) -> DbResult<MemoryStorage> {
+    let storage = MemoryStorage::default();
+    let mut state = open_state(&storage)?;
+    for (address, account) in alloc {
+        let address = address.with_evm_space();
+        if account.code.is_empty() {
+            state.add_balance(
+                &address,
+                &account.balance,
+                CleanupMode::ForceCreate,
+                account.nonce,
+            )?;
+        } else {
+            state.new_contract_with_admin(
+                &address,
+                &Address::zero(),
+                account.balance,
+                account.nonce,
+                Some(STORAGE_LAYOUT_REGULAR_V0),
+            )?;
+            state.init_code(&address, account.code.clone(), Address::zero())?;
+        }
+        for (key, value) in &account.storage {
+            let mut key_bytes = vec![0; 32];
+            key.to_big_endian(&mut key_bytes);
+            state.set_storage(&address, key_bytes, *value, Address::zero())?;
+        }
+    }
+    state.commit(EpochId::default(), /* debug_record = */ None)?;
+    Ok(storage)
+}
+
+/// The result of an executed transaction. `error` is set if the execution
+/// fails, in which case only the nonce is bumped and the fee is charged.
+pub struct TransactionResult {
+    pub error: Option<String>,
+    pub gas_used: U256,
+    pub logs: Vec<LogEntry>,
+}
+
+/// Execute `transaction` on `storage` and commit the changes. Returns the
+/// reason if the transaction is rejected without being executed. The fee is
+/// paid to the block author as Ethereum does, because Conflux distributes
+/// it in the block reward instead.
+pub fn execute(
+    storage: &MemoryStorage, env: &Env, machine: &Machine, spec: &Spec,
+    transaction: &SignedTransaction,
+) -> DbResult<Result<TransactionResult, String>>
+{
+    let base_gas_required = gas_required_for(
+        transaction.action() == &Action::Create,
+        &transaction.data(),
+        spec,
+    );
+    if *transaction.gas() < base_gas_required.into() {
+        return Ok(Err("intrinsic gas too low".into()));
+    }
+    if *transaction.gas() > env.gas_limit {
+        return Ok(Err("gas limit exceeds block gas limit".into()));
+    }
+
+    let mut state = open_state(storage)?;
+    let outcome = ExecutiveGeneric::new(&mut state, env, machine, spec)
+        .transact(transaction, TransactOptions::with_no_tracing())?;
+    let (error, executed) = match outcome {
+        ExecutionOutcome::NotExecutedDrop(e) => {
+            return Ok(Err(format!("{:?}", e)));
+        }
+        ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
+            return Ok(Err(format!("{:?}", e)));
+        }
+        ExecutionOutcome::ExecutionErrorBumpNonce(e, executed) => {
+            (Some(format!("{:?}", e)), executed)
+        }
+        ExecutionOutcome::Finished(executed) => (None, executed),
+    };
+    state.add_balance(
+        &env.author.with_evm_space(),
+        &executed.fee,
+        CleanupMode::NoEmpty,
+        spec.account_start_nonce,
+    )?;
+    state.commit(EpochId::default(), /* debug_record = */ None)?;
+
+    Ok(Ok(TransactionResult {
+        error,
+        gas_used: executed.gas_used,
+        logs: executed.logs,
+    }))
+}
+
+#[derive(Default)]
+pub struct PostAccount {
+    pub balance: U256,
+    pub nonce: U256,
+    pub code_hash: H256,
+    pub code: Vec<u8>,
+    pub storage: BTreeMap<H256, U256>,
+}
+
+/// Read the eSpace accounts from the storage.
+pub fn post_state(storage: &MemoryStorage) -> BTreeMap<Address, PostAccount> {
+    let mut accounts = BTreeMap::<Address, PostAccount>::new();
+    let mut existing = HashSet::new();
+    for (key, value) in storage.entries.lock().iter() {
+        let key = StorageKeyWithSpace::from_key_bytes::<SkipInputCheck>(key);
+        if key.space != Space::Ethereum {
+            continue;
+        }
+        let rlp = Rlp::new(value);
+        match key.key {
+            StorageKey::AccountKey(address_bytes) => {
+                let address = Address::from_slice(address_bytes);
+                let account = accounts.entry(address).or_default();
+                account.balance = rlp.val_at(0).expect("valid account");
+                account.nonce = rlp.val_at(1).expect("valid account");
+                account.code_hash = rlp.val_at(2).expect("valid account");
+                existing.insert(address);
+            }
+            StorageKey::StorageKey {
+                address_bytes,
+                storage_key,
+            } => {
+                let value: StorageValue =
+                    rlp.as_val().expect("valid storage value");
+                accounts
+                    .entry(Address::from_slice(address_bytes))
+                    .or_default()
+                    .storage
+                    .insert(H256::from_slice(storage_key), value.value);
+            }
+            StorageKey::CodeKey { address_bytes, .. } => {
+                let code_info: CodeInfo = rlp.as_val().expect("valid code");
+                accounts
+                    .entry(Address::from_slice(address_bytes))
+                    .or_default()
+                    .code = (*code_info.code).clone();
+            }
+            _ => {}
+        }
+    }
+    accounts.retain(|address, _| existing.contains(address));
+    accounts
+}
+
+pub fn state_root(accounts: &BTreeMap<Address, PostAccount>) -> H256 {
+    secure_trie_root(accounts.iter().map(|(address, account)| {
+        let storage_root = secure_trie_root(account.storage.iter().filter_map(
+            |(key, value)| {
+                if value.is_zero() {
+                    None
+                } else {
+                    Some((key.as_bytes().to_vec(), rlp::encode(value)))
+                }
+            },
+        ));
+        let code_hash = if account.code_hash.is_zero() {
+            KECCAK_EMPTY
+        } else {
+            account.code_hash
+        };
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&account.nonce)
+            .append(&account.balance)
+            .append(&storage_root)
+            .append(&code_hash);
+        (address.as_bytes().to_vec(), stream.out())
+    }))
+}
+
+pub fn logs_hash(logs: &[LogEntry]) -> H256 {
+    let mut stream = RlpStream::new_list(logs.len());
+    for log in logs {
+        stream
+            .begin_list(3)
+            .append(&log.address)
+            .append_list(&log.topics)
+            .append(&log.data);
+    }
+    keccak(stream.out())
+}
@@ -0,0 +1,186 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The JSON formats of the Ethereum `GeneralStateTests` fixtures and of the
+//! `t8n` inputs.
+
+use cfx_types::{Address, H256, U256};
+use rustc_hex::FromHex;
+use serde::{de, Deserialize, Deserializer};
+use std::{collections::BTreeMap, str::FromStr};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateTest {
+    pub env: TestEnv,
+    pub pre: BTreeMap<Address, AccountState>,
+    pub transaction: MultiTransaction,
+    /// The expected post states of each fork.
+    pub post: BTreeMap<String, Vec<PostState>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestEnv {
+    pub current_coinbase: Address,
+    #[serde(default, deserialize_with = "option_u256")]
+    pub current_difficulty: Option<U256>,
+    #[serde(deserialize_with = "u256")]
+    pub current_gas_limit: U256,
+    #[serde(deserialize_with = "u256")]
+    pub current_number: U256,
+    #[serde(deserialize_with = "u256")]
+    pub current_timestamp: U256,
+    #[serde(default)]
+    pub previous_hash: Option<H256>,
+}
+
+/// An account of the pre state. The fields other than `balance` may be
+/// omitted in the `t8n` alloc.
+#[derive(Deserialize)]
+pub struct AccountState {
+    #[serde(deserialize_with = "u256")]
+    pub balance: U256,
+    #[serde(default, deserialize_with = "bytes")]
+    pub code: Vec<u8>,
+    #[serde(default, deserialize_with = "u256")]
+    pub nonce: U256,
+    #[serde(default, deserialize_with = "storage")]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// A transaction with several choices of data, gas limit and value, which
+/// are selected by the `indexes` of a post state.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiTransaction {
+    #[serde(deserialize_with = "bytes_list")]
+    pub data: Vec<Vec<u8>>,
+    #[serde(deserialize_with = "u256_list")]
+    pub gas_limit: Vec<U256>,
+    /// Not set for EIP-1559 transactions, which are not supported.
+    #[serde(default, deserialize_with = "option_u256")]
+    pub gas_price: Option<U256>,
+    #[serde(deserialize_with = "u256")]
+    pub nonce: U256,
+    pub secret_key: H256,
+    #[serde(deserialize_with = "option_address")]
+    pub to: Option<Address>,
+    #[serde(deserialize_with = "u256_list")]
+    pub value: Vec<U256>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+    pub hash: H256,
+    pub logs: H256,
+    pub indexes: PostStateIndexes,
+    #[serde(default)]
+    pub expect_exception: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PostStateIndexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+/// A transaction of the `t8n` input. Only the transactions with `secretKey`
+/// are supported, which are signed here.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nTransaction {
+    #[serde(deserialize_with = "u256")]
+    pub nonce: U256,
+    #[serde(deserialize_with = "u256")]
+    pub gas_price: U256,
+    #[serde(deserialize_with = "u256")]
+    pub gas: U256,
+    #[serde(default, deserialize_with = "option_address")]
+    pub to: Option<Address>,
+    #[serde(deserialize_with = "u256")]
+    pub value: U256,
+    #[serde(deserialize_with = "bytes")]
+    pub input: Vec<u8>,
+    #[serde(default)]
+    pub secret_key: Option<H256>,
+}
+
+pub fn parse_u256(value: &str) -> Result<U256, String> {
+    let hex = value.trim_start_matches("0x");
+    if hex.is_empty() {
+        return Ok(U256::zero());
+    }
+    if value.starts_with("0x") {
+        U256::from_str(hex).map_err(|e| format!("{}: {:?}", value, e))
+    } else {
+        U256::from_dec_str(value).map_err(|e| format!("{}: {:?}", value, e))
+    }
+}
+
+pub fn parse_bytes(value: &str) -> Result<Vec<u8>, String> {
+    value
+        .trim_start_matches("0x")
+        .from_hex()
+        .map_err(|e| format!("{}: {}", value, e))
+}
+
+fn u256<'de, D: Deserializer<'de>>(d: D) -> Result<U256, D::Error> {
+    parse_u256(&String::deserialize(d)?).map_err(de::Error::custom)
+}
+
+fn option_u256<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<U256>, D::Error> {
+    Ok(Some(u256(d)?))
+}
+
+fn u256_list<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<U256>, D::Error> {
+    Vec::<String>::deserialize(d)?
+        .iter()
+        .map(|value| parse_u256(value).map_err(de::Error::custom))
+        .collect()
+}
+
+fn bytes<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    parse_bytes(&String::deserialize(d)?).map_err(de::Error::custom)
+}
+
+fn bytes_list<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Vec<Vec<u8>>, D::Error> {
+    Vec::<String>::deserialize(d)?
+        .iter()
+        .map(|value| parse_bytes(value).map_err(de::Error::custom))
+        .collect()
+}
+
+fn storage<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<BTreeMap<U256, U256>, D::Error> {
+    BTreeMap::<String, String>::deserialize(d)?
+        .iter()
+        .map(|(key, value)| {
+            Ok((
+                parse_u256(key).map_err(de::Error::custom)?,
+                parse_u256(value).map_err(de::Error::custom)?,
+            ))
+        })
+        .collect()
+}
+
+/// An empty string means contract creation.
+fn option_address<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<Address>, D::Error> {
+    match Option::<String>::deserialize(d)? {
+        None => Ok(None),
+        Some(to) if to.trim_start_matches("0x").is_empty() => Ok(None),
+        Some(to) => Address::from_str(to.trim_start_matches("0x"))
+            .map(Some)
+            .map_err(|e| de::Error::custom(format!("{}: {:?}", to, e))),
+    }
+}
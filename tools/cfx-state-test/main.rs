@@ -0,0 +1,521 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+mod execution;
+mod fixture;
+mod trie;
+
+use crate::{
+    execution::{build_pre_state, execute, logs_hash, post_state, state_root},
+    fixture::{AccountState, PostState, StateTest, T8nTransaction, TestEnv},
+};
+use cfx_types::{Address, Secret, H256, U256};
+use cfxcore::{
+    machine::{new_machine_with_builtin, Machine},
+    spec::CommonParams,
+    vm::{Env, Spec},
+    vm_factory::VmFactory,
+};
+use primitives::{Action, Eip155Transaction, SignedTransaction, Transaction};
+use rustc_hex::ToHex;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The eSpace chain id in the default params, which is also the chain id of
+/// the Ethereum mainnet used by the fixtures.
+const CHAIN_ID: u32 = 1;
+
+/// The forks whose gas schedule can be expressed by `Spec`. The forks after
+/// Istanbul need the access lists of EIP-2929, which are not in eSpace.
+const SUPPORTED_FORKS: &[&str] = &[
+    "Byzantium",
+    "Constantinople",
+    "ConstantinopleFix",
+    "Petersburg",
+    "Istanbul",
+];
+
+/// Why the tests of EIP-1559 transactions are skipped.
+const EIP_1559_UNSUPPORTED: &str = "EIP-1559 transactions are not in eSpace";
+
+/// The forks before Byzantium, all the other unsupported forks are after
+/// Istanbul.
+const PRE_BYZANTIUM_FORKS: &[&str] =
+    &["Frontier", "Homestead", "EIP150", "EIP158"];
+
+/// The eSpace spec with the gas costs of `fork`, or the reason why the fork is
+/// not supported.
+fn fork_spec(machine: &Machine, fork: &str) -> Result<Spec, &'static str> {
+    if !SUPPORTED_FORKS.contains(&fork) {
+        return Err(if PRE_BYZANTIUM_FORKS.contains(&fork) {
+            "gas schedule not expressible by eSpace"
+        } else {
+            "EIP-2929 access lists are not in eSpace"
+        });
+    }
+    let mut spec = machine.spec(/* number = */ 0);
+    if fork == "Istanbul" {
+        // EIP-1884 and EIP-2028.
+        spec.sload_gas = 800;
+        spec.balance_gas = 700;
+        spec.extcodehash_gas = 700;
+        spec.tx_data_non_zero_gas = 16;
+    }
+    Ok(spec)
+}
+
+fn new_env(env: &TestEnv) -> Env {
+    Env {
+        number: env.current_number.low_u64(),
+        author: env.current_coinbase,
+        timestamp: env.current_timestamp.low_u64(),
+        difficulty: env.current_difficulty.unwrap_or_default(),
+        gas_limit: env.current_gas_limit,
+        last_hash: env.previous_hash.unwrap_or_default(),
+        epoch_height: env.current_number.low_u64(),
+        ..Default::default()
+    }
+}
+
+fn sign_transaction(
+    nonce: U256, gas_price: U256, gas: U256, to: Option<Address>, value: U256,
+    data: Vec<u8>, secret_key: &H256,
+) -> SignedTransaction
+{
+    Transaction::from(Eip155Transaction {
+        nonce,
+        gas_price,
+        gas,
+        action: to.map_or(Action::Create, Action::Call),
+        value,
+        chain_id: Some(CHAIN_ID),
+        data,
+    })
+    .sign(&Secret::from(*secret_key))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Collect the JSON files under `path` recursively.
+fn collect_fixtures(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let mut entries: Vec<_> = match fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                return;
+            }
+        };
+        entries.sort_by_key(|e| e.path());
+        for entry in entries {
+            collect_fixtures(&entry.path(), files);
+        }
+    } else if path.extension().map_or(false, |ext| ext == "json") {
+        files.push(path.to_path_buf());
+    }
+}
+
+#[derive(Default)]
+struct ForkReport {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    /// Why the post states of the fork are skipped.
+    skip_reason: Option<&'static str>,
+}
+
+/// Run one post state of a test. Returns the reason of the failure.
+fn run_post_state(
+    test: &StateTest, index: usize, expected: &PostState, machine: &Machine,
+    spec: &Spec,
+) -> Result<(), String>
+{
+    let transaction = &test.transaction;
+    let gas_price = match transaction.gas_price {
+        Some(gas_price) => gas_price,
+        None => return Err(EIP_1559_UNSUPPORTED.into()),
+    };
+    let indexes = &expected.indexes;
+    let (data, gas, value) = match (
+        transaction.data.get(indexes.data),
+        transaction.gas_limit.get(indexes.gas),
+        transaction.value.get(indexes.value),
+    ) {
+        (Some(data), Some(gas), Some(value)) => (data, gas, value),
+        _ => return Err(format!("post state {} has invalid indexes", index)),
+    };
+    let signed = sign_transaction(
+        transaction.nonce,
+        gas_price,
+        *gas,
+        transaction.to,
+        *value,
+        data.clone(),
+        &transaction.secret_key,
+    );
+
+    let env = new_env(&test.env);
+    let storage = build_pre_state(&test.pre).map_err(|e| e.to_string())?;
+    let result = execute(&storage, &env, machine, spec, &signed)
+        .map_err(|e| e.to_string())?;
+    let logs = match (result, &expected.expect_exception) {
+        (Err(_), Some(_)) => return Ok(()),
+        (Err(reason), None) => {
+            return Err(format!("transaction rejected: {}", reason))
+        }
+        (Ok(_), Some(exception)) => {
+            return Err(format!("expected exception {}", exception))
+        }
+        (Ok(result), None) => result.logs,
+    };
+
+    let root = state_root(&post_state(&storage));
+    if root != expected.hash {
+        return Err(format!(
+            "state root mismatch: got {:?}, expected {:?}",
+            root, expected.hash
+        ));
+    }
+    let logs = logs_hash(&logs);
+    if logs != expected.logs {
+        return Err(format!(
+            "logs hash mismatch: got {:?}, expected {:?}",
+            logs, expected.logs
+        ));
+    }
+    Ok(())
+}
+
+fn run_state_tests(
+    paths: &[&str], fork_filter: Option<&str>, name_filter: Option<&str>,
+) -> bool {
+    let machine = new_machine();
+    let mut files = Vec::new();
+    for path in paths {
+        collect_fixtures(Path::new(path), &mut files);
+    }
+
+    let mut reports = BTreeMap::<String, ForkReport>::new();
+    let mut load_failures = 0;
+    for file in &files {
+        let tests: BTreeMap<String, StateTest> = match read_json(file) {
+            Ok(tests) => tests,
+            Err(e) => {
+                eprintln!("{}", e);
+                load_failures += 1;
+                continue;
+            }
+        };
+        for (name, test) in &tests {
+            if name_filter.map_or(false, |filter| !name.contains(filter)) {
+                continue;
+            }
+            for (fork, post_states) in &test.post {
+                if fork_filter.map_or(false, |filter| filter != fork) {
+                    continue;
+                }
+                let report = reports.entry(fork.clone()).or_default();
+                let spec = match fork_spec(&machine, fork) {
+                    Ok(spec) => spec,
+                    Err(reason) => {
+                        report.skipped += post_states.len();
+                        report.skip_reason = Some(reason);
+                        continue;
+                    }
+                };
+                if test.transaction.gas_price.is_none() {
+                    report.skipped += post_states.len();
+                    report.skip_reason = Some(EIP_1559_UNSUPPORTED);
+                    continue;
+                }
+                for (index, expected) in post_states.iter().enumerate() {
+                    match run_post_state(test, index, expected, &machine, &spec)
+                    {
+                        Ok(()) => report.passed += 1,
+                        Err(reason) => {
+                            report.failed += 1;
+                            eprintln!(
+                                "FAIL {} {}[{}]: {}",
+                                name, fork, index, reason
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    println!(
+        "{:<20} {:>8} {:>8} {:>8}  {}",
+        "Fork", "Passed", "Failed", "Skipped", "Skip reason"
+    );
+    for (fork, report) in &reports {
+        println!(
+            "{:<20} {:>8} {:>8} {:>8}  {}",
+            fork,
+            report.passed,
+            report.failed,
+            report.skipped,
+            report.skip_reason.unwrap_or("")
+        );
+    }
+    if load_failures > 0 {
+        println!("{} fixture files failed to load", load_failures);
+    }
+    load_failures == 0 && reports.values().all(|report| report.failed == 0)
+}
+
+/// Execute the transactions one by one on the pre state, as `evm t8n` of
+/// go-ethereum does, and return the result and the post state.
+fn transition(
+    alloc: &BTreeMap<Address, AccountState>, env: &TestEnv,
+    txs: &[T8nTransaction], fork: &str,
+) -> Result<(Value, Value), String>
+{
+    let machine = new_machine();
+    let spec = fork_spec(&machine, fork)
+        .map_err(|reason| format!("Unsupported fork {}: {}", fork, reason))?;
+    let env = new_env(env);
+    let storage = build_pre_state(alloc).map_err(|e| e.to_string())?;
+
+    let mut logs = Vec::new();
+    let mut receipts = Vec::new();
+    let mut rejected = Vec::new();
+    let mut cumulative_gas_used = U256::zero();
+    for (index, tx) in txs.iter().enumerate() {
+        let secret_key = match &tx.secret_key {
+            Some(secret_key) => secret_key,
+            None => {
+                rejected.push(json!({
+                    "index": index,
+                    "error": "only transactions with secretKey are supported",
+                }));
+                continue;
+            }
+        };
+        let signed = sign_transaction(
+            tx.nonce,
+            tx.gas_price,
+            tx.gas,
+            tx.to,
+            tx.value,
+            tx.input.clone(),
+            secret_key,
+        );
+        match execute(&storage, &env, &machine, &spec, &signed)
+            .map_err(|e| e.to_string())?
+        {
+            Err(reason) => {
+                rejected.push(json!({ "index": index, "error": reason }))
+            }
+            Ok(result) => {
+                cumulative_gas_used += result.gas_used;
+                let status = if result.error.is_none() { "0x1" } else { "0x0" };
+                receipts.push(json!({
+                    "transactionHash": signed.hash(),
+                    "status": status,
+                    "cumulativeGasUsed": cumulative_gas_used,
+                    "gasUsed": result.gas_used,
+                    "logsHash": logs_hash(&result.logs),
+                    "transactionIndex": index,
+                }));
+                logs.extend(result.logs);
+            }
+        }
+    }
+
+    let accounts = post_state(&storage);
+    let result = json!({
+        "stateRoot": state_root(&accounts),
+        "logsHash": logs_hash(&logs),
+        "gasUsed": cumulative_gas_used,
+        "receipts": receipts,
+        "rejected": rejected,
+    });
+    let alloc: serde_json::Map<String, Value> = accounts
+        .iter()
+        .map(|(address, account)| {
+            let storage: serde_json::Map<String, Value> = account
+                .storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(key, value)| (format!("{:?}", key), json!(value)))
+                .collect();
+            let mut entry = json!({
+                "balance": account.balance,
+                "nonce": account.nonce,
+            });
+            if !account.code.is_empty() {
+                entry["code"] =
+                    json!(format!("0x{}", account.code.to_hex::<String>()));
+            }
+            if !storage.is_empty() {
+                entry["storage"] = Value::Object(storage);
+            }
+            (format!("{:?}", address), entry)
+        })
+        .collect();
+    Ok((result, Value::Object(alloc)))
+}
+
+fn write_output(path: &str, value: &Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value).unwrap();
+    if path == "stdout" {
+        println!("{}", content);
+        Ok(())
+    } else {
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+fn run_t8n(matches: &clap::ArgMatches) -> Result<(), String> {
+    let alloc = read_json(Path::new(matches.value_of("input.alloc").unwrap()))?;
+    let env = read_json(Path::new(matches.value_of("input.env").unwrap()))?;
+    let txs: Vec<T8nTransaction> =
+        read_json(Path::new(matches.value_of("input.txs").unwrap()))?;
+    let (result, alloc) = transition(
+        &alloc,
+        &env,
+        &txs,
+        matches.value_of("state.fork").unwrap(),
+    )?;
+    write_output(matches.value_of("output.result").unwrap(), &result)?;
+    write_output(matches.value_of("output.alloc").unwrap(), &alloc)
+}
+
+fn new_machine() -> Machine {
+    // All the CIPs are activated from the genesis in the default params.
+    new_machine_with_builtin(CommonParams::default(), VmFactory::new(1024 * 32))
+}
+
+fn main() {
+    let matches = clap::App::new("cfx-state-test")
+        .version("0.1")
+        .about(
+"Run the Ethereum GeneralStateTests fixtures against the eSpace execution.
+
+The post state of every test is compared with the state root and logs hash in
+the fixture, and the number of passed and failed cases is reported per fork.
+The forks whose gas schedule can not be expressed by eSpace, including all the
+forks after Istanbul, are reported as skipped with the reason.
+
+The fee of a transaction is paid to the coinbase as Ethereum does, but the
+charged gas still follows the eSpace rules, so the tests which depend on the
+refund or on the unused gas are expected to fail.
+Example usage:
+    cfx-state-test state ./GeneralStateTests/stExample --fork Istanbul
+    cfx-state-test t8n --input.alloc alloc.json --input.env env.json
+        --input.txs txs.json --state.fork Istanbul")
+        .subcommand(
+            clap::SubCommand::with_name("state")
+                .about("Run the state test fixtures")
+                .arg(
+                    clap::Arg::with_name("path")
+                        .value_name("PATH")
+                        .help("Fixture files or directories")
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("fork")
+                        .long("fork")
+                        .value_name("FORK")
+                        .help("Only runs the post states of the fork")
+                        .takes_value(true),
+                )
+                .arg(
+                    clap::Arg::with_name("test")
+                        .long("test")
+                        .value_name("NAME")
+                        .help("Only runs the tests whose name contains NAME")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("t8n")
+                .about("Apply transactions to a state, for fuzzers")
+                .arg(
+                    clap::Arg::with_name("input.alloc")
+                        .long("input.alloc")
+                        .value_name("FILE")
+                        .help("Sets the pre state")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("input.env")
+                        .long("input.env")
+                        .value_name("FILE")
+                        .help("Sets the block environment")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("input.txs")
+                        .long("input.txs")
+                        .value_name("FILE")
+                        .help("Sets the transactions with their secretKey")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("state.fork")
+                        .long("state.fork")
+                        .value_name("FORK")
+                        .help("Sets the fork of the gas schedule")
+                        .takes_value(true)
+                        .default_value("Istanbul"),
+                )
+                .arg(
+                    clap::Arg::with_name("output.result")
+                        .long("output.result")
+                        .value_name("FILE")
+                        .help("Sets the result file, or stdout")
+                        .takes_value(true)
+                        .default_value("stdout"),
+                )
+                .arg(
+                    clap::Arg::with_name("output.alloc")
+                        .long("output.alloc")
+                        .value_name("FILE")
+                        .help("Sets the post state file, or stdout")
+                        .takes_value(true)
+                        .default_value("stdout"),
+                ),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("state", Some(matches)) => {
+            let paths: Vec<&str> = matches.values_of("path").unwrap().collect();
+            if !run_state_tests(
+                &paths,
+                matches.value_of("fork"),
+                matches.value_of("test"),
+            ) {
+                std::process::exit(1);
+            }
+        }
+        ("t8n", Some(matches)) => {
+            if let Err(e) = run_t8n(matches) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            eprintln!("{}", matches.usage());
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2022 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The root of an Ethereum Merkle Patricia trie, which is used to compare the
+//! post state with the Ethereum test fixtures.
+
+use cfx_types::H256;
+use keccak_hash::keccak;
+use rlp::RlpStream;
+use std::collections::BTreeMap;
+
+/// Compute the root of a secure trie, where every key is hashed by keccak
+/// before being inserted.
+pub fn secure_trie_root<I>(items: I) -> H256
+where I: IntoIterator<Item = (Vec<u8>, Vec<u8>)> {
+    let items: BTreeMap<Vec<u8>, Vec<u8>> = items
+        .into_iter()
+        .map(|(key, value)| (to_nibbles(keccak(key).as_bytes()), value))
+        .collect();
+    let items: Vec<_> = items.into_iter().collect();
+    keccak(encode_node(&items, 0))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+}
+
+/// Encode the nibbles with the hex prefix of their parity and the node type.
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 0x20 } else { 0x00 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        encoded.push(pair[0] << 4 | pair[1]);
+    }
+    encoded
+}
+
+/// Encode the node of the sorted `items`, whose keys share the first `depth`
+/// nibbles. All the keys have the same length, so branch nodes never hold a
+/// value.
+fn encode_node(items: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    match items {
+        [] => rlp::NULL_RLP.to_vec(),
+        [(key, value)] => {
+            let mut stream = RlpStream::new_list(2);
+            stream.append(&hex_prefix(&key[depth..], true));
+            stream.append(value);
+            stream.out()
+        }
+        _ => {
+            let first = &items[0].0;
+            let last = &items[items.len() - 1].0;
+            let shared = first[depth..]
+                .iter()
+                .zip(&last[depth..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if shared > 0 {
+                let mut stream = RlpStream::new_list(2);
+                stream
+                    .append(&hex_prefix(&first[depth..depth + shared], false));
+                append_child(&mut stream, encode_node(items, depth + shared));
+                return stream.out();
+            }
+
+            let mut stream = RlpStream::new_list(17);
+            let mut begin = 0;
+            for nibble in 0..16 {
+                let end = begin
+                    + items[begin..]
+                        .iter()
+                        .take_while(|(key, _)| key[depth] == nibble)
+                        .count();
+                if end > begin {
+                    append_child(
+                        &mut stream,
+                        encode_node(&items[begin..end], depth + 1),
+                    );
+                } else {
+                    stream.append_empty_data();
+                }
+                begin = end;
+            }
+            stream.append_empty_data();
+            stream.out()
+        }
+    }
+}
+
+/// Nodes shorter than 32 bytes are embedded in their parent, other nodes are
+/// referred by their hashes.
+fn append_child(stream: &mut RlpStream, encoded: Vec<u8>) {
+    if encoded.len() < 32 {
+        stream.append_raw(&encoded, 1);
+    } else {
+        stream.append(&keccak(&encoded));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::secure_trie_root;
+    use cfx_types::{Address, H256, U256};
+    use keccak_hash::{keccak, KECCAK_EMPTY};
+    use rlp::RlpStream;
+    use rustc_hex::FromHex;
+
+    fn account(
+        nonce: u64, balance: u64, storage_root: H256, code_hash: H256,
+    ) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&U256::from(nonce))
+            .append(&U256::from(balance))
+            .append(&storage_root)
+            .append(&code_hash);
+        stream.out()
+    }
+
+    fn address(hex: &str) -> Vec<u8> {
+        hex.parse::<Address>().unwrap().as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_empty_root() {
+        assert_eq!(
+            secure_trie_root(vec![]),
+            "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_post_state_root() {
+        // The post state of a transaction calling a contract which stores
+        // 1 + 1 at slot 0, whose roots are computed by the `HashBuilder` of
+        // alloy-trie.
+        let storage_root = secure_trie_root(vec![(
+            H256::zero().as_bytes().to_vec(),
+            rlp::encode(&U256::from(2)),
+        )]);
+        assert_eq!(
+            storage_root,
+            "63cfcda8d81a8b1840b1b9722c37f929a4037e53ad1ce6abdef31c0c8bac1f61"
+                .parse()
+                .unwrap()
+        );
+
+        let empty_root = secure_trie_root(vec![]);
+        let code: Vec<u8> = "600160010160005500".from_hex().unwrap();
+        let root = secure_trie_root(vec![
+            (
+                address("095e7baea6a6c7c4c2dfeb977efac326af552d87"),
+                account(0, 0x0de0b6b3a7640001, storage_root, keccak(&code)),
+            ),
+            (
+                address("2adc25665018aa1fe0e6bc666dac8fc2697ff9ba"),
+                account(0, 0x1f5e0, empty_root, KECCAK_EMPTY),
+            ),
+            (
+                address("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"),
+                account(1, 0x0de0b6b3a763ca1f, empty_root, KECCAK_EMPTY),
+            ),
+        ]);
+        assert_eq!(
+            root,
+            "c266745ec68a29152b91a7d5d420632d34020d51c9941a5bb6bb316aa23ef840"
+                .parse()
+                .unwrap()
+        );
+    }
+}
@@ -53,6 +53,12 @@ impl<T> PrioritySendQueue<T> {
         None
     }
 
+    pub fn pop_front_by_priority(
+        &mut self, priority: SendQueuePriority,
+    ) -> Option<T> {
+        self.queue_mut(priority).pop_front()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.queue(SendQueuePriority::High).is_empty()
             && self.queue(SendQueuePriority::Normal).is_empty()